@@ -0,0 +1,29 @@
+_Static_assert(sizeof(long) == 8, "long must be 64 bits");
+
+_Alignas(32) int table[4] = {1, 2, 3, 4};
+_Alignas(double) int counter;
+
+struct line {
+    int tag;
+    _Alignas(32) int cache[2];
+    _Alignas(long) int last;
+};
+
+_Static_assert(sizeof(struct line) == 64, "cache and last start on their requested boundaries");
+_Static_assert(_Alignof(struct line) == 32);
+
+struct line shared = {1, {2, 3}, 4};
+
+int main(void)
+{
+    _Alignas(16) int scratch = 2;
+    _Alignas(64) long wide[2] = {3, 4};
+    struct line local = shared;
+    _Static_assert(_Alignof(int[4]) == _Alignof(int));
+
+    counter = table[3] + scratch + wide[1];
+    return counter + ((unsigned long)table % 32 == 0) + ((unsigned long)&scratch % 16 == 0) +
+           ((unsigned long)wide % 64 == 0) + ((unsigned long)&shared % 32 == 0) +
+           ((unsigned long)&local % 32 == 0) + (local.cache[1] + local.last == 7) +
+           ((unsigned long)&local.last - (unsigned long)&local == 40);
+}
@@ -0,0 +1,74 @@
+void exit(int) __attribute__((__noreturn__));
+__extension__ long big = 5;
+int counter __attribute__((section(".mydata"))) = 7;
+int zeroed __attribute__((section(".mydata2")));
+double d __attribute__((aligned(64))) = 1.5;
+
+struct __attribute__((packed)) header {
+    _Bool flag;
+    double value;
+    unsigned kind : 3;
+    long delta : 40;
+};
+
+struct slot {
+    _Bool used;
+    int id __attribute__((aligned(16)));
+} __attribute__((aligned(32)));
+
+_Static_assert(sizeof(struct header) == 15, "packed members follow each other directly");
+_Static_assert(sizeof(struct slot) == 32 && _Alignof(struct slot) == 32);
+
+struct header last = {1, 2.5, 5, -1000};
+
+__attribute__((aligned(32))) int f(void)
+{
+    return 3;
+}
+
+int sum(int *__restrict p, int *__attribute__((unused)) q)
+{
+    return *p + *q;
+}
+
+__attribute__((section(".text.hot"))) int twice(int x)
+{
+    return x * 2;
+}
+
+_Noreturn void die(int code)
+{
+    exit(code);
+}
+
+inline int square(int x);
+int square(int x)
+{
+    return x * x;
+}
+
+int main(void)
+{
+    int a __attribute__((unused)) = 1;
+    int b __attribute__((aligned(16))) = 2;
+    struct header h = last;
+    struct slot s = {1, 7};
+    int x = ({
+        int t = a + b;
+        t * 10;
+    });
+    int y = ({
+        int a = 4;
+        a;
+    }) + a;
+    ({ counter++; });
+    h.value *= 2;
+    h.kind++;
+    h.delta -= 24;
+
+    int ok = x == 30 && y == 5 && counter == 8 && __extension__ big == 5 && zeroed == 0 &&
+             sum(&a, &b) == 3 && twice(21) == 42 && square(3) == 9 && (long)&d % 64 == 0 &&
+             (long)f % 32 == 0 && h.value == 5.0 && h.kind == 6 && h.delta == -1024 &&
+             (long)&h.value - (long)&h == 1 && (long)&s.id - (long)&s == 16;
+    die(ok ? 4 : 9);
+}
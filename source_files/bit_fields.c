@@ -0,0 +1,50 @@
+struct flags {
+    unsigned ready : 1;
+    unsigned mode : 3;
+    int delta : 5;
+    unsigned : 0;
+    _Bool dirty : 1;
+    _Bool locked : 1;
+    long offset : 40;
+    unsigned long tag : 24;
+};
+
+struct packed_pair {
+    int low : 4;
+    int : 4;
+    int high : 24;
+};
+
+struct mixed {
+    _Bool first;
+    unsigned rest : 20;
+    int after;
+};
+
+_Static_assert(sizeof(struct flags) == 16, "offset and tag share the second long");
+_Static_assert(sizeof(struct packed_pair) == 4, "every field fits in one int");
+_Static_assert(sizeof(struct mixed) == 8, "rest starts right after first");
+
+struct flags settings = {1, 5, -3, 1, 0, -100000, 11259375};
+struct mixed spread = {1, 1048575, -2};
+
+int main(void)
+{
+    struct flags f = settings;
+    struct packed_pair pair = {-1, 8388607};
+    struct mixed *m = &spread;
+    int wrapped;
+
+    f.mode = 9;
+    f.delta += 20;
+    f.locked = 2;
+    f.tag++;
+    pair.low--;
+    wrapped = (pair.high = -1);
+    m->rest += 2;
+
+    return (f.ready == 1) + (f.mode == 1) + (f.delta == -15) + f.dirty + f.locked +
+           (f.offset == -100000) + (f.tag == 11259376) + (pair.low == -2) + (pair.high == -1) +
+           (wrapped == -1) + (m->first == 1) + (m->rest == 1) + (m->after == -2) +
+           (settings.mode - 6 < 0);
+}
@@ -0,0 +1,13 @@
+int main(void)
+{
+    unsigned int mask = 4294967295u;
+    int negative = -16;
+    long wide = 1l << 40;
+
+    int mixed = 12 & 10 | 1 ^ 4;
+    unsigned int logical = mask >> 28;
+    int arithmetic = negative >> 2;
+    long shifted = wide >> 38;
+
+    return mixed + logical + arithmetic + shifted;
+}
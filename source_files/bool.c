@@ -0,0 +1,21 @@
+_Bool enabled = 256;
+_Bool nonnull = &enabled;
+_Bool bits[4] = {1, 0, -1};
+
+_Bool is_positive(double x)
+{
+    return x > 0;
+}
+
+int main(void)
+{
+    _Bool half = 0.5;
+    _Bool toggled = 1;
+    toggled--;
+    toggled--;
+
+    int *p = 0;
+    _Bool from_pointer = p;
+
+    return enabled + nonnull + bits[2] + half + toggled + from_pointer + is_positive(-2.0) + -half;
+}
@@ -0,0 +1,71 @@
+/* The same index expressions and loads again and again, which
+   --eliminate-common-subexpressions works out once each. Loads after a
+   store, a call or a write to a variable a pointer can reach are still
+   done again, and so is every load through a pointer to volatile. */
+
+int grid[4][5] = {{1, 2, 3, 4, 5}, {6, 7, 8, 9, 10}, {11, 12, 13, 14, 15}, {16, 17, 18, 19, 20}};
+int counter = 0;
+
+int bump(void) {
+    counter = counter + 1;
+    return counter;
+}
+
+int stencil(int i, int j) {
+    int n = 5;
+    int centre = grid[i][j] * (i * n + j);
+    int across = grid[i][j + 1] + grid[i][j - 1] + (j + i * n) * 2;
+    int down = i + 1 < 4 ? grid[i + 1][j] + grid[i][j] : grid[i][j] * (n * i + j);
+    return centre + across + down;
+}
+
+long loads(int *p, long *q) {
+    long a = *p + *p * *p;
+    *q = a;
+    long b = *p + *q;
+    int c = *p + bump();
+    int d = *p;
+    counter = 3;
+    long e = *q + (*p == d) + (d == *p);
+    return a * 7 + b * 5 + c * 3 + d + e;
+}
+
+int flags(volatile int *v, int x) {
+    int first = *v;
+    int second = *v;
+    unsigned wide = (unsigned)x * 2u;
+    long widened = (long)x + (long)x;
+    unsigned long unsigned_widened = (unsigned long)x;
+    return first + second + wide + widened + (unsigned_widened > 4000000000ul) * 1000;
+}
+
+/* The value of `a` on entry only reaches the return through a phi, which
+   has to keep the parameter's name. */
+unsigned pick(unsigned a, int c) {
+    return c ? a : 5u;
+}
+
+/* Both arms end up writing the parameter `u` itself, so the operation
+   reads it as its second operand and writes it at once. */
+unsigned mix(unsigned u, unsigned w) {
+    return (w & 1023u) ? (w & u) : u;
+}
+
+unsigned drop(unsigned u, unsigned w) {
+    return (w & 1023u) ? (w - u) : u;
+}
+
+int main(void) {
+    int h = 0;
+    h = h * 31 + stencil(1, 2);
+    h = h * 31 + stencil(3, 3);
+    int x = 6;
+    long y = 0;
+    h = h * 31 + loads(&x, &y);
+    h = h * 31 + (int)y;
+    h = h * 31 + flags(&x, -3);
+    h = h * 31 + counter;
+    h = h * 31 + pick(67u, 1) + pick(67u, 0);
+    h = h * 31 + (int)(mix(938760676u, 5u) % 251u) + (int)(drop(6u, 1029u) % 251u);
+    return (h % 256 + 256) % 256;
+}
@@ -0,0 +1,18 @@
+int main(void)
+{
+    int a = 5;
+    int b = a++ + ++a * 2;
+    a += 3;
+    a -= 1;
+    a *= 2;
+    a /= 3;
+    a %= 4;
+    b <<= 2;
+    b >>= 1;
+    b &= 127;
+    b |= 1;
+    b ^= 3;
+    --b;
+    b--;
+    return a + b;
+}
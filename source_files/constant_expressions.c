@@ -0,0 +1,13 @@
+int buffer[4 * sizeof(int) - 8];
+long limit = (1L << 40) / 1024 - 1;
+double ratio = 3 / 2 + 0.5;
+int *last = buffer + (sizeof buffer / sizeof(int) - 1);
+
+int main(void)
+{
+    // The right operand of && is never evaluated, so dividing by zero there is fine.
+    int lazy = 0 && 1 / 0;
+
+    *last = 3;
+    return buffer[7] + (limit == 1073741823) + (ratio == 1.5) + lazy;
+}
@@ -0,0 +1,45 @@
+int check(int ok, int bit) { return ok ? 0 : bit; }
+/* A truth value folded into a `_Bool` is stored as one byte, and so does not
+   spill into the elements around it. */
+int stored_bool(void) {
+    _Bool arr[4] = {1, 0, 3, 0};
+    _Bool *p = &arr[1];
+    *p = 42;
+    return arr[0] + arr[1] * 2 + arr[2] * 4 + arr[3] * 8;
+}
+
+int propagated_bool(void) {
+    _Bool arr[4] = {1, 0, 3, 1};
+    _Bool *p = &arr[1];
+    int k = 7;
+    _Bool b = k;
+    *p = b;
+    return arr[1] * 2 + arr[2] * 4 + arr[3] * 8;
+}
+
+int main(void) {
+    int r = 0;
+    r += check(2147483647 + 1 == -2147483647 - 1, 1);
+    r += check(-(-2147483647 - 1) == -2147483647 - 1, 2);
+    r += check(4294967295u + 2u == 1u, 3);
+    r += check(-7 / 2 == -3 && -7 % 2 == -1, 4);
+    r += check(7u / 2u == 3u && 4294967295u % 10u == 5u, 5);
+    r += check((1 << 31) < 0 && (-16 >> 2) == -4 && (4294967295u >> 4) == 268435455u, 6);
+    r += check(18446744073709551615ul / 3ul == 6148914691236517205ul, 7);
+    r += check(-1 < 0 && !(4294967295u < 0u) && -1l < 1l, 8);
+    r += check((long)-5 == -5l && (unsigned long)-1 == 18446744073709551615ul, 9);
+    r += check((int)4294967297l == 1 && (unsigned)-1 == 4294967295u, 10);
+    r += check(1.5 + 2.25 == 3.75 && 1.0 / 3.0 * 3.0 == 1.0, 11);
+    r += check((int)3.99 == 3 && (int)-3.99 == -3 && (unsigned long)1e19 == 10000000000000000000ul, 12);
+    r += check((double)9007199254740993l == 9007199254740992.0, 13);
+    r += check((float)16777217 == 16777216.0f && (double)0.1f != 0.1, 14);
+    r += check(!0.0 && !!-0.0 == 0 && !(0.0 != -0.0), 15);
+    r += check((1 && 0) == 0 && (0 || 5) == 1 && (3 ? 4 : 5) == 4 && (0 ? 4 : 5) == 5, 16);
+    r += check(~0u == 4294967295u && ~0l == -1l && -0.0 == 0.0, 17);
+    double nan = 0.0 / 0.0;
+    r += check(nan != nan && !(1.0 / 0.0 < 0), 18);
+    r += check((_Bool)2 == 1 && (_Bool)0.5 == 1 && (_Bool)0 == 0, 19);
+    r += check((1 << 33 - 2) == (int)2147483648u, 20);
+    r += check(stored_bool() == 7 && propagated_bool() == 14, 21);
+    return r;
+}
@@ -0,0 +1,32 @@
+/* Constants that only reach their uses through local variables and
+   branches, which --propagate-constants folds away completely. */
+
+int counter = 0;
+
+int bump(void) {
+    counter = counter + 1;
+    return counter;
+}
+
+int pick(void) {
+    int a = 4;
+    int b = a > 3 ? a * 10 : bump();
+    int c = b && (a = 7);
+    int d = (c || bump()) + (a == 7 ? 100 : bump());
+    return a + b + c + d;
+}
+
+int mixed(int x) {
+    int limit = 16;
+    int scaled = limit / 4 + (x > limit ? 1 : 0);
+    int wide = limit < 8 ? bump() : limit * 2;
+    return scaled * wide + x;
+}
+
+int main(void) {
+    int h = pick();
+    h = h * 31 + mixed(3);
+    h = h * 31 + mixed(40);
+    h = h * 31 + counter;
+    return h % 256;
+}
@@ -0,0 +1,37 @@
+int g = 10;
+volatile int vol = 3;
+int bump(void) { g = g + 1; return g; }
+int set(int *p, int v) { *p = v; return v; }
+unsigned check_unsigned(void) {
+    int x = -1;
+    unsigned u = x;
+    return u / 2u;
+}
+int main(void) {
+    int a = 5;
+    int b = a;
+    int *p = &a;
+    *p = 7;
+    int c = a;
+    int before = g;
+    bump();
+    int after = g;
+    int x = 1;
+    int y = x;
+    x = 2;
+    int z = a > 6 ? (x = 3) : (x = 4);
+    int w = x;
+    int v1 = vol;
+    int v2 = vol;
+    int q = 4;
+    set(&q, 9);
+    int r = q;
+    int s = b;
+    b = s;
+    s = b;
+    long l = a;
+    int t = (a = 100, b + a);
+    return (b == 5) + (c == 7) * 2 + (before == 10) * 4 + (after == 11) * 8 + (y == 1) * 16
+        + (w == 3) * 32 + (v1 + v2 == 6) * 64 + (r == 9) * 128 + (check_unsigned() == 2147483647u) * 256
+        + (z == 3) * 512 + (l == 7) * 1024 + (t == 105) * 2048 + (s == 5) * 4096 > 8190;
+}
@@ -0,0 +1,32 @@
+int g = 1;
+volatile int vol = 0;
+int counter = 0;
+int read_g(void) { return g; }
+void touch(void) { counter = counter + 1; }
+int through(int *p) { return *p; }
+int main(void) {
+    int dead = 10;
+    dead = 20;
+    int a = 3;
+    int *p = &a;
+    a = 4;
+    int via_pointer = *p;
+    int x = 5;
+    int y = x * 2;
+    y = 7;
+    g = 2;
+    int from_call = read_g();
+    g = 3;
+    vol = 1;
+    vol = 2;
+    int discarded = vol;
+    int b = 8;
+    int passed = through(&b);
+    touch();
+    touch();
+    int arr[3] = {1, 2, 3};
+    int last = arr[2];
+    int c = (a = 11, a + 1);
+    return (via_pointer == 4) + (y == 7) * 2 + (from_call == 2) * 4 + (passed == 8) * 8
+        + (counter == 2) * 16 + (last == 3) * 32 + (c == 12) * 64 + (vol == 2) * 128 + (g == 3) * 256 + dead - 20 == 511;
+}
@@ -0,0 +1,43 @@
+struct counter {
+    int n;
+};
+
+struct entry {
+    struct counter *m;
+};
+
+int *first(int *values)
+{
+    return values;
+}
+
+struct entry *entries(struct entry *list)
+{
+    return list;
+}
+
+int main(void)
+{
+    int a = 2;
+    int b = 3;
+    int *p = &a;
+    struct counter hits = {7};
+    struct entry list[2] = {{0}, {&hits}};
+
+    int chained = a = b = 5;
+    int nested = a > 4 ? b < 5 ? 1 : 2 : 3;
+    int comma = (a++, b++, a + b);
+    int postfix = first(p)[0]++;
+    int member = entries(list)[1].m->n++;
+    int cast = (int)(long)-1 + (unsigned)(b);
+
+    int twice(int value);
+    int called = twice(b);
+
+    return chained + nested + comma + postfix + member + hits.n + cast + called + !a + sizeof *p;
+}
+
+int twice(int value)
+{
+    return value * 2;
+}
@@ -0,0 +1,25 @@
+float average(float a, float b)
+{
+    return (a + b) / 2.0f;
+}
+
+double scale(double x, ...)
+{
+    __builtin_va_list args;
+    __builtin_va_start(args, x);
+
+    double factor = __builtin_va_arg(args, double);
+
+    __builtin_va_end(args);
+    return x * factor;
+}
+
+int main(void)
+{
+    float mean = average(3.5f, 4.5f);
+    unsigned long big = (unsigned long) 1e19;
+
+    // The float argument is promoted to double.
+    double result = scale(mean, 1.25f);
+    return (int) result + (big > 9223372036854775808ul);
+}
@@ -0,0 +1,26 @@
+int add(int a, int b)
+{
+    return a + b;
+}
+
+int multiply(int a, int b)
+{
+    return a * b;
+}
+
+int apply(int (*operation)(int, int), int a, int b)
+{
+    return operation(a, b);
+}
+
+// A function returning a pointer to a function.
+int (*pick(int product))(int, int)
+{
+    return product ? multiply : &add;
+}
+
+int main(void)
+{
+    int (*operation)(int, int) = pick(1);
+    return apply(operation, 6, 7) - (*pick(0))(1, 1) + (operation == multiply);
+}
@@ -0,0 +1,28 @@
+int squares[5] = {0, 1, 4, [4] = 16};
+int grid[2][3] = {{1, 2}, [1][2] = 6};
+int *middle = &squares[2];
+// Without a length, an array is as long as its initializer needs.
+int primes[] = {2, 3, 5, [6] = 17};
+long pairs[][2] = {1, 2, 3};
+long total;
+
+int sum(int *values, int count)
+{
+    int result = 0;
+    result += count > 0 ? values[0] : 0;
+    result += count > 1 ? values[1] : 0;
+    result += count > 2 ? values[2] : 0;
+    return result;
+}
+
+int main(void)
+{
+    // Elements without an initializer are zeroed.
+    int local[4] = {[1] = 3, 5};
+    int counts[] = {[2] = 1, 1};
+
+    total = sum(squares, 3) + sum(grid[1], 3);
+    total += sizeof primes / sizeof primes[0] + sizeof pairs / sizeof pairs[0];
+    total += sizeof counts + primes[6] + pairs[1][0];
+    return total + *middle + local[2] + local[3] + (sizeof grid == 24);
+}
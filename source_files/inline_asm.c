@@ -0,0 +1,32 @@
+unsigned long read_timestamp(void)
+{
+    unsigned int low;
+    unsigned int high;
+    asm volatile("rdtsc" : "=a"(low), "=d"(high));
+    return ((unsigned long)high << 32) | low;
+}
+
+int has_cpuid_leaves(void)
+{
+    unsigned int eax;
+    unsigned int ebx;
+    unsigned int ecx;
+    unsigned int edx;
+    asm volatile("cpuid" : "=a"(eax), "=b"(ebx), "=c"(ecx), "=d"(edx) : "a"(0), "c"(0));
+    return eax > 0;
+}
+
+int main(void)
+{
+    int values[2] = {1, 2};
+    long doubled = 21;
+    int sum;
+
+    asm("addq %0, %0" : "+r"(doubled));
+    asm("addl %[amount], %0" : "+m"(values[1]) : [amount] "i"(3));
+    asm("movl %1, %0\n\taddl %2, %0" : "=&r"(sum) : "r"(values[0]), "m"(values[1]) : "cc");
+    asm("nop");
+
+    unsigned long start = read_timestamp();
+    return (read_timestamp() >= start) + has_cpuid_leaves() + doubled + sum;
+}
@@ -0,0 +1,19 @@
+int first(const int *values)
+{
+    return values[0];
+}
+
+int main(void)
+{
+    const int limit = 10;
+    volatile int counter = 0;
+    int value = 4;
+
+    // Adding qualifiers to the referenced type is an implicit conversion.
+    const int *view = &value;
+    int *const fixed = &value;
+
+    counter++;
+    *fixed += limit;
+    return first(view) + counter;
+}
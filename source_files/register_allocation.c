@@ -0,0 +1,82 @@
+long sum8(long a, long b, long c, long d, long e, long f, long g, long h) { return a + 2*b + 3*c + 4*d + 5*e + 6*f + 7*g + 8*h; }
+double fsum(double a, float b, double c, float d, double e, float f, double g, float h, double i, float j, double k) {
+    return a + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8 + i * 9 + j * 10 + k * 11;
+}
+int divs(int a, int b, int c) {
+    int q = a / b;
+    int r = a % b;
+    int s = c << (b & 7);
+    int t = c >> r;
+    unsigned u = (unsigned) a / (unsigned) c;
+    return q * 1000 + r * 100 + s + t + u;
+}
+long many(long a, long b) {
+    long v1 = a + 1;
+    long v2 = a + 2;
+    long v3 = a + 3;
+    long v4 = a + 4;
+    long v5 = a + 5;
+    long v6 = a + 6;
+    long v7 = a + 7;
+    long v8 = a + 8;
+    long v9 = b + 9;
+    long v10 = b + 10;
+    long v11 = b + 11;
+    long v12 = b + 12;
+    long v13 = b + 13;
+    long v14 = b + 14;
+    long v15 = b + 15;
+    long v16 = b + 16;
+    long w = sum8(v1, v2, v3, v4, v5, v6, v7, v8);
+    return w + v1 * v9 + v2 * v10 + v3 * v11 + v4 * v12 + v5 * v13 + v6 * v14 + v7 * v15 + v8 * v16
+        + sum8(v16, v15, v14, v13, v12, v11, v10, v9);
+}
+double fmany(double a) {
+    double v1 = a + 1;
+    double v2 = a + 2;
+    double v3 = a + 3;
+    double v4 = a + 4;
+    double v5 = a + 5;
+    double v6 = a + 6;
+    double v7 = a + 7;
+    double v8 = a + 8;
+    double v9 = a + 9;
+    double v10 = a + 10;
+    double v11 = a + 11;
+    double v12 = a + 12;
+    double v13 = a + 13;
+    double v14 = a + 14;
+    double v15 = a + 15;
+    double v16 = a + 16;
+    double w = fsum(v1, v2, v3, v4, v5, v6, v7, v8, v9, v10, v11);
+    return w + v1 * v9 + v2 * v10 + v3 * v11 + v4 * v12 + v5 * v13 + v6 * v14 + v7 * v15 + v8 * v16 - v12 / v13;
+}
+int cond(int a, int b) {
+    int x = a > b ? a - b : b - a;
+    int y = (a && b) || x;
+    double d = a;
+    int z = d < 2.5 ? 1 : 0;
+    unsigned long ul = (unsigned long) a * 3000000000u;
+    double big = (double) ul;
+    unsigned long back = (unsigned long) big;
+    return x + y * 10 + z * 100 + (back == ul) * 1000;
+}
+int asmclob(int a) {
+    int b = a + 1;
+    int c = a + 2;
+    int d = a + 3;
+    int out;
+    __asm__ volatile ("movl %1, %0\n\taddl $5, %0\n\tmovl $0, %%ebx\n\tmovl $0, %%ecx\n\tmovl $0, %%r12d" : "=r"(out) : "r"(a) : "rbx", "rcx", "r12");
+    return out + b * 100 + c * 10000 + d;
+}
+unsigned long h = 7;
+void mix(long v) { h = h * 1000003 + (unsigned long) v; }
+int main(void) {
+    mix(many(3, 4));
+    mix((long) (fmany(1.5) * 1000));
+    mix(divs(100, 7, 12)); mix(divs(-100, 7, 3)); mix(divs(7, 3, -5));
+    mix(cond(1, 5)); mix(cond(4, 2));
+    mix((long) (fsum(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11) * 1000));
+    mix(asmclob(7));
+    return (int) (h % 251);
+}
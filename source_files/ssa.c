@@ -0,0 +1,17 @@
+int f(int c, int d) {
+    int r = 1;
+    int s = 2;
+    int t = c && (r = d + 3) && (s = r * 2);
+    int u = d ? (r = r + s) : (s = s - r);
+    int v = (c || (r = s + 7)) + ((r = s) && (s = r + 1));
+    return r * 1000 + s * 100 + t * 10 + u + v;
+}
+int main(void) {
+    int h = 0;
+    h = h * 7 + f(0, 0);
+    h = h * 7 + f(1, 0);
+    h = h * 7 + f(0, 5);
+    h = h * 7 + f(1, 5);
+    h = h * 7 + f(3, -3);
+    return h % 251;
+}
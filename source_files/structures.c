@@ -0,0 +1,74 @@
+struct point {
+    int x;
+    long y;
+};
+
+struct segment {
+    struct point ends[2];
+    _Bool closed;
+    double length;
+};
+
+struct node;
+
+struct list {
+    struct node *head;
+    int count;
+};
+
+struct node {
+    int value;
+    struct node *next;
+};
+
+_Static_assert(sizeof(struct point) == 16, "x is padded to y's alignment");
+_Static_assert(sizeof(struct segment) == 48, "closed is padded to length's alignment");
+_Static_assert(_Alignof(struct segment) == 8);
+
+struct point origin;
+struct point corner = {3, 4};
+struct segment diagonal = {.ends[1] = {3, 4}, .closed = 1, 5.0};
+struct node third = {3, 0};
+struct node second = {2, &third};
+struct list numbers = {&second, 2};
+long *corner_y = &corner.y;
+int *end_x = &diagonal.ends[1].x;
+
+int sum(struct list *list)
+{
+    return list->head->value + list->head->next->value;
+}
+
+struct point *farther(struct point *a, struct point *b)
+{
+    return a->x * a->x + a->y * a->y > b->x * b->x + b->y * b->y ? a : b;
+}
+
+int main(void)
+{
+    struct point p = corner;
+    struct point q = {.x = 1};
+    struct segment s = {p, {5, 12}};
+    struct node first = {1, &second};
+    const struct point fixed = {7, 8};
+
+    q.y = q.x + 10;
+    p.x++;
+    s.ends[0] = q;
+    s.ends[1].y *= 2;
+    first.next->next->value += 10;
+    numbers.head = &first;
+    numbers.count = 3;
+
+    struct box {
+        struct point top_left;
+        int area;
+    };
+    struct box box = {.area = 6, .top_left.y = 2, .top_left.x = 1};
+
+    return (p.x == 4) + (q.y == 11) + (s.ends[0].y == 11) + (s.ends[1].y == 24) +
+           (sum(&numbers) == 3) + (third.value == 13) + (*corner_y == 4) + (*end_x == 3) +
+           (farther(&p, &q) == &q) + (origin.x == 0) + (diagonal.closed == 1) +
+           (fixed.y == 8) + (box.top_left.y + box.area == 8) + (sizeof s.ends[1] == 16) +
+           ((1 ? p : q).x == 4);
+}
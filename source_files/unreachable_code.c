@@ -0,0 +1,14 @@
+int g = 1;
+volatile int v = 2;
+int side(int x) { g = g + x; return x; }
+void nothing(void) { }
+int pick(int a) { return a ? side(10) : side(100); }
+int main(void) {
+    int r = 0 && side(5);
+    int s = 1 || side(7);
+    int t = (v && 1) ? 3 : 4;
+    int u = 1 ? side(2) : side(1000);
+    nothing();
+    return r + s + t + u + g + pick(0) + pick(1) + (0 ? 7 : 8);
+    g = 99;
+}
@@ -0,0 +1,33 @@
+long sum(int n, int *values)
+{
+    return n == 0 ? 0 : values[n - 1] + sum(n - 1, values);
+}
+
+int grid(int rows, int columns)
+{
+    int cells[rows][columns];
+    int (*row)[columns] = cells;
+
+    cells[0][0] = 1;
+    row[rows - 1][columns - 1] = 2;
+    return cells[rows - 1][columns - 1] + sizeof cells[0] / sizeof(int) + (row + rows - row);
+}
+
+int reuse(int n)
+{
+    unsigned long first = ({ int a[n]; a[0] = 1; (unsigned long)a; });
+    unsigned long second = ({ int b[n]; b[0] = 2; (unsigned long)b; });
+    return first == second;
+}
+
+int main(void)
+{
+    int n = 4;
+    int values[n];
+
+    values[0] = 1;
+    values[1] = 2;
+    values[2] = 3;
+    values[3] = sizeof values;
+    return sum(n, values) + grid(3, 5) + reuse(n);
+}
@@ -0,0 +1,25 @@
+int next(__builtin_va_list args)
+{
+    return __builtin_va_arg(args, int);
+}
+
+long sum(int count, ...)
+{
+    __builtin_va_list args;
+    __builtin_va_start(args, count);
+
+    long total = 0;
+    total = total + __builtin_va_arg(args, int);
+    total = total + __builtin_va_arg(args, long);
+    total = total + *__builtin_va_arg(args, int *);
+    total = total + next(args);
+
+    __builtin_va_end(args);
+    return total;
+}
+
+int main(void)
+{
+    int seven = 7;
+    return sum(4, 1, 2l, &seven, 4);
+}
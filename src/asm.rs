@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy)]
+use crate::symbols::StaticInit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Register {
     RAX,
     RBX,
@@ -8,10 +10,111 @@ pub enum Register {
     RDI,
     RSP,
     RBP,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
     EAX,
+    EBX,
+    ECX,
+    EDX,
+    ESI,
+    EDI,
+    R8D,
+    R9D,
+    R10D,
+    R11D,
+    R12D,
+    R13D,
+    R14D,
+    R15D,
+    AL,
+    BL,
+    CL,
+    DL,
+    SIL,
+    DIL,
+    R8B,
+    R9B,
+    R10B,
+    R11B,
+    R12B,
+    R13B,
+    R14B,
+    R15B,
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    XMM8,
+    XMM9,
+    XMM10,
+    XMM11,
+    XMM12,
+    XMM13,
+    XMM14,
+    XMM15,
+}
+
+impl Register {
+    /// The part of this register that holds a value of type `ty`, e.g. `%eax`
+    /// for `%rax` and a longword.
+    pub fn sized(self, ty: AsmType) -> Register {
+        use Register::*;
+
+        // Vector registers hold a float or a double under the same name.
+        if self.is_xmm() && ty.is_floating() {
+            return self;
+        }
+
+        let family = match self {
+            RAX | EAX | AL => [RAX, EAX, AL],
+            RBX | EBX | BL => [RBX, EBX, BL],
+            RCX | ECX | CL => [RCX, ECX, CL],
+            RDX | EDX | DL => [RDX, EDX, DL],
+            RSI | ESI | SIL => [RSI, ESI, SIL],
+            RDI | EDI | DIL => [RDI, EDI, DIL],
+            R8 | R8D | R8B => [R8, R8D, R8B],
+            R9 | R9D | R9B => [R9, R9D, R9B],
+            R10 | R10D | R10B => [R10, R10D, R10B],
+            R11 | R11D | R11B => [R11, R11D, R11B],
+            R12 | R12D | R12B => [R12, R12D, R12B],
+            R13 | R13D | R13B => [R13, R13D, R13B],
+            R14 | R14D | R14B => [R14, R14D, R14B],
+            R15 | R15D | R15B => [R15, R15D, R15B],
+            _ => panic!("No {:?} sized version of {:?}", ty, self),
+        };
+
+        match ty {
+            AsmType::Quadword => family[0],
+            AsmType::Longword => family[1],
+            AsmType::Byte => family[2],
+            _ => panic!("No {:?} sized version of {:?}", ty, self),
+        }
+    }
+
+    /// Whether a function has to preserve this 64-bit register for its caller.
+    pub fn is_callee_saved(self) -> bool {
+        use Register::*;
+
+        matches!(self, RBX | R12 | R13 | R14 | R15)
+    }
+
+    pub fn is_xmm(self) -> bool {
+        // The vector registers come last.
+        self >= Register::XMM0
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AsmType {
     /// 1 byte (8 bits)
     Byte,
@@ -22,6 +125,9 @@ pub enum AsmType {
     /// 8 bytes (64 bits)
     Quadword,
 
+    /// 4 bytes (32 bits), a single-precision float
+    Single,
+
     /// 8 bytes (64 bits), typically used for floating-point doubles
     Double,
 
@@ -31,11 +137,52 @@ pub enum AsmType {
     ByteArray { size: i64, alignment: i64 },
 }
 
-#[derive(Debug, Clone)]
+impl AsmType {
+    /// Types that live in vector registers and use the SSE instructions.
+    pub fn is_floating(&self) -> bool {
+        matches!(self, AsmType::Single | AsmType::Double)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operand {
     Imm(i64),
     Reg(Register),
-    Mem(String),
+    /// A TACKY variable that has not been assigned a stack slot yet.
+    Pseudo(String),
+    /// The part of an aggregate TACKY variable `offset` bytes in, before the
+    /// variable is assigned a stack slot.
+    PseudoMem(String, i64),
+    /// A stack slot, addressed as an offset from `%rbp`.
+    Stack(i64),
+    /// The memory at an offset from the address in a register.
+    Memory(Register, i64),
+    /// The memory at `base + index * scale`.
+    Indexed {
+        base: Register,
+        index: Register,
+        scale: i64,
+    },
+    /// A named object in the data sections, addressed relative to `%rip`.
+    Data(String),
+}
+
+/// The flags tested by conditional jumps and `set`. `L`/`G` compare signed
+/// values and `B`/`A` unsigned ones, as well as floating point values. `P`
+/// is set when a floating point comparison is unordered, i.e. involves a NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CondCode {
+    E,
+    NE,
+    L,
+    LE,
+    G,
+    GE,
+    B,
+    BE,
+    A,
+    AE,
+    P,
 }
 
 #[derive(Debug, Clone)]
@@ -53,16 +200,359 @@ pub enum Instruction {
         src: Operand,
         dst: Operand,
     },
+    Add {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    Sub {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    Imul {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    And {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    Or {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    Xor {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Shift left. The count must be an immediate or `%cl`.
+    Sal {
+        ty: AsmType,
+        count: Operand,
+        dst: Operand,
+    },
+    /// Arithmetic (sign preserving) shift right. The count must be an immediate or `%cl`.
+    Sar {
+        ty: AsmType,
+        count: Operand,
+        dst: Operand,
+    },
+    /// Logical (zero filling) shift right. The count must be an immediate or `%cl`.
+    Shr {
+        ty: AsmType,
+        count: Operand,
+        dst: Operand,
+    },
+    /// Signed division of `%edx:%eax` by `src`, quotient in `%eax`, remainder in `%edx`.
+    Idiv {
+        ty: AsmType,
+        src: Operand,
+    },
+    /// Unsigned division of `%edx:%eax` by `src`, quotient in `%eax`, remainder in `%edx`.
+    Div {
+        ty: AsmType,
+        src: Operand,
+    },
+    /// Sign extend `%eax` into `%edx` ahead of an `Idiv`.
+    Cdq {
+        ty: AsmType,
+    },
+    /// Sign extend a longword into a quadword.
+    Movsx {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Zero extend `src` of type `src_ty` into the wider `dst_ty`. Extending
+    /// a longword is rewritten into plain moves before emission.
+    MovZeroExtend {
+        src_ty: AsmType,
+        dst_ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Floating point multiplication.
+    MulFloat {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Floating point division of `dst` by `src`.
+    DivFloat {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Convert the signed integer `src` of type `src_ty` to the floating point
+    /// type `dst_ty`.
+    Cvtsi2 {
+        src_ty: AsmType,
+        dst_ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Convert the floating point `src` of type `src_ty` to the signed integer
+    /// type `dst_ty`, rounding toward zero.
+    Cvtt2si {
+        src_ty: AsmType,
+        dst_ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Convert a float to a double.
+    Cvtss2sd {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Convert a double to a float.
+    Cvtsd2ss {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Set flags from `dst - src`. Floating point values set them like
+    /// unsigned integers.
+    Cmp {
+        ty: AsmType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Set the byte `dst` to 1 if the condition holds and 0 otherwise.
+    SetCC {
+        cond: CondCode,
+        dst: Operand,
+    },
+    Jmp(String),
+    JmpCC {
+        cond: CondCode,
+        target: String,
+    },
+    Label(String),
+    /// Load the address of the memory operand `src` into `dst`.
+    Lea {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Push a quadword onto the stack.
+    Push(Operand),
+    /// Call a function, through the PLT when it is not defined in this file.
+    /// The call reads the argument registers in `uses`.
+    Call {
+        name: String,
+        plt: bool,
+        uses: Vec<Register>,
+    },
+    /// Call the function whose address is in `target`, reading the argument
+    /// registers in `uses`.
+    CallIndirect {
+        target: Operand,
+        uses: Vec<Register>,
+    },
+    /// The assembly of an `asm` statement. The template's references to
+    /// operands are filled in from `operands`, numbered as in the statement
+    /// and possibly named by `names`, when it is emitted. The code reads the
+    /// registers in `uses` and overwrites those in `clobbers`.
+    InlineAsm {
+        template: String,
+        operands: Vec<Operand>,
+        names: Vec<Option<String>>,
+        uses: Vec<Register>,
+        clobbers: Vec<Register>,
+    },
+    AllocateStack(i64),
+    DeallocateStack(i64),
     Ret,
 }
 
+impl Instruction {
+    /// The operands of the instruction, each with the type it accesses the
+    /// operand as. The operands of inline assembly are not among them, since
+    /// they are already in place when it is generated.
+    pub fn operands(&self) -> Vec<(&Operand, AsmType)> {
+        match self {
+            Instruction::Neg { ty, dst } | Instruction::Not { ty, dst } => vec![(dst, *ty)],
+            Instruction::SetCC { dst, .. } => vec![(dst, AsmType::Byte)],
+            Instruction::Push(src) | Instruction::CallIndirect { target: src, .. } => {
+                vec![(src, AsmType::Quadword)]
+            }
+            Instruction::Idiv { ty, src } | Instruction::Div { ty, src } => vec![(src, *ty)],
+            Instruction::Mov { ty, src, dst }
+            | Instruction::Add { ty, src, dst }
+            | Instruction::Sub { ty, src, dst }
+            | Instruction::Imul { ty, src, dst }
+            | Instruction::And { ty, src, dst }
+            | Instruction::Or { ty, src, dst }
+            | Instruction::Xor { ty, src, dst }
+            | Instruction::MulFloat { ty, src, dst }
+            | Instruction::DivFloat { ty, src, dst }
+            | Instruction::Cmp { ty, src, dst }
+            | Instruction::Sal {
+                ty,
+                count: src,
+                dst,
+            }
+            | Instruction::Sar {
+                ty,
+                count: src,
+                dst,
+            }
+            | Instruction::Shr {
+                ty,
+                count: src,
+                dst,
+            } => vec![(src, *ty), (dst, *ty)],
+            Instruction::MovZeroExtend {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            }
+            | Instruction::Cvtsi2 {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            }
+            | Instruction::Cvtt2si {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } => vec![(src, *src_ty), (dst, *dst_ty)],
+            Instruction::Movsx { src, dst } => {
+                vec![(src, AsmType::Longword), (dst, AsmType::Quadword)]
+            }
+            Instruction::Lea { src, dst } => {
+                vec![(src, AsmType::Quadword), (dst, AsmType::Quadword)]
+            }
+            Instruction::Cvtss2sd { src, dst } => {
+                vec![(src, AsmType::Single), (dst, AsmType::Double)]
+            }
+            Instruction::Cvtsd2ss { src, dst } => {
+                vec![(src, AsmType::Double), (dst, AsmType::Single)]
+            }
+            Instruction::Cdq { .. }
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC { .. }
+            | Instruction::Label(_)
+            | Instruction::Call { .. }
+            | Instruction::InlineAsm { .. }
+            | Instruction::AllocateStack(_)
+            | Instruction::DeallocateStack(_)
+            | Instruction::Ret => Vec::new(),
+        }
+    }
+
+    /// The operands of the instruction, as for `operands`.
+    pub fn operands_mut(&mut self) -> Vec<(&mut Operand, AsmType)> {
+        match self {
+            Instruction::Neg { ty, dst } | Instruction::Not { ty, dst } => vec![(dst, *ty)],
+            Instruction::SetCC { dst, .. } => vec![(dst, AsmType::Byte)],
+            Instruction::Push(src) | Instruction::CallIndirect { target: src, .. } => {
+                vec![(src, AsmType::Quadword)]
+            }
+            Instruction::Idiv { ty, src } | Instruction::Div { ty, src } => vec![(src, *ty)],
+            Instruction::Mov { ty, src, dst }
+            | Instruction::Add { ty, src, dst }
+            | Instruction::Sub { ty, src, dst }
+            | Instruction::Imul { ty, src, dst }
+            | Instruction::And { ty, src, dst }
+            | Instruction::Or { ty, src, dst }
+            | Instruction::Xor { ty, src, dst }
+            | Instruction::MulFloat { ty, src, dst }
+            | Instruction::DivFloat { ty, src, dst }
+            | Instruction::Cmp { ty, src, dst }
+            | Instruction::Sal {
+                ty,
+                count: src,
+                dst,
+            }
+            | Instruction::Sar {
+                ty,
+                count: src,
+                dst,
+            }
+            | Instruction::Shr {
+                ty,
+                count: src,
+                dst,
+            } => vec![(src, *ty), (dst, *ty)],
+            Instruction::MovZeroExtend {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            }
+            | Instruction::Cvtsi2 {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            }
+            | Instruction::Cvtt2si {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } => vec![(src, *src_ty), (dst, *dst_ty)],
+            Instruction::Movsx { src, dst } => {
+                vec![(src, AsmType::Longword), (dst, AsmType::Quadword)]
+            }
+            Instruction::Lea { src, dst } => {
+                vec![(src, AsmType::Quadword), (dst, AsmType::Quadword)]
+            }
+            Instruction::Cvtss2sd { src, dst } => {
+                vec![(src, AsmType::Single), (dst, AsmType::Double)]
+            }
+            Instruction::Cvtsd2ss { src, dst } => {
+                vec![(src, AsmType::Double), (dst, AsmType::Single)]
+            }
+            Instruction::Cdq { .. }
+            | Instruction::Jmp(_)
+            | Instruction::JmpCC { .. }
+            | Instruction::Label(_)
+            | Instruction::Call { .. }
+            | Instruction::InlineAsm { .. }
+            | Instruction::AllocateStack(_)
+            | Instruction::DeallocateStack(_)
+            | Instruction::Ret => Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TopLevel {
     Function {
         name: String,
         global: bool,
+        /// The alignment asked for with the `aligned` attribute, if any.
+        alignment: Option<i64>,
+        /// The section asked for with the `section` attribute, instead of
+        /// `.text`.
+        section: Option<String>,
         instructions: Vec<Instruction>,
     },
+    /// A read-only constant that instructions cannot take as an immediate,
+    /// such as a floating point value.
+    StaticConstant {
+        name: String,
+        alignment: i64,
+        ty: AsmType,
+        /// The constant's bit pattern.
+        bits: i64,
+    },
+    /// An object with static storage duration, in `.data`, or in `.bss` when
+    /// it starts out zeroed, unless it asks for another section.
+    StaticVariable {
+        name: String,
+        global: bool,
+        alignment: i64,
+        section: Option<String>,
+        init: Vec<StaticInit>,
+    },
 }
 
 #[derive(Debug, Clone)]
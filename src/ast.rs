@@ -18,18 +18,110 @@ pub enum StorageClass {
 #[derive(Debug, Clone)]
 pub enum Statement {
     ReturnStatement(Option<Expression>),
+    ExpressionStatement(Expression),
+    NullStatement,
+    Asm(AsmStatement),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A GNU extended `asm` statement: `asm ("template" : outputs : inputs :
+/// clobbers)`. A basic `asm ("template")` is parsed into one without
+/// operands whose template has its `%` signs escaped.
+///
+/// `volatile` is accepted but not recorded, since no `asm` statement is ever
+/// removed or moved.
+#[derive(Debug, Clone)]
+pub struct AsmStatement {
+    pub template: String,
+    pub outputs: Vec<AsmOperand>,
+    pub inputs: Vec<AsmOperand>,
+    pub clobbers: Vec<String>,
+}
+
+/// `[name] "constraint" (expr)`, an operand of an `asm` statement.
+#[derive(Debug, Clone)]
+pub struct AsmOperand {
+    pub name: Option<String>,
+    pub constraint: String,
+    pub expr: Expression,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOperator {
     Negate,
     Complement,
+    Not,
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+impl BinaryOperator {
+    /// Operators that compare their operands and produce an `int` truth value.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Equal
+                | BinaryOperator::NotEqual
+                | BinaryOperator::LessThan
+                | BinaryOperator::LessOrEqual
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterOrEqual
+        )
+    }
+
+    /// `%`, the bitwise operators and the shifts, which need integer operands.
+    pub fn requires_integers(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Remainder
+                | BinaryOperator::BitwiseAnd
+                | BinaryOperator::BitwiseOr
+                | BinaryOperator::BitwiseXor
+                | BinaryOperator::ShiftLeft
+                | BinaryOperator::ShiftRight
+        )
+    }
+
+    /// `&&` and `||`, which only evaluate their right operand when they have to.
+    pub fn is_logical(&self) -> bool {
+        matches!(self, BinaryOperator::And | BinaryOperator::Or)
+    }
+}
+
+/// The direction of a `++` or `--` operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncDecOperator {
+    Increment,
+    Decrement,
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     ConstInt(i32),
     ConstLong(i64),
+    ConstUInt(u32),
+    ConstULong(u64),
+    ConstFloat(f32),
     ConstDouble(f64),
     StringConstant(String),
     Grouping(Box<Expression>),
@@ -42,6 +134,98 @@ pub enum Expression {
         operator: UnaryOperator,
         expr: Box<Expression>,
     },
+    Var(Identifier),
+    /// Conversion to `typ`. Inserted by the type checker wherever C performs an
+    /// implicit conversion.
+    Cast {
+        typ: types::T,
+        expr: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Assignment {
+        lvalue: Box<Expression>,
+        value: Box<Expression>,
+    },
+    /// `lvalue op= value`, where the lvalue is evaluated only once.
+    CompoundAssignment {
+        operator: BinaryOperator,
+        lvalue: Box<Expression>,
+        value: Box<Expression>,
+    },
+    /// `++expr` or `--expr`, which evaluates to the updated value.
+    PrefixIncDec {
+        operator: IncDecOperator,
+        expr: Box<Expression>,
+    },
+    /// `expr++` or `expr--`, which evaluates to the original value.
+    PostfixIncDec {
+        operator: IncDecOperator,
+        expr: Box<Expression>,
+    },
+    /// `condition ? then : otherwise`
+    Conditional {
+        condition: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Box<Expression>,
+    },
+    /// `left, right`: evaluates `left` for its side effects, then `right`.
+    Comma {
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    FunctionCall {
+        callee: Box<Expression>,
+        args: Vec<Expression>,
+    },
+    AddressOf(Box<Expression>),
+    Dereference(Box<Expression>),
+    /// `expr[index]`. After type checking `expr` is always the pointer operand.
+    Subscript {
+        expr: Box<Expression>,
+        index: Box<Expression>,
+    },
+    /// `expr.member`
+    Dot {
+        expr: Box<Expression>,
+        member: String,
+    },
+    /// `expr->member`
+    Arrow {
+        expr: Box<Expression>,
+        member: String,
+    },
+    SizeOfExpr(Box<Expression>),
+    SizeOfType(types::T),
+    /// `_Alignof(typ)`
+    AlignOfType(types::T),
+    /// `__builtin_va_start(ap, last)`. The second argument only names the last
+    /// named parameter, which the compiler already knows, so it is not kept.
+    VaStart(Box<Expression>),
+    /// `__builtin_va_arg(ap, typ)`
+    VaArg {
+        ap: Box<Expression>,
+        typ: types::T,
+    },
+    /// `__builtin_va_end(ap)`
+    VaEnd(Box<Expression>),
+    /// `({ ... })`, a GNU statement expression: runs the block and evaluates
+    /// to the value of its last statement if that is an expression
+    /// statement, or to nothing otherwise.
+    StatementExpression(Block),
+}
+
+impl Expression {
+    /// Strips any redundant parentheses, so `(x) = 1` is treated like `x = 1`.
+    pub fn ungrouped(&self) -> &Expression {
+        match self {
+            Expression::Grouping(inner) => inner.ungrouped(),
+            _ => self,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +240,17 @@ pub enum BlockItem {
 #[derive(Debug, Clone)]
 pub struct Block(pub Vec<BlockItem>);
 
+impl Block {
+    /// The value of the block as a statement expression: its last item, if
+    /// that is an expression statement.
+    pub fn result(&self) -> Option<&Expression> {
+        match self.0.last() {
+            Some(BlockItem::Stmt(Statement::ExpressionStatement(expr))) => Some(expr),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FunctionParam {
     pub typ: types::T,
@@ -67,19 +262,110 @@ pub struct FunctionDeclaration {
     pub name: String,
     pub return_type: types::T,
     pub params: Vec<FunctionParam>,
+    /// Whether the parameter list ends in `...`.
+    pub variadic: bool,
     pub body: Option<Block>,
     pub storage_class: Option<StorageClass>,
+    pub attributes: Attributes,
+}
+
+/// What the GNU `__attribute__((...))` lists on a declaration ask for.
+/// Attributes that have no effect here, such as `unused`, are accepted and
+/// dropped by the parser.
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    /// `noreturn`, or `_Noreturn`: the function never returns to its caller.
+    pub noreturn: bool,
+    /// `aligned(N)`: the alignment in bytes the object or function needs at
+    /// least. Unlike `_Alignas`, a smaller alignment than the type's own is
+    /// silently ignored.
+    pub aligned: Option<i64>,
+    /// `section("name")`: the section the object or function is placed in.
+    pub section: Option<String>,
+    /// `packed`: the members of a structure, or the one member it is given
+    /// to, need no alignment beyond what `aligned` or `_Alignas` ask for.
+    pub packed: bool,
+}
+
+/// Picks out the element or member of an aggregate that an item of a brace
+/// enclosed initializer list initializes, as in `[2] = 1` or `.x = 1`.
+#[derive(Debug, Clone)]
+pub enum Designator {
+    Index(Expression),
+    Member(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Initializer {
+    Single(Expression),
+    /// `{ ... }`, where each item may start with designators. After type
+    /// checking every element of an array has exactly one item, in order and
+    /// without designators.
+    Compound(Vec<(Vec<Designator>, Initializer)>),
+    /// An object of the given type that the initializer leaves out, which is
+    /// zeroed. Inserted by the type checker.
+    Zero(types::T),
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub typ: types::T,
+    pub init: Option<Initializer>,
+    pub storage_class: Option<StorageClass>,
+    /// The alignment in bytes requested with `_Alignas`, if any.
+    pub alignment: Option<i64>,
+    pub attributes: Attributes,
+    /// The length of each variable length array in `typ`, along with the
+    /// variable its type refers to it by. Each is evaluated and stored when
+    /// the declaration is reached.
+    pub lengths: Vec<(String, Expression)>,
+    /// Whether the length of the array declared was left out, as in
+    /// `int a[] = {1, 2}`. `typ` is then its element type until the type
+    /// checker counts the elements the initializer sets.
+    pub length_omitted: bool,
+}
+
+/// `struct tag { members };`, which defines a structure type, or `struct
+/// tag;`, which declares one to be defined later.
+#[derive(Debug, Clone)]
+pub struct StructDeclaration {
+    pub tag: String,
+    /// The members in declaration order, or `None` if the declaration does
+    /// not define the structure.
+    pub members: Option<Vec<MemberDeclaration>>,
+    /// The attributes given along with the members, as in `struct
+    /// __attribute__((packed)) tag { members }`.
+    pub attributes: Attributes,
 }
 
+/// A member of a structure, or with a width a bit-field, as in `unsigned
+/// flags : 3`. A bit-field may have no name, in which case it only takes up
+/// space.
 #[derive(Debug, Clone)]
-pub struct VariableDeclaration {}
+pub struct MemberDeclaration {
+    pub name: String,
+    pub typ: types::T,
+    /// The width of a bit-field in bits.
+    pub width: Option<i64>,
+    /// The alignment in bytes requested with `_Alignas`, if any.
+    pub alignment: Option<i64>,
+    pub attributes: Attributes,
+}
 
+/// `_Static_assert(condition, message)`: compilation fails with `message`
+/// unless the integer constant expression `condition` is nonzero. The
+/// message may be left out.
 #[derive(Debug, Clone)]
-pub struct StructDeclaration {}
+pub struct StaticAssertion {
+    pub condition: Expression,
+    pub message: Option<String>,
+}
 
 #[derive(Debug, Clone)]
 pub enum Declaration {
     FunDecl(FunctionDeclaration),
     VarDecl(VariableDeclaration),
     StructDecl(StructDeclaration),
+    StaticAssert(StaticAssertion),
 }
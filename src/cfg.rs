@@ -0,0 +1,244 @@
+//! The control flow graph of a function: its instructions, either TACKY or
+//! assembly, split into basic blocks, with an edge from each block to every
+//! block that can run right after it.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{asm, ir};
+
+/// What an instruction does to the flow of control, which is all the graph
+/// needs to know about it.
+pub enum Flow<'a> {
+    Label(&'a str),
+    Jump(&'a str),
+    ConditionalJump(&'a str),
+    Return,
+    /// Continuing with the next instruction.
+    Next,
+}
+
+/// An instruction that a control flow graph can be built from.
+pub trait FlowInstruction {
+    fn flow(&self) -> Flow<'_>;
+}
+
+impl FlowInstruction for ir::Instruction {
+    fn flow(&self) -> Flow<'_> {
+        match self {
+            ir::Instruction::Label(label) => Flow::Label(label),
+            ir::Instruction::Jump(target) => Flow::Jump(target),
+            ir::Instruction::JumpIfZero { target, .. }
+            | ir::Instruction::JumpIfNotZero { target, .. } => Flow::ConditionalJump(target),
+            ir::Instruction::Return(_) => Flow::Return,
+            _ => Flow::Next,
+        }
+    }
+}
+
+impl FlowInstruction for asm::Instruction {
+    fn flow(&self) -> Flow<'_> {
+        match self {
+            asm::Instruction::Label(label) => Flow::Label(label),
+            asm::Instruction::Jmp(target) => Flow::Jump(target),
+            asm::Instruction::JmpCC { target, .. } => Flow::ConditionalJump(target),
+            asm::Instruction::Ret => Flow::Return,
+            _ => Flow::Next,
+        }
+    }
+}
+
+/// A node of the graph. `Entry` and `Exit` stand for the start and the end
+/// of the function and have no instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeId {
+    Entry,
+    Block(usize),
+    Exit,
+}
+
+/// A run of instructions that is only ever entered at the top and left at
+/// the bottom. Only the first instruction can be a label and only the last
+/// can jump or return.
+#[derive(Debug, Clone)]
+pub struct BasicBlock<I> {
+    pub instructions: Vec<I>,
+    pub predecessors: Vec<NodeId>,
+    pub successors: Vec<NodeId>,
+}
+
+/// The graph of a function's TACKY, unless it is given assembly.
+#[derive(Debug, Clone)]
+pub struct Cfg<I = ir::Instruction> {
+    /// The blocks, keyed by their position in the function. Falling off the
+    /// end of a block continues with the next one.
+    pub blocks: BTreeMap<usize, BasicBlock<I>>,
+    pub entry_successors: Vec<NodeId>,
+    pub exit_predecessors: Vec<NodeId>,
+}
+
+impl<I: FlowInstruction> Cfg<I> {
+    pub fn new(instructions: Vec<I>) -> Cfg<I> {
+        let mut partitions: Vec<Vec<I>> = Vec::new();
+        let mut current = Vec::new();
+        for instr in instructions {
+            if matches!(instr.flow(), Flow::Label(_)) && !current.is_empty() {
+                partitions.push(std::mem::take(&mut current));
+            }
+            let ends_block = matches!(
+                instr.flow(),
+                Flow::Jump(_) | Flow::ConditionalJump(_) | Flow::Return
+            );
+            current.push(instr);
+            if ends_block {
+                partitions.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            partitions.push(current);
+        }
+
+        let labels: HashMap<String, usize> = partitions
+            .iter()
+            .enumerate()
+            .filter_map(|(id, instructions)| match instructions.first()?.flow() {
+                Flow::Label(label) => Some((label.to_string(), id)),
+                _ => None,
+            })
+            .collect();
+
+        let count = partitions.len();
+        let mut cfg = Cfg {
+            blocks: BTreeMap::new(),
+            entry_successors: Vec::new(),
+            exit_predecessors: Vec::new(),
+        };
+        for (id, instructions) in partitions.into_iter().enumerate() {
+            cfg.blocks.insert(
+                id,
+                BasicBlock {
+                    instructions,
+                    predecessors: Vec::new(),
+                    successors: Vec::new(),
+                },
+            );
+        }
+
+        let first = if count == 0 {
+            NodeId::Exit
+        } else {
+            NodeId::Block(0)
+        };
+        cfg.add_edge(NodeId::Entry, first);
+
+        for id in 0..count {
+            let next = if id + 1 < count {
+                NodeId::Block(id + 1)
+            } else {
+                NodeId::Exit
+            };
+            let target = |label: &str| NodeId::Block(labels[label]);
+
+            let successors = match cfg.blocks[&id].instructions.last().map(I::flow) {
+                Some(Flow::Return) => vec![NodeId::Exit],
+                Some(Flow::Jump(label)) => vec![target(label)],
+                Some(Flow::ConditionalJump(label)) => vec![target(label), next],
+                _ => vec![next],
+            };
+            for successor in successors {
+                cfg.add_edge(NodeId::Block(id), successor);
+            }
+        }
+
+        cfg
+    }
+
+    /// The instructions of the remaining blocks, in order.
+    pub fn into_instructions(self) -> Vec<I> {
+        self.blocks
+            .into_values()
+            .flat_map(|block| block.instructions)
+            .collect()
+    }
+
+    pub fn successors(&self, node: NodeId) -> &[NodeId] {
+        match node {
+            NodeId::Entry => &self.entry_successors,
+            NodeId::Block(id) => &self.blocks[&id].successors,
+            NodeId::Exit => &[],
+        }
+    }
+
+    pub fn predecessors(&self, node: NodeId) -> &[NodeId] {
+        match node {
+            NodeId::Entry => &[],
+            NodeId::Block(id) => &self.blocks[&id].predecessors,
+            NodeId::Exit => &self.exit_predecessors,
+        }
+    }
+
+    fn add_edge(&mut self, from: NodeId, to: NodeId) {
+        let successors = match from {
+            NodeId::Entry => &mut self.entry_successors,
+            NodeId::Block(id) => &mut self.blocks.get_mut(&id).unwrap().successors,
+            NodeId::Exit => panic!("the exit has no successors"),
+        };
+        // A conditional jump to the next block has only one successor.
+        if successors.contains(&to) {
+            return;
+        }
+        successors.push(to);
+
+        let predecessors = match to {
+            NodeId::Entry => panic!("the entry has no predecessors"),
+            NodeId::Block(id) => &mut self.blocks.get_mut(&id).unwrap().predecessors,
+            NodeId::Exit => &mut self.exit_predecessors,
+        };
+        predecessors.push(from);
+    }
+
+    /// Removes the edge from one node to another, as when a conditional jump
+    /// turns out to always go the same way.
+    pub fn remove_edge(&mut self, from: NodeId, to: NodeId) {
+        let successors = match from {
+            NodeId::Entry => &mut self.entry_successors,
+            NodeId::Block(id) => &mut self.blocks.get_mut(&id).unwrap().successors,
+            NodeId::Exit => panic!("the exit has no successors"),
+        };
+        successors.retain(|s| *s != to);
+
+        let predecessors = match to {
+            NodeId::Entry => panic!("the entry has no predecessors"),
+            NodeId::Block(id) => &mut self.blocks.get_mut(&id).unwrap().predecessors,
+            NodeId::Exit => &mut self.exit_predecessors,
+        };
+        predecessors.retain(|p| *p != from);
+    }
+
+    /// Removes a block along with every edge into or out of it.
+    pub fn remove_block(&mut self, id: usize) {
+        let node = NodeId::Block(id);
+        let block = self
+            .blocks
+            .remove(&id)
+            .unwrap_or_else(|| panic!("block {id} is not in the graph"));
+
+        for successor in block.successors {
+            let predecessors = match successor {
+                NodeId::Block(other) if other == id => continue,
+                NodeId::Block(other) => &mut self.blocks.get_mut(&other).unwrap().predecessors,
+                NodeId::Exit => &mut self.exit_predecessors,
+                NodeId::Entry => unreachable!(),
+            };
+            predecessors.retain(|p| *p != node);
+        }
+        for predecessor in block.predecessors {
+            let successors = match predecessor {
+                NodeId::Block(other) if other == id => continue,
+                NodeId::Block(other) => &mut self.blocks.get_mut(&other).unwrap().successors,
+                NodeId::Entry => &mut self.entry_successors,
+                NodeId::Exit => unreachable!(),
+            };
+            successors.retain(|s| *s != node);
+        }
+    }
+}
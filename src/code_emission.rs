@@ -1,6 +1,8 @@
 use crate::asm::{self, Instruction, TopLevel};
+use crate::inline_asm::{self, Modifier};
+use crate::symbols::StaticInit;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct CodeEmitter;
 
 impl CodeEmitter {
@@ -16,21 +18,38 @@ impl CodeEmitter {
                 TopLevel::Function {
                     name,
                     global,
+                    alignment,
+                    section,
                     instructions,
                 } => self.emit_function(
                     name.to_owned(),
                     *global,
-                    instructions.iter().cloned().collect(),
+                    *alignment,
+                    section.as_deref(),
+                    instructions.to_vec(),
                 ),
+                TopLevel::StaticConstant {
+                    name,
+                    alignment,
+                    ty,
+                    bits,
+                } => self.emit_static_constant(name, *alignment, *ty, *bits),
+                TopLevel::StaticVariable {
+                    name,
+                    global,
+                    alignment,
+                    section,
+                    init,
+                } => self.emit_static_variable(name, *global, *alignment, section.as_deref(), init),
             };
 
             output.push_str(&result);
         }
 
         let executable_stack_section = r#".section .note.GNU-stack,"",@progbits"#;
-        output.push_str("\n");
+        output.push('\n');
         output.push_str(executable_stack_section);
-        output.push_str("\n");
+        output.push('\n');
 
         output
     }
@@ -39,10 +58,20 @@ impl CodeEmitter {
         &mut self,
         name: String,
         global: bool,
+        alignment: Option<i64>,
+        section: Option<&str>,
         instructions: Vec<Instruction>,
     ) -> String {
         let mut output = String::new();
 
+        match section {
+            Some(section) => output.push_str(&format!(".section {section},\"ax\",@progbits\n")),
+            None => output.push_str(".text\n"),
+        }
+        if let Some(alignment) = alignment {
+            output.push_str(&format!(".balign {alignment}\n"));
+        }
+
         // add global directive when appropriate.
         if global {
             output.push_str(&format!(".globl {name}\n"));
@@ -51,10 +80,90 @@ impl CodeEmitter {
         // function label
         output.push_str(&format!("{name}:\n"));
 
+        // prologue
+        output.push_str("    pushq %rbp\n");
+        output.push_str("    movq %rsp, %rbp\n");
+
         // instruction emission
         for instr in instructions {
             let instr_str = self.emit_instruction(&instr);
-            output.push_str(&format!("    {instr_str}\n"));
+            if let Instruction::Label(_) = instr {
+                output.push_str(&format!("{instr_str}\n"));
+            } else {
+                output.push_str(&format!("    {instr_str}\n"));
+            }
+        }
+
+        output
+    }
+
+    fn emit_static_constant(
+        &mut self,
+        name: &str,
+        alignment: i64,
+        ty: asm::AsmType,
+        bits: i64,
+    ) -> String {
+        let (directive, size) = match ty {
+            asm::AsmType::Single => (".long", 4),
+            asm::AsmType::Double => (".quad", 8),
+            _ => panic!("Unsupported static constant type {:?}", ty),
+        };
+
+        let mut output = String::new();
+        output.push_str(".section .rodata\n");
+        output.push_str(&format!(".balign {alignment}\n"));
+        output.push_str(&format!("{name}:\n"));
+        output.push_str(&format!("    {directive} {bits}\n"));
+
+        // SSE instructions with a 16 byte operand read past the value itself.
+        if alignment > size {
+            output.push_str(&format!("    .zero {}\n", alignment - size));
+        }
+
+        output
+    }
+
+    fn emit_static_variable(
+        &mut self,
+        name: &str,
+        global: bool,
+        alignment: i64,
+        section: Option<&str>,
+        init: &[StaticInit],
+    ) -> String {
+        let mut output = String::new();
+
+        if global {
+            output.push_str(&format!(".globl {name}\n"));
+        }
+
+        // Zeroed objects take no space in the object file.
+        if let Some(section) = section {
+            output.push_str(&format!(".section {section},\"aw\",@progbits\n"));
+        } else if init.iter().all(StaticInit::is_zero) {
+            output.push_str(".bss\n");
+        } else {
+            output.push_str(".data\n");
+        }
+
+        output.push_str(&format!(".balign {alignment}\n"));
+        output.push_str(&format!("{name}:\n"));
+
+        for item in init {
+            let directive = match item {
+                StaticInit::UChar(v) => format!(".byte {v}"),
+                StaticInit::Int(v) => format!(".long {v}"),
+                StaticInit::UInt(v) => format!(".long {v}"),
+                StaticInit::Long(v) => format!(".quad {v}"),
+                StaticInit::ULong(v) => format!(".quad {v}"),
+                StaticInit::Float(v) => format!(".long {}", v.to_bits()),
+                StaticInit::Double(v) => format!(".quad {}", v.to_bits()),
+                StaticInit::Zero(bytes) => format!(".zero {bytes}"),
+                StaticInit::Address { name, offset: 0 } => format!(".quad {name}"),
+                StaticInit::Address { name, offset } => format!(".quad {name}{offset:+}"),
+            };
+            output.push_str(&format!("    {directive}\n"));
         }
 
         output
@@ -67,20 +176,15 @@ impl CodeEmitter {
                     asm::AsmType::Byte => "movb",
                     asm::AsmType::Longword => "movl",
                     asm::AsmType::Quadword => "movq",
+                    asm::AsmType::Single => "movss",
                     asm::AsmType::Double => "movsd",
                     asm::AsmType::ByteArray { .. } => todo!("ByteArray not yet supported"),
                 };
 
-                let src_str = match src {
-                    asm::Operand::Imm(val) => format!("${val}"),
-                    asm::Operand::Reg(r) => format!("%{:?}", r).to_lowercase(),
-                    asm::Operand::Mem(name) => format!("-{}(%rbp)", name), // simple stack offset model for temps
-                };
-
+                let src_str = self.emit_operand(src);
                 let dst_str = match dst {
-                    asm::Operand::Reg(r) => format!("%{:?}", r).to_lowercase(),
-                    asm::Operand::Mem(name) => format!("-{}(%rbp)", name),
-                    _ => panic!("Unsupported destination operand"),
+                    asm::Operand::Imm(_) => panic!("Unsupported destination operand"),
+                    _ => self.emit_operand(dst),
                 };
 
                 format!("{mnemonic} {src_str}, {dst_str}")
@@ -91,14 +195,15 @@ impl CodeEmitter {
                     asm::AsmType::Byte => "negb",
                     asm::AsmType::Longword => "negl",
                     asm::AsmType::Quadword => "negq",
-                    asm::AsmType::Double => panic!("Cannot negate double"),
+                    asm::AsmType::Single | asm::AsmType::Double => {
+                        panic!("Cannot negate {:?} in place", ty)
+                    }
                     asm::AsmType::ByteArray { .. } => panic!("Unsupported"),
                 };
 
                 let dst_str = match dst {
-                    asm::Operand::Reg(r) => format!("%{:?}", r).to_lowercase(),
-                    asm::Operand::Mem(name) => format!("-{}(%rbp)", name),
-                    _ => panic!("Unsupported operand for neg"),
+                    asm::Operand::Imm(_) => panic!("Unsupported operand for neg"),
+                    _ => self.emit_operand(dst),
                 };
 
                 format!("{mnemonic} {dst_str}")
@@ -109,20 +214,255 @@ impl CodeEmitter {
                     asm::AsmType::Byte => "notb",
                     asm::AsmType::Longword => "notl",
                     asm::AsmType::Quadword => "notq",
-                    asm::AsmType::Double => panic!("Cannot bitwise complement double"),
+                    asm::AsmType::Single | asm::AsmType::Double => {
+                        panic!("Cannot bitwise complement {:?}", ty)
+                    }
                     asm::AsmType::ByteArray { .. } => panic!("Unsupported"),
                 };
 
                 let dst_str = match dst {
-                    asm::Operand::Reg(r) => format!("%{:?}", r).to_lowercase(),
-                    asm::Operand::Mem(name) => format!("-{}(%rbp)", name),
-                    _ => panic!("Unsupported operand for not"),
+                    asm::Operand::Imm(_) => panic!("Unsupported operand for not"),
+                    _ => self.emit_operand(dst),
                 };
 
                 format!("{mnemonic} {dst_str}")
             }
 
-            asm::Instruction::Ret => "ret".into(),
+            asm::Instruction::Add { ty, src, dst } => self.emit_binary("add", *ty, src, dst),
+            asm::Instruction::Sub { ty, src, dst } => self.emit_binary("sub", *ty, src, dst),
+            asm::Instruction::Imul { ty, src, dst } => self.emit_binary("imul", *ty, src, dst),
+            asm::Instruction::And { ty, src, dst } => self.emit_binary("and", *ty, src, dst),
+            asm::Instruction::Or { ty, src, dst } => self.emit_binary("or", *ty, src, dst),
+            // The vector registers only have a bitwise xor of the whole register.
+            asm::Instruction::Xor {
+                ty: asm::AsmType::Single,
+                src,
+                dst,
+            } => format!(
+                "xorps {}, {}",
+                self.emit_operand(src),
+                self.emit_operand(dst)
+            ),
+            asm::Instruction::Xor {
+                ty: asm::AsmType::Double,
+                src,
+                dst,
+            } => format!(
+                "xorpd {}, {}",
+                self.emit_operand(src),
+                self.emit_operand(dst)
+            ),
+            asm::Instruction::Xor { ty, src, dst } => self.emit_binary("xor", *ty, src, dst),
+            asm::Instruction::MulFloat { ty, src, dst } => self.emit_binary("mul", *ty, src, dst),
+            asm::Instruction::DivFloat { ty, src, dst } => self.emit_binary("div", *ty, src, dst),
+            asm::Instruction::Sal { ty, count, dst } => self.emit_binary("sal", *ty, count, dst),
+            asm::Instruction::Sar { ty, count, dst } => self.emit_binary("sar", *ty, count, dst),
+            asm::Instruction::Shr { ty, count, dst } => self.emit_binary("shr", *ty, count, dst),
+
+            asm::Instruction::Idiv { ty, src } => {
+                format!("idiv{} {}", self.suffix(*ty), self.emit_operand(src))
+            }
+
+            asm::Instruction::Div { ty, src } => {
+                format!("div{} {}", self.suffix(*ty), self.emit_operand(src))
+            }
+
+            asm::Instruction::Movsx { src, dst } => {
+                format!(
+                    "movslq {}, {}",
+                    self.emit_operand(src),
+                    self.emit_operand(dst)
+                )
+            }
+
+            asm::Instruction::MovZeroExtend {
+                src_ty: asm::AsmType::Byte,
+                dst_ty,
+                src,
+                dst,
+            } => format!(
+                "movzb{} {}, {}",
+                self.suffix(*dst_ty),
+                self.emit_operand(src),
+                self.emit_operand(dst)
+            ),
+
+            asm::Instruction::MovZeroExtend { .. } => {
+                panic!("MovZeroExtend from a longword should have been rewritten before emission")
+            }
+
+            asm::Instruction::Cdq { ty } => match ty {
+                asm::AsmType::Longword => "cdq".into(),
+                asm::AsmType::Quadword => "cqo".into(),
+                _ => panic!("Cannot sign extend {:?} for division", ty),
+            },
+
+            asm::Instruction::Cmp { ty, src, dst } if ty.is_floating() => {
+                self.emit_binary("comi", *ty, src, dst)
+            }
+            asm::Instruction::Cmp { ty, src, dst } => self.emit_binary("cmp", *ty, src, dst),
+
+            asm::Instruction::Cvtsi2 {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } => format!(
+                "cvtsi2{}{} {}, {}",
+                self.suffix(*dst_ty),
+                self.suffix(*src_ty),
+                self.emit_operand(src),
+                self.emit_operand(dst)
+            ),
+
+            asm::Instruction::Cvtt2si {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } => format!(
+                "cvtt{}2si{} {}, {}",
+                self.suffix(*src_ty),
+                self.suffix(*dst_ty),
+                self.emit_operand(src),
+                self.emit_operand(dst)
+            ),
+
+            asm::Instruction::Cvtss2sd { src, dst } => {
+                format!(
+                    "cvtss2sd {}, {}",
+                    self.emit_operand(src),
+                    self.emit_operand(dst)
+                )
+            }
+
+            asm::Instruction::Cvtsd2ss { src, dst } => {
+                format!(
+                    "cvtsd2ss {}, {}",
+                    self.emit_operand(src),
+                    self.emit_operand(dst)
+                )
+            }
+
+            asm::Instruction::SetCC { cond, dst } => {
+                // set only writes a single byte.
+                let dst_str = match dst {
+                    asm::Operand::Reg(r) => {
+                        self.emit_operand(&asm::Operand::Reg(r.sized(asm::AsmType::Byte)))
+                    }
+                    _ => self.emit_operand(dst),
+                };
+
+                format!("set{} {dst_str}", self.cond_code(*cond))
+            }
+
+            asm::Instruction::Jmp(label) => format!("jmp {}", self.local_label(label)),
+
+            asm::Instruction::JmpCC { cond, target } => {
+                format!("j{} {}", self.cond_code(*cond), self.local_label(target))
+            }
+
+            asm::Instruction::Label(label) => format!("{}:", self.local_label(label)),
+
+            asm::Instruction::Lea { src, dst } => {
+                format!(
+                    "leaq {}, {}",
+                    self.emit_operand(src),
+                    self.emit_operand(dst)
+                )
+            }
+
+            asm::Instruction::Push(operand) => format!("pushq {}", self.emit_operand(operand)),
+
+            asm::Instruction::Call { name, plt, .. } => {
+                if *plt {
+                    format!("call {name}@PLT")
+                } else {
+                    format!("call {name}")
+                }
+            }
+
+            asm::Instruction::CallIndirect { target, .. } => {
+                format!("call *{}", self.emit_operand(target))
+            }
+
+            asm::Instruction::AllocateStack(bytes) => format!("subq ${bytes}, %rsp"),
+
+            asm::Instruction::DeallocateStack(bytes) => format!("addq ${bytes}, %rsp"),
+
+            asm::Instruction::InlineAsm {
+                template,
+                operands,
+                names,
+                ..
+            } => inline_asm::substitute(template, names, |i, modifier| {
+                match (&operands[i], modifier) {
+                    (asm::Operand::Reg(reg), Some(Modifier::Size(ty))) => {
+                        self.emit_operand(&asm::Operand::Reg(reg.sized(ty)))
+                    }
+                    (asm::Operand::Imm(value), Some(Modifier::Constant)) => value.to_string(),
+                    (operand, _) => self.emit_operand(operand),
+                }
+            })
+            .expect("asm templates were checked by the type checker"),
+
+            // Tear down the stack frame set up in the function prologue.
+            asm::Instruction::Ret => "movq %rbp, %rsp\n    popq %rbp\n    ret".into(),
+        }
+    }
+
+    fn emit_binary(
+        &mut self,
+        mnemonic: &str,
+        ty: asm::AsmType,
+        src: &asm::Operand,
+        dst: &asm::Operand,
+    ) -> String {
+        let suffix = self.suffix(ty);
+        let src_str = self.emit_operand(src);
+        let dst_str = self.emit_operand(dst);
+
+        format!("{mnemonic}{suffix} {src_str}, {dst_str}")
+    }
+
+    fn suffix(&self, ty: asm::AsmType) -> &'static str {
+        match ty {
+            asm::AsmType::Byte => "b",
+            asm::AsmType::Longword => "l",
+            asm::AsmType::Quadword => "q",
+            asm::AsmType::Single => "ss",
+            asm::AsmType::Double => "sd",
+            asm::AsmType::ByteArray { .. } => panic!("ByteArray has no instruction suffix"),
+        }
+    }
+
+    fn cond_code(&self, cond: asm::CondCode) -> String {
+        format!("{:?}", cond).to_lowercase()
+    }
+
+    /// Labels inside a function are prefixed with `.L` so they stay out of the
+    /// object file's symbol table.
+    fn local_label(&self, label: &str) -> String {
+        format!(".L{label}")
+    }
+
+    fn emit_operand(&self, operand: &asm::Operand) -> String {
+        match operand {
+            asm::Operand::Imm(val) => format!("${val}"),
+            asm::Operand::Reg(r) => format!("%{:?}", r).to_lowercase(),
+            asm::Operand::Stack(offset) => format!("{offset}(%rbp)"),
+            asm::Operand::Memory(reg, offset) => {
+                format!("{offset}({})", self.emit_operand(&asm::Operand::Reg(*reg)))
+            }
+            asm::Operand::Indexed { base, index, scale } => format!(
+                "({}, {}, {scale})",
+                self.emit_operand(&asm::Operand::Reg(*base)),
+                self.emit_operand(&asm::Operand::Reg(*index))
+            ),
+            asm::Operand::Data(name) => format!("{name}(%rip)"),
+            asm::Operand::Pseudo(name) | asm::Operand::PseudoMem(name, _) => {
+                panic!("Pseudo register {name} should have been replaced before emission")
+            }
         }
     }
 }
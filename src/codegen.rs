@@ -1,87 +1,2198 @@
-use crate::asm::{self, AsmType, Operand, Register};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::asm::{self, AsmType, CondCode, Operand, Register};
 use crate::asm::{Instruction, Program, TopLevel};
+use crate::inline_asm::{self, Clobber, Constraint, Location};
 use crate::ir::{self, Value};
+use crate::regalloc::{self, Strategy};
+use crate::symbols::SymbolTable;
+use crate::types;
+
+/// The registers the first six integer arguments are passed in, in order.
+const ARG_REGISTERS: [Register; 6] = [
+    Register::RDI,
+    Register::RSI,
+    Register::RDX,
+    Register::RCX,
+    Register::R8,
+    Register::R9,
+];
+
+/// The registers the first eight floating point arguments are passed in, in order.
+const FLOAT_ARG_REGISTERS: [Register; 8] = [
+    Register::XMM0,
+    Register::XMM1,
+    Register::XMM2,
+    Register::XMM3,
+    Register::XMM4,
+    Register::XMM5,
+    Register::XMM6,
+    Register::XMM7,
+];
+
+/// Bytes of the register save area taken by the integer argument registers.
+const GP_SAVE_AREA: i64 = 8 * ARG_REGISTERS.len() as i64;
+
+/// Bytes at the top of a variadic function's frame where the argument
+/// registers are spilled for `va_arg`: the integer registers, followed by the
+/// vector registers at 16 bytes apiece.
+const REGISTER_SAVE_AREA: i64 = GP_SAVE_AREA + 16 * FLOAT_ARG_REGISTERS.len() as i64;
+
+/// Where an argument is passed under the System V calling convention.
+#[derive(Debug, Clone, Copy)]
+enum ArgLocation {
+    Reg(Register),
+    /// The position of the argument's eightbyte among the stack arguments.
+    Stack(usize),
+}
+
+/// The function a call instruction calls.
+#[derive(Debug, Clone, Copy)]
+enum CallTarget<'a> {
+    Named(&'a str),
+    /// The function a pointer value points to.
+    Pointer(&'a Value),
+}
 
-pub struct CodeGenerator;
+#[derive(Debug, Clone)]
+pub struct CodeGenerator {
+    symbols: SymbolTable,
+    /// Functions with a body in this file, which can be called directly.
+    defined_functions: HashSet<String>,
+    /// Where the named parameters of the function being generated are passed.
+    named_params: Vec<ArgLocation>,
+    /// Read-only constants referenced by the generated code.
+    constants: Vec<TopLevel>,
+    next_label: usize,
+    /// How the pseudos of each function are given registers.
+    regalloc: Strategy,
+    /// Whether to print what register allocation took and left for each
+    /// function, to compare the strategies.
+    print_regalloc_stats: bool,
+}
 
 impl CodeGenerator {
-    pub fn new() -> Self {
-        Self
+    pub fn new(symbols: SymbolTable, regalloc: Strategy, print_regalloc_stats: bool) -> Self {
+        Self {
+            symbols,
+            defined_functions: HashSet::new(),
+            named_params: Vec::new(),
+            constants: Vec::new(),
+            next_label: 0,
+            regalloc,
+            print_regalloc_stats,
+        }
     }
 
     pub fn generate(&mut self, program: &ir::Program) -> asm::Program {
         let mut stmts = Vec::new();
 
-        for func in &program.0 {
-            let top_level = self.generate_function(func);
-            stmts.push(top_level);
+        self.defined_functions = program
+            .0
+            .iter()
+            .filter_map(|top_level| match top_level {
+                ir::TopLevel::Function(func) => Some(func.identifier.clone()),
+                ir::TopLevel::StaticVariable { .. } => None,
+            })
+            .collect();
+
+        for top_level in &program.0 {
+            stmts.push(match top_level {
+                ir::TopLevel::Function(func) => self.generate_function(func),
+                ir::TopLevel::StaticVariable { name, global, init } => TopLevel::StaticVariable {
+                    name: name.clone(),
+                    global: *global,
+                    alignment: declared_alignment(name, &self.symbols),
+                    section: self.symbols.section(name).map(String::from),
+                    init: init.clone(),
+                },
+            });
         }
 
+        stmts.append(&mut self.constants);
+
         Program(stmts)
     }
 
     fn generate_function(&mut self, func: &ir::Function) -> TopLevel {
         let mut instructions = Vec::new();
+        let param_types: Vec<AsmType> = func
+            .params
+            .iter()
+            .map(|param| convert_type(self.symbols.type_of(param), &self.symbols))
+            .collect();
+        self.named_params = classify_args(&param_types);
+
+        let reserved = if func.variadic {
+            for (i, reg) in ARG_REGISTERS.iter().enumerate() {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: Operand::Reg(*reg),
+                    dst: Operand::Stack(-REGISTER_SAVE_AREA + 8 * i as i64),
+                });
+            }
+            for (i, reg) in FLOAT_ARG_REGISTERS.iter().enumerate() {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Double,
+                    src: Operand::Reg(*reg),
+                    dst: Operand::Stack(-REGISTER_SAVE_AREA + GP_SAVE_AREA + 16 * i as i64),
+                });
+            }
+            REGISTER_SAVE_AREA
+        } else {
+            0
+        };
+
+        // Copy the parameters out of the argument registers and the caller's
+        // frame, so they can be treated like any other variable.
+        let params = func.params.iter().zip(&param_types).zip(&self.named_params);
+        for ((param, ty), location) in params {
+            let src = match location {
+                ArgLocation::Reg(reg) => Operand::Reg(reg.sized(*ty)),
+                // Above the saved %rbp and the return address.
+                ArgLocation::Stack(i) => Operand::Stack(16 + 8 * *i as i64),
+            };
+
+            instructions.push(Instruction::Mov {
+                ty: *ty,
+                src,
+                dst: Operand::Pseudo(param.clone()),
+            });
+        }
 
         if let Some(body) = &func.body {
             for instr in body {
-                match instr {
-                    ir::Instruction::Return(val) => {
-                        // Move constant or variable into EAX
-                        let op = match val {
-                            Value::Constant(c) => Operand::Imm((*c).into()),
-                            Value::Var(name) => Operand::Mem(name.clone()), // assumes a simple memory model for temps
+                self.generate_instruction(instr, &mut instructions);
+            }
+        }
+
+        let realigned = self.realign_frame(&mut instructions);
+
+        let pseudos = self.register_candidates(&instructions);
+        let returns = self.return_registers(&func.identifier);
+        let start = Instant::now();
+        let mut allocated =
+            regalloc::allocate_registers(&mut instructions, &pseudos, &returns, self.regalloc);
+        let elapsed = start.elapsed();
+        if realigned && !allocated.contains(&Register::RBX) {
+            allocated.push(Register::RBX);
+        }
+        // Those still pseudos were left for stack slots.
+        let spilled = instructions
+            .iter_mut()
+            .flat_map(|instr| instr.operands_mut())
+            .filter_map(|(operand, _)| match operand {
+                Operand::Pseudo(name) if pseudos.contains_key(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>()
+            .len();
+        let stack_size = self.replace_pseudos(&mut instructions, reserved);
+        let (stack_size, instructions) = save_callee_saved(stack_size, instructions, &allocated);
+        let instructions = self.fixup_instructions(stack_size, instructions);
+
+        if self.print_regalloc_stats {
+            let count = instructions
+                .iter()
+                .filter(|instr| !matches!(instr, Instruction::Label(_)))
+                .count();
+            println!(
+                "{:?} register allocation in {}: {:?}, {} of {} pseudos spilled, {} instructions",
+                self.regalloc,
+                func.identifier,
+                elapsed,
+                spilled,
+                pseudos.len(),
+                count
+            );
+        }
+
+        TopLevel::Function {
+            name: func.identifier.clone(),
+            global: true,
+            alignment: self.symbols.requested_alignment(&func.identifier),
+            section: self.symbols.section(&func.identifier).map(String::from),
+            instructions,
+        }
+    }
+
+    fn generate_instruction(
+        &mut self,
+        instr: &ir::Instruction,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        match instr {
+            ir::Instruction::Return(val) => {
+                if let Some(val) = val {
+                    let ty = self.asm_type(val);
+                    instructions.push(Instruction::Mov {
+                        ty,
+                        src: self.convert_value(val),
+                        dst: Operand::Reg(return_register(ty)),
+                    });
+                }
+
+                instructions.push(Instruction::Ret);
+            }
+
+            ir::Instruction::Unary {
+                op: ir::UnaryOperator::Not,
+                src,
+                dst,
+            } => {
+                let ty = self.asm_type(src);
+                let src_op = self.convert_value(src);
+                let dst_op = self.convert_value(dst);
+
+                instructions.extend(compare_to_zero(ty, src_op));
+                instructions.push(Instruction::Mov {
+                    ty: self.asm_type(dst),
+                    src: Operand::Imm(0),
+                    dst: dst_op.clone(),
+                });
+
+                if ty.is_floating() {
+                    // NaN compares unordered, which sets the flags like equality
+                    // and also sets the parity flag, but it is not zero.
+                    let end_label = self.make_label("not_end");
+                    instructions.push(Instruction::JmpCC {
+                        cond: CondCode::P,
+                        target: end_label.clone(),
+                    });
+                    instructions.push(Instruction::SetCC {
+                        cond: CondCode::E,
+                        dst: dst_op,
+                    });
+                    instructions.push(Instruction::Label(end_label));
+                } else {
+                    instructions.push(Instruction::SetCC {
+                        cond: CondCode::E,
+                        dst: dst_op,
+                    });
+                }
+            }
+
+            ir::Instruction::Unary { op, src, dst } => {
+                let ty = self.asm_type(src);
+                let src_op = self.convert_value(src);
+                let dst_op = self.convert_value(dst);
+
+                instructions.push(Instruction::Mov {
+                    ty,
+                    src: src_op,
+                    dst: dst_op.clone(),
+                });
+
+                match op {
+                    // Flip the sign bit.
+                    ir::UnaryOperator::Negate if ty.is_floating() => {
+                        let negative_zero = match ty {
+                            AsmType::Single => ir::Constant::Float(-0.0),
+                            _ => ir::Constant::Double(-0.0),
+                        };
+                        instructions.push(Instruction::Xor {
+                            ty,
+                            src: self.static_constant(&negative_zero, 16),
+                            dst: dst_op,
+                        });
+                    }
+                    ir::UnaryOperator::Negate => {
+                        instructions.push(Instruction::Neg { ty, dst: dst_op });
+                    }
+                    ir::UnaryOperator::Complement => {
+                        instructions.push(Instruction::Not { ty, dst: dst_op });
+                    }
+                    ir::UnaryOperator::Not => unreachable!(),
+                }
+            }
+
+            ir::Instruction::Binary {
+                op,
+                src1,
+                src2,
+                dst,
+            } => {
+                let ty = self.asm_type(src1);
+                let signed = src1.typ(&self.symbols).is_signed();
+                let src1_op = self.convert_value(src1);
+                let src2_op = self.convert_value(src2);
+                let dst_op = self.convert_value(dst);
+
+                match op {
+                    op if op.is_comparison() && ty.is_floating() => {
+                        // An unordered comparison, against NaN, sets the flags
+                        // like "below and equal" plus the parity flag. Only
+                        // "above" conditions are false for it, so `<` and `<=`
+                        // are tested the other way around, and `==` and `!=`
+                        // check parity first.
+                        let (cmp_src, cmp_dst, cond) = match op {
+                            ir::BinaryOperator::LessThan => (src1_op, src2_op, CondCode::A),
+                            ir::BinaryOperator::LessOrEqual => (src1_op, src2_op, CondCode::AE),
+                            _ => (src2_op, src1_op, condition_code(*op, false)),
                         };
+                        instructions.push(Instruction::Cmp {
+                            ty,
+                            src: cmp_src,
+                            dst: cmp_dst,
+                        });
 
+                        let unordered = i64::from(*op == ir::BinaryOperator::NotEqual);
                         instructions.push(Instruction::Mov {
-                            ty: AsmType::Longword,
-                            src: op,
-                            dst: Operand::Reg(Register::EAX),
+                            ty: self.asm_type(dst),
+                            src: Operand::Imm(unordered),
+                            dst: dst_op.clone(),
                         });
 
-                        instructions.push(Instruction::Ret);
+                        if matches!(op, ir::BinaryOperator::Equal | ir::BinaryOperator::NotEqual) {
+                            let end_label = self.make_label("compare_end");
+                            instructions.push(Instruction::JmpCC {
+                                cond: CondCode::P,
+                                target: end_label.clone(),
+                            });
+                            instructions.push(Instruction::SetCC { cond, dst: dst_op });
+                            instructions.push(Instruction::Label(end_label));
+                        } else {
+                            instructions.push(Instruction::SetCC { cond, dst: dst_op });
+                        }
+                    }
+                    op if op.is_comparison() => {
+                        // Compare first, since the destination may alias an operand.
+                        instructions.push(Instruction::Cmp {
+                            ty,
+                            src: src2_op,
+                            dst: src1_op,
+                        });
+                        instructions.push(Instruction::Mov {
+                            ty: self.asm_type(dst),
+                            src: Operand::Imm(0),
+                            dst: dst_op.clone(),
+                        });
+                        instructions.push(Instruction::SetCC {
+                            cond: condition_code(*op, signed),
+                            dst: dst_op,
+                        });
+                    }
+                    ir::BinaryOperator::Divide if ty.is_floating() => {
+                        let target = result_target(ty, &src2_op, &dst_op);
+                        instructions.push(Instruction::Mov {
+                            ty,
+                            src: src1_op,
+                            dst: target.clone(),
+                        });
+                        instructions.push(Instruction::DivFloat {
+                            ty,
+                            src: src2_op,
+                            dst: target.clone(),
+                        });
+                        if target != dst_op {
+                            instructions.push(Instruction::Mov {
+                                ty,
+                                src: target,
+                                dst: dst_op,
+                            });
+                        }
                     }
+                    ir::BinaryOperator::Divide | ir::BinaryOperator::Remainder => {
+                        // Division leaves the quotient in %eax and the remainder in %edx.
+                        let result = if *op == ir::BinaryOperator::Divide {
+                            Register::RAX
+                        } else {
+                            Register::RDX
+                        };
+
+                        instructions.push(Instruction::Mov {
+                            ty,
+                            src: src1_op,
+                            dst: Operand::Reg(Register::RAX.sized(ty)),
+                        });
+
+                        if signed {
+                            instructions.push(Instruction::Cdq { ty });
+                            instructions.push(Instruction::Idiv { ty, src: src2_op });
+                        } else {
+                            instructions.push(Instruction::Mov {
+                                ty,
+                                src: Operand::Imm(0),
+                                dst: Operand::Reg(Register::RDX.sized(ty)),
+                            });
+                            instructions.push(Instruction::Div { ty, src: src2_op });
+                        }
 
-                    ir::Instruction::Unary { op, src, dst } => {
-                        let src_op = match src {
-                            Value::Constant(c) => Operand::Imm((*c).into()),
-                            Value::Var(name) => Operand::Mem(name.clone()),
+                        instructions.push(Instruction::Mov {
+                            ty,
+                            src: Operand::Reg(result.sized(ty)),
+                            dst: dst_op,
+                        });
+                    }
+                    _ => {
+                        // The destination is written before the second operand
+                        // is read, so when they are the same an operation that
+                        // commutes reads its operands the other way round.
+                        let (src1_op, src2_op) = if src2_op == dst_op && commutes(*op) {
+                            (src2_op, src1_op)
+                        } else {
+                            (src1_op, src2_op)
                         };
-                        let dst_op = Operand::Mem(dst.name()); // implement name() for Value::Var
-
-                        match op {
-                            ir::UnaryOperator::Negate => {
-                                instructions.push(Instruction::Mov {
-                                    ty: AsmType::Longword,
-                                    src: src_op,
-                                    dst: dst_op.clone(),
-                                });
-                                instructions.push(Instruction::Neg {
-                                    ty: AsmType::Longword,
-                                    dst: dst_op,
-                                });
+                        let target = result_target(ty, &src2_op, &dst_op);
+                        instructions.push(Instruction::Mov {
+                            ty,
+                            src: src1_op,
+                            dst: target.clone(),
+                        });
+
+                        let src = src2_op;
+                        let dst = target.clone();
+                        instructions.push(match op {
+                            ir::BinaryOperator::Add => Instruction::Add { ty, src, dst },
+                            ir::BinaryOperator::Subtract => Instruction::Sub { ty, src, dst },
+                            ir::BinaryOperator::Multiply if ty.is_floating() => {
+                                Instruction::MulFloat { ty, src, dst }
                             }
-                            ir::UnaryOperator::Complement => {
-                                instructions.push(Instruction::Mov {
-                                    ty: AsmType::Longword,
-                                    src: src_op,
-                                    dst: dst_op.clone(),
-                                });
-                                instructions.push(Instruction::Not {
-                                    ty: AsmType::Longword,
-                                    dst: dst_op,
-                                });
+                            ir::BinaryOperator::Multiply => Instruction::Imul { ty, src, dst },
+                            ir::BinaryOperator::BitwiseAnd => Instruction::And { ty, src, dst },
+                            ir::BinaryOperator::BitwiseOr => Instruction::Or { ty, src, dst },
+                            ir::BinaryOperator::BitwiseXor => Instruction::Xor { ty, src, dst },
+                            ir::BinaryOperator::ShiftLeft => Instruction::Sal {
+                                ty,
+                                count: src,
+                                dst,
+                            },
+                            // Signed values shift in copies of the sign bit, unsigned ones zeros.
+                            ir::BinaryOperator::ShiftRight if signed => Instruction::Sar {
+                                ty,
+                                count: src,
+                                dst,
+                            },
+                            ir::BinaryOperator::ShiftRight => Instruction::Shr {
+                                ty,
+                                count: src,
+                                dst,
+                            },
+                            _ => unreachable!(),
+                        });
+                        if target != dst_op {
+                            instructions.push(Instruction::Mov {
+                                ty,
+                                src: target,
+                                dst: dst_op,
+                            });
+                        }
+                    }
+                }
+            }
+
+            // A structure is too big for a single `mov`, so it is copied a
+            // piece at a time.
+            ir::Instruction::Copy { src, dst } if self.is_structure(src) => {
+                let size = self.symbols.size_of(&src.typ(&self.symbols));
+                let src = self.convert_value(src);
+                let dst = self.convert_value(dst);
+                copy_bytes(&src, &dst, size, instructions);
+            }
+
+            ir::Instruction::Copy { src, dst } => {
+                instructions.push(Instruction::Mov {
+                    ty: self.asm_type(src),
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::CopyToOffset { src, dst, offset } if self.is_structure(src) => {
+                let size = self.symbols.size_of(&src.typ(&self.symbols));
+                let src = self.convert_value(src);
+                let dst = Operand::PseudoMem(dst.clone(), *offset);
+                copy_bytes(&src, &dst, size, instructions);
+            }
+
+            ir::Instruction::CopyToOffset { src, dst, offset } => {
+                instructions.push(Instruction::Mov {
+                    ty: self.asm_type(src),
+                    src: self.convert_value(src),
+                    dst: Operand::PseudoMem(dst.clone(), *offset),
+                });
+            }
+
+            ir::Instruction::SignExtend { src, dst } => {
+                instructions.push(Instruction::Movsx {
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::ZeroExtend { src, dst } => {
+                instructions.push(Instruction::MovZeroExtend {
+                    src_ty: self.asm_type(src),
+                    dst_ty: self.asm_type(dst),
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::Truncate { src, dst } => {
+                // Reading the low bytes of the wider value is all a truncation takes.
+                instructions.push(Instruction::Mov {
+                    ty: self.asm_type(dst),
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::IntToFloating { src, dst } => {
+                instructions.push(Instruction::Cvtsi2 {
+                    src_ty: self.asm_type(src),
+                    dst_ty: self.asm_type(dst),
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::FloatingToInt { src, dst } => {
+                instructions.push(Instruction::Cvtt2si {
+                    src_ty: self.asm_type(src),
+                    dst_ty: self.asm_type(dst),
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::UIntToFloating { src, dst } => {
+                let dst_ty = self.asm_type(dst);
+                let src_op = self.convert_value(src);
+                let dst_op = self.convert_value(dst);
+
+                if self.asm_type(src) == AsmType::Longword {
+                    // Every unsigned int is a valid signed long.
+                    instructions.push(Instruction::MovZeroExtend {
+                        src_ty: AsmType::Longword,
+                        dst_ty: AsmType::Quadword,
+                        src: src_op,
+                        dst: Operand::Reg(Register::RAX),
+                    });
+                    instructions.push(Instruction::Cvtsi2 {
+                        src_ty: AsmType::Quadword,
+                        dst_ty,
+                        src: Operand::Reg(Register::RAX),
+                        dst: dst_op,
+                    });
+                } else {
+                    // cvtsi2 only converts signed values. One that is out of
+                    // range for that is halved, keeping the lowest bit so the
+                    // result still rounds correctly, converted and doubled.
+                    let large_label = self.make_label("ulong_to_floating_large");
+                    let end_label = self.make_label("ulong_to_floating_end");
+
+                    instructions.push(Instruction::Cmp {
+                        ty: AsmType::Quadword,
+                        src: Operand::Imm(0),
+                        dst: src_op.clone(),
+                    });
+                    instructions.push(Instruction::JmpCC {
+                        cond: CondCode::L,
+                        target: large_label.clone(),
+                    });
+                    instructions.push(Instruction::Cvtsi2 {
+                        src_ty: AsmType::Quadword,
+                        dst_ty,
+                        src: src_op.clone(),
+                        dst: dst_op.clone(),
+                    });
+                    instructions.push(Instruction::Jmp(end_label.clone()));
+
+                    instructions.push(Instruction::Label(large_label));
+                    instructions.push(Instruction::Mov {
+                        ty: AsmType::Quadword,
+                        src: src_op,
+                        dst: Operand::Reg(Register::RAX),
+                    });
+                    instructions.push(Instruction::Mov {
+                        ty: AsmType::Quadword,
+                        src: Operand::Reg(Register::RAX),
+                        dst: Operand::Reg(Register::RDX),
+                    });
+                    instructions.push(Instruction::Shr {
+                        ty: AsmType::Quadword,
+                        count: Operand::Imm(1),
+                        dst: Operand::Reg(Register::RDX),
+                    });
+                    instructions.push(Instruction::And {
+                        ty: AsmType::Quadword,
+                        src: Operand::Imm(1),
+                        dst: Operand::Reg(Register::RAX),
+                    });
+                    instructions.push(Instruction::Or {
+                        ty: AsmType::Quadword,
+                        src: Operand::Reg(Register::RAX),
+                        dst: Operand::Reg(Register::RDX),
+                    });
+                    instructions.push(Instruction::Cvtsi2 {
+                        src_ty: AsmType::Quadword,
+                        dst_ty,
+                        src: Operand::Reg(Register::RDX),
+                        dst: dst_op.clone(),
+                    });
+                    instructions.push(Instruction::Add {
+                        ty: dst_ty,
+                        src: dst_op.clone(),
+                        dst: dst_op,
+                    });
+                    instructions.push(Instruction::Label(end_label));
+                }
+            }
+
+            ir::Instruction::FloatingToUInt { src, dst } => {
+                let src_ty = self.asm_type(src);
+                let src_op = self.convert_value(src);
+                let dst_op = self.convert_value(dst);
+
+                if self.asm_type(dst) == AsmType::Longword {
+                    // Convert to a signed long, which holds every unsigned int,
+                    // and keep the low half.
+                    instructions.push(Instruction::Cvtt2si {
+                        src_ty,
+                        dst_ty: AsmType::Quadword,
+                        src: src_op,
+                        dst: Operand::Reg(Register::RAX),
+                    });
+                    instructions.push(Instruction::Mov {
+                        ty: AsmType::Longword,
+                        src: Operand::Reg(Register::EAX),
+                        dst: dst_op,
+                    });
+                } else {
+                    // Values from 2^63 up are out of range for cvtt2si, so
+                    // 2^63 is taken off before converting and added back after.
+                    let two_to_63 = match src_ty {
+                        AsmType::Single => ir::Constant::Float(2f32.powi(63)),
+                        _ => ir::Constant::Double(2f64.powi(63)),
+                    };
+                    let upper_bound = self.static_constant(&two_to_63, two_to_63.typ().size());
+                    let large_label = self.make_label("floating_to_ulong_large");
+                    let end_label = self.make_label("floating_to_ulong_end");
+
+                    instructions.push(Instruction::Cmp {
+                        ty: src_ty,
+                        src: upper_bound.clone(),
+                        dst: src_op.clone(),
+                    });
+                    instructions.push(Instruction::JmpCC {
+                        cond: CondCode::AE,
+                        target: large_label.clone(),
+                    });
+                    instructions.push(Instruction::Cvtt2si {
+                        src_ty,
+                        dst_ty: AsmType::Quadword,
+                        src: src_op.clone(),
+                        dst: dst_op.clone(),
+                    });
+                    instructions.push(Instruction::Jmp(end_label.clone()));
+
+                    instructions.push(Instruction::Label(large_label));
+                    instructions.push(Instruction::Mov {
+                        ty: src_ty,
+                        src: src_op,
+                        dst: Operand::Reg(Register::XMM0),
+                    });
+                    instructions.push(Instruction::Sub {
+                        ty: src_ty,
+                        src: upper_bound,
+                        dst: Operand::Reg(Register::XMM0),
+                    });
+                    instructions.push(Instruction::Cvtt2si {
+                        src_ty,
+                        dst_ty: AsmType::Quadword,
+                        src: Operand::Reg(Register::XMM0),
+                        dst: dst_op.clone(),
+                    });
+                    instructions.push(Instruction::Add {
+                        ty: AsmType::Quadword,
+                        src: Operand::Imm(i64::MIN),
+                        dst: dst_op,
+                    });
+                    instructions.push(Instruction::Label(end_label));
+                }
+            }
+
+            ir::Instruction::FloatingConvert { src, dst } => {
+                let src_op = self.convert_value(src);
+                let dst_op = self.convert_value(dst);
+
+                instructions.push(if self.asm_type(src) == AsmType::Single {
+                    Instruction::Cvtss2sd {
+                        src: src_op,
+                        dst: dst_op,
+                    }
+                } else {
+                    Instruction::Cvtsd2ss {
+                        src: src_op,
+                        dst: dst_op,
+                    }
+                });
+            }
+
+            ir::Instruction::Jump(target) => instructions.push(Instruction::Jmp(target.clone())),
+
+            ir::Instruction::JumpIfZero { condition, target }
+            | ir::Instruction::JumpIfNotZero { condition, target } => {
+                let cond = if matches!(instr, ir::Instruction::JumpIfZero { .. }) {
+                    CondCode::E
+                } else {
+                    CondCode::NE
+                };
+
+                let ty = self.asm_type(condition);
+                let condition = self.convert_value(condition);
+                instructions.extend(compare_to_zero(ty, condition));
+
+                // NaN compares unordered, setting the parity flag as well as
+                // the flags for equality, but it is not zero.
+                if ty.is_floating() && cond == CondCode::E {
+                    let nan_label = self.make_label("nan");
+                    instructions.push(Instruction::JmpCC {
+                        cond: CondCode::P,
+                        target: nan_label.clone(),
+                    });
+                    instructions.push(Instruction::JmpCC {
+                        cond,
+                        target: target.clone(),
+                    });
+                    instructions.push(Instruction::Label(nan_label));
+                } else {
+                    instructions.push(Instruction::JmpCC {
+                        cond,
+                        target: target.clone(),
+                    });
+                    if ty.is_floating() {
+                        instructions.push(Instruction::JmpCC {
+                            cond: CondCode::P,
+                            target: target.clone(),
+                        });
+                    }
+                }
+            }
+
+            ir::Instruction::Label(name) => instructions.push(Instruction::Label(name.clone())),
+
+            ir::Instruction::VaStart { ap } => {
+                let count = |pred: fn(&ArgLocation) -> bool| {
+                    self.named_params.iter().filter(|l| pred(l)).count() as i64
+                };
+                let named_gp = count(|l| matches!(l, ArgLocation::Reg(r) if !r.is_xmm()));
+                let named_fp = count(|l| matches!(l, ArgLocation::Reg(r) if r.is_xmm()));
+                let named_on_stack = count(|l| matches!(l, ArgLocation::Stack(_)));
+                let ap_field = |offset| Operand::Memory(Register::RAX, offset);
+
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(ap),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Longword,
+                    src: Operand::Imm(8 * named_gp),
+                    dst: ap_field(0),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Longword,
+                    src: Operand::Imm(GP_SAVE_AREA + 16 * named_fp),
+                    dst: ap_field(4),
+                });
+                instructions.push(Instruction::Lea {
+                    src: Operand::Stack(16 + 8 * named_on_stack),
+                    dst: Operand::Reg(Register::RDX),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: Operand::Reg(Register::RDX),
+                    dst: ap_field(8),
+                });
+                instructions.push(Instruction::Lea {
+                    src: Operand::Stack(-REGISTER_SAVE_AREA),
+                    dst: Operand::Reg(Register::RDX),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: Operand::Reg(Register::RDX),
+                    dst: ap_field(16),
+                });
+            }
+
+            ir::Instruction::VaArg { ap, dst } => {
+                let ty = self.asm_type(dst);
+                let stack_label = self.make_label("va_arg_stack");
+                let end_label = self.make_label("va_arg_end");
+                let ap_field = |offset| Operand::Memory(Register::RAX, offset);
+
+                // Floating point values come from the vector part of the
+                // register save area, tracked by the second offset in `ap`.
+                let (offset_field, area_end, step) = if ty.is_floating() {
+                    (4, REGISTER_SAVE_AREA, 16)
+                } else {
+                    (0, GP_SAVE_AREA, 8)
+                };
+
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(ap),
+                    dst: Operand::Reg(Register::RAX),
+                });
+
+                // Take the argument from the register save area while any of it is left.
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Longword,
+                    src: ap_field(offset_field),
+                    dst: Operand::Reg(Register::EDX),
+                });
+                instructions.push(Instruction::Cmp {
+                    ty: AsmType::Longword,
+                    src: Operand::Imm(area_end),
+                    dst: Operand::Reg(Register::EDX),
+                });
+                instructions.push(Instruction::JmpCC {
+                    cond: CondCode::AE,
+                    target: stack_label.clone(),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: ap_field(16),
+                    dst: Operand::Reg(Register::RCX),
+                });
+                instructions.push(Instruction::Mov {
+                    ty,
+                    src: Operand::Indexed {
+                        base: Register::RCX,
+                        index: Register::RDX,
+                        scale: 1,
+                    },
+                    dst: self.convert_value(dst),
+                });
+                instructions.push(Instruction::Add {
+                    ty: AsmType::Longword,
+                    src: Operand::Imm(step),
+                    dst: ap_field(offset_field),
+                });
+                instructions.push(Instruction::Jmp(end_label.clone()));
+
+                // Otherwise it is the next eightbyte of the caller's stack arguments.
+                instructions.push(Instruction::Label(stack_label));
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: ap_field(8),
+                    dst: Operand::Reg(Register::RCX),
+                });
+                instructions.push(Instruction::Mov {
+                    ty,
+                    src: Operand::Memory(Register::RCX, 0),
+                    dst: self.convert_value(dst),
+                });
+                instructions.push(Instruction::Add {
+                    ty: AsmType::Quadword,
+                    src: Operand::Imm(8),
+                    dst: ap_field(8),
+                });
+                instructions.push(Instruction::Label(end_label));
+            }
+
+            ir::Instruction::FunCall { name, args, dst } => {
+                self.generate_call(CallTarget::Named(name), args, dst.as_ref(), instructions);
+            }
+
+            ir::Instruction::IndirectCall { target, args, dst } => {
+                self.generate_call(
+                    CallTarget::Pointer(target),
+                    args,
+                    dst.as_ref(),
+                    instructions,
+                );
+            }
+
+            // A function's address comes from the code itself when it is defined
+            // in this file, and from the GOT when it may be in a shared library.
+            ir::Instruction::GetAddress {
+                src: Value::Var(name),
+                dst,
+            } if matches!(self.symbols.type_of(name), types::T::FunType { .. }) => {
+                let dst = self.convert_value(dst);
+                instructions.push(if self.defined_functions.contains(name) {
+                    Instruction::Lea {
+                        src: Operand::Data(name.clone()),
+                        dst,
+                    }
+                } else {
+                    Instruction::Mov {
+                        ty: AsmType::Quadword,
+                        src: Operand::Data(format!("{name}@GOTPCREL")),
+                        dst,
+                    }
+                });
+            }
+
+            ir::Instruction::GetAddress { src, dst } => {
+                instructions.push(Instruction::Lea {
+                    src: self.convert_value(src),
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::Load { src_ptr, dst } => {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(src_ptr),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                let src = Operand::Memory(Register::RAX, 0);
+                if self.is_structure(dst) {
+                    let size = self.symbols.size_of(&dst.typ(&self.symbols));
+                    let dst = self.convert_value(dst);
+                    copy_bytes(&src, &dst, size, instructions);
+                } else {
+                    instructions.push(Instruction::Mov {
+                        ty: self.asm_type(dst),
+                        src,
+                        dst: self.convert_value(dst),
+                    });
+                }
+            }
+
+            ir::Instruction::Store { src, dst_ptr } => {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(dst_ptr),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                let dst = Operand::Memory(Register::RAX, 0);
+                if self.is_structure(src) {
+                    let size = self.symbols.size_of(&src.typ(&self.symbols));
+                    let src = self.convert_value(src);
+                    copy_bytes(&src, &dst, size, instructions);
+                } else {
+                    instructions.push(Instruction::Mov {
+                        ty: self.asm_type(src),
+                        src: self.convert_value(src),
+                        dst,
+                    });
+                }
+            }
+
+            ir::Instruction::AddPtr {
+                ptr,
+                index,
+                scale,
+                dst,
+            } => {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(ptr),
+                    dst: Operand::Reg(Register::RAX),
+                });
+
+                let src = match index {
+                    Value::Constant(c) => Operand::Memory(Register::RAX, c.as_i64() * scale),
+                    Value::Var(_) => {
+                        instructions.push(Instruction::Mov {
+                            ty: AsmType::Quadword,
+                            src: self.convert_value(index),
+                            dst: Operand::Reg(Register::RDX),
+                        });
+
+                        // An index register can only be scaled by 1, 2, 4 or 8.
+                        if matches!(scale, 1 | 2 | 4 | 8) {
+                            Operand::Indexed {
+                                base: Register::RAX,
+                                index: Register::RDX,
+                                scale: *scale,
+                            }
+                        } else {
+                            instructions.push(Instruction::Imul {
+                                ty: AsmType::Quadword,
+                                src: Operand::Imm(*scale),
+                                dst: Operand::Reg(Register::RDX),
+                            });
+                            Operand::Indexed {
+                                base: Register::RAX,
+                                index: Register::RDX,
+                                scale: 1,
                             }
                         }
                     }
+                };
+
+                instructions.push(Instruction::Lea {
+                    src,
+                    dst: self.convert_value(dst),
+                });
+            }
+
+            ir::Instruction::InlineAsm {
+                template,
+                outputs,
+                inputs,
+                clobbers,
+            } => self.generate_inline_asm(template, outputs, inputs, clobbers, instructions),
+
+            // Grow the stack by `size` rounded up to a multiple of 16, which
+            // keeps `%rsp` aligned for calls. The epilogue restores `%rsp`
+            // from `%rbp`, freeing the space when the function returns, if
+            // `RestoreStack` has not already at the end of its scope.
+            ir::Instruction::StackAllocate { size, dst } => {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(size),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                instructions.push(Instruction::Add {
+                    ty: AsmType::Quadword,
+                    src: Operand::Imm(15),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                instructions.push(Instruction::And {
+                    ty: AsmType::Quadword,
+                    src: Operand::Imm(-16),
+                    dst: Operand::Reg(Register::RAX),
+                });
+                instructions.push(Instruction::Sub {
+                    ty: AsmType::Quadword,
+                    src: Operand::Reg(Register::RAX),
+                    dst: Operand::Reg(Register::RSP),
+                });
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: Operand::Reg(Register::RSP),
+                    dst: self.convert_value(dst),
+                });
+            }
+            ir::Instruction::SaveStack { dst } => instructions.push(Instruction::Mov {
+                ty: AsmType::Quadword,
+                src: Operand::Reg(Register::RSP),
+                dst: self.convert_value(dst),
+            }),
+            ir::Instruction::RestoreStack { src } => instructions.push(Instruction::Mov {
+                ty: AsmType::Quadword,
+                src: self.convert_value(src),
+                dst: Operand::Reg(Register::RSP),
+            }),
+        }
+    }
+
+    /// Loads the inputs of an `asm` statement and the addresses of its memory
+    /// operands into the registers picked for them, runs the assembly and
+    /// copies the outputs out of their registers.
+    fn generate_inline_asm(
+        &mut self,
+        template: &str,
+        outputs: &[ir::AsmOperand],
+        inputs: &[ir::AsmOperand],
+        clobbers: &[String],
+        instructions: &mut Vec<Instruction>,
+    ) {
+        const CHECKED: &str = "asm statements were checked by the type checker";
+        let constraints = |operands: &[ir::AsmOperand], output| {
+            operands
+                .iter()
+                .map(|operand| Constraint::parse(&operand.constraint, output).expect(CHECKED))
+                .collect::<Vec<_>>()
+        };
+        let clobbered: Vec<Register> = clobbers
+            .iter()
+            .filter_map(|clobber| match Clobber::parse(clobber).expect(CHECKED) {
+                Clobber::Register(reg) => Some(reg),
+                Clobber::Memory | Clobber::Ignored => None,
+            })
+            .collect();
+        let locations = inline_asm::assign_locations(
+            &constraints(outputs, true),
+            &constraints(inputs, false),
+            &clobbered,
+        )
+        .expect(CHECKED);
+
+        let mut operands = Vec::new();
+        let mut uses = Vec::new();
+        let mut written = clobbered.clone();
+        let mut copy_out = Vec::new();
+        for (i, (operand, location)) in outputs.iter().chain(inputs).zip(locations).enumerate() {
+            let ty = self.asm_type(&operand.value);
+            let value = self.convert_value(&operand.value);
+            operands.push(match location {
+                Location::Register(reg) if i < outputs.len() => {
+                    written.push(reg);
+                    copy_out.push(Instruction::Mov {
+                        ty,
+                        src: Operand::Reg(reg.sized(ty)),
+                        dst: value,
+                    });
+                    Operand::Reg(reg.sized(ty))
                 }
+                Location::Register(reg) => {
+                    uses.push(reg);
+                    instructions.push(Instruction::Mov {
+                        ty,
+                        src: value,
+                        dst: Operand::Reg(reg.sized(ty)),
+                    });
+                    Operand::Reg(reg.sized(ty))
+                }
+                Location::Memory(reg) => {
+                    uses.push(reg);
+                    instructions.push(Instruction::Mov {
+                        ty: AsmType::Quadword,
+                        src: value,
+                        dst: Operand::Reg(reg),
+                    });
+                    Operand::Memory(reg, 0)
+                }
+                Location::Immediate => value,
+            });
+        }
+
+        instructions.push(Instruction::InlineAsm {
+            template: template.to_owned(),
+            operands,
+            names: outputs
+                .iter()
+                .chain(inputs)
+                .map(|o| o.name.clone())
+                .collect(),
+            uses,
+            clobbers: written,
+        });
+        instructions.extend(copy_out);
+    }
+
+    /// Calls a function following the System V calling convention: integer
+    /// and floating point arguments fill their own argument registers and the
+    /// rest go on the stack, last one first.
+    fn generate_call(
+        &mut self,
+        target: CallTarget,
+        args: &[Value],
+        dst: Option<&Value>,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        let types: Vec<AsmType> = args.iter().map(|arg| self.asm_type(arg)).collect();
+        let locations = classify_args(&types);
+        let stack_args: Vec<(&Value, AsmType)> = args
+            .iter()
+            .zip(&types)
+            .zip(&locations)
+            .filter(|(_, location)| matches!(location, ArgLocation::Stack(_)))
+            .map(|((arg, ty), _)| (arg, *ty))
+            .collect();
+
+        // %rsp has to be 16-byte aligned at the call.
+        let padding = if stack_args.len() % 2 == 1 { 8 } else { 0 };
+        if padding != 0 {
+            instructions.push(Instruction::AllocateStack(padding));
+        }
+
+        for ((arg, ty), location) in args.iter().zip(&types).zip(&locations) {
+            if let ArgLocation::Reg(reg) = location {
+                instructions.push(Instruction::Mov {
+                    ty: *ty,
+                    src: self.convert_value(arg),
+                    dst: Operand::Reg(reg.sized(*ty)),
+                });
             }
         }
 
-        TopLevel::Function {
-            name: func.identifier.clone(),
-            global: true,
-            instructions,
+        for (arg, ty) in stack_args.iter().rev() {
+            let operand = self.convert_value(arg);
+
+            // pushq always reads 8 bytes, which could run past the end of a
+            // 4 byte value in memory, so those go through a register instead,
+            // or are stored below the stack pointer if they are floats, which
+            // may be in a vector register.
+            if matches!(ty, AsmType::Quadword | AsmType::Double)
+                || matches!(operand, Operand::Imm(_))
+            {
+                instructions.push(Instruction::Push(operand));
+            } else if ty.is_floating() {
+                instructions.push(Instruction::AllocateStack(8));
+                instructions.push(Instruction::Mov {
+                    ty: *ty,
+                    src: operand,
+                    dst: Operand::Memory(Register::RSP, 0),
+                });
+            } else {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Longword,
+                    src: operand,
+                    dst: Operand::Reg(Register::EAX),
+                });
+                instructions.push(Instruction::Push(Operand::Reg(Register::RAX)));
+            }
+        }
+
+        let fun_type = match target {
+            CallTarget::Named(name) => self.symbols.type_of(name).clone(),
+            CallTarget::Pointer(ptr) => match ptr.typ(&self.symbols) {
+                types::T::Pointer(referenced) => *referenced,
+                other => panic!("Called a non-pointer of type {:?}", other),
+            },
+        };
+
+        // The call reads the registers its arguments are in.
+        let mut uses: Vec<Register> = locations
+            .iter()
+            .filter_map(|location| match location {
+                ArgLocation::Reg(reg) => Some(*reg),
+                ArgLocation::Stack(_) => None,
+            })
+            .collect();
+
+        // A variadic callee is told how many vector registers hold arguments in %al.
+        if let types::T::FunType { variadic: true, .. } = fun_type {
+            let vector_args = uses.iter().filter(|reg| reg.is_xmm()).count();
+            instructions.push(Instruction::Mov {
+                ty: AsmType::Longword,
+                src: Operand::Imm(vector_args as i64),
+                dst: Operand::Reg(Register::EAX),
+            });
+            uses.push(Register::RAX);
+        }
+
+        match target {
+            CallTarget::Named(name) => instructions.push(Instruction::Call {
+                name: name.to_owned(),
+                plt: !self.defined_functions.contains(name),
+                uses,
+            }),
+            // %r11 does not pass arguments, so it is free to hold the target.
+            CallTarget::Pointer(ptr) => {
+                instructions.push(Instruction::Mov {
+                    ty: AsmType::Quadword,
+                    src: self.convert_value(ptr),
+                    dst: Operand::Reg(Register::R11),
+                });
+                instructions.push(Instruction::CallIndirect {
+                    target: Operand::Reg(Register::R11),
+                    uses,
+                });
+            }
+        }
+
+        let bytes = 8 * stack_args.len() as i64 + padding;
+        if bytes != 0 {
+            instructions.push(Instruction::DeallocateStack(bytes));
+        }
+
+        if let Some(dst) = dst {
+            let ty = self.asm_type(dst);
+            instructions.push(Instruction::Mov {
+                ty,
+                src: Operand::Reg(return_register(ty)),
+                dst: self.convert_value(dst),
+            });
+        }
+    }
+
+    fn make_label(&mut self, prefix: &str) -> String {
+        let label = format!("{prefix}.{}", self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// A read-only copy of the floating point constant `value`, shared with
+    /// every other use of the same bits and alignment.
+    fn static_constant(&mut self, value: &ir::Constant, alignment: i64) -> Operand {
+        let ty = convert_type(&value.typ(), &self.symbols);
+        let bits = value.as_i64();
+
+        let existing = self.constants.iter().find_map(|constant| match constant {
+            TopLevel::StaticConstant {
+                name,
+                alignment: a,
+                ty: t,
+                bits: b,
+            } if (*a, *t, *b) == (alignment, ty, bits) => Some(name.clone()),
+            _ => None,
+        });
+
+        let name = existing.unwrap_or_else(|| {
+            let prefix = if ty == AsmType::Single {
+                "float"
+            } else {
+                "double"
+            };
+            let name = format!(".L{}", self.make_label(prefix));
+            self.constants.push(TopLevel::StaticConstant {
+                name: name.clone(),
+                alignment,
+                ty,
+                bits,
+            });
+            name
+        });
+
+        Operand::Data(name)
+    }
+
+    fn convert_value(&mut self, val: &Value) -> Operand {
+        match val {
+            Value::Constant(c @ (ir::Constant::Float(_) | ir::Constant::Double(_))) => {
+                self.static_constant(c, c.typ().size())
+            }
+            Value::Constant(c) => Operand::Imm(c.as_i64()),
+            // Objects with static storage duration live in the data sections.
+            Value::Var(name) if self.symbols.is_static(name) => Operand::Data(name.clone()),
+            Value::Var(name) => Operand::Pseudo(name.clone()),
+        }
+    }
+
+    fn is_structure(&self, val: &Value) -> bool {
+        matches!(val.typ(&self.symbols), types::T::Structure(_))
+    }
+
+    fn asm_type(&self, val: &Value) -> AsmType {
+        convert_type(&val.typ(&self.symbols), &self.symbols)
+    }
+
+    /// The pseudos that can be kept in a register, with their types: those
+    /// with scalar types whose address is never taken. Volatile variables
+    /// stay in memory, as do those declared with an alignment, which is
+    /// only kept for their stack slot.
+    fn register_candidates(&self, instructions: &[Instruction]) -> HashMap<String, AsmType> {
+        let mut candidates = HashMap::new();
+        let mut address_taken = HashSet::new();
+        for instr in instructions {
+            if let Instruction::Lea {
+                src: Operand::Pseudo(name),
+                ..
+            } = instr
+            {
+                address_taken.insert(name);
+            }
+            for (operand, _) in instr.operands() {
+                if let Operand::Pseudo(name) = operand {
+                    let typ = self.symbols.type_of(name);
+                    if typ.is_scalar()
+                        && !typ.qualifiers().volatile
+                        && self.symbols.requested_alignment(name).is_none()
+                    {
+                        candidates.insert(name.clone(), convert_type(typ, &self.symbols));
+                    }
+                }
+            }
+        }
+
+        candidates.retain(|name, _| !address_taken.contains(name));
+        candidates
+    }
+
+    /// The registers the function `name` returns its value in.
+    fn return_registers(&self, name: &str) -> Vec<Register> {
+        match self.symbols.type_of(name) {
+            types::T::FunType { ret, .. } if **ret != types::T::Void => {
+                vec![return_register(convert_type(ret, &self.symbols))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Gives the variables aligned to more than the 16 bytes the frame is
+    /// aligned to their slots in an area below the rest of the frame, whose
+    /// start is aligned at run time by rounding `%rsp` down and kept in
+    /// `%rbx`. Since the slots are addressed from `%rbx`, the register
+    /// allocator leaves it alone while any of them is still used. Returns
+    /// whether there were any.
+    fn realign_frame(&mut self, instructions: &mut Vec<Instruction>) -> bool {
+        let mut offsets: HashMap<String, i64> = HashMap::new();
+        let mut area_size = 0;
+        let mut area_alignment = 16;
+        let symbols = &self.symbols;
+
+        for instr in instructions.iter_mut() {
+            for (operand, _) in instr.operands_mut() {
+                let (name, offset) = match operand {
+                    Operand::Pseudo(name) => (name.clone(), 0),
+                    Operand::PseudoMem(name, offset) => (name.clone(), *offset),
+                    _ => continue,
+                };
+                let alignment = declared_alignment(&name, symbols);
+                if alignment <= 16 {
+                    continue;
+                }
+
+                let base = *offsets.entry(name.clone()).or_insert_with(|| {
+                    let base = (area_size + alignment - 1) / alignment * alignment;
+                    area_size = base + symbols.size_of(symbols.type_of(&name));
+                    area_alignment = area_alignment.max(alignment);
+                    base
+                });
+                *operand = Operand::Memory(Register::RBX, base + offset);
+            }
+        }
+
+        if offsets.is_empty() {
+            return false;
+        }
+
+        let rsp = Operand::Reg(Register::RSP);
+        let realign = [
+            Instruction::Sub {
+                ty: AsmType::Quadword,
+                src: Operand::Imm((area_size + 15) / 16 * 16),
+                dst: rsp.clone(),
+            },
+            Instruction::And {
+                ty: AsmType::Quadword,
+                src: Operand::Imm(-area_alignment),
+                dst: rsp.clone(),
+            },
+            Instruction::Mov {
+                ty: AsmType::Quadword,
+                src: rsp,
+                dst: Operand::Reg(Register::RBX),
+            },
+        ];
+        instructions.splice(0..0, realign);
+        true
+    }
+
+    /// Assigns every pseudo register its own stack slot below the `reserved`
+    /// bytes at the top of the frame and returns the number of bytes of stack
+    /// the function needs.
+    fn replace_pseudos(&mut self, instructions: &mut [Instruction], reserved: i64) -> i64 {
+        let mut offsets: HashMap<String, i64> = HashMap::new();
+        let mut stack_size = reserved;
+        let symbols = &self.symbols;
+
+        let mut replace = |operand: &mut Operand| {
+            let (name, offset) = match operand {
+                Operand::Pseudo(name) => (name.clone(), 0),
+                Operand::PseudoMem(name, offset) => (name.clone(), *offset),
+                _ => return,
+            };
+
+            let base = *offsets.entry(name.clone()).or_insert_with(|| {
+                let typ = symbols.type_of(&name);
+                let alignment = declared_alignment(&name, symbols);
+                // A variable length array's slot holds the array's address.
+                let size = if typ.has_variable_size() {
+                    8
+                } else {
+                    symbols.size_of(typ)
+                };
+                // Round up so the slot is aligned.
+                stack_size = (stack_size + size + alignment - 1) / alignment * alignment;
+                -stack_size
+            });
+            *operand = Operand::Stack(base + offset);
+        };
+
+        for instr in instructions.iter_mut() {
+            for (operand, _) in instr.operands_mut() {
+                replace(operand);
+            }
         }
+
+        stack_size
     }
+
+    /// Rewrites instructions whose operands x86-64 cannot encode, such as
+    /// memory-to-memory moves, and reserves the function's stack space.
+    fn fixup_instructions(
+        &mut self,
+        stack_size: i64,
+        instructions: Vec<Instruction>,
+    ) -> Vec<Instruction> {
+        let mut fixed = Vec::new();
+
+        if stack_size > 0 {
+            // Keep %rsp 16-byte aligned so calls made from this function are ABI compliant.
+            fixed.push(Instruction::AllocateStack((stack_size + 15) / 16 * 16));
+        }
+
+        for instr in instructions {
+            match instr {
+                // SSE arithmetic can only write to a register.
+                Instruction::Add { ty, src, dst } if ty.is_floating() && !is_register(&dst) => {
+                    fixed.extend(via_xmm15(ty, dst, |dst| Instruction::Add { ty, src, dst }));
+                }
+                Instruction::Sub { ty, src, dst } if ty.is_floating() && !is_register(&dst) => {
+                    fixed.extend(via_xmm15(ty, dst, |dst| Instruction::Sub { ty, src, dst }));
+                }
+                Instruction::Xor { ty, src, dst } if ty.is_floating() && !is_register(&dst) => {
+                    fixed.extend(via_xmm15(ty, dst, |dst| Instruction::Xor { ty, src, dst }));
+                }
+                Instruction::MulFloat { ty, src, dst } if !is_register(&dst) => {
+                    fixed.extend(via_xmm15(ty, dst, |dst| Instruction::MulFloat {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+                Instruction::DivFloat { ty, src, dst } if !is_register(&dst) => {
+                    fixed.extend(via_xmm15(ty, dst, |dst| Instruction::DivFloat {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+
+                // comiss and comisd compare against a register.
+                Instruction::Cmp { ty, src, dst } if ty.is_floating() && !is_register(&dst) => {
+                    let xmm15 = Operand::Reg(Register::XMM15);
+                    fixed.push(Instruction::Mov {
+                        ty,
+                        src: dst,
+                        dst: xmm15.clone(),
+                    });
+                    fixed.push(Instruction::Cmp {
+                        ty,
+                        src,
+                        dst: xmm15,
+                    });
+                }
+
+                // cvtsi2 cannot take an immediate and can only write to a register.
+                Instruction::Cvtsi2 {
+                    src_ty,
+                    dst_ty,
+                    src,
+                    dst,
+                } => {
+                    let src = if matches!(src, Operand::Imm(_)) {
+                        let r10 = Operand::Reg(Register::R10.sized(src_ty));
+                        fixed.push(Instruction::Mov {
+                            ty: src_ty,
+                            src,
+                            dst: r10.clone(),
+                        });
+                        r10
+                    } else {
+                        src
+                    };
+
+                    fixed.extend(to_register(dst_ty, dst, Register::XMM15, |dst| {
+                        Instruction::Cvtsi2 {
+                            src_ty,
+                            dst_ty,
+                            src,
+                            dst,
+                        }
+                    }));
+                }
+
+                // The remaining conversions can only write to a register.
+                Instruction::Cvtt2si {
+                    src_ty,
+                    dst_ty,
+                    src,
+                    dst,
+                } => {
+                    let r11 = Register::R11.sized(dst_ty);
+                    fixed.extend(to_register(dst_ty, dst, r11, |dst| Instruction::Cvtt2si {
+                        src_ty,
+                        dst_ty,
+                        src,
+                        dst,
+                    }));
+                }
+                Instruction::Cvtss2sd { src, dst } => {
+                    fixed.extend(to_register(AsmType::Double, dst, Register::XMM15, |dst| {
+                        Instruction::Cvtss2sd { src, dst }
+                    }));
+                }
+                Instruction::Cvtsd2ss { src, dst } => {
+                    fixed.extend(to_register(AsmType::Single, dst, Register::XMM15, |dst| {
+                        Instruction::Cvtsd2ss { src, dst }
+                    }));
+                }
+
+                // movl only takes a 32 bit immediate, so keep just the low bits.
+                Instruction::Mov {
+                    ty: AsmType::Longword,
+                    src: Operand::Imm(val),
+                    dst,
+                } if is_large(val) => {
+                    fixed.push(Instruction::Mov {
+                        ty: AsmType::Longword,
+                        src: Operand::Imm(val as i32 as i64),
+                        dst,
+                    });
+                }
+
+                Instruction::Mov { ty, src, dst }
+                    if (is_memory(&src) || is_large_imm(&src)) && is_memory(&dst) =>
+                {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Mov {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+
+                Instruction::Add { ty, src, dst } if needs_scratch(&src, &dst) => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Add {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+                Instruction::Sub { ty, src, dst } if needs_scratch(&src, &dst) => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Sub {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+                Instruction::And { ty, src, dst } if needs_scratch(&src, &dst) => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::And {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+                Instruction::Or { ty, src, dst } if needs_scratch(&src, &dst) => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Or { ty, src, dst }));
+                }
+                Instruction::Xor { ty, src, dst } if needs_scratch(&src, &dst) => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Xor {
+                        ty,
+                        src,
+                        dst,
+                    }));
+                }
+
+                // cmp cannot compare two memory operands or take an immediate
+                // as its second operand.
+                Instruction::Cmp { ty, src, dst }
+                    if needs_scratch(&src, &dst) || matches!(dst, Operand::Imm(_)) =>
+                {
+                    let src = if needs_scratch(&src, &dst) {
+                        let r10 = Operand::Reg(Register::R10.sized(ty));
+                        fixed.push(Instruction::Mov {
+                            ty,
+                            src,
+                            dst: r10.clone(),
+                        });
+                        r10
+                    } else {
+                        src
+                    };
+
+                    let dst = if matches!(dst, Operand::Imm(_)) {
+                        let r11 = Operand::Reg(Register::R11.sized(ty));
+                        fixed.push(Instruction::Mov {
+                            ty,
+                            src: dst,
+                            dst: r11.clone(),
+                        });
+                        r11
+                    } else {
+                        dst
+                    };
+
+                    fixed.push(Instruction::Cmp { ty, src, dst });
+                }
+
+                // lea can only write to a register.
+                Instruction::Lea { src, dst } if is_memory(&dst) => {
+                    fixed.push(Instruction::Lea {
+                        src,
+                        dst: Operand::Reg(Register::R11),
+                    });
+                    fixed.push(Instruction::Mov {
+                        ty: AsmType::Quadword,
+                        src: Operand::Reg(Register::R11),
+                        dst,
+                    });
+                }
+
+                Instruction::Push(src) if is_large_imm(&src) => {
+                    fixed.extend(via_scratch(AsmType::Quadword, src, Instruction::Push));
+                }
+
+                // There is no push for vector registers.
+                Instruction::Push(Operand::Reg(reg)) if reg.is_xmm() => {
+                    fixed.push(Instruction::AllocateStack(8));
+                    fixed.push(Instruction::Mov {
+                        ty: AsmType::Double,
+                        src: Operand::Reg(reg),
+                        dst: Operand::Memory(Register::RSP, 0),
+                    });
+                }
+
+                // imul cannot write to memory, so multiply in %r11 and store the result.
+                Instruction::Imul { ty, src, dst } if is_memory(&dst) || is_large_imm(&src) => {
+                    let src = if is_large_imm(&src) {
+                        let r10 = Operand::Reg(Register::R10.sized(ty));
+                        fixed.push(Instruction::Mov {
+                            ty,
+                            src,
+                            dst: r10.clone(),
+                        });
+                        r10
+                    } else {
+                        src
+                    };
+
+                    let r11 = Operand::Reg(Register::R11.sized(ty));
+                    fixed.push(Instruction::Mov {
+                        ty,
+                        src: dst.clone(),
+                        dst: r11.clone(),
+                    });
+                    fixed.push(Instruction::Imul {
+                        ty,
+                        src,
+                        dst: r11.clone(),
+                    });
+                    fixed.push(Instruction::Mov { ty, src: r11, dst });
+                }
+
+                // Division cannot take an immediate operand.
+                Instruction::Idiv {
+                    ty,
+                    src: src @ Operand::Imm(_),
+                } => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Idiv { ty, src }));
+                }
+                Instruction::Div {
+                    ty,
+                    src: src @ Operand::Imm(_),
+                } => {
+                    fixed.extend(via_scratch(ty, src, |src| Instruction::Div { ty, src }));
+                }
+
+                // A variable shift count has to live in %cl.
+                Instruction::Sal { ty, count, dst } if !matches!(count, Operand::Imm(_)) => {
+                    fixed.extend(via_cl(ty, count, |count| Instruction::Sal {
+                        ty,
+                        count,
+                        dst,
+                    }));
+                }
+                Instruction::Sar { ty, count, dst } if !matches!(count, Operand::Imm(_)) => {
+                    fixed.extend(via_cl(ty, count, |count| Instruction::Sar {
+                        ty,
+                        count,
+                        dst,
+                    }));
+                }
+                Instruction::Shr { ty, count, dst } if !matches!(count, Operand::Imm(_)) => {
+                    fixed.extend(via_cl(ty, count, |count| Instruction::Shr {
+                        ty,
+                        count,
+                        dst,
+                    }));
+                }
+
+                // movslq cannot take an immediate source or write to memory.
+                Instruction::Movsx { src, dst } => {
+                    let src = if matches!(src, Operand::Imm(_)) {
+                        fixed.push(Instruction::Mov {
+                            ty: AsmType::Longword,
+                            src,
+                            dst: Operand::Reg(Register::R10D),
+                        });
+                        Operand::Reg(Register::R10D)
+                    } else {
+                        src
+                    };
+
+                    if is_memory(&dst) {
+                        fixed.push(Instruction::Movsx {
+                            src,
+                            dst: Operand::Reg(Register::R11),
+                        });
+                        fixed.push(Instruction::Mov {
+                            ty: AsmType::Quadword,
+                            src: Operand::Reg(Register::R11),
+                            dst,
+                        });
+                    } else {
+                        fixed.push(Instruction::Movsx { src, dst });
+                    }
+                }
+
+                // movzb cannot take an immediate source or write to memory.
+                Instruction::MovZeroExtend {
+                    src_ty: AsmType::Byte,
+                    dst_ty,
+                    src,
+                    dst,
+                } => {
+                    let src = if matches!(src, Operand::Imm(_)) {
+                        fixed.push(Instruction::Mov {
+                            ty: AsmType::Byte,
+                            src,
+                            dst: Operand::Reg(Register::R10B),
+                        });
+                        Operand::Reg(Register::R10B)
+                    } else {
+                        src
+                    };
+
+                    let r11 = Register::R11.sized(dst_ty);
+                    fixed.extend(to_register(dst_ty, dst, r11, |dst| {
+                        Instruction::MovZeroExtend {
+                            src_ty: AsmType::Byte,
+                            dst_ty,
+                            src,
+                            dst,
+                        }
+                    }));
+                }
+
+                // Writing a 32 bit register clears the upper half of the full register,
+                // so a plain movl is a zero extension.
+                Instruction::MovZeroExtend { src, dst, .. } => match dst {
+                    Operand::Reg(reg) => fixed.push(Instruction::Mov {
+                        ty: AsmType::Longword,
+                        src,
+                        dst: Operand::Reg(reg.sized(AsmType::Longword)),
+                    }),
+                    _ => {
+                        fixed.push(Instruction::Mov {
+                            ty: AsmType::Longword,
+                            src,
+                            dst: Operand::Reg(Register::R11D),
+                        });
+                        fixed.push(Instruction::Mov {
+                            ty: AsmType::Quadword,
+                            src: Operand::Reg(Register::R11),
+                            dst,
+                        });
+                    }
+                },
+
+                other => fixed.push(other),
+            }
+        }
+
+        fixed
+    }
+}
+
+/// Saves the callee-saved registers that inline assembly uses, along with
+/// those in `allocated` that pseudos were assigned, in stack slots below the
+/// `stack_size` bytes the function already needs, restoring them before
+/// every return. Returns the new stack size.
+fn save_callee_saved(
+    stack_size: i64,
+    instructions: Vec<Instruction>,
+    allocated: &[Register],
+) -> (i64, Vec<Instruction>) {
+    let mut saved: Vec<Register> = allocated.to_vec();
+    for instr in &instructions {
+        if let Instruction::InlineAsm { uses, clobbers, .. } = instr {
+            for reg in uses.iter().chain(clobbers) {
+                if reg.is_callee_saved() && !saved.contains(reg) {
+                    saved.push(*reg);
+                }
+            }
+        }
+    }
+    if saved.is_empty() {
+        return (stack_size, instructions);
+    }
+
+    let slot = |i: usize| Operand::Stack(-(stack_size + 8 * (i as i64 + 1)));
+    let mut with_saves: Vec<Instruction> = saved
+        .iter()
+        .enumerate()
+        .map(|(i, reg)| Instruction::Mov {
+            ty: AsmType::Quadword,
+            src: Operand::Reg(*reg),
+            dst: slot(i),
+        })
+        .collect();
+    for instr in instructions {
+        if let Instruction::Ret = instr {
+            with_saves.extend(saved.iter().enumerate().map(|(i, reg)| Instruction::Mov {
+                ty: AsmType::Quadword,
+                src: slot(i),
+                dst: Operand::Reg(*reg),
+            }));
+        }
+        with_saves.push(instr);
+    }
+
+    (stack_size + 8 * saved.len() as i64, with_saves)
+}
+
+fn convert_type(typ: &types::T, symbols: &SymbolTable) -> AsmType {
+    // Variable length arrays are represented by their address.
+    if typ.has_variable_size() {
+        return AsmType::Quadword;
+    }
+
+    match typ.unqualified() {
+        types::T::Bool => AsmType::Byte,
+        types::T::Int | types::T::UInt => AsmType::Longword,
+        types::T::Long | types::T::ULong | types::T::Pointer(_) => AsmType::Quadword,
+        types::T::Float => AsmType::Single,
+        types::T::Double => AsmType::Double,
+        typ @ (types::T::Array { .. } | types::T::Structure(_)) => AsmType::ByteArray {
+            size: symbols.size_of(typ),
+            alignment: variable_alignment(typ, symbols),
+        },
+        _ => panic!("No assembly type for {:?}", typ),
+    }
+}
+
+/// The alignment of the variable `name`: what its type needs, or more if its
+/// declaration asked for it with `_Alignas`.
+fn declared_alignment(name: &str, symbols: &SymbolTable) -> i64 {
+    let alignment = variable_alignment(symbols.type_of(name), symbols);
+    symbols
+        .requested_alignment(name)
+        .map_or(alignment, |requested| requested.max(alignment))
+}
+
+/// The alignment of a variable of type `typ`. The System V ABI aligns arrays
+/// of 16 bytes or more to 16 bytes, beyond what their elements need.
+fn variable_alignment(typ: &types::T, symbols: &SymbolTable) -> i64 {
+    match typ.unqualified() {
+        _ if typ.has_variable_size() => 8,
+        types::T::Array { .. } if symbols.size_of(typ) >= 16 => 16,
+        _ => symbols.alignment_of(typ),
+    }
+}
+
+/// Integers and pointers go in the six integer argument registers and floating
+/// point values in the eight vector ones, in order. Whatever does not fit is
+/// passed on the stack.
+fn classify_args(types: &[AsmType]) -> Vec<ArgLocation> {
+    let mut gp_registers = ARG_REGISTERS.iter();
+    let mut float_registers = FLOAT_ARG_REGISTERS.iter();
+    let mut stack_args = 0;
+
+    types
+        .iter()
+        .map(|ty| {
+            let reg = if ty.is_floating() {
+                float_registers.next()
+            } else {
+                gp_registers.next()
+            };
+
+            match reg {
+                Some(reg) => ArgLocation::Reg(*reg),
+                None => {
+                    stack_args += 1;
+                    ArgLocation::Stack(stack_args - 1)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The register a value of type `ty` is returned in.
+fn return_register(ty: AsmType) -> Register {
+    if ty.is_floating() {
+        Register::XMM0
+    } else {
+        Register::RAX.sized(ty)
+    }
+}
+
+/// Sets the flags by comparing `operand` with zero. Floating point values are
+/// compared against a zeroed %xmm0, since SSE has no immediates.
+fn compare_to_zero(ty: AsmType, operand: Operand) -> Vec<Instruction> {
+    if ty.is_floating() {
+        let xmm0 = Operand::Reg(Register::XMM0);
+        vec![
+            Instruction::Xor {
+                ty,
+                src: xmm0.clone(),
+                dst: xmm0.clone(),
+            },
+            Instruction::Cmp {
+                ty,
+                src: operand,
+                dst: xmm0,
+            },
+        ]
+    } else {
+        vec![Instruction::Cmp {
+            ty,
+            src: Operand::Imm(0),
+            dst: operand,
+        }]
+    }
+}
+
+/// The condition under which a comparison holds. Pointers compare as unsigned.
+fn condition_code(op: ir::BinaryOperator, signed: bool) -> CondCode {
+    match (op, signed) {
+        (ir::BinaryOperator::Equal, _) => CondCode::E,
+        (ir::BinaryOperator::NotEqual, _) => CondCode::NE,
+        (ir::BinaryOperator::LessThan, true) => CondCode::L,
+        (ir::BinaryOperator::LessOrEqual, true) => CondCode::LE,
+        (ir::BinaryOperator::GreaterThan, true) => CondCode::G,
+        (ir::BinaryOperator::GreaterOrEqual, true) => CondCode::GE,
+        (ir::BinaryOperator::LessThan, false) => CondCode::B,
+        (ir::BinaryOperator::LessOrEqual, false) => CondCode::BE,
+        (ir::BinaryOperator::GreaterThan, false) => CondCode::A,
+        (ir::BinaryOperator::GreaterOrEqual, false) => CondCode::AE,
+        _ => panic!("{:?} is not a comparison", op),
+    }
+}
+
+/// Moves `size` bytes from the memory at `src` to that at `dst`, eight at a
+/// time and then in smaller pieces.
+fn copy_bytes(src: &Operand, dst: &Operand, size: i64, instructions: &mut Vec<Instruction>) {
+    let mut offset = 0;
+    while offset < size {
+        let (ty, width) = match size - offset {
+            8.. => (AsmType::Quadword, 8),
+            4.. => (AsmType::Longword, 4),
+            _ => (AsmType::Byte, 1),
+        };
+        instructions.push(Instruction::Mov {
+            ty,
+            src: at_offset(src, offset),
+            dst: at_offset(dst, offset),
+        });
+        offset += width;
+    }
+}
+
+/// The memory `offset` bytes past where `operand` refers to.
+fn at_offset(operand: &Operand, offset: i64) -> Operand {
+    match operand {
+        Operand::Pseudo(name) => Operand::PseudoMem(name.clone(), offset),
+        Operand::PseudoMem(name, base) => Operand::PseudoMem(name.clone(), base + offset),
+        Operand::Memory(reg, base) => Operand::Memory(*reg, base + offset),
+        Operand::Data(name) if offset == 0 => Operand::Data(name.clone()),
+        Operand::Data(name) => Operand::Data(format!("{name}+{offset}")),
+        other => panic!("{:?} is not in memory", other),
+    }
+}
+
+fn is_memory(operand: &Operand) -> bool {
+    matches!(
+        operand,
+        Operand::Stack(_) | Operand::Memory(..) | Operand::Indexed { .. } | Operand::Data(_)
+    )
+}
+
+fn is_register(operand: &Operand) -> bool {
+    matches!(operand, Operand::Reg(_))
+}
+
+fn is_large(val: i64) -> bool {
+    i32::try_from(val).is_err()
+}
+
+/// Immediates that do not fit in 32 bits can only be used by `movq` into a register.
+fn is_large_imm(operand: &Operand) -> bool {
+    matches!(operand, Operand::Imm(val) if is_large(*val))
+}
+
+fn needs_scratch(src: &Operand, dst: &Operand) -> bool {
+    (is_memory(src) && is_memory(dst)) || is_large_imm(src)
+}
+
+/// Where a two-operand instruction works out `dst = src1 op src2`: `dst`
+/// itself, unless that is `src2`, which copying `src1` there would overwrite.
+/// The result then goes in %r11, or %xmm15 for floating point values, which
+/// are never allocated, and is copied to `dst` after.
+fn result_target(ty: AsmType, src2: &Operand, dst: &Operand) -> Operand {
+    if src2 != dst {
+        dst.clone()
+    } else if ty.is_floating() {
+        Operand::Reg(Register::XMM15)
+    } else {
+        Operand::Reg(Register::R11.sized(ty))
+    }
+}
+
+/// Whether swapping the operands of `op` gives the same result.
+fn commutes(op: ir::BinaryOperator) -> bool {
+    matches!(
+        op,
+        ir::BinaryOperator::Add
+            | ir::BinaryOperator::Multiply
+            | ir::BinaryOperator::BitwiseAnd
+            | ir::BinaryOperator::BitwiseOr
+            | ir::BinaryOperator::BitwiseXor
+    )
+}
+
+/// Loads `src` into a scratch register, %r10 or %xmm14 for floating point
+/// values, and then runs `instr` with it.
+fn via_scratch(
+    ty: AsmType,
+    src: Operand,
+    instr: impl FnOnce(Operand) -> Instruction,
+) -> Vec<Instruction> {
+    let scratch = if ty.is_floating() {
+        Operand::Reg(Register::XMM14)
+    } else {
+        Operand::Reg(Register::R10.sized(ty))
+    };
+    vec![
+        Instruction::Mov {
+            ty,
+            src,
+            dst: scratch.clone(),
+        },
+        instr(scratch),
+    ]
+}
+
+/// Runs the SSE instruction `instr` on a copy of `dst` in %xmm15 and stores
+/// the result back.
+fn via_xmm15(
+    ty: AsmType,
+    dst: Operand,
+    instr: impl FnOnce(Operand) -> Instruction,
+) -> Vec<Instruction> {
+    let xmm15 = Operand::Reg(Register::XMM15);
+    vec![
+        Instruction::Mov {
+            ty,
+            src: dst.clone(),
+            dst: xmm15.clone(),
+        },
+        instr(xmm15.clone()),
+        Instruction::Mov {
+            ty,
+            src: xmm15,
+            dst,
+        },
+    ]
+}
+
+/// Runs `instr`, which can only write to a register, with `scratch` as its
+/// destination when `dst` is not one, then stores the result to `dst`.
+fn to_register(
+    ty: AsmType,
+    dst: Operand,
+    scratch: Register,
+    instr: impl FnOnce(Operand) -> Instruction,
+) -> Vec<Instruction> {
+    if is_register(&dst) {
+        return vec![instr(dst)];
+    }
+
+    vec![
+        instr(Operand::Reg(scratch)),
+        Instruction::Mov {
+            ty,
+            src: Operand::Reg(scratch),
+            dst,
+        },
+    ]
+}
+
+/// Loads a shift count into %rcx and then runs `instr` with %cl as the count.
+fn via_cl(
+    ty: AsmType,
+    count: Operand,
+    instr: impl FnOnce(Operand) -> Instruction,
+) -> Vec<Instruction> {
+    vec![
+        Instruction::Mov {
+            ty,
+            src: count,
+            dst: Operand::Reg(Register::RCX.sized(ty)),
+        },
+        instr(Operand::Reg(Register::CL)),
+    ]
 }
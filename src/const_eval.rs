@@ -0,0 +1,472 @@
+//! Evaluation of constant expressions at compile time, for the places C
+//! requires one: array sizes, designators and the initializers of objects
+//! with static storage duration.
+
+use crate::ast::{BinaryOperator, Expression, UnaryOperator};
+use crate::errors::CompilerError;
+use crate::symbols::StaticInit;
+use crate::typechecker::{common_type, operation_type};
+use crate::types;
+
+/// The value of an arithmetic constant, held wide enough for any type it
+/// can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arithmetic {
+    Signed(i64),
+    Unsigned(u64),
+    Floating(f64),
+}
+
+impl Arithmetic {
+    /// This value converted to the arithmetic type `typ`, wrapping integers
+    /// that do not fit.
+    fn convert(self, typ: &types::T) -> Arithmetic {
+        let bits = match self {
+            Arithmetic::Signed(v) => v,
+            Arithmetic::Unsigned(v) => v as i64,
+            Arithmetic::Floating(v) if typ.is_signed() => v as i64,
+            Arithmetic::Floating(v) => v as u64 as i64,
+        };
+
+        match typ.unqualified() {
+            // Any nonzero value, even one that truncates to zero, becomes 1.
+            types::T::Bool => Arithmetic::Unsigned(u64::from(!self.is_zero())),
+            types::T::Int => Arithmetic::Signed(i64::from(bits as i32)),
+            types::T::Long => Arithmetic::Signed(bits),
+            types::T::UInt => Arithmetic::Unsigned(u64::from(bits as u32)),
+            types::T::ULong => Arithmetic::Unsigned(bits as u64),
+            types::T::Float => Arithmetic::Floating(match self {
+                Arithmetic::Signed(v) => v as f32,
+                Arithmetic::Unsigned(v) => v as f32,
+                Arithmetic::Floating(v) => v as f32,
+            } as f64),
+            types::T::Double => Arithmetic::Floating(match self {
+                Arithmetic::Signed(v) => v as f64,
+                Arithmetic::Unsigned(v) => v as f64,
+                Arithmetic::Floating(v) => v,
+            }),
+            other => panic!("No arithmetic constant of type {:?}", other),
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Arithmetic::Signed(v) => v == 0,
+            Arithmetic::Unsigned(v) => v == 0,
+            Arithmetic::Floating(v) => v == 0.0,
+        }
+    }
+}
+
+/// The value of a constant expression together with its type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    pub value: Arithmetic,
+    pub typ: types::T,
+}
+
+impl Constant {
+    fn int(value: bool) -> Constant {
+        Constant {
+            value: Arithmetic::Signed(i64::from(value)),
+            typ: types::T::Int,
+        }
+    }
+
+    /// The value as an integer, or `None` for a floating point constant.
+    /// Unsigned values above `i64::MAX` come out negative.
+    pub fn integer(&self) -> Option<i64> {
+        match self.value {
+            Arithmetic::Signed(v) => Some(v),
+            Arithmetic::Unsigned(v) => Some(v as i64),
+            Arithmetic::Floating(_) => None,
+        }
+    }
+
+    /// The static data for this value stored in an object of type `typ`.
+    pub fn static_init(&self, typ: &types::T) -> StaticInit {
+        match (self.value.convert(typ), typ.unqualified()) {
+            (Arithmetic::Signed(v), types::T::Int) => StaticInit::Int(v as i32),
+            (Arithmetic::Signed(v), _) => StaticInit::Long(v),
+            (Arithmetic::Unsigned(v), types::T::Bool) => StaticInit::UChar(v as u8),
+            (Arithmetic::Unsigned(v), types::T::UInt) => StaticInit::UInt(v as u32),
+            (Arithmetic::Unsigned(v), _) => StaticInit::ULong(v),
+            (Arithmetic::Floating(v), types::T::Float) => StaticInit::Float(v as f32),
+            (Arithmetic::Floating(v), _) => StaticInit::Double(v),
+        }
+    }
+}
+
+/// The value of `expr` if it is an arithmetic constant expression, or `None`
+/// if it is not constant at all. An expression that is constant but has no
+/// valid value, such as `1 / 0` or `2147483647 + 1`, is an error.
+///
+/// Works both on expressions straight from the parser and on ones that went
+/// through the type checker, whose explicit conversions change nothing.
+pub fn evaluate(expr: &Expression) -> Result<Option<Constant>, CompilerError> {
+    match (Evaluator { evaluated: true }).fold(expr) {
+        Ok(constant) => Ok(Some(constant)),
+        Err(Failure::NotConstant) => Ok(None),
+        Err(Failure::Invalid(err)) => Err(err),
+    }
+}
+
+/// The value of `expr` if it is an integer constant expression, as for
+/// `evaluate`.
+pub fn evaluate_integer(expr: &Expression) -> Result<Option<i64>, CompilerError> {
+    Ok(evaluate(expr)?.and_then(|constant| constant.integer()))
+}
+
+enum Failure {
+    NotConstant,
+    Invalid(CompilerError),
+}
+
+struct Evaluator {
+    /// False within an operand that is never evaluated, like the right side
+    /// of `0 && x`. It must still be a constant expression, but whatever goes
+    /// wrong when computing its value does not matter.
+    evaluated: bool,
+}
+
+impl Evaluator {
+    fn fold(&self, expr: &Expression) -> Result<Constant, Failure> {
+        let constant = |value, typ| Ok(Constant { value, typ });
+
+        match expr {
+            Expression::ConstInt(v) => constant(Arithmetic::Signed(i64::from(*v)), types::T::Int),
+            Expression::ConstLong(v) => constant(Arithmetic::Signed(*v), types::T::Long),
+            Expression::ConstUInt(v) => {
+                constant(Arithmetic::Unsigned(u64::from(*v)), types::T::UInt)
+            }
+            Expression::ConstULong(v) => constant(Arithmetic::Unsigned(*v), types::T::ULong),
+            Expression::ConstFloat(v) => {
+                constant(Arithmetic::Floating(f64::from(*v)), types::T::Float)
+            }
+            Expression::ConstDouble(v) => constant(Arithmetic::Floating(*v), types::T::Double),
+            Expression::Grouping(inner) => self.fold(inner),
+            Expression::Cast { typ, expr } if typ.is_arithmetic() => {
+                let value = self.fold(expr)?;
+                self.convert(value, typ.unqualified())
+            }
+            Expression::Unary { operator, expr } => {
+                let operand = self.fold(expr)?;
+                self.fold_unary(*operator, operand)
+            }
+            Expression::Binary {
+                operator: operator @ (BinaryOperator::And | BinaryOperator::Or),
+                left,
+                right,
+            } => {
+                let left = !self.fold(left)?.value.is_zero();
+                // The right operand is only evaluated if the left one does not
+                // already decide the result.
+                let decided = left == (*operator == BinaryOperator::Or);
+                let right = self.operand(right, !decided)?;
+                Ok(Constant::int(if decided {
+                    left
+                } else {
+                    !right.value.is_zero()
+                }))
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.fold(left)?;
+                let right = self.fold(right)?;
+                self.fold_binary(*operator, left, right)
+            }
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = !self.fold(condition)?.value.is_zero();
+                let then = self.operand(then, condition)?;
+                let otherwise = self.operand(otherwise, !condition)?;
+                let typ = common_type(&then.typ, &otherwise.typ);
+                self.convert(if condition { then } else { otherwise }, &typ)
+            }
+            Expression::SizeOfType(typ) => {
+                check_complete(typ)?;
+                constant(Arithmetic::Unsigned(typ.size() as u64), types::T::ULong)
+            }
+            Expression::AlignOfType(typ) => {
+                check_complete(typ)?;
+                constant(
+                    Arithmetic::Unsigned(typ.alignment() as u64),
+                    types::T::ULong,
+                )
+            }
+            _ => Err(Failure::NotConstant),
+        }
+    }
+
+    /// Folds an operand that is only evaluated if `evaluated` is true.
+    fn operand(&self, expr: &Expression, evaluated: bool) -> Result<Constant, Failure> {
+        Evaluator {
+            evaluated: self.evaluated && evaluated,
+        }
+        .fold(expr)
+    }
+
+    /// Reports that a constant has no valid value, unless it is never
+    /// evaluated, in which case any value of the right type will do.
+    fn invalid(&self, message: String, typ: &types::T) -> Result<Constant, Failure> {
+        if self.evaluated {
+            Err(Failure::Invalid(CompilerError::SemanticError { message }))
+        } else {
+            Ok(Constant {
+                value: Arithmetic::Signed(0).convert(typ),
+                typ: typ.clone(),
+            })
+        }
+    }
+
+    /// Converts `constant` to the arithmetic type `typ`. Integers wrap, but
+    /// a floating point value has to fit in the integer type it becomes,
+    /// unless that is `_Bool`, which only asks whether it is zero.
+    fn convert(&self, constant: Constant, typ: &types::T) -> Result<Constant, Failure> {
+        if let Arithmetic::Floating(v) = constant.value
+            && typ.is_integer()
+            && *typ.unqualified() != types::T::Bool
+        {
+            let (min, max) = integer_range(typ);
+            let truncated = v.trunc();
+            if !(truncated >= min as f64 && truncated <= max as f64) {
+                return self.invalid(format!("conversion of {v} to {:?} overflows", typ), typ);
+            }
+        }
+
+        Ok(Constant {
+            value: constant.value.convert(typ),
+            typ: typ.clone(),
+        })
+    }
+
+    fn fold_unary(&self, operator: UnaryOperator, operand: Constant) -> Result<Constant, Failure> {
+        let typ = operand.typ.clone();
+        match (operator, operand.value) {
+            (UnaryOperator::Not, value) => Ok(Constant::int(value.is_zero())),
+            (UnaryOperator::Plus, _) => Ok(operand),
+            (UnaryOperator::Negate, Arithmetic::Floating(v)) => Ok(Constant {
+                value: Arithmetic::Floating(-v),
+                typ,
+            }),
+            (UnaryOperator::Negate, Arithmetic::Unsigned(v)) => Ok(Constant {
+                value: Arithmetic::Unsigned(v.wrapping_neg()).convert(&typ),
+                typ,
+            }),
+            (UnaryOperator::Negate, Arithmetic::Signed(v)) => {
+                self.signed_result(-i128::from(v), &typ)
+            }
+            (UnaryOperator::Complement, Arithmetic::Floating(_)) => {
+                Err(Failure::Invalid(CompilerError::SemanticError {
+                    message: format!("invalid operand to {:?}: {:?}", operator, typ),
+                }))
+            }
+            (UnaryOperator::Complement, Arithmetic::Signed(v)) => Ok(Constant {
+                value: Arithmetic::Signed(!v),
+                typ,
+            }),
+            (UnaryOperator::Complement, Arithmetic::Unsigned(v)) => Ok(Constant {
+                value: Arithmetic::Unsigned(!v).convert(&typ),
+                typ,
+            }),
+        }
+    }
+
+    fn fold_binary(
+        &self,
+        operator: BinaryOperator,
+        left: Constant,
+        right: Constant,
+    ) -> Result<Constant, Failure> {
+        if (operator.requires_integers()
+            || matches!(
+                operator,
+                BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight
+            ))
+            && (left.typ.is_floating() || right.typ.is_floating())
+        {
+            return Err(Failure::Invalid(CompilerError::SemanticError {
+                message: format!(
+                    "invalid operands to {:?}: {:?} and {:?}",
+                    operator, left.typ, right.typ
+                ),
+            }));
+        }
+
+        if let BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight = operator {
+            return self.fold_shift(operator, left, right);
+        }
+
+        let typ = operation_type(operator, &left.typ, &right.typ);
+        let left = left.value.convert(&typ);
+        let right = right.value.convert(&typ);
+
+        if operator.is_comparison() {
+            let ordering = match (left, right) {
+                (Arithmetic::Signed(l), Arithmetic::Signed(r)) => l.partial_cmp(&r),
+                (Arithmetic::Unsigned(l), Arithmetic::Unsigned(r)) => l.partial_cmp(&r),
+                (Arithmetic::Floating(l), Arithmetic::Floating(r)) => l.partial_cmp(&r),
+                _ => unreachable!("Operands were converted to the same type"),
+            };
+            return Ok(Constant::int(match ordering {
+                // Every comparison with NaN is false except `!=`.
+                None => operator == BinaryOperator::NotEqual,
+                Some(ordering) => match operator {
+                    BinaryOperator::Equal => ordering.is_eq(),
+                    BinaryOperator::NotEqual => ordering.is_ne(),
+                    BinaryOperator::LessThan => ordering.is_lt(),
+                    BinaryOperator::LessOrEqual => ordering.is_le(),
+                    BinaryOperator::GreaterThan => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                },
+            }));
+        }
+
+        if matches!(operator, BinaryOperator::Divide | BinaryOperator::Remainder)
+            && typ.is_integer()
+            && right.is_zero()
+        {
+            return self.invalid("division by zero in constant expression".into(), &typ);
+        }
+
+        match (left, right) {
+            (Arithmetic::Floating(l), Arithmetic::Floating(r)) => {
+                let value = match operator {
+                    BinaryOperator::Add => l + r,
+                    BinaryOperator::Subtract => l - r,
+                    BinaryOperator::Multiply => l * r,
+                    BinaryOperator::Divide => l / r,
+                    _ => unreachable!("{:?} does not apply to floating point values", operator),
+                };
+                self.convert(
+                    Constant {
+                        value: Arithmetic::Floating(value),
+                        typ: typ.clone(),
+                    },
+                    &typ,
+                )
+            }
+            (Arithmetic::Signed(l), Arithmetic::Signed(r)) => {
+                let (l, r) = (i128::from(l), i128::from(r));
+                let value = match operator {
+                    BinaryOperator::Add => l + r,
+                    BinaryOperator::Subtract => l - r,
+                    BinaryOperator::Multiply => l * r,
+                    BinaryOperator::Divide => l / r,
+                    // Undefined whenever the quotient overflows, as with
+                    // `INT_MIN % -1`, where `idiv` traps too.
+                    BinaryOperator::Remainder => {
+                        self.signed_result(l / r, &typ)?;
+                        l % r
+                    }
+                    BinaryOperator::BitwiseAnd => l & r,
+                    BinaryOperator::BitwiseOr => l | r,
+                    BinaryOperator::BitwiseXor => l ^ r,
+                    _ => unreachable!("{:?} was handled above", operator),
+                };
+                self.signed_result(value, &typ)
+            }
+            (Arithmetic::Unsigned(l), Arithmetic::Unsigned(r)) => {
+                let value = match operator {
+                    BinaryOperator::Add => l.wrapping_add(r),
+                    BinaryOperator::Subtract => l.wrapping_sub(r),
+                    BinaryOperator::Multiply => l.wrapping_mul(r),
+                    BinaryOperator::Divide => l / r,
+                    BinaryOperator::Remainder => l % r,
+                    BinaryOperator::BitwiseAnd => l & r,
+                    BinaryOperator::BitwiseOr => l | r,
+                    BinaryOperator::BitwiseXor => l ^ r,
+                    _ => unreachable!("{:?} was handled above", operator),
+                };
+                Ok(Constant {
+                    value: Arithmetic::Unsigned(value).convert(&typ),
+                    typ,
+                })
+            }
+            _ => unreachable!("Operands were converted to the same type"),
+        }
+    }
+
+    /// Shifts take the type of their left operand. A count that is negative
+    /// or not less than its width in bits is undefined.
+    fn fold_shift(
+        &self,
+        operator: BinaryOperator,
+        left: Constant,
+        right: Constant,
+    ) -> Result<Constant, Failure> {
+        let typ = left.typ;
+        let bits = typ.size() * 8;
+        let count = match right.value {
+            Arithmetic::Signed(v) if (0..bits).contains(&v) => v as u32,
+            Arithmetic::Unsigned(v) if v < bits as u64 => v as u32,
+            _ => {
+                return self.invalid(format!("shift count is out of range for {:?}", typ), &typ);
+            }
+        };
+
+        match (operator, left.value) {
+            (BinaryOperator::ShiftLeft, Arithmetic::Signed(v)) => {
+                self.signed_result(i128::from(v) << count, &typ)
+            }
+            (_, Arithmetic::Signed(v)) => Ok(Constant {
+                value: Arithmetic::Signed(v >> count),
+                typ,
+            }),
+            (BinaryOperator::ShiftLeft, Arithmetic::Unsigned(v)) => Ok(Constant {
+                value: Arithmetic::Unsigned(v << count).convert(&typ),
+                typ,
+            }),
+            (_, Arithmetic::Unsigned(v)) => Ok(Constant {
+                value: Arithmetic::Unsigned(v >> count),
+                typ,
+            }),
+            (_, Arithmetic::Floating(_)) => unreachable!("Shifted a floating point value"),
+        }
+    }
+
+    /// The exact result of signed arithmetic, which has to fit in `typ`.
+    fn signed_result(&self, value: i128, typ: &types::T) -> Result<Constant, Failure> {
+        let (min, max) = integer_range(typ);
+        if value < min || value > max {
+            return self.invalid("integer overflow in constant expression".into(), typ);
+        }
+
+        Ok(Constant {
+            value: Arithmetic::Signed(value as i64),
+            typ: typ.clone(),
+        })
+    }
+}
+
+fn check_complete(typ: &types::T) -> Result<(), Failure> {
+    match typ.unqualified() {
+        types::T::Void | types::T::FunType { .. } => {
+            Err(Failure::Invalid(CompilerError::SemanticError {
+                message: format!("cannot take the size of incomplete type {:?}", typ),
+            }))
+        }
+        // Only the type checker knows the layout of a structure, and it
+        // replaces the size of one with a constant, so this is only reached
+        // for an array length written before then.
+        types::T::Structure(_) => Err(Failure::NotConstant),
+        types::T::Array { typ, .. } => check_complete(typ),
+        _ => Ok(()),
+    }
+}
+
+/// The smallest and largest value of the integer type `typ`.
+fn integer_range(typ: &types::T) -> (i128, i128) {
+    let bits = typ.size() * 8;
+    if typ.is_signed() {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
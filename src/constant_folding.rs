@@ -0,0 +1,306 @@
+//! Constant folding: works out at compile time the result of every operation
+//! whose operands are constants, with the wrapping, rounding and shifting
+//! behavior of the instructions the operation would otherwise compile to.
+
+use std::collections::HashMap;
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::ir::{self, BinaryOperator, Constant, Instruction, UnaryOperator, Value};
+use crate::symbols::SymbolTable;
+use crate::types;
+
+/// Folds the operations in a function body that only have constant
+/// operands, and resolves conditional jumps on constants.
+///
+/// A temporary that is written only once, with a folded constant, is
+/// replaced by the constant wherever it is read and its definition is
+/// dropped. Temporaries are always written before they are read, so this
+/// also reaches the operations that use the result, as in `~(-2)`. Folded
+/// results written to any other variable become a `Copy` of the constant.
+pub fn fold_constants(instructions: &[Instruction], symbols: &SymbolTable) -> Vec<Instruction> {
+    let mut definitions: HashMap<&str, usize> = HashMap::new();
+    for instr in instructions {
+        for name in instr.destinations() {
+            *definitions.entry(name).or_default() += 1;
+        }
+    }
+
+    let mut constants: HashMap<String, Constant> = HashMap::new();
+    let mut folded = Vec::new();
+    for instr in instructions {
+        let mut instr = instr.clone();
+        instr.replace_sources(|value| match value {
+            Value::Var(name) => constants.get(name).map(|c| Value::Constant(*c)),
+            Value::Constant(_) => None,
+        });
+
+        let Some(result) = fold_instruction(&instr, symbols) else {
+            folded.push(instr);
+            continue;
+        };
+        match result {
+            Folded::Constant(constant, Value::Var(dst))
+                if ir::is_temporary(&dst) && definitions.get(dst.as_str()) == Some(&1) =>
+            {
+                constants.insert(dst, constant);
+            }
+            Folded::Constant(constant, dst) => folded.push(Instruction::Copy {
+                src: Value::Constant(constant),
+                dst,
+            }),
+            Folded::Jump(target) => folded.push(Instruction::Jump(target)),
+            Folded::Removed => {}
+        }
+    }
+
+    folded
+}
+
+/// What an instruction with constant operands is replaced by.
+pub enum Folded {
+    /// Writing the constant to the value.
+    Constant(Constant, Value),
+    /// A conditional jump that is always taken.
+    Jump(String),
+    /// A conditional jump that is never taken.
+    Removed,
+}
+
+/// What the instruction comes to if its operands are constants and it is
+/// one that can be worked out at compile time.
+pub fn fold_instruction(instr: &Instruction, symbols: &SymbolTable) -> Option<Folded> {
+    let constant = |value: &Value| match value {
+        Value::Constant(constant) => Some(*constant),
+        Value::Var(_) => None,
+    };
+
+    let (result, dst) = match instr {
+        Instruction::Unary { op, src, dst } => (
+            of_destination_type(fold_unary(*op, constant(src)?)?, dst, symbols),
+            dst,
+        ),
+        Instruction::Binary {
+            op,
+            src1,
+            src2,
+            dst,
+        } => (
+            of_destination_type(
+                fold_binary(*op, constant(src1)?, constant(src2)?)?,
+                dst,
+                symbols,
+            ),
+            dst,
+        ),
+        // A copy also converts between signed and unsigned types of the same
+        // size, which only changes how the bits are read.
+        Instruction::Copy { src, dst } => match constant(src)? {
+            src @ (Constant::Float(_) | Constant::Double(_)) => (src, dst),
+            src => (Constant::of_type(&dst.typ(symbols), src.as_i64()), dst),
+        },
+        Instruction::SignExtend { src, dst }
+        | Instruction::ZeroExtend { src, dst }
+        | Instruction::Truncate { src, dst } => (
+            Constant::of_type(&dst.typ(symbols), constant(src)?.as_i64()),
+            dst,
+        ),
+        Instruction::IntToFloating { src, dst } => {
+            let value = constant(src)?.as_i64();
+            (floating(&dst.typ(symbols), value as f64, value as f32), dst)
+        }
+        Instruction::UIntToFloating { src, dst } => {
+            let value = constant(src)?.as_i64() as u64;
+            (floating(&dst.typ(symbols), value as f64, value as f32), dst)
+        }
+        Instruction::FloatingToInt { src, dst } | Instruction::FloatingToUInt { src, dst } => (
+            fold_floating_to_integer(constant(src)?, &dst.typ(symbols))?,
+            dst,
+        ),
+        Instruction::FloatingConvert { src, dst } => match constant(src)? {
+            Constant::Float(value) => (Constant::Double(value as f64), dst),
+            Constant::Double(value) => (Constant::Float(value as f32), dst),
+            _ => return None,
+        },
+        Instruction::JumpIfZero { condition, target } => {
+            return Some(if is_zero(constant(condition)?) {
+                Folded::Jump(target.clone())
+            } else {
+                Folded::Removed
+            });
+        }
+        Instruction::JumpIfNotZero { condition, target } => {
+            return Some(if is_zero(constant(condition)?) {
+                Folded::Removed
+            } else {
+                Folded::Jump(target.clone())
+            });
+        }
+        _ => return None,
+    };
+
+    Some(Folded::Constant(result, dst.clone()))
+}
+
+/// The integer `result` as a constant of the type of `dst`. A comparison or
+/// `!` gives an `int` whatever it writes to, which may be narrower, like the
+/// `_Bool` a conversion to `_Bool` compares into.
+fn of_destination_type(result: Constant, dst: &Value, symbols: &SymbolTable) -> Constant {
+    match result {
+        Constant::Float(_) | Constant::Double(_) => result,
+        _ => Constant::of_type(&dst.typ(symbols), result.as_i64()),
+    }
+}
+
+fn is_zero(constant: Constant) -> bool {
+    match constant {
+        Constant::Float(value) => value == 0.0,
+        Constant::Double(value) => value == 0.0,
+        _ => constant.as_i64() == 0,
+    }
+}
+
+/// The floating point constant of type `typ`. Both conversions are worked
+/// out from the source value, since rounding twice could give a different
+/// `float` than converting directly does.
+fn floating(typ: &types::T, double: f64, single: f32) -> Constant {
+    match typ {
+        types::T::Float => Constant::Float(single),
+        _ => Constant::Double(double),
+    }
+}
+
+fn fold_unary(op: UnaryOperator, operand: Constant) -> Option<Constant> {
+    Some(match (op, operand) {
+        (UnaryOperator::Not, operand) => Constant::Int(i32::from(is_zero(operand))),
+        (UnaryOperator::Negate, Constant::Float(value)) => Constant::Float(-value),
+        (UnaryOperator::Negate, Constant::Double(value)) => Constant::Double(-value),
+        (_, Constant::Float(_) | Constant::Double(_) | Constant::Bool(_)) => return None,
+        (UnaryOperator::Negate, operand) => {
+            Constant::of_type(&operand.typ(), operand.as_i64().wrapping_neg())
+        }
+        (UnaryOperator::Complement, operand) => {
+            Constant::of_type(&operand.typ(), !operand.as_i64())
+        }
+    })
+}
+
+/// Folds a binary operation. The operands have the same type, except that
+/// the count of a shift may have any integer type.
+fn fold_binary(op: BinaryOperator, left: Constant, right: Constant) -> Option<Constant> {
+    match (left, right) {
+        (Constant::Float(x), Constant::Float(y)) => {
+            Some(fold_floating(op, x, y)?.map_or_else(truth, Constant::Float))
+        }
+        (Constant::Double(x), Constant::Double(y)) => {
+            Some(fold_floating(op, x, y)?.map_or_else(truth, Constant::Double))
+        }
+        (Constant::Float(_) | Constant::Double(_) | Constant::Bool(_), _) => None,
+        _ => fold_integer(op, left, right),
+    }
+}
+
+fn truth(value: bool) -> Constant {
+    Constant::Int(i32::from(value))
+}
+
+/// Folds an integer operation. Signed operands are sign extended to 64 bits
+/// and unsigned ones zero extended, so doing the arithmetic on 64 bits and
+/// truncating the result wraps like the narrower instruction would.
+fn fold_integer(op: BinaryOperator, left: Constant, right: Constant) -> Option<Constant> {
+    let typ = left.typ();
+    let signed = typ.is_signed();
+    let bits = typ.size() * 8;
+    let (x, y) = (left.as_i64(), right.as_i64());
+    let (ux, uy) = (x as u64, y as u64);
+    // The shift instructions only use the low bits of the count.
+    let count = (y & (bits - 1)) as u32;
+
+    let value = match op {
+        BinaryOperator::Add => x.wrapping_add(y),
+        BinaryOperator::Subtract => x.wrapping_sub(y),
+        BinaryOperator::Multiply => x.wrapping_mul(y),
+        // Dividing by zero, or the most negative value by -1, traps at run
+        // time, so it is left for then.
+        BinaryOperator::Divide | BinaryOperator::Remainder if y == 0 => return None,
+        BinaryOperator::Divide | BinaryOperator::Remainder
+            if signed && y == -1 && x == i64::MIN >> (64 - bits) =>
+        {
+            return None;
+        }
+        BinaryOperator::Divide if signed => x / y,
+        BinaryOperator::Divide => (ux / uy) as i64,
+        BinaryOperator::Remainder if signed => x % y,
+        BinaryOperator::Remainder => (ux % uy) as i64,
+        BinaryOperator::BitwiseAnd => x & y,
+        BinaryOperator::BitwiseOr => x | y,
+        BinaryOperator::BitwiseXor => x ^ y,
+        BinaryOperator::ShiftLeft => x << count,
+        BinaryOperator::ShiftRight if signed => x >> count,
+        BinaryOperator::ShiftRight => (ux >> count) as i64,
+        BinaryOperator::Equal => return Some(truth(x == y)),
+        BinaryOperator::NotEqual => return Some(truth(x != y)),
+        BinaryOperator::LessThan if signed => return Some(truth(x < y)),
+        BinaryOperator::LessThan => return Some(truth(ux < uy)),
+        BinaryOperator::LessOrEqual if signed => return Some(truth(x <= y)),
+        BinaryOperator::LessOrEqual => return Some(truth(ux <= uy)),
+        BinaryOperator::GreaterThan if signed => return Some(truth(x > y)),
+        BinaryOperator::GreaterThan => return Some(truth(ux > uy)),
+        BinaryOperator::GreaterOrEqual if signed => return Some(truth(x >= y)),
+        BinaryOperator::GreaterOrEqual => return Some(truth(ux >= uy)),
+    };
+
+    Some(Constant::of_type(&typ, value))
+}
+
+/// Folds a floating point operation to either its value or, for a
+/// comparison, its truth. IEEE 754 arithmetic is the same at compile time
+/// as at run time, including comparisons with NaN.
+fn fold_floating<F>(op: BinaryOperator, x: F, y: F) -> Option<Result<F, bool>>
+where
+    F: Copy + PartialOrd + Add<Output = F> + Sub<Output = F> + Mul<Output = F> + Div<Output = F>,
+{
+    Some(match op {
+        BinaryOperator::Add => Ok(x + y),
+        BinaryOperator::Subtract => Ok(x - y),
+        BinaryOperator::Multiply => Ok(x * y),
+        BinaryOperator::Divide => Ok(x / y),
+        BinaryOperator::Equal => Err(x == y),
+        BinaryOperator::NotEqual => Err(x != y),
+        BinaryOperator::LessThan => Err(x < y),
+        BinaryOperator::LessOrEqual => Err(x <= y),
+        BinaryOperator::GreaterThan => Err(x > y),
+        BinaryOperator::GreaterOrEqual => Err(x >= y),
+        _ => return None,
+    })
+}
+
+/// Converts a floating point constant to the integer type `typ`, rounding
+/// toward zero. Values out of the range of `typ`, and NaN, are left for run
+/// time: the conversion is undefined, and what the instructions do with
+/// them differs between types.
+fn fold_floating_to_integer(constant: Constant, typ: &types::T) -> Option<Constant> {
+    let value = match constant {
+        Constant::Float(value) => value as f64,
+        Constant::Double(value) => value,
+        _ => return None,
+    }
+    .trunc();
+
+    let (min, max) = match typ {
+        types::T::Int => (i32::MIN as f64, i32::MAX as f64),
+        types::T::Long => (i64::MIN as f64, i64::MAX as f64),
+        types::T::UInt => (0.0, u32::MAX as f64),
+        types::T::ULong => (0.0, u64::MAX as f64),
+        _ => return None,
+    };
+    // The upper bounds of the 64-bit types round up to a power of two that
+    // is itself out of range.
+    if value.is_nan() || value < min || value >= max + 1.0 {
+        return None;
+    }
+
+    Some(match typ {
+        types::T::ULong => Constant::ULong(value as u64),
+        _ => Constant::of_type(typ, value as i64),
+    })
+}
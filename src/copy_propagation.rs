@@ -0,0 +1,227 @@
+//! Copy propagation: where the only value a variable can hold is the source
+//! of an earlier `Copy`, reads of the variable read the source instead, and
+//! copies that would not change anything are removed.
+//!
+//! Which copies reach each instruction is found with a forward dataflow
+//! analysis. A copy `dst = src` reaches a point if it runs on every path to
+//! it and neither `dst` nor `src` can have changed since.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::cfg::{Cfg, NodeId};
+use crate::ir::{Constant, Instruction, Value};
+use crate::symbols::SymbolTable;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ReachingCopy {
+    src: Value,
+    dst: Value,
+}
+
+pub fn propagate_copies(mut cfg: Cfg, symbols: &SymbolTable) -> Cfg {
+    let analysis = Analysis::new(&cfg, symbols);
+    let reaching = analysis.reaching_copies(&cfg);
+
+    for (id, block) in cfg.blocks.iter_mut() {
+        let mut copies = reaching[id].clone();
+        let mut instructions = Vec::new();
+        for mut instr in std::mem::take(&mut block.instructions) {
+            if let Instruction::Copy { src, dst } = &instr
+                && analysis.is_redundant(&copies, src, dst)
+            {
+                continue;
+            }
+
+            instr.replace_sources(|value| {
+                copies
+                    .iter()
+                    .find(|copy| copy.dst == *value)
+                    .map(|copy| copy.src.clone())
+            });
+            analysis.transfer(&mut copies, &instr);
+            instructions.push(instr);
+        }
+        block.instructions = instructions;
+    }
+
+    cfg
+}
+
+struct Analysis<'a> {
+    symbols: &'a SymbolTable,
+    /// The variables whose address is taken in the function.
+    address_taken: HashSet<String>,
+    /// Every copy that can be propagated, which is what a block starts out
+    /// assuming reaches its end.
+    all_copies: HashSet<ReachingCopy>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(cfg: &Cfg, symbols: &'a SymbolTable) -> Analysis<'a> {
+        let mut analysis = Analysis {
+            symbols,
+            address_taken: HashSet::new(),
+            all_copies: HashSet::new(),
+        };
+
+        for instr in cfg.blocks.values().flat_map(|block| &block.instructions) {
+            match instr {
+                Instruction::GetAddress {
+                    src: Value::Var(name),
+                    ..
+                } => {
+                    analysis.address_taken.insert(name.clone());
+                }
+                Instruction::Copy { src, dst } => {
+                    if let Some(copy) = analysis.propagatable(src, dst) {
+                        analysis.all_copies.insert(copy);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        analysis
+    }
+
+    /// Whether the value is a variable that may be read or written other than
+    /// by name: through a pointer if its address is taken, or by any other
+    /// function if it has static storage duration.
+    fn is_aliased(&self, value: &Value) -> bool {
+        match value {
+            Value::Var(name) => self.address_taken.contains(name) || self.symbols.is_static(name),
+            Value::Constant(_) => false,
+        }
+    }
+
+    /// The copy `dst = src` as it can be propagated, if it can. Volatile
+    /// variables are never involved, since that would add or remove
+    /// accesses to them. The types have to match, except that a constant
+    /// is converted to the type of `dst`, as the copy itself would.
+    fn propagatable(&self, src: &Value, dst: &Value) -> Option<ReachingCopy> {
+        if src.is_volatile(self.symbols) || dst.is_volatile(self.symbols) {
+            return None;
+        }
+
+        let typ = dst.typ(self.symbols);
+        let src = match src {
+            Value::Constant(constant) if constant.typ() != typ => {
+                if !constant.typ().is_integer() {
+                    return None;
+                }
+                Value::Constant(Constant::of_type(&typ, constant.as_i64()))
+            }
+            Value::Var(_) if src.typ(self.symbols) != typ => return None,
+            src => src.clone(),
+        };
+
+        Some(ReachingCopy {
+            src,
+            dst: dst.clone(),
+        })
+    }
+
+    /// Whether the copy `dst = src` changes nothing, because it, or the copy
+    /// the other way round, is already in effect.
+    fn is_redundant(&self, copies: &HashSet<ReachingCopy>, src: &Value, dst: &Value) -> bool {
+        let reversed = ReachingCopy {
+            src: dst.clone(),
+            dst: src.clone(),
+        };
+        copies.contains(&reversed)
+            || self
+                .propagatable(src, dst)
+                .is_some_and(|copy| copies.contains(&copy))
+    }
+
+    /// The copies that reach the start of each block.
+    fn reaching_copies(&self, cfg: &Cfg) -> BTreeMap<usize, HashSet<ReachingCopy>> {
+        let mut outs: HashMap<usize, HashSet<ReachingCopy>> = cfg
+            .blocks
+            .keys()
+            .map(|id| (*id, self.all_copies.clone()))
+            .collect();
+
+        let mut pending: VecDeque<usize> = cfg.blocks.keys().copied().collect();
+        while let Some(id) = pending.pop_front() {
+            let mut copies = self.meet(cfg, id, &outs);
+            for instr in &cfg.blocks[&id].instructions {
+                self.transfer(&mut copies, instr);
+            }
+
+            if copies != outs[&id] {
+                outs.insert(id, copies);
+                for successor in cfg.successors(NodeId::Block(id)) {
+                    if let NodeId::Block(next) = successor
+                        && !pending.contains(next)
+                    {
+                        pending.push_back(*next);
+                    }
+                }
+            }
+        }
+
+        cfg.blocks
+            .keys()
+            .map(|id| (*id, self.meet(cfg, *id, &outs)))
+            .collect()
+    }
+
+    /// The copies that reach the end of every predecessor of the block.
+    /// Nothing reaches the start of the function.
+    fn meet(
+        &self,
+        cfg: &Cfg,
+        id: usize,
+        outs: &HashMap<usize, HashSet<ReachingCopy>>,
+    ) -> HashSet<ReachingCopy> {
+        let mut copies: Option<HashSet<ReachingCopy>> = None;
+        for predecessor in cfg.predecessors(NodeId::Block(id)) {
+            let incoming = match predecessor {
+                NodeId::Block(other) => &outs[other],
+                _ => return HashSet::new(),
+            };
+            copies = Some(match copies {
+                Some(copies) => copies.intersection(incoming).cloned().collect(),
+                None => incoming.clone(),
+            });
+        }
+        copies.unwrap_or_default()
+    }
+
+    /// Updates the copies that reach past `instr`.
+    fn transfer(&self, copies: &mut HashSet<ReachingCopy>, instr: &Instruction) {
+        match instr {
+            Instruction::Copy { src, dst } => {
+                if self.is_redundant(copies, src, dst) {
+                    return;
+                }
+                kill(copies, &dst.name());
+                if let Some(copy) = self.propagatable(src, dst) {
+                    copies.insert(copy);
+                }
+                return;
+            }
+            // These can read and write any aliased variable.
+            Instruction::FunCall { .. }
+            | Instruction::IndirectCall { .. }
+            | Instruction::Store { .. }
+            | Instruction::InlineAsm { .. }
+            | Instruction::VaStart { .. }
+            | Instruction::VaArg { .. } => {
+                copies.retain(|copy| !self.is_aliased(&copy.src) && !self.is_aliased(&copy.dst));
+            }
+            _ => {}
+        }
+
+        for name in instr.destinations() {
+            kill(copies, name);
+        }
+    }
+}
+
+/// Forgets the copies that `name` is either side of, once it is written.
+fn kill(copies: &mut HashSet<ReachingCopy>, name: &str) {
+    let written = Value::Var(name.to_string());
+    copies.retain(|copy| copy.src != written && copy.dst != written);
+}
@@ -0,0 +1,199 @@
+//! Dead store elimination: removes the instructions whose only effect is to
+//! write a variable that is never read afterwards.
+//!
+//! Which variables are live, that is may still be read, at each point is
+//! found with a backward dataflow analysis. A variable is live before an
+//! instruction if the instruction reads it, or if it is live after the
+//! instruction and the instruction does not overwrite it.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use crate::cfg::{Cfg, NodeId};
+use crate::ir::{Instruction, Value};
+use crate::symbols::SymbolTable;
+
+pub fn eliminate_dead_stores(mut cfg: Cfg, symbols: &SymbolTable) -> Cfg {
+    let analysis = Analysis::new(&cfg, symbols);
+    let live_out = analysis.live_variables(&cfg);
+
+    for (id, block) in cfg.blocks.iter_mut() {
+        let mut live = live_out[id].clone();
+        let mut instructions = Vec::new();
+        for instr in std::mem::take(&mut block.instructions).into_iter().rev() {
+            if analysis.is_dead_store(&live, &instr) {
+                continue;
+            }
+            analysis.transfer(&mut live, &instr);
+            instructions.push(instr);
+        }
+        instructions.reverse();
+        block.instructions = instructions;
+    }
+
+    cfg
+}
+
+struct Analysis<'a> {
+    symbols: &'a SymbolTable,
+    /// The variables of the function that may be read other than by name:
+    /// through a pointer if their address is taken, or by any other function
+    /// if they have static storage duration.
+    aliased: HashSet<String>,
+    /// The static variables the function uses, which the rest of the program
+    /// may read once it returns.
+    statics: HashSet<String>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(cfg: &Cfg, symbols: &'a SymbolTable) -> Analysis<'a> {
+        let mut analysis = Analysis {
+            symbols,
+            aliased: HashSet::new(),
+            statics: HashSet::new(),
+        };
+
+        for instr in cfg.blocks.values().flat_map(|block| &block.instructions) {
+            if let Instruction::GetAddress {
+                src: Value::Var(name),
+                ..
+            } = instr
+            {
+                analysis.aliased.insert(name.clone());
+            }
+
+            let sources = instr.sources().into_iter().filter_map(|value| match value {
+                Value::Var(name) => Some(name.as_str()),
+                Value::Constant(_) => None,
+            });
+            for name in sources.chain(instr.destinations()) {
+                if symbols.is_static(name) {
+                    analysis.statics.insert(name.to_string());
+                }
+            }
+        }
+        analysis.aliased.extend(analysis.statics.iter().cloned());
+
+        analysis
+    }
+
+    /// Whether the instruction does nothing but write variables that are not
+    /// live after it. Instructions that call, write through a pointer or run
+    /// assembly always have other effects, and loads are kept since the
+    /// location they read may be volatile. Neither a write nor a read of a
+    /// volatile variable is ever removed.
+    fn is_dead_store(&self, live: &HashSet<String>, instr: &Instruction) -> bool {
+        let removable = matches!(
+            instr,
+            Instruction::Unary { .. }
+                | Instruction::Binary { .. }
+                | Instruction::Copy { .. }
+                | Instruction::CopyToOffset { .. }
+                | Instruction::SignExtend { .. }
+                | Instruction::ZeroExtend { .. }
+                | Instruction::Truncate { .. }
+                | Instruction::IntToFloating { .. }
+                | Instruction::FloatingToInt { .. }
+                | Instruction::UIntToFloating { .. }
+                | Instruction::FloatingToUInt { .. }
+                | Instruction::FloatingConvert { .. }
+                | Instruction::GetAddress { .. }
+                | Instruction::AddPtr { .. }
+        );
+        if !removable
+            || instr
+                .sources()
+                .into_iter()
+                .any(|value| value.is_volatile(self.symbols))
+        {
+            return false;
+        }
+
+        let destinations = instr.destinations();
+        !destinations.is_empty()
+            && destinations.into_iter().all(|name| {
+                !live.contains(name) && !Value::Var(name.to_string()).is_volatile(self.symbols)
+            })
+    }
+
+    /// The variables that are live at the end of each block.
+    fn live_variables(&self, cfg: &Cfg) -> BTreeMap<usize, HashSet<String>> {
+        let mut ins: BTreeMap<usize, HashSet<String>> =
+            cfg.blocks.keys().map(|id| (*id, HashSet::new())).collect();
+
+        let mut pending: VecDeque<usize> = cfg.blocks.keys().rev().copied().collect();
+        while let Some(id) = pending.pop_front() {
+            let mut live = self.meet(cfg, id, &ins);
+            for instr in cfg.blocks[&id].instructions.iter().rev() {
+                self.transfer(&mut live, instr);
+            }
+
+            if live != ins[&id] {
+                ins.insert(id, live);
+                for predecessor in cfg.predecessors(NodeId::Block(id)) {
+                    if let NodeId::Block(previous) = predecessor
+                        && !pending.contains(previous)
+                    {
+                        pending.push_back(*previous);
+                    }
+                }
+            }
+        }
+
+        cfg.blocks
+            .keys()
+            .map(|id| (*id, self.meet(cfg, *id, &ins)))
+            .collect()
+    }
+
+    /// The variables that are live at the start of any successor of the
+    /// block. The static variables are live when the function returns.
+    fn meet(
+        &self,
+        cfg: &Cfg,
+        id: usize,
+        ins: &BTreeMap<usize, HashSet<String>>,
+    ) -> HashSet<String> {
+        let mut live = HashSet::new();
+        for successor in cfg.successors(NodeId::Block(id)) {
+            match successor {
+                NodeId::Block(next) => live.extend(ins[next].iter().cloned()),
+                NodeId::Exit => live.extend(self.statics.iter().cloned()),
+                NodeId::Entry => unreachable!(),
+            }
+        }
+        live
+    }
+
+    /// Updates the variables that are live before `instr`, given those that
+    /// are live after it.
+    fn transfer(&self, live: &mut HashSet<String>, instr: &Instruction) {
+        match instr {
+            // Writing only part of a variable leaves the rest of it live.
+            Instruction::CopyToOffset { .. } => {}
+            _ => {
+                for name in instr.destinations() {
+                    live.remove(name);
+                }
+            }
+        }
+
+        for value in instr.sources() {
+            if let Value::Var(name) = value {
+                live.insert(name.clone());
+            }
+        }
+
+        // These can read any aliased variable.
+        if matches!(
+            instr,
+            Instruction::FunCall { .. }
+                | Instruction::IndirectCall { .. }
+                | Instruction::Load { .. }
+                | Instruction::InlineAsm { .. }
+                | Instruction::VaStart { .. }
+                | Instruction::VaArg { .. }
+        ) {
+            live.extend(self.aliased.iter().cloned());
+        }
+    }
+}
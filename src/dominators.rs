@@ -0,0 +1,166 @@
+//! Dominators: a node dominates another when every path from the entry to
+//! the other goes through it. The immediate dominators are found with the
+//! iterative algorithm of Cooper, Harvey and Kennedy, which visits the nodes
+//! in reverse postorder until nothing changes.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::cfg::{Cfg, FlowInstruction, NodeId};
+
+/// The dominator tree of the nodes that can be reached from the entry.
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    /// The immediate dominator of every reachable node but the entry.
+    idom: HashMap<NodeId, NodeId>,
+    /// The nodes each node immediately dominates, in reverse postorder.
+    children: HashMap<NodeId, Vec<NodeId>>,
+}
+
+impl Dominators {
+    pub fn new<I: FlowInstruction>(cfg: &Cfg<I>) -> Dominators {
+        let order = reverse_postorder(cfg);
+        let position: HashMap<NodeId, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (*node, i))
+            .collect();
+
+        // The entry is its own dominator until the end, which ends the walks
+        // up the tree in `intersect`.
+        let mut idom = HashMap::from([(NodeId::Entry, NodeId::Entry)]);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in &order[1..] {
+                // A predecessor earlier in the order, such as the one the
+                // search came from, always has a dominator by now.
+                let new_idom = cfg
+                    .predecessors(*node)
+                    .iter()
+                    .filter(|predecessor| idom.contains_key(*predecessor))
+                    .copied()
+                    .reduce(|a, b| intersect(&idom, &position, a, b))
+                    .expect("a reachable node has a visited predecessor");
+                if idom.insert(*node, new_idom) != Some(new_idom) {
+                    changed = true;
+                }
+            }
+        }
+        idom.remove(&NodeId::Entry);
+
+        let mut children: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for node in &order[1..] {
+            children.entry(idom[node]).or_default().push(*node);
+        }
+
+        Dominators { idom, children }
+    }
+
+    /// The closest node that dominates `node` other than itself, if `node`
+    /// is reachable and not the entry.
+    pub fn immediate_dominator(&self, node: NodeId) -> Option<NodeId> {
+        self.idom.get(&node).copied()
+    }
+
+    pub fn is_reachable(&self, node: NodeId) -> bool {
+        node == NodeId::Entry || self.idom.contains_key(&node)
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`. A node
+    /// dominates itself.
+    pub fn dominates(&self, a: NodeId, b: NodeId) -> bool {
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            match self.idom.get(&node) {
+                Some(parent) => node = *parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// The nodes `node` immediately dominates.
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        self.children.get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    /// The reachable nodes in an order where each one comes after the nodes
+    /// that dominate it, starting with the entry.
+    pub fn preorder(&self) -> Vec<NodeId> {
+        let mut order = Vec::new();
+        let mut pending = vec![NodeId::Entry];
+        while let Some(node) = pending.pop() {
+            order.push(node);
+            pending.extend(self.children(node).iter().rev());
+        }
+        order
+    }
+
+    /// The dominance frontier of each node: the nodes it does not strictly
+    /// dominate but that have a predecessor it dominates. These are where
+    /// what is defined in the node meets what is defined on other paths.
+    pub fn frontiers<I: FlowInstruction>(&self, cfg: &Cfg<I>) -> HashMap<NodeId, BTreeSet<NodeId>> {
+        let mut frontiers: HashMap<NodeId, BTreeSet<NodeId>> = HashMap::new();
+        for (node, idom) in &self.idom {
+            let predecessors = cfg.predecessors(*node);
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for predecessor in predecessors {
+                if !self.is_reachable(*predecessor) {
+                    continue;
+                }
+                let mut runner = *predecessor;
+                while runner != *idom {
+                    frontiers.entry(runner).or_default().insert(*node);
+                    runner = self.idom[&runner];
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// The nearest common dominator of `a` and `b`, found by walking up from
+/// whichever of them comes later in reverse postorder.
+fn intersect(
+    idom: &HashMap<NodeId, NodeId>,
+    position: &HashMap<NodeId, usize>,
+    mut a: NodeId,
+    mut b: NodeId,
+) -> NodeId {
+    while a != b {
+        while position[&a] > position[&b] {
+            a = idom[&a];
+        }
+        while position[&b] > position[&a] {
+            b = idom[&b];
+        }
+    }
+    a
+}
+
+/// The nodes reachable from the entry, each one before its successors
+/// except along the edges that go back round a loop.
+fn reverse_postorder<I: FlowInstruction>(cfg: &Cfg<I>) -> Vec<NodeId> {
+    let mut visited = HashSet::from([NodeId::Entry]);
+    let mut postorder = Vec::new();
+    // Each node on the path being searched, with how many of its successors
+    // have been looked at.
+    let mut path = vec![(NodeId::Entry, 0)];
+    while let Some((node, next)) = path.pop() {
+        match cfg.successors(node).get(next) {
+            Some(successor) => {
+                path.push((node, next + 1));
+                if visited.insert(*successor) {
+                    path.push((*successor, 0));
+                }
+            }
+            None => postorder.push(node),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
@@ -1,8 +1,15 @@
 use std::{path::PathBuf, process::Command};
 
 use crate::{
-    Cli, code_emission::CodeEmitter, codegen::CodeGenerator, ir::TackyGenerator, lexer::Lexer,
+    Cli,
+    code_emission::CodeEmitter,
+    codegen::CodeGenerator,
+    ir::TackyGenerator,
+    lexer::Lexer,
+    optimizer::{self, Optimizations},
     parser::Parser,
+    resolver::Resolver,
+    typechecker::TypeChecker,
 };
 
 pub fn execute(cli_args: Cli) {
@@ -65,7 +72,7 @@ fn run_compiler(cli_args: Cli, preprocessed_file: PathBuf) -> PathBuf {
 
     let errors = lexer.errors();
 
-    if errors.len() > 0 {
+    if !errors.is_empty() {
         errors.iter().for_each(|err| {
             eprintln!("{err}");
         });
@@ -92,15 +99,52 @@ fn run_compiler(cli_args: Cli, preprocessed_file: PathBuf) -> PathBuf {
         std::process::exit(0);
     }
 
-    let mut tacky_gen = TackyGenerator::new();
-    let tacky_program = tacky_gen.emit_tacky_program(&program);
+    let mut resolver = Resolver::new();
+    let program = match resolver.resolve_program(&program) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut type_checker = TypeChecker::new();
+    let program = match type_checker.check_program(&program) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    if cli_args.validate {
+        println!("VALIDATED PROGRAM: {:#?}", &program);
+        std::process::exit(0);
+    }
+
+    let mut tacky_gen = TackyGenerator::new(type_checker.symbols().clone());
+    let mut tacky_program = tacky_gen.emit_tacky_program(&program);
+
+    // Each pass flag turns its pass on, on top of those of the level.
+    let mut optimizations = Optimizations::level(cli_args.optimization_level);
+    optimizations.fold_constants |= cli_args.fold_constants;
+    optimizations.eliminate_unreachable_code |= cli_args.eliminate_unreachable_code;
+    optimizations.propagate_copies |= cli_args.propagate_copies;
+    optimizations.eliminate_dead_stores |= cli_args.eliminate_dead_stores;
+    optimizations.propagate_constants |= cli_args.propagate_constants;
+    optimizations.eliminate_common_subexpressions |= cli_args.eliminate_common_subexpressions;
+    optimizations.convert_to_ssa = cli_args.convert_to_ssa;
+    optimizations.print_after = cli_args.print_after;
+    let mut symbols = tacky_gen.symbols().clone();
+    optimizer::optimize(&mut tacky_program, optimizations, &mut symbols);
 
     if cli_args.tacky {
         println!("TACKY: {:#?}", &tacky_program);
         std::process::exit(0);
     }
 
-    let mut code_generator = CodeGenerator::new();
+    let mut code_generator =
+        CodeGenerator::new(symbols, cli_args.regalloc, cli_args.print_regalloc_stats);
     let asm_ast = code_generator.generate(&tacky_program);
 
     if cli_args.codegen {
@@ -10,6 +10,9 @@ pub enum CompilerError {
     ParseError {
         message: String,
     },
+    SemanticError {
+        message: String,
+    },
 }
 
 impl Display for CompilerError {
@@ -21,6 +24,7 @@ impl Display for CompilerError {
                 column,
             } => f.write_fmt(format_args!("[{}:{}] Error: {}", line, column, message)),
             CompilerError::ParseError { message } => f.write_str(message),
+            CompilerError::SemanticError { message } => f.write_str(message),
         }
     }
 }
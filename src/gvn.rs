@@ -0,0 +1,349 @@
+//! Global value numbering over SSA form, walking down the dominator tree as
+//! Briggs, Cooper and Simpson describe: an instruction that works out what
+//! an instruction in a block that dominates it already has is removed, and
+//! what it wrote is read from the earlier one's result instead.
+//!
+//! Since a version never changes once written, an operation on versions and
+//! constants gives the same result wherever it is done. A load also reads
+//! what an earlier one through the same pointer did, but only until anything
+//! that could write memory: a store, a call, assembly, or a write to a
+//! variable a pointer could point to. Loads through a pointer to `volatile`
+//! are never removed. A copy between versions of the same type, and a phi
+//! that only ever picks one value, or picks the same values as another phi
+//! in its block, leave their destination another name for a value too.
+
+use std::collections::HashMap;
+use std::mem::Discriminant;
+
+use crate::cfg::NodeId;
+use crate::dominators::Dominators;
+use crate::ir::{BinaryOperator, Instruction, UnaryOperator, Value};
+use crate::ssa::{Phi, Ssa};
+use crate::symbols::SymbolTable;
+use crate::types;
+
+/// What an instruction works out, with the dominating version or constant
+/// each operand reads. The same expression only holds the same value in a
+/// version of the same type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expression {
+    Unary(UnaryOperator, Value),
+    Binary(BinaryOperator, Value, Value),
+    /// A copy or conversion, by its kind of instruction. Those to different
+    /// types are told apart by the types of the versions holding them.
+    Convert(Discriminant<Instruction>, Value),
+    Address(String),
+    AddPtr(Value, Value, i64),
+}
+
+impl Expression {
+    /// The same expression with its operands the other way round, if that
+    /// gives the same result.
+    fn commuted(&self) -> Option<Expression> {
+        match self {
+            Expression::Binary(
+                op @ (BinaryOperator::Add
+                | BinaryOperator::Multiply
+                | BinaryOperator::BitwiseAnd
+                | BinaryOperator::BitwiseOr
+                | BinaryOperator::BitwiseXor
+                | BinaryOperator::Equal
+                | BinaryOperator::NotEqual),
+                src1,
+                src2,
+            ) => Some(Expression::Binary(*op, src2.clone(), src1.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// What the walk down the dominator tree has left to do.
+enum Visit {
+    Enter(NodeId),
+    /// Forget the expressions the subtree entered since added.
+    Leave(usize),
+}
+
+pub fn eliminate_common_subexpressions(ssa: &mut Ssa, symbols: &SymbolTable) {
+    let dominators = Dominators::new(&ssa.cfg);
+    let mut numbering = Numbering {
+        symbols,
+        replacements: HashMap::new(),
+        available: HashMap::new(),
+        added: Vec::new(),
+        loads: HashMap::new(),
+    };
+
+    let mut pending = vec![Visit::Enter(NodeId::Entry)];
+    while let Some(visit) = pending.pop() {
+        match visit {
+            Visit::Enter(node) => {
+                pending.push(Visit::Leave(numbering.added.len()));
+                let children = dominators.children(node);
+                pending.extend(children.iter().rev().map(|child| Visit::Enter(*child)));
+                if let NodeId::Block(id) = node {
+                    numbering.visit(ssa, id, &dominators);
+                }
+            }
+            Visit::Leave(mark) => numbering.forget(mark),
+        }
+    }
+
+    // The phis are the only reads a write does not dominate, so the ones in
+    // blocks the walk got to first still name what was removed.
+    for phi in ssa.phis.values_mut().flatten() {
+        for (_, src) in &mut phi.sources {
+            if let Some(new) = numbering.replacement(src) {
+                *src = new;
+            }
+        }
+    }
+}
+
+struct Numbering<'a> {
+    symbols: &'a SymbolTable,
+    /// The version to read instead of each one found to hold the same value.
+    replacements: HashMap<String, String>,
+    /// The versions holding each expression worked out in the blocks that
+    /// dominate the one being looked at, and in it so far.
+    available: HashMap<Expression, Vec<String>>,
+    /// The expressions added to `available`, in order, so that those of a
+    /// subtree can be taken out again on leaving it.
+    added: Vec<Expression>,
+    /// The version each load read into, by the pointer it read through, as
+    /// of the end of each block where that is still known.
+    loads: HashMap<usize, HashMap<Value, String>>,
+}
+
+impl Numbering<'_> {
+    fn visit(&mut self, ssa: &mut Ssa, id: usize, dominators: &Dominators) {
+        // What was loaded is only still there if control can only have come
+        // straight from the block that loaded it.
+        let node = NodeId::Block(id);
+        let mut loads = match (
+            dominators.immediate_dominator(node),
+            ssa.cfg.predecessors(node),
+        ) {
+            (Some(NodeId::Block(idom)), [predecessor]) if *predecessor == NodeId::Block(idom) => {
+                self.loads.get(&idom).cloned().unwrap_or_default()
+            }
+            _ => HashMap::new(),
+        };
+
+        if let Some(phis) = ssa.phis.remove(&id) {
+            let mut kept: Vec<Phi> = Vec::new();
+            for mut phi in phis {
+                for (_, src) in &mut phi.sources {
+                    if let Some(new) = self.replacement(src) {
+                        *src = new;
+                    }
+                }
+                let same = self.only_source(&phi).or_else(|| {
+                    kept.iter()
+                        .find(|other| {
+                            other.sources == phi.sources && self.same_type(&other.dst, &phi.dst)
+                        })
+                        .map(|other| other.dst.clone())
+                });
+                match same {
+                    Some(name) => {
+                        self.replacements.insert(phi.dst, name);
+                    }
+                    None => kept.push(phi),
+                }
+            }
+            if !kept.is_empty() {
+                ssa.phis.insert(id, kept);
+            }
+        }
+
+        let block = ssa.cfg.blocks.get_mut(&id).unwrap();
+        let mut instructions = std::mem::take(&mut block.instructions);
+        instructions.retain_mut(|instr| {
+            instr.replace_sources(|value| self.replacement(value));
+            if self.is_redundant(instr, ssa, &mut loads) {
+                return false;
+            }
+            if writes_memory(instr, ssa) {
+                loads.clear();
+            }
+            true
+        });
+        ssa.cfg.blocks.get_mut(&id).unwrap().instructions = instructions;
+
+        if !loads.is_empty() {
+            self.loads.insert(id, loads);
+        }
+    }
+
+    /// Whether the instruction writes a version with a value an earlier one
+    /// holds, which it is then replaced by. Otherwise what it works out is
+    /// made available to what it dominates.
+    fn is_redundant(
+        &mut self,
+        instr: &Instruction,
+        ssa: &Ssa,
+        loads: &mut HashMap<Value, String>,
+    ) -> bool {
+        let [dst] = instr.destinations()[..] else {
+            return false;
+        };
+        if !ssa.is_version(&Value::Var(dst.to_string())) {
+            return false;
+        }
+
+        let earlier = match instr {
+            Instruction::Copy {
+                src: src @ Value::Var(name),
+                ..
+            } if ssa.is_version(src) && self.same_type(name, dst) => Some(name.clone()),
+            Instruction::Load { src_ptr, .. } => {
+                if !ssa.is_version(src_ptr) || self.points_to_volatile(src_ptr) {
+                    return false;
+                }
+                match loads.get(src_ptr) {
+                    Some(name) if self.same_type(name, dst) => Some(name.clone()),
+                    _ => {
+                        loads.insert(src_ptr.clone(), dst.to_string());
+                        None
+                    }
+                }
+            }
+            _ => {
+                let Some(expression) = expression(instr, ssa) else {
+                    return false;
+                };
+                let found = std::iter::once(&expression)
+                    .chain(expression.commuted().as_ref())
+                    .filter_map(|key| self.available.get(key))
+                    .flatten()
+                    .find(|name| self.same_type(name, dst))
+                    .cloned();
+                if found.is_none() {
+                    self.available
+                        .entry(expression.clone())
+                        .or_default()
+                        .push(dst.to_string());
+                    self.added.push(expression);
+                }
+                found
+            }
+        };
+
+        match earlier {
+            Some(name) => {
+                self.replacements.insert(dst.to_string(), name);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The one version other than its own destination the phi picks, if
+    /// there is only one.
+    fn only_source(&self, phi: &Phi) -> Option<String> {
+        let mut names = phi.sources.iter().filter_map(|(_, src)| match src {
+            Value::Var(name) if *name == phi.dst => None,
+            Value::Var(name) => Some(Some(name)),
+            Value::Constant(_) => Some(None),
+        });
+        let first = names.next()??;
+        (names.all(|name| name == Some(first)) && self.same_type(first, &phi.dst))
+            .then(|| first.clone())
+    }
+
+    /// Takes the expressions added since `mark` out of `available`.
+    fn forget(&mut self, mark: usize) {
+        for expression in self.added.drain(mark..) {
+            let names = self.available.get_mut(&expression).unwrap();
+            names.pop();
+            if names.is_empty() {
+                self.available.remove(&expression);
+            }
+        }
+    }
+
+    /// The version to read instead of `value`, if it is one that was found
+    /// to hold the same value as an earlier one.
+    fn replacement(&self, value: &Value) -> Option<Value> {
+        let Value::Var(name) = value else {
+            return None;
+        };
+        let mut name = self.replacements.get(name)?;
+        while let Some(next) = self.replacements.get(name) {
+            name = next;
+        }
+        Some(Value::Var(name.clone()))
+    }
+
+    fn same_type(&self, a: &str, b: &str) -> bool {
+        self.symbols.type_of(a) == self.symbols.type_of(b)
+    }
+
+    fn points_to_volatile(&self, ptr: &Value) -> bool {
+        match ptr {
+            Value::Var(name) => match self.symbols.type_of(name).unqualified() {
+                types::T::Pointer(referenced) => referenced.qualifiers().volatile,
+                _ => true,
+            },
+            Value::Constant(_) => true,
+        }
+    }
+}
+
+/// What the instruction works out, if it only depends on its operands and
+/// they are all constants or versions, which never change.
+fn expression(instr: &Instruction, ssa: &Ssa) -> Option<Expression> {
+    let fixed = instr
+        .sources()
+        .into_iter()
+        .all(|value| matches!(value, Value::Constant(_)) || ssa.is_version(value));
+    if !fixed {
+        return None;
+    }
+
+    Some(match instr {
+        Instruction::Unary { op, src, .. } => Expression::Unary(*op, src.clone()),
+        Instruction::Binary { op, src1, src2, .. } => {
+            Expression::Binary(*op, src1.clone(), src2.clone())
+        }
+        Instruction::Copy { src, .. }
+        | Instruction::SignExtend { src, .. }
+        | Instruction::ZeroExtend { src, .. }
+        | Instruction::Truncate { src, .. }
+        | Instruction::IntToFloating { src, .. }
+        | Instruction::FloatingToInt { src, .. }
+        | Instruction::UIntToFloating { src, .. }
+        | Instruction::FloatingToUInt { src, .. }
+        | Instruction::FloatingConvert { src, .. } => {
+            Expression::Convert(std::mem::discriminant(instr), src.clone())
+        }
+        Instruction::GetAddress {
+            src: Value::Var(name),
+            ..
+        } => Expression::Address(name.clone()),
+        Instruction::AddPtr {
+            ptr, index, scale, ..
+        } => Expression::AddPtr(ptr.clone(), index.clone(), *scale),
+        _ => return None,
+    })
+}
+
+/// Whether the instruction may change what a pointer points to: anything
+/// that writes through a pointer, calls, or runs assembly, and any write to
+/// a variable that is not a version, since only those can be pointed to.
+fn writes_memory(instr: &Instruction, ssa: &Ssa) -> bool {
+    matches!(
+        instr,
+        Instruction::Store { .. }
+            | Instruction::FunCall { .. }
+            | Instruction::IndirectCall { .. }
+            | Instruction::InlineAsm { .. }
+            | Instruction::VaStart { .. }
+            | Instruction::VaArg { .. }
+            | Instruction::CopyToOffset { .. }
+    ) || instr
+        .destinations()
+        .into_iter()
+        .any(|name| !ssa.is_version(&Value::Var(name.to_string())))
+}
@@ -0,0 +1,333 @@
+//! The pieces of GNU extended `asm` statements that the type checker
+//! validates and code generation then relies on: operand constraints,
+//! clobber lists, the registers operands are placed in and the `%`
+//! references in the template.
+
+use crate::asm::{AsmType, Register};
+use crate::errors::CompilerError;
+
+/// Where a constraint asks for an operand to be placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// Any general purpose register: `r`, or `g` and `rm`, which also allow
+    /// places that are never chosen here.
+    AnyRegister,
+    /// One particular register: `a`, `b`, `c`, `d`, `S` or `D`.
+    Register(Register),
+    /// Memory: `m`. The operand is addressed through a register.
+    Memory,
+    /// An integer constant: `i` or `n`.
+    Immediate,
+    /// The place of the output operand with this number, for inputs only.
+    Matching(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraint {
+    pub placement: Placement,
+    /// `+`: an output whose previous value is also read.
+    pub read_write: bool,
+}
+
+impl Constraint {
+    /// Parses the constraint of an output operand, which has to start with
+    /// `=` or `+`, or of an input operand, which must not.
+    pub fn parse(constraint: &str, output: bool) -> Result<Constraint, CompilerError> {
+        let invalid = |reason: &str| {
+            Err(CompilerError::SemanticError {
+                message: format!("invalid asm constraint \"{constraint}\": {reason}"),
+            })
+        };
+
+        let read_write = constraint.starts_with('+');
+        let letters = constraint.trim_start_matches(['=', '+']);
+        if output && letters.len() == constraint.len() {
+            return invalid("an output has to start with '=' or '+'");
+        }
+        if !output && letters.len() != constraint.len() {
+            return invalid("an input cannot start with '=' or '+'");
+        }
+
+        // `&` (early clobber) and `%` (commutative) only matter to compilers
+        // that let operands share registers, which this one never does.
+        let letters: Vec<char> = letters
+            .chars()
+            .filter(|c| !matches!(c, '&' | '%'))
+            .collect();
+
+        if let Ok(index) = letters.iter().collect::<String>().parse::<usize>() {
+            if output {
+                return invalid("an output cannot match another operand");
+            }
+            return Ok(Constraint {
+                placement: Placement::Matching(index),
+                read_write,
+            });
+        }
+
+        // With several alternatives, a register is preferred over memory and
+        // memory over a constant.
+        let mut placements = Vec::new();
+        for letter in letters {
+            placements.push(match letter {
+                'r' | 'g' | 'q' => Placement::AnyRegister,
+                'a' => Placement::Register(Register::RAX),
+                'b' => Placement::Register(Register::RBX),
+                'c' => Placement::Register(Register::RCX),
+                'd' => Placement::Register(Register::RDX),
+                'S' => Placement::Register(Register::RSI),
+                'D' => Placement::Register(Register::RDI),
+                'm' | 'o' | 'V' => Placement::Memory,
+                'i' | 'n' if !output => Placement::Immediate,
+                _ => return invalid(&format!("unsupported constraint '{letter}'")),
+            });
+        }
+
+        let rank = |placement: &Placement| match placement {
+            Placement::AnyRegister | Placement::Register(_) => 0,
+            Placement::Memory => 1,
+            _ => 2,
+        };
+        match placements.into_iter().min_by_key(rank) {
+            Some(placement) => Ok(Constraint {
+                placement,
+                read_write,
+            }),
+            None => invalid("no place is allowed"),
+        }
+    }
+}
+
+/// An entry of the clobber list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clobber {
+    /// A register the assembly overwrites, by its 64-bit name.
+    Register(Register),
+    /// `memory`: the assembly reads or writes memory other than its operands.
+    Memory,
+    /// `cc`, or a register this compiler never uses, such as `%xmm8`.
+    Ignored,
+}
+
+impl Clobber {
+    pub fn parse(name: &str) -> Result<Clobber, CompilerError> {
+        use Register::*;
+
+        let register = name.trim_start_matches('%');
+        let general = [
+            (RAX, ["rax", "eax", "ax", "al"]),
+            (RBX, ["rbx", "ebx", "bx", "bl"]),
+            (RCX, ["rcx", "ecx", "cx", "cl"]),
+            (RDX, ["rdx", "edx", "dx", "dl"]),
+            (RSI, ["rsi", "esi", "si", "sil"]),
+            (RDI, ["rdi", "edi", "di", "dil"]),
+            (R8, ["r8", "r8d", "r8w", "r8b"]),
+            (R9, ["r9", "r9d", "r9w", "r9b"]),
+            (R10, ["r10", "r10d", "r10w", "r10b"]),
+            (R11, ["r11", "r11d", "r11w", "r11b"]),
+            (R12, ["r12", "r12d", "r12w", "r12b"]),
+            (R13, ["r13", "r13d", "r13w", "r13b"]),
+            (R14, ["r14", "r14d", "r14w", "r14b"]),
+            (R15, ["r15", "r15d", "r15w", "r15b"]),
+        ];
+        let vector = [
+            (XMM0, "xmm0"),
+            (XMM1, "xmm1"),
+            (XMM2, "xmm2"),
+            (XMM3, "xmm3"),
+            (XMM4, "xmm4"),
+            (XMM5, "xmm5"),
+            (XMM6, "xmm6"),
+            (XMM7, "xmm7"),
+            (XMM14, "xmm14"),
+            (XMM15, "xmm15"),
+        ];
+
+        if let Some((reg, _)) = general.iter().find(|(_, names)| names.contains(&register)) {
+            return Ok(Clobber::Register(*reg));
+        }
+        if let Some((reg, _)) = vector.iter().find(|(_, n)| *n == register) {
+            return Ok(Clobber::Register(*reg));
+        }
+
+        match register {
+            "memory" => Ok(Clobber::Memory),
+            "cc" | "xmm8" | "xmm9" | "xmm10" | "xmm11" | "xmm12" | "xmm13" => Ok(Clobber::Ignored),
+            "rsp" | "esp" | "sp" | "rbp" | "ebp" | "bp" => Err(CompilerError::SemanticError {
+                message: format!("the stack and frame pointers cannot be clobbered: {name}"),
+            }),
+            _ => Err(CompilerError::SemanticError {
+                message: format!("unknown register name \"{name}\" in asm"),
+            }),
+        }
+    }
+}
+
+/// Where an operand ends up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Register(Register),
+    /// The memory at the address held in the register.
+    Memory(Register),
+    Immediate,
+}
+
+/// The registers an `r` operand or a memory operand's address can be put in,
+/// in order of preference. None of them has to be preserved for the caller.
+const OPERAND_REGISTERS: [Register; 9] = [
+    Register::RAX,
+    Register::RCX,
+    Register::RDX,
+    Register::RSI,
+    Register::RDI,
+    Register::R8,
+    Register::R9,
+    Register::R10,
+    Register::R11,
+];
+
+/// Picks a location for every operand, outputs first and then inputs, as
+/// they are numbered. Two outputs or two inputs never share a register, but
+/// an input may be loaded into the register an output is read from.
+/// Clobbered registers are left alone.
+pub fn assign_locations(
+    outputs: &[Constraint],
+    inputs: &[Constraint],
+    clobbers: &[Register],
+) -> Result<Vec<Location>, CompilerError> {
+    let conflict = |message: String| CompilerError::SemanticError { message };
+
+    // Operands that ask for a particular register get it. The rest come
+    // from the registers nothing asks for.
+    let mut taken: Vec<Register> = clobbers.to_vec();
+    for (i, constraint) in outputs.iter().chain(inputs).enumerate() {
+        if let Placement::Register(reg) = constraint.placement {
+            let (group, offset) = if i < outputs.len() {
+                (outputs, 0)
+            } else {
+                (inputs, outputs.len())
+            };
+            let shared = group
+                .iter()
+                .take(i - offset)
+                .any(|other| other.placement == constraint.placement);
+            if shared || clobbers.contains(&reg) {
+                return Err(conflict(format!(
+                    "asm operand {i} asks for %{}, which is already in use",
+                    format!("{reg:?}").to_lowercase()
+                )));
+            }
+            taken.push(reg);
+        }
+    }
+
+    let mut free = OPERAND_REGISTERS
+        .into_iter()
+        .filter(|reg| !taken.contains(reg));
+    let mut next_free = |i: usize| match free.next() {
+        Some(reg) => Ok(reg),
+        None => Err(conflict(format!("no register is left for asm operand {i}"))),
+    };
+
+    let mut locations = Vec::new();
+    for (i, constraint) in outputs.iter().chain(inputs).enumerate() {
+        locations.push(match constraint.placement {
+            Placement::AnyRegister => Location::Register(next_free(i)?),
+            Placement::Register(reg) => Location::Register(reg),
+            Placement::Memory => Location::Memory(next_free(i)?),
+            Placement::Immediate => Location::Immediate,
+            Placement::Matching(output) => match locations.get(output) {
+                Some(Location::Register(reg)) if output < outputs.len() => {
+                    Location::Register(*reg)
+                }
+                _ => {
+                    return Err(conflict(format!(
+                        "asm operand {i} matches operand {output}, which is not an output in a register"
+                    )));
+                }
+            },
+        });
+    }
+
+    Ok(locations)
+}
+
+/// Replaces the references to operands in an extended asm template with
+/// what `operand` renders for them: `%N` or `%[name]`, optionally with a
+/// modifier letter in between, as in `%k0`. `%%` stands for a single `%`.
+pub fn substitute(
+    template: &str,
+    names: &[Option<String>],
+    mut operand: impl FnMut(usize, Option<Modifier>) -> String,
+) -> Result<String, CompilerError> {
+    let invalid = |message: String| Err(CompilerError::SemanticError { message });
+
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+
+        let modifier = match chars.peek() {
+            Some(letter) if letter.is_ascii_alphabetic() => {
+                let letter = *letter;
+                chars.next();
+                match letter {
+                    'b' => Some(Modifier::Size(AsmType::Byte)),
+                    'k' => Some(Modifier::Size(AsmType::Longword)),
+                    'q' => Some(Modifier::Size(AsmType::Quadword)),
+                    'c' => Some(Modifier::Constant),
+                    _ => {
+                        return invalid(format!("unsupported operand modifier '%{letter}' in asm"));
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let index = if chars.peek() == Some(&'[') {
+            chars.next();
+            let name: String = chars.by_ref().take_while(|c| *c != ']').collect();
+            match names
+                .iter()
+                .position(|n| n.as_deref() == Some(name.as_str()))
+            {
+                Some(index) => index,
+                None => return invalid(format!("no asm operand is named [{name}]")),
+            }
+        } else {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            match digits.parse::<usize>() {
+                Ok(index) => index,
+                Err(_) => return invalid(format!("invalid '%' in asm template \"{template}\"")),
+            }
+        };
+
+        if index >= names.len() {
+            return invalid(format!("asm operand number {index} out of range"));
+        }
+        text.push_str(&operand(index, modifier));
+    }
+
+    Ok(text)
+}
+
+/// A letter between `%` and an operand number that changes how the operand
+/// is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modifier {
+    /// `b`, `k` or `q`: the register of this size, e.g. `%eax` for `%k0`.
+    Size(AsmType),
+    /// `c`: a constant without the leading `$`.
+    Constant,
+}
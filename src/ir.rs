@@ -1,27 +1,380 @@
-use crate::ast::{self};
+use std::collections::HashSet;
+
+use crate::{
+    ast::{self},
+    inline_asm::{Constraint, Placement},
+    symbols::{BitField, StaticInit, SymbolTable},
+    typechecker::{expression_type, is_lvalue, lvalue_type},
+    types,
+};
 
 #[derive(Debug, Clone)]
-pub struct Program(pub Vec<Function>);
+pub struct Program(pub Vec<TopLevel>);
+
+#[derive(Debug, Clone)]
+pub enum TopLevel {
+    Function(Function),
+    /// An object with static storage duration and its initial contents.
+    StaticVariable {
+        name: String,
+        global: bool,
+        init: Vec<StaticInit>,
+    },
+}
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub identifier: String,
+    pub params: Vec<String>,
+    /// Whether the function takes `...`, so needs a register save area.
+    pub variadic: bool,
     pub body: Option<Vec<Instruction>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
-    Return(Value),
+    /// Return from the function, with no value for a `void` function.
+    Return(Option<Value>),
     Unary {
         op: UnaryOperator,
         src: Value,
         dst: Value,
     },
+    Binary {
+        op: BinaryOperator,
+        src1: Value,
+        src2: Value,
+        dst: Value,
+    },
+    Copy {
+        src: Value,
+        dst: Value,
+    },
+    /// Write `src` to the part of the aggregate variable `dst` that starts
+    /// `offset` bytes in.
+    CopyToOffset {
+        src: Value,
+        dst: String,
+        offset: i64,
+    },
+    /// Widen a signed integer, copying its sign bit into the new high bits.
+    SignExtend {
+        src: Value,
+        dst: Value,
+    },
+    /// Widen an unsigned integer, filling the new high bits with zeros.
+    ZeroExtend {
+        src: Value,
+        dst: Value,
+    },
+    /// Narrow an integer by keeping only its low bytes.
+    Truncate {
+        src: Value,
+        dst: Value,
+    },
+    /// Convert a signed integer to `float` or `double`.
+    IntToFloating {
+        src: Value,
+        dst: Value,
+    },
+    /// Convert `float` or `double` to a signed integer, rounding toward zero.
+    FloatingToInt {
+        src: Value,
+        dst: Value,
+    },
+    /// Convert an unsigned integer to `float` or `double`.
+    UIntToFloating {
+        src: Value,
+        dst: Value,
+    },
+    /// Convert `float` or `double` to an unsigned integer, rounding toward zero.
+    FloatingToUInt {
+        src: Value,
+        dst: Value,
+    },
+    /// Convert between `float` and `double`.
+    FloatingConvert {
+        src: Value,
+        dst: Value,
+    },
+    Jump(String),
+    JumpIfZero {
+        condition: Value,
+        target: String,
+    },
+    JumpIfNotZero {
+        condition: Value,
+        target: String,
+    },
+    Label(String),
+    /// Call a function, storing its result in `dst` unless it returns `void`.
+    FunCall {
+        name: String,
+        args: Vec<Value>,
+        dst: Option<Value>,
+    },
+    /// Call the function `target` points to, storing its result in `dst`
+    /// unless it returns `void`.
+    IndirectCall {
+        target: Value,
+        args: Vec<Value>,
+        dst: Option<Value>,
+    },
+    /// An `asm` statement. Operands with a memory constraint are given as the
+    /// address of the object. Outputs in registers are written to the given
+    /// variables once the assembly has run, and a `+` output's previous value
+    /// is passed as an extra input matching it.
+    InlineAsm {
+        template: String,
+        outputs: Vec<AsmOperand>,
+        inputs: Vec<AsmOperand>,
+        clobbers: Vec<String>,
+    },
+    /// Store the address of the variable or function `src` in `dst`.
+    GetAddress {
+        src: Value,
+        dst: Value,
+    },
+    /// Read the value `src_ptr` points to into `dst`.
+    Load {
+        src_ptr: Value,
+        dst: Value,
+    },
+    /// Write `src` to the location `dst_ptr` points to.
+    Store {
+        src: Value,
+        dst_ptr: Value,
+    },
+    /// `dst = ptr + index * scale`, where `scale` is the size of the referenced type.
+    AddPtr {
+        ptr: Value,
+        index: Value,
+        scale: i64,
+        dst: Value,
+    },
+    /// Reserve `size` bytes on the stack, 16-byte aligned, for a variable
+    /// length array and store their address in `dst`. They stay reserved
+    /// until the function returns, or until the stack is restored to what
+    /// `SaveStack` saved before them.
+    StackAllocate {
+        size: Value,
+        dst: Value,
+    },
+    /// Save the top of the stack in `dst`, on entry to a scope that declares
+    /// a variable length array.
+    SaveStack {
+        dst: Value,
+    },
+    /// Restore the top of the stack to what `SaveStack` saved in `src`, which
+    /// frees the arrays reserved since, on exit from their scope.
+    RestoreStack {
+        src: Value,
+    },
+    /// Initialize the `va_list` that `ap` points to for the current function.
+    VaStart {
+        ap: Value,
+    },
+    /// Read the next variadic argument, of the type of `dst`, through the
+    /// `va_list` that `ap` points to.
+    VaArg {
+        ap: Value,
+        dst: Value,
+    },
 }
 
-#[derive(Debug, Clone)]
+impl Instruction {
+    /// The values the instruction reads. The operand of `GetAddress` is not
+    /// among them, since only its address is used.
+    pub fn sources(&self) -> Vec<&Value> {
+        match self {
+            Instruction::Return(value) => value.iter().collect(),
+            Instruction::Unary { src, .. }
+            | Instruction::Copy { src, .. }
+            | Instruction::CopyToOffset { src, .. }
+            | Instruction::SignExtend { src, .. }
+            | Instruction::ZeroExtend { src, .. }
+            | Instruction::Truncate { src, .. }
+            | Instruction::IntToFloating { src, .. }
+            | Instruction::FloatingToInt { src, .. }
+            | Instruction::UIntToFloating { src, .. }
+            | Instruction::FloatingToUInt { src, .. }
+            | Instruction::FloatingConvert { src, .. } => vec![src],
+            Instruction::Binary { src1, src2, .. } => vec![src1, src2],
+            Instruction::JumpIfZero { condition, .. }
+            | Instruction::JumpIfNotZero { condition, .. } => vec![condition],
+            Instruction::FunCall { args, .. } => args.iter().collect(),
+            Instruction::IndirectCall { target, args, .. } => {
+                std::iter::once(target).chain(args.iter()).collect()
+            }
+            Instruction::InlineAsm {
+                outputs, inputs, ..
+            } => outputs
+                .iter()
+                .filter(|output| output.in_memory(true))
+                .chain(inputs.iter())
+                .map(|operand| &operand.value)
+                .collect(),
+            Instruction::Load { src_ptr, .. } => vec![src_ptr],
+            Instruction::Store { src, dst_ptr } => vec![src, dst_ptr],
+            Instruction::AddPtr { ptr, index, .. } => vec![ptr, index],
+            Instruction::StackAllocate { size, .. } => vec![size],
+            Instruction::RestoreStack { src } => vec![src],
+            Instruction::VaStart { ap } | Instruction::VaArg { ap, .. } => vec![ap],
+            Instruction::Jump(_)
+            | Instruction::Label(_)
+            | Instruction::GetAddress { .. }
+            | Instruction::SaveStack { .. } => Vec::new(),
+        }
+    }
+
+    /// The values the instruction reads, as for `sources`.
+    pub fn sources_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Instruction::Return(value) => value.iter_mut().collect(),
+            Instruction::Unary { src, .. }
+            | Instruction::Copy { src, .. }
+            | Instruction::CopyToOffset { src, .. }
+            | Instruction::SignExtend { src, .. }
+            | Instruction::ZeroExtend { src, .. }
+            | Instruction::Truncate { src, .. }
+            | Instruction::IntToFloating { src, .. }
+            | Instruction::FloatingToInt { src, .. }
+            | Instruction::UIntToFloating { src, .. }
+            | Instruction::FloatingToUInt { src, .. }
+            | Instruction::FloatingConvert { src, .. } => vec![src],
+            Instruction::Binary { src1, src2, .. } => vec![src1, src2],
+            Instruction::JumpIfZero { condition, .. }
+            | Instruction::JumpIfNotZero { condition, .. } => vec![condition],
+            Instruction::FunCall { args, .. } => args.iter_mut().collect(),
+            Instruction::IndirectCall { target, args, .. } => {
+                std::iter::once(target).chain(args.iter_mut()).collect()
+            }
+            Instruction::InlineAsm {
+                outputs, inputs, ..
+            } => outputs
+                .iter_mut()
+                .filter(|output| output.in_memory(true))
+                .chain(inputs.iter_mut())
+                .map(|operand| &mut operand.value)
+                .collect(),
+            Instruction::Load { src_ptr, .. } => vec![src_ptr],
+            Instruction::Store { src, dst_ptr } => vec![src, dst_ptr],
+            Instruction::AddPtr { ptr, index, .. } => vec![ptr, index],
+            Instruction::StackAllocate { size, .. } => vec![size],
+            Instruction::RestoreStack { src } => vec![src],
+            Instruction::VaStart { ap } | Instruction::VaArg { ap, .. } => vec![ap],
+            Instruction::Jump(_)
+            | Instruction::Label(_)
+            | Instruction::GetAddress { .. }
+            | Instruction::SaveStack { .. } => Vec::new(),
+        }
+    }
+
+    /// Replaces each value the instruction reads by what `replacement` gives
+    /// for it, if anything. The target of an indirect call is never replaced
+    /// by a constant, since code generation takes the callee's type from it.
+    pub fn replace_sources(&mut self, mut replacement: impl FnMut(&Value) -> Option<Value>) {
+        let keeps_variable = matches!(self, Instruction::IndirectCall { .. });
+        for (i, value) in self.sources_mut().into_iter().enumerate() {
+            match replacement(value) {
+                Some(Value::Constant(_)) if keeps_variable && i == 0 => {}
+                Some(new) => *value = new,
+                None => {}
+            }
+        }
+    }
+
+    /// The names of the variables the instruction writes directly, rather
+    /// than through a pointer. `CopyToOffset` only writes part of its
+    /// variable.
+    pub fn destinations(&self) -> Vec<&str> {
+        let dst = match self {
+            Instruction::Unary { dst, .. }
+            | Instruction::Binary { dst, .. }
+            | Instruction::Copy { dst, .. }
+            | Instruction::SignExtend { dst, .. }
+            | Instruction::ZeroExtend { dst, .. }
+            | Instruction::Truncate { dst, .. }
+            | Instruction::IntToFloating { dst, .. }
+            | Instruction::FloatingToInt { dst, .. }
+            | Instruction::UIntToFloating { dst, .. }
+            | Instruction::FloatingToUInt { dst, .. }
+            | Instruction::FloatingConvert { dst, .. }
+            | Instruction::GetAddress { dst, .. }
+            | Instruction::Load { dst, .. }
+            | Instruction::AddPtr { dst, .. }
+            | Instruction::StackAllocate { dst, .. }
+            | Instruction::SaveStack { dst }
+            | Instruction::VaArg { dst, .. }
+            | Instruction::FunCall { dst: Some(dst), .. }
+            | Instruction::IndirectCall { dst: Some(dst), .. } => dst,
+            Instruction::CopyToOffset { dst, .. } => return vec![dst],
+            Instruction::InlineAsm { outputs, .. } => {
+                return outputs
+                    .iter()
+                    .filter(|output| !output.in_memory(true))
+                    .filter_map(|output| match &output.value {
+                        Value::Var(name) => Some(name.as_str()),
+                        Value::Constant(_) => None,
+                    })
+                    .collect();
+            }
+            _ => return Vec::new(),
+        };
+
+        match dst {
+            Value::Var(name) => vec![name],
+            Value::Constant(_) => Vec::new(),
+        }
+    }
+
+    /// The names of the variables the instruction writes directly, as for
+    /// `destinations`.
+    pub fn destinations_mut(&mut self) -> Vec<&mut String> {
+        let dst = match self {
+            Instruction::Unary { dst, .. }
+            | Instruction::Binary { dst, .. }
+            | Instruction::Copy { dst, .. }
+            | Instruction::SignExtend { dst, .. }
+            | Instruction::ZeroExtend { dst, .. }
+            | Instruction::Truncate { dst, .. }
+            | Instruction::IntToFloating { dst, .. }
+            | Instruction::FloatingToInt { dst, .. }
+            | Instruction::UIntToFloating { dst, .. }
+            | Instruction::FloatingToUInt { dst, .. }
+            | Instruction::FloatingConvert { dst, .. }
+            | Instruction::GetAddress { dst, .. }
+            | Instruction::Load { dst, .. }
+            | Instruction::AddPtr { dst, .. }
+            | Instruction::StackAllocate { dst, .. }
+            | Instruction::SaveStack { dst }
+            | Instruction::VaArg { dst, .. }
+            | Instruction::FunCall { dst: Some(dst), .. }
+            | Instruction::IndirectCall { dst: Some(dst), .. } => dst,
+            Instruction::CopyToOffset { dst, .. } => return vec![dst],
+            Instruction::InlineAsm { outputs, .. } => {
+                return outputs
+                    .iter_mut()
+                    .filter(|output| !output.in_memory(true))
+                    .filter_map(|output| match &mut output.value {
+                        Value::Var(name) => Some(name),
+                        Value::Constant(_) => None,
+                    })
+                    .collect();
+            }
+            _ => return Vec::new(),
+        };
+
+        match dst {
+            Value::Var(name) => vec![name],
+            Value::Constant(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Value {
-    Constant(i32),
+    Constant(Constant),
     Var(String),
 }
 
@@ -32,70 +385,513 @@ impl Value {
             Value::Constant(_) => panic!("Cannot get name of a constant"),
         }
     }
+
+    pub fn typ(&self, symbols: &SymbolTable) -> types::T {
+        match self {
+            // Qualifiers say how a variable may be accessed, which does not
+            // change the type of its value.
+            Value::Var(name) => symbols.type_of(name).unqualified().clone(),
+            Value::Constant(c) => c.typ(),
+        }
+    }
+
+    /// Whether the value is a `volatile` variable, every read and write of
+    /// which has to happen exactly as written.
+    pub fn is_volatile(&self, symbols: &SymbolTable) -> bool {
+        match self {
+            Value::Var(name) => symbols.type_of(name).qualifiers().volatile,
+            Value::Constant(_) => false,
+        }
+    }
 }
 
+/// Two constants are equal when they have the same type and bit pattern, so
+/// a NaN equals itself and `0.0` does not equal `-0.0`.
 #[derive(Debug, Clone, Copy)]
+pub enum Constant {
+    Bool(bool),
+    Int(i32),
+    Long(i64),
+    UInt(u32),
+    ULong(u64),
+    Float(f32),
+    Double(f64),
+}
+
+impl PartialEq for Constant {
+    fn eq(&self, other: &Constant) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.as_i64() == other.as_i64()
+    }
+}
+
+impl Eq for Constant {}
+
+impl std::hash::Hash for Constant {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        self.as_i64().hash(state);
+    }
+}
+
+impl Constant {
+    /// The constant of arithmetic or pointer type `typ` with the given value,
+    /// wrapping it if it does not fit. Pointer constants are unsigned longs.
+    pub fn of_type(typ: &types::T, value: i64) -> Self {
+        match typ {
+            types::T::Pointer(_) => Constant::ULong(value as u64),
+            types::T::Bool => Constant::Bool(value != 0),
+            types::T::Int => Constant::Int(value as i32),
+            types::T::Long => Constant::Long(value),
+            types::T::UInt => Constant::UInt(value as u32),
+            types::T::ULong => Constant::ULong(value as u64),
+            types::T::Float => Constant::Float(value as f32),
+            types::T::Double => Constant::Double(value as f64),
+            _ => panic!("No integer constant of type {:?}", typ),
+        }
+    }
+
+    pub fn typ(&self) -> types::T {
+        match self {
+            Constant::Bool(_) => types::T::Bool,
+            Constant::Int(_) => types::T::Int,
+            Constant::Long(_) => types::T::Long,
+            Constant::UInt(_) => types::T::UInt,
+            Constant::ULong(_) => types::T::ULong,
+            Constant::Float(_) => types::T::Float,
+            Constant::Double(_) => types::T::Double,
+        }
+    }
+
+    /// The constant's bit pattern, sign or zero extended to 64 bits as its type
+    /// requires. Floating point constants are returned as their raw bits.
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            Constant::Bool(v) => i64::from(*v),
+            Constant::Int(v) => *v as i64,
+            Constant::Long(v) => *v,
+            Constant::UInt(v) => *v as i64,
+            Constant::ULong(v) => *v as i64,
+            Constant::Float(v) => v.to_bits() as i64,
+            Constant::Double(v) => v.to_bits() as i64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnaryOperator {
     Complement,
     Negate,
+    Not,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+impl BinaryOperator {
+    /// Operators that compare their operands and produce an `int` truth value.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Equal
+                | BinaryOperator::NotEqual
+                | BinaryOperator::LessThan
+                | BinaryOperator::LessOrEqual
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterOrEqual
+        )
+    }
+}
+
+/// Where the result of an expression lives. Lvalues reached through a pointer
+/// are only read or written when the surrounding expression needs to.
+#[derive(Debug, Clone)]
+enum ExpResult {
+    PlainOperand(Value),
+    DereferencedPointer(Value),
+    /// A bit-field of declared type `typ`, which is read and written by way
+    /// of the whole unit that `ptr` points to.
+    BitField {
+        ptr: Value,
+        typ: types::T,
+        field: BitField,
+    },
+}
+
+/// Whether `name` is a temporary made by TACKY generation, rather than a
+/// variable of the program.
+pub fn is_temporary(name: &str) -> bool {
+    name.starts_with("tmp.")
+}
+
+/// Stands in for the result of an expression of type `void`, which is never used.
+const VOID_RESULT: Value = Value::Constant(Constant::Int(0));
+
+/// An operand of an `asm` statement, as for `ast::AsmOperand`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmOperand {
+    pub name: Option<String>,
+    pub constraint: String,
+    pub value: Value,
+}
+
+impl AsmOperand {
+    /// Whether the operand is in memory, so `value` is its address.
+    pub fn in_memory(&self, output: bool) -> bool {
+        Constraint::parse(&self.constraint, output)
+            .is_ok_and(|constraint| constraint.placement == Placement::Memory)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TackyGenerator {
     next_temp: usize,
+    next_label: usize,
+    symbols: SymbolTable,
 }
 
 impl TackyGenerator {
-    pub fn new() -> Self {
-        Self { next_temp: 0 }
+    pub fn new(symbols: SymbolTable) -> Self {
+        Self {
+            next_temp: 0,
+            next_label: 0,
+            symbols,
+        }
+    }
+
+    /// The symbol table from type checking, extended with every temporary
+    /// created while generating TACKY.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
     }
 
     pub fn emit_tacky_program(&mut self, program: &ast::Program) -> Program {
-        let mut functions = Vec::new();
+        let mut top_levels = Vec::new();
+        let mut statics = HashSet::new();
 
         for decl in &program.0 {
             match decl {
-                ast::Declaration::FunDecl(func_decl) => {
+                // Declarations without a body only matter to the type checker.
+                ast::Declaration::FunDecl(func_decl) if func_decl.body.is_some() => {
                     let func = self.generate_function(func_decl);
-                    functions.push(func);
+                    top_levels.push(TopLevel::Function(func));
                 }
-                ast::Declaration::VarDecl(_) | ast::Declaration::StructDecl(_) => {
-                    // For now, we ignore these in our minimal subset
-                    continue;
+                ast::Declaration::FunDecl(_) => continue,
+                // A global may be declared several times but is only defined
+                // once, with the initial contents the type checker settled on.
+                ast::Declaration::VarDecl(var) if statics.insert(var.name.clone()) => {
+                    let init = match self.symbols.static_init(&var.name) {
+                        Some(init) => init.to_vec(),
+                        None => vec![StaticInit::Zero(self.symbols.size_of(&var.typ))],
+                    };
+                    top_levels.push(TopLevel::StaticVariable {
+                        name: var.name.clone(),
+                        global: true,
+                        init,
+                    });
                 }
+                ast::Declaration::VarDecl(_)
+                | ast::Declaration::StructDecl(_)
+                | ast::Declaration::StaticAssert(_) => continue,
             }
         }
 
-        Program(functions)
+        Program(top_levels)
     }
 
     pub fn generate_function(&mut self, func: &ast::FunctionDeclaration) -> Function {
         let mut instructions = Vec::new();
 
+        // An unnamed parameter still takes up an argument slot, so give it a
+        // name nothing else refers to.
+        let params = func
+            .params
+            .iter()
+            .filter(|p| p.typ != types::T::Void)
+            .map(|p| {
+                if p.name.is_empty() {
+                    self.make_temp(&p.typ)
+                } else {
+                    p.name.clone()
+                }
+            })
+            .collect();
+
         if let Some(body) = &func.body {
             for item in &body.0 {
-                match item {
-                    ast::BlockItem::Stmt(stmt) => {
-                        self.emit_statement(stmt.clone(), &mut instructions)
-                    }
-                    ast::BlockItem::Decl(_) => panic!("Variable declarations not handled yet"),
-                }
+                self.emit_block_item(item, &mut instructions);
             }
         }
 
+        // Falling off the end of a function returns 0. This is required for main
+        // and harmless everywhere else, since any other return value is undefined.
+        let result = match &func.return_type {
+            types::T::Void => None,
+            types::T::Pointer(_) => Some(Value::Constant(Constant::ULong(0))),
+            typ => Some(Value::Constant(Constant::of_type(typ, 0))),
+        };
+        instructions.push(Instruction::Return(result));
+
         Function {
             identifier: func.name.clone(),
+            params,
+            variadic: func.variadic,
             body: Some(instructions),
         }
     }
 
+    fn emit_block_item(&mut self, item: &ast::BlockItem, instructions: &mut Vec<Instruction>) {
+        match item {
+            ast::BlockItem::Stmt(stmt) => self.emit_statement(stmt.clone(), instructions),
+            ast::BlockItem::Decl(ast::Declaration::VarDecl(decl)) => {
+                self.emit_var_decl(decl.clone(), instructions)
+            }
+            // Checked by the type checker, with nothing left to do at run time.
+            ast::BlockItem::Decl(
+                ast::Declaration::StaticAssert(_)
+                | ast::Declaration::FunDecl(_)
+                | ast::Declaration::StructDecl(_),
+            ) => {}
+        }
+    }
+
     pub fn emit_statement(&mut self, stmt: ast::Statement, instructions: &mut Vec<Instruction>) {
         match stmt {
             ast::Statement::ReturnStatement(Some(expr)) => {
                 let val = self.emit_tacky(expr, instructions);
-                instructions.push(Instruction::Return(val));
+                instructions.push(Instruction::Return(Some(val)));
+            }
+            ast::Statement::ReturnStatement(None) => {
+                instructions.push(Instruction::Return(None));
+            }
+            ast::Statement::ExpressionStatement(expr) => {
+                self.emit_tacky(expr, instructions);
+            }
+            ast::Statement::NullStatement => {}
+            ast::Statement::Asm(asm) => self.emit_asm(asm, instructions),
+        }
+    }
+
+    fn emit_asm(&mut self, asm: ast::AsmStatement, instructions: &mut Vec<Instruction>) {
+        let in_memory = |operand: &ast::AsmOperand, output| {
+            Constraint::parse(&operand.constraint, output)
+                .expect("asm constraints were checked by the type checker")
+                .placement
+                == Placement::Memory
+        };
+
+        // Outputs in registers are produced in temporaries and written back
+        // to the objects they name afterwards.
+        let mut outputs = Vec::new();
+        let mut read_write = Vec::new();
+        let mut write_backs = Vec::new();
+        for (i, output) in asm.outputs.into_iter().enumerate() {
+            let typ = lvalue_type(&output.expr, &self.symbols);
+            let memory = in_memory(&output, true);
+            let plus = output.constraint.starts_with('+');
+            let lvalue = self.emit_lvalue(output.expr, instructions);
+
+            let value = if memory {
+                self.address_of(lvalue, &types::T::Pointer(Box::new(typ)), instructions)
+            } else {
+                let typ = typ.unqualified();
+                if plus {
+                    let current = self.read_lvalue(lvalue.clone(), typ, instructions);
+                    read_write.push(AsmOperand {
+                        name: None,
+                        constraint: i.to_string(),
+                        value: current,
+                    });
+                }
+                let result = Value::Var(self.make_temp(typ));
+                write_backs.push((lvalue, result.clone()));
+                result
+            };
+            outputs.push(AsmOperand {
+                name: output.name,
+                constraint: output.constraint,
+                value,
+            });
+        }
+
+        let mut inputs = Vec::new();
+        for input in asm.inputs {
+            let value = if in_memory(&input, false) {
+                let pointer = types::T::Pointer(Box::new(lvalue_type(&input.expr, &self.symbols)));
+                let lvalue = self.emit_lvalue(input.expr, instructions);
+                self.address_of(lvalue, &pointer, instructions)
+            } else {
+                self.emit_tacky(input.expr, instructions)
+            };
+            inputs.push(AsmOperand {
+                name: input.name,
+                constraint: input.constraint,
+                value,
+            });
+        }
+        inputs.extend(read_write);
+
+        instructions.push(Instruction::InlineAsm {
+            template: asm.template,
+            outputs,
+            inputs,
+            clobbers: asm.clobbers,
+        });
+
+        for (lvalue, result) in write_backs {
+            match lvalue {
+                ExpResult::PlainOperand(dst) => {
+                    instructions.push(Instruction::Copy { src: result, dst })
+                }
+                ExpResult::DereferencedPointer(dst_ptr) => instructions.push(Instruction::Store {
+                    src: result,
+                    dst_ptr,
+                }),
+                ExpResult::BitField { ptr, typ, field } => {
+                    self.write_bit_field(ptr, &typ, field, result, instructions);
+                }
+            }
+        }
+    }
+
+    pub fn emit_var_decl(
+        &mut self,
+        decl: ast::VariableDeclaration,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        for (count, length) in decl.lengths {
+            let src = self.emit_tacky(length, instructions);
+            instructions.push(Instruction::Copy {
+                src,
+                dst: Value::Var(count),
+            });
+        }
+
+        // The variable holds the address of an array whose size is only
+        // known now.
+        if decl.typ.has_variable_size() {
+            let size = self.emit_size(&decl.typ, instructions);
+            instructions.push(Instruction::StackAllocate {
+                size,
+                dst: Value::Var(decl.name.clone()),
+            });
+        }
+
+        match decl.init {
+            Some(ast::Initializer::Single(init)) => {
+                let src = self.emit_tacky(init, instructions);
+                instructions.push(Instruction::Copy {
+                    src,
+                    dst: Value::Var(decl.name),
+                });
+            }
+            Some(init) => self.emit_initializer(&decl.name, 0, &decl.typ, init, instructions),
+            None => {}
+        }
+    }
+
+    /// Initializes the part of the aggregate variable `name` of type `typ`
+    /// that starts `offset` bytes in, one scalar at a time.
+    fn emit_initializer(
+        &mut self,
+        name: &str,
+        offset: i64,
+        typ: &types::T,
+        init: ast::Initializer,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        match init {
+            ast::Initializer::Single(expr) => {
+                let src = self.emit_tacky(expr, instructions);
+                instructions.push(Instruction::CopyToOffset {
+                    src,
+                    dst: name.to_owned(),
+                    offset,
+                });
+            }
+            ast::Initializer::Compound(items) => match typ.unqualified() {
+                types::T::Array { typ: element, .. } => {
+                    let size = self.symbols.size_of(element);
+                    for (i, (_, item)) in items.into_iter().enumerate() {
+                        let offset = offset + i as i64 * size;
+                        self.emit_initializer(name, offset, element, item, instructions);
+                    }
+                }
+                types::T::Structure(_) => {
+                    let members = self.symbols.layout_of(typ).members.clone();
+                    for ((_, item), member) in items.into_iter().zip(members) {
+                        let offset = offset + member.offset;
+                        let Some(field) = member.bit_field else {
+                            self.emit_initializer(name, offset, &member.typ, item, instructions);
+                            continue;
+                        };
+
+                        // A bit-field shares its unit with others, so is
+                        // written into it rather than over it.
+                        let src = match item {
+                            ast::Initializer::Single(expr) => self.emit_tacky(expr, instructions),
+                            _ => Value::Constant(Constant::of_type(member.typ.unqualified(), 0)),
+                        };
+                        let ptr_type = types::T::Pointer(Box::new(member.typ.clone()));
+                        let ptr = Value::Var(self.make_temp(&ptr_type));
+                        instructions.push(Instruction::GetAddress {
+                            src: Value::Var(name.to_owned()),
+                            dst: ptr.clone(),
+                        });
+                        let unit = Value::Var(self.make_temp(&ptr_type));
+                        instructions.push(Instruction::AddPtr {
+                            ptr,
+                            index: Value::Constant(Constant::Long(offset)),
+                            scale: 1,
+                            dst: unit.clone(),
+                        });
+                        self.write_bit_field(
+                            unit,
+                            member.typ.unqualified(),
+                            field,
+                            src,
+                            instructions,
+                        );
+                    }
+                }
+                _ => panic!("Compound initializer for {:?} was not checked", typ),
+            },
+            // Zero whatever is left out eight bytes at a time where possible,
+            // down to single bytes for arrays of `_Bool`.
+            ast::Initializer::Zero(typ) => {
+                let end = offset + self.symbols.size_of(&typ);
+                let mut offset = offset;
+                while offset < end {
+                    let zero = match end - offset {
+                        8.. => Constant::Long(0),
+                        4.. => Constant::Int(0),
+                        _ => Constant::Bool(false),
+                    };
+                    instructions.push(Instruction::CopyToOffset {
+                        src: Value::Constant(zero),
+                        dst: name.to_owned(),
+                        offset,
+                    });
+                    offset += zero.typ().size();
+                }
             }
-            _ => panic!("Unsupported statement type"),
         }
     }
 
@@ -104,13 +900,24 @@ impl TackyGenerator {
         expr: ast::Expression,
         instructions: &mut Vec<Instruction>,
     ) -> Value {
+        let typ = expression_type(&expr, &self.symbols);
+
         match expr {
-            ast::Expression::ConstInt(val) => Value::Constant(val),
+            ast::Expression::ConstInt(val) => Value::Constant(Constant::Int(val)),
+            ast::Expression::ConstLong(val) => Value::Constant(Constant::Long(val)),
+            ast::Expression::ConstUInt(val) => Value::Constant(Constant::UInt(val)),
+            ast::Expression::ConstULong(val) => Value::Constant(Constant::ULong(val)),
+            ast::Expression::ConstFloat(val) => Value::Constant(Constant::Float(val)),
+            ast::Expression::ConstDouble(val) => Value::Constant(Constant::Double(val)),
+
+            ast::Expression::Unary {
+                operator: ast::UnaryOperator::Plus,
+                expr,
+            } => self.emit_tacky(*expr, instructions),
 
             ast::Expression::Unary { operator, expr } => {
                 let src = self.emit_tacky(*expr, instructions);
-                let dst_name = self.make_temp();
-                let dst = Value::Var(dst_name.clone());
+                let dst = Value::Var(self.make_temp(&typ));
 
                 instructions.push(Instruction::Unary {
                     op: self.convert_op(operator),
@@ -126,13 +933,959 @@ impl TackyGenerator {
                 self.emit_tacky(*inner, instructions)
             }
 
+            expr @ (ast::Expression::Var(_)
+            | ast::Expression::Dereference(_)
+            | ast::Expression::Subscript { .. }
+            | ast::Expression::Dot { .. }
+            | ast::Expression::Arrow { .. }) => {
+                let lvalue = self.emit_lvalue(expr, instructions);
+                self.read_lvalue(lvalue, &typ, instructions)
+            }
+
+            ast::Expression::Cast { typ: target, expr } => {
+                let from = expression_type(&expr, &self.symbols);
+                let src = self.emit_tacky(*expr, instructions);
+                if target == types::T::Void {
+                    return VOID_RESULT;
+                }
+
+                self.emit_cast(src, &from, &target, instructions)
+            }
+
+            ast::Expression::Binary {
+                operator: operator @ (ast::BinaryOperator::And | ast::BinaryOperator::Or),
+                left,
+                right,
+            } => self.emit_logical(operator, *left, *right, instructions),
+
+            ast::Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left_type = expression_type(&left, &self.symbols);
+                let right_type = expression_type(&right, &self.symbols);
+                let src1 = self.emit_tacky(*left, instructions);
+                let src2 = self.emit_tacky(*right, instructions);
+                let dst = Value::Var(self.make_temp(&typ));
+
+                match (&left_type, &right_type) {
+                    // The difference of two pointers counts elements, not bytes.
+                    (types::T::Pointer(referenced), types::T::Pointer(_))
+                        if operator == ast::BinaryOperator::Subtract =>
+                    {
+                        let bytes = Value::Var(self.make_temp(&types::T::Long));
+                        instructions.push(Instruction::Binary {
+                            op: BinaryOperator::Subtract,
+                            src1,
+                            src2,
+                            dst: bytes.clone(),
+                        });
+                        let size = self.emit_size(referenced, instructions);
+                        instructions.push(Instruction::Binary {
+                            op: BinaryOperator::Divide,
+                            src1: bytes,
+                            src2: size,
+                            dst: dst.clone(),
+                        });
+                    }
+                    (types::T::Pointer(_), _) if !operator.is_comparison() => {
+                        let subtract = operator == ast::BinaryOperator::Subtract;
+                        self.emit_add_ptr(
+                            src1,
+                            src2,
+                            subtract,
+                            &left_type,
+                            dst.clone(),
+                            instructions,
+                        );
+                    }
+                    _ => instructions.push(Instruction::Binary {
+                        op: self.convert_binary_op(operator),
+                        src1,
+                        src2,
+                        dst: dst.clone(),
+                    }),
+                }
+
+                dst
+            }
+
+            ast::Expression::Assignment { lvalue, value } => {
+                let lvalue = self.emit_lvalue(*lvalue, instructions);
+                let src = self.emit_tacky(*value, instructions);
+
+                match lvalue {
+                    ExpResult::PlainOperand(dst) => {
+                        instructions.push(Instruction::Copy {
+                            src,
+                            dst: dst.clone(),
+                        });
+                        dst
+                    }
+                    ExpResult::DereferencedPointer(dst_ptr) => {
+                        instructions.push(Instruction::Store {
+                            src: src.clone(),
+                            dst_ptr,
+                        });
+                        src
+                    }
+                    ExpResult::BitField { ptr, typ, field } => {
+                        self.write_bit_field(ptr, &typ, field, src, instructions)
+                    }
+                }
+            }
+
+            ast::Expression::CompoundAssignment {
+                operator,
+                lvalue,
+                value,
+            } => {
+                // The type checker converted the right hand side to the type the
+                // operation is performed in, which may be wider than the lvalue.
+                let computation_type = expression_type(&value, &self.symbols);
+                let lvalue = self.emit_lvalue(*lvalue, instructions);
+                let src2 = self.emit_tacky(*value, instructions);
+                let src1 = self.read_lvalue(lvalue.clone(), &typ, instructions);
+                let result = self.update_target(&lvalue, &typ);
+
+                if matches!(typ, types::T::Pointer(_)) {
+                    let subtract = operator == ast::BinaryOperator::Subtract;
+                    self.emit_add_ptr(src1, src2, subtract, &typ, result.clone(), instructions);
+                } else if computation_type == typ {
+                    instructions.push(Instruction::Binary {
+                        op: self.convert_binary_op(operator),
+                        src1,
+                        src2,
+                        dst: result.clone(),
+                    });
+                } else {
+                    let src1 = self.emit_cast(src1, &typ, &computation_type, instructions);
+                    let wide_result = Value::Var(self.make_temp(&computation_type));
+                    instructions.push(Instruction::Binary {
+                        op: self.convert_binary_op(operator),
+                        src1,
+                        src2,
+                        dst: wide_result.clone(),
+                    });
+
+                    let converted =
+                        self.emit_cast(wide_result, &computation_type, &typ, instructions);
+                    instructions.push(Instruction::Copy {
+                        src: converted,
+                        dst: result.clone(),
+                    });
+                }
+
+                self.finish_update(lvalue, result, instructions)
+            }
+
+            ast::Expression::PrefixIncDec { operator, expr } => {
+                let lvalue = self.emit_lvalue(*expr, instructions);
+                let current = self.read_lvalue(lvalue.clone(), &typ, instructions);
+                let result = self.update_target(&lvalue, &typ);
+
+                self.emit_inc_dec(operator, current, &typ, result.clone(), instructions);
+                self.finish_update(lvalue, result, instructions)
+            }
+
+            ast::Expression::PostfixIncDec { operator, expr } => {
+                let lvalue = self.emit_lvalue(*expr, instructions);
+                let old_value = Value::Var(self.make_temp(&typ));
+
+                let read = match &lvalue {
+                    ExpResult::PlainOperand(src) => Instruction::Copy {
+                        src: src.clone(),
+                        dst: old_value.clone(),
+                    },
+                    ExpResult::DereferencedPointer(src_ptr) => Instruction::Load {
+                        src_ptr: src_ptr.clone(),
+                        dst: old_value.clone(),
+                    },
+                    ExpResult::BitField { .. } => Instruction::Copy {
+                        src: self.read_lvalue(lvalue.clone(), &typ, instructions),
+                        dst: old_value.clone(),
+                    },
+                };
+                instructions.push(read);
+
+                let result = self.update_target(&lvalue, &typ);
+                self.emit_inc_dec(
+                    operator,
+                    old_value.clone(),
+                    &typ,
+                    result.clone(),
+                    instructions,
+                );
+                self.finish_update(lvalue, result, instructions);
+
+                old_value
+            }
+
+            ast::Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let else_label = self.make_label("cond_else");
+                let end_label = self.make_label("cond_end");
+                let result = if typ == types::T::Void {
+                    None
+                } else {
+                    Some(Value::Var(self.make_temp(&typ)))
+                };
+
+                let condition = self.emit_tacky(*condition, instructions);
+                instructions.push(Instruction::JumpIfZero {
+                    condition,
+                    target: else_label.clone(),
+                });
+
+                let then = self.emit_tacky(*then, instructions);
+                if let Some(dst) = &result {
+                    instructions.push(Instruction::Copy {
+                        src: then,
+                        dst: dst.clone(),
+                    });
+                }
+                instructions.push(Instruction::Jump(end_label.clone()));
+
+                instructions.push(Instruction::Label(else_label));
+                let otherwise = self.emit_tacky(*otherwise, instructions);
+                if let Some(dst) = &result {
+                    instructions.push(Instruction::Copy {
+                        src: otherwise,
+                        dst: dst.clone(),
+                    });
+                }
+                instructions.push(Instruction::Label(end_label));
+
+                result.unwrap_or(VOID_RESULT)
+            }
+
+            ast::Expression::Comma { left, right } => {
+                self.emit_tacky(*left, instructions);
+                self.emit_tacky(*right, instructions)
+            }
+
+            ast::Expression::FunctionCall { callee, args } => {
+                // The type checker leaves the function's name as the callee of a
+                // direct call, and a pointer to it otherwise.
+                let name = match callee.ungrouped() {
+                    ast::Expression::Var(name)
+                        if matches!(
+                            self.symbols.get(&name.name()),
+                            Some(types::T::FunType { .. })
+                        ) =>
+                    {
+                        Some(name.name())
+                    }
+                    _ => None,
+                };
+                let target = match name {
+                    Some(_) => None,
+                    None => Some(self.emit_tacky(*callee, instructions)),
+                };
+
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.emit_tacky(arg, instructions))
+                    .collect();
+                let dst = if typ == types::T::Void {
+                    None
+                } else {
+                    Some(Value::Var(self.make_temp(&typ)))
+                };
+
+                instructions.push(match (name, target) {
+                    (Some(name), _) => Instruction::FunCall {
+                        name,
+                        args,
+                        dst: dst.clone(),
+                    },
+                    (None, target) => Instruction::IndirectCall {
+                        target: target.expect("indirect calls evaluate their target"),
+                        args,
+                        dst: dst.clone(),
+                    },
+                });
+
+                dst.unwrap_or(VOID_RESULT)
+            }
+
+            ast::Expression::AddressOf(inner) => {
+                let lvalue = self.emit_lvalue(*inner, instructions);
+                self.address_of(lvalue, &typ, instructions)
+            }
+
+            // The type checker only leaves the operand of sizeof in place
+            // when it is a variable length array, which is evaluated.
+            ast::Expression::SizeOfExpr(inner) => {
+                let inner_type = lvalue_type(&inner, &self.symbols);
+                self.emit_lvalue(*inner, instructions);
+                let size = self.emit_size(&inner_type, instructions);
+                self.emit_cast(size, &types::T::Long, &types::T::ULong, instructions)
+            }
+
+            // The builtins work on the address of the va_list's record, which is
+            // what `ap` has decayed to.
+            ast::Expression::VaStart(ap) => {
+                let ap = self.emit_tacky(*ap, instructions);
+                instructions.push(Instruction::VaStart { ap });
+                VOID_RESULT
+            }
+            ast::Expression::VaArg { ap, .. } => {
+                let ap = self.emit_tacky(*ap, instructions);
+                let dst = Value::Var(self.make_temp(&typ));
+                instructions.push(Instruction::VaArg {
+                    ap,
+                    dst: dst.clone(),
+                });
+                dst
+            }
+            // Nothing needs releasing, but `ap` is still evaluated.
+            ast::Expression::VaEnd(ap) => {
+                self.emit_tacky(*ap, instructions);
+                VOID_RESULT
+            }
+            // The variable length arrays declared in the block are freed when
+            // it ends, by putting the stack back the way it was before them.
+            ast::Expression::StatementExpression(block) => {
+                let declares_vla = block.0.iter().any(|item| {
+                    matches!(
+                        item,
+                        ast::BlockItem::Decl(ast::Declaration::VarDecl(decl))
+                            if decl.typ.has_variable_size()
+                    )
+                });
+                let saved = declares_vla.then(|| {
+                    let saved = Value::Var(self.make_temp(&types::T::ULong));
+                    instructions.push(Instruction::SaveStack { dst: saved.clone() });
+                    saved
+                });
+
+                let result = match block.result() {
+                    Some(result) => {
+                        for item in &block.0[..block.0.len() - 1] {
+                            self.emit_block_item(item, instructions);
+                        }
+                        self.emit_tacky(result.clone(), instructions)
+                    }
+                    None => {
+                        for item in &block.0 {
+                            self.emit_block_item(item, instructions);
+                        }
+                        VOID_RESULT
+                    }
+                };
+
+                if let Some(src) = saved {
+                    instructions.push(Instruction::RestoreStack { src });
+                }
+                result
+            }
+
             _ => panic!("Unsupported expression type: {:?}", expr),
         }
     }
 
-    fn make_temp(&mut self) -> String {
+    /// Lowers `&&` and `||` to jumps so the right operand is only evaluated
+    /// when it decides the result.
+    fn emit_logical(
+        &mut self,
+        operator: ast::BinaryOperator,
+        left: ast::Expression,
+        right: ast::Expression,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        let is_and = operator == ast::BinaryOperator::And;
+        let short_circuit = self.make_label(if is_and { "and_false" } else { "or_true" });
+        let end_label = self.make_label(if is_and { "and_end" } else { "or_end" });
+        let result = Value::Var(self.make_temp(&types::T::Int));
+
+        let jump = |condition, target: &String| {
+            let target = target.clone();
+            if is_and {
+                Instruction::JumpIfZero { condition, target }
+            } else {
+                Instruction::JumpIfNotZero { condition, target }
+            }
+        };
+
+        let left = self.emit_tacky(left, instructions);
+        instructions.push(jump(left, &short_circuit));
+        let right = self.emit_tacky(right, instructions);
+        instructions.push(jump(right, &short_circuit));
+
+        instructions.push(Instruction::Copy {
+            src: Value::Constant(Constant::Int(is_and as i32)),
+            dst: result.clone(),
+        });
+        instructions.push(Instruction::Jump(end_label.clone()));
+        instructions.push(Instruction::Label(short_circuit));
+        instructions.push(Instruction::Copy {
+            src: Value::Constant(Constant::Int(!is_and as i32)),
+            dst: result.clone(),
+        });
+        instructions.push(Instruction::Label(end_label));
+
+        result
+    }
+
+    /// Evaluates the operand of an assignment or increment exactly once and
+    /// returns the location it designates.
+    fn emit_lvalue(
+        &mut self,
+        expr: ast::Expression,
+        instructions: &mut Vec<Instruction>,
+    ) -> ExpResult {
+        match expr {
+            // A variable length array lives wherever its variable points.
+            ast::Expression::Var(ident)
+                if self.symbols.type_of(&ident.name()).has_variable_size() =>
+            {
+                ExpResult::DereferencedPointer(Value::Var(ident.name()))
+            }
+            ast::Expression::Var(ident) => ExpResult::PlainOperand(Value::Var(ident.name())),
+            ast::Expression::Grouping(inner) => self.emit_lvalue(*inner, instructions),
+            ast::Expression::Dereference(inner) => {
+                ExpResult::DereferencedPointer(self.emit_tacky(*inner, instructions))
+            }
+            ast::Expression::Subscript { expr, index } => {
+                let ptr_type = expression_type(&expr, &self.symbols);
+                let ptr = self.emit_tacky(*expr, instructions);
+                let index = self.emit_tacky(*index, instructions);
+                let dst = Value::Var(self.make_temp(&ptr_type));
+
+                self.emit_add_ptr(ptr, index, false, &ptr_type, dst.clone(), instructions);
+                ExpResult::DereferencedPointer(dst)
+            }
+            // A member is reached from the address of its structure, so a
+            // structure that is only a value is stored in a temporary first.
+            ast::Expression::Dot { expr, member } => {
+                let structure = lvalue_type(&expr, &self.symbols);
+                let base = if is_lvalue(&expr) {
+                    self.emit_lvalue(*expr, instructions)
+                } else {
+                    ExpResult::PlainOperand(self.emit_tacky(*expr, instructions))
+                };
+                let ptr_type = types::T::Pointer(Box::new(structure.clone()));
+                let ptr = self.address_of(base, &ptr_type, instructions);
+                self.emit_member_address(ptr, &structure, &member, instructions)
+            }
+            ast::Expression::Arrow { expr, member } => {
+                let types::T::Pointer(structure) = expression_type(&expr, &self.symbols) else {
+                    panic!("'->' applied to a non-pointer: {:?}", expr);
+                };
+                let ptr = self.emit_tacky(*expr, instructions);
+                self.emit_member_address(ptr, &structure, &member, instructions)
+            }
+            _ => panic!("Invalid lvalue: {:?}", expr),
+        }
+    }
+
+    /// The location of `member` in the structure of type `structure` that
+    /// `ptr` points to.
+    fn emit_member_address(
+        &mut self,
+        ptr: Value,
+        structure: &types::T,
+        member: &str,
+        instructions: &mut Vec<Instruction>,
+    ) -> ExpResult {
+        let member = self.symbols.member(structure, member).clone();
+        let ptr = if member.offset == 0 {
+            ptr
+        } else {
+            let dst = Value::Var(self.make_temp(&types::T::Pointer(Box::new(member.typ.clone()))));
+            instructions.push(Instruction::AddPtr {
+                ptr,
+                index: Value::Constant(Constant::Long(member.offset)),
+                scale: 1,
+                dst: dst.clone(),
+            });
+            dst
+        };
+
+        match member.bit_field {
+            Some(field) => ExpResult::BitField {
+                ptr,
+                typ: member.typ.unqualified().clone(),
+                field,
+            },
+            None => ExpResult::DereferencedPointer(ptr),
+        }
+    }
+
+    /// The address of `lvalue` as a pointer of type `typ`.
+    fn address_of(
+        &mut self,
+        lvalue: ExpResult,
+        typ: &types::T,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        match lvalue {
+            ExpResult::PlainOperand(src) => {
+                let dst = Value::Var(self.make_temp(typ));
+                instructions.push(Instruction::GetAddress {
+                    src,
+                    dst: dst.clone(),
+                });
+                dst
+            }
+            // `&*p` is just `p`.
+            ExpResult::DereferencedPointer(ptr) => ptr,
+            ExpResult::BitField { .. } => panic!("Took the address of a bit-field"),
+        }
+    }
+
+    /// The current value of an lvalue of type `typ`.
+    fn read_lvalue(
+        &mut self,
+        lvalue: ExpResult,
+        typ: &types::T,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        match lvalue {
+            ExpResult::PlainOperand(val) => val,
+            ExpResult::DereferencedPointer(src_ptr) => {
+                let dst = Value::Var(self.make_temp(typ));
+                instructions.push(Instruction::Load {
+                    src_ptr,
+                    dst: dst.clone(),
+                });
+                dst
+            }
+            ExpResult::BitField {
+                ptr,
+                typ: declared,
+                field,
+            } => {
+                let bits = self.load_unit(ptr, field.size, instructions);
+                self.extract_bit_field(bits, &declared, field, typ, instructions)
+            }
+        }
+    }
+
+    /// Where an updated value is computed: variables are updated in place, while
+    /// values behind a pointer are computed in a temporary and stored afterwards.
+    fn update_target(&mut self, lvalue: &ExpResult, typ: &types::T) -> Value {
+        match lvalue {
+            ExpResult::PlainOperand(val) => val.clone(),
+            ExpResult::DereferencedPointer(_) | ExpResult::BitField { .. } => {
+                Value::Var(self.make_temp(typ))
+            }
+        }
+    }
+
+    /// Writes a value computed in the `update_target` of `lvalue` back to it.
+    fn finish_update(
+        &mut self,
+        lvalue: ExpResult,
+        result: Value,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        match lvalue {
+            ExpResult::PlainOperand(_) => result,
+            ExpResult::DereferencedPointer(dst_ptr) => {
+                instructions.push(Instruction::Store {
+                    src: result.clone(),
+                    dst_ptr,
+                });
+                result
+            }
+            ExpResult::BitField { ptr, typ, field } => {
+                self.write_bit_field(ptr, &typ, field, result, instructions)
+            }
+        }
+    }
+
+    /// The unit of `size` bytes that `ptr` points to, widened to an
+    /// unsigned long. The unit only holds bits, so a single byte is zero
+    /// extended rather than read as a `_Bool`, and a unit of any other size
+    /// than an int or a long is put together a byte at a time.
+    fn load_unit(&mut self, ptr: Value, size: i64, instructions: &mut Vec<Instruction>) -> Value {
+        let unit = match size {
+            1 => types::T::Bool,
+            4 => types::T::UInt,
+            8 => types::T::ULong,
+            _ => {
+                let mut bits = Value::Constant(Constant::ULong(0));
+                for byte in 0..size {
+                    let ptr = self.byte_address(ptr.clone(), byte, instructions);
+                    let value = self.load_unit(ptr, 1, instructions);
+                    let count = Value::Constant(Constant::ULong(byte as u64 * 8));
+                    let shifted =
+                        self.emit_bits(BinaryOperator::ShiftLeft, value, count, instructions);
+                    bits = self.emit_bits(BinaryOperator::BitwiseOr, bits, shifted, instructions);
+                }
+                return bits;
+            }
+        };
+
+        let src = Value::Var(self.make_temp(&unit));
+        instructions.push(Instruction::Load {
+            src_ptr: ptr,
+            dst: src.clone(),
+        });
+        if unit != types::T::Bool {
+            return self.emit_cast(src, &unit, &types::T::ULong, instructions);
+        }
+
+        let dst = Value::Var(self.make_temp(&types::T::ULong));
+        instructions.push(Instruction::ZeroExtend {
+            src,
+            dst: dst.clone(),
+        });
+        dst
+    }
+
+    /// Stores the low `size` bytes of the unsigned long `bits` to the unit
+    /// that `ptr` points to, the way `load_unit` reads it.
+    fn store_unit(
+        &mut self,
+        ptr: Value,
+        size: i64,
+        bits: Value,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        let src = match size {
+            1 => {
+                let dst = Value::Var(self.make_temp(&types::T::Bool));
+                instructions.push(Instruction::Truncate {
+                    src: bits,
+                    dst: dst.clone(),
+                });
+                dst
+            }
+            4 => self.emit_cast(bits, &types::T::ULong, &types::T::UInt, instructions),
+            8 => bits,
+            _ => {
+                for byte in 0..size {
+                    let ptr = self.byte_address(ptr.clone(), byte, instructions);
+                    let count = Value::Constant(Constant::ULong(byte as u64 * 8));
+                    let shifted = self.emit_bits(
+                        BinaryOperator::ShiftRight,
+                        bits.clone(),
+                        count,
+                        instructions,
+                    );
+                    self.store_unit(ptr, 1, shifted, instructions);
+                }
+                return;
+            }
+        };
+        instructions.push(Instruction::Store { src, dst_ptr: ptr });
+    }
+
+    /// `ptr` moved on by `offset` bytes.
+    fn byte_address(
+        &mut self,
+        ptr: Value,
+        offset: i64,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        if offset == 0 {
+            return ptr;
+        }
+
+        let dst = Value::Var(self.make_temp(&types::T::Pointer(Box::new(types::T::Bool))));
+        instructions.push(Instruction::AddPtr {
+            ptr,
+            index: Value::Constant(Constant::Long(offset)),
+            scale: 1,
+            dst: dst.clone(),
+        });
+        dst
+    }
+
+    /// `op` applied to the unsigned longs `src1` and `src2`.
+    fn emit_bits(
+        &mut self,
+        op: BinaryOperator,
+        src1: Value,
+        src2: Value,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        let dst = Value::Var(self.make_temp(&types::T::ULong));
+        instructions.push(Instruction::Binary {
+            op,
+            src1,
+            src2,
+            dst: dst.clone(),
+        });
+        dst
+    }
+
+    /// The value of type `typ` of the bit-field `field` in `bits`, its unit
+    /// widened to an unsigned long. The field is shifted up to the top and
+    /// back down, which sign extends it if its declared type `declared` is
+    /// signed.
+    fn extract_bit_field(
+        &mut self,
+        bits: Value,
+        declared: &types::T,
+        field: BitField,
+        typ: &types::T,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        let wide = if declared.is_signed() {
+            types::T::Long
+        } else {
+            types::T::ULong
+        };
+        let bits = self.emit_cast(bits, &types::T::ULong, &wide, instructions);
+
+        let mut value = bits;
+        for (op, count) in [
+            (BinaryOperator::ShiftLeft, 64 - field.shift - field.width),
+            (BinaryOperator::ShiftRight, 64 - field.width),
+        ] {
+            let dst = Value::Var(self.make_temp(&wide));
+            instructions.push(Instruction::Binary {
+                op,
+                src1: value,
+                src2: Value::Constant(Constant::of_type(&wide, count)),
+                dst: dst.clone(),
+            });
+            value = dst;
+        }
+
+        self.emit_cast(value, &wide, typ, instructions)
+    }
+
+    /// Writes `src` to the bit-field `field` of declared type `declared` in
+    /// the unit that `ptr` points to, leaving the rest of the unit as it
+    /// was, and returns the value the bit-field ends up with, which is `src`
+    /// cut down to its width.
+    fn write_bit_field(
+        &mut self,
+        ptr: Value,
+        declared: &types::T,
+        field: BitField,
+        src: Value,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        let typ = src.typ(&self.symbols);
+        let mask = u64::MAX >> (64 - field.width) << field.shift;
+        let old = self.load_unit(ptr.clone(), field.size, instructions);
+        let new = self.emit_cast(src, &typ, &types::T::ULong, instructions);
+
+        let constant = |value| Value::Constant(Constant::ULong(value));
+        let shifted = self.emit_bits(
+            BinaryOperator::ShiftLeft,
+            new,
+            constant(field.shift as u64),
+            instructions,
+        );
+        let new = self.emit_bits(
+            BinaryOperator::BitwiseAnd,
+            shifted,
+            constant(mask),
+            instructions,
+        );
+        let kept = self.emit_bits(
+            BinaryOperator::BitwiseAnd,
+            old,
+            constant(!mask),
+            instructions,
+        );
+        let bits = self.emit_bits(BinaryOperator::BitwiseOr, kept, new, instructions);
+        self.store_unit(ptr, field.size, bits.clone(), instructions);
+
+        self.extract_bit_field(bits, declared, field, &typ, instructions)
+    }
+
+    fn emit_inc_dec(
+        &mut self,
+        operator: ast::IncDecOperator,
+        src: Value,
+        typ: &types::T,
+        dst: Value,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        if matches!(typ, types::T::Pointer(_)) {
+            let one = Value::Constant(Constant::Long(1));
+            let subtract = operator == ast::IncDecOperator::Decrement;
+            self.emit_add_ptr(src, one, subtract, typ, dst, instructions);
+        } else if *typ == types::T::Bool {
+            // Step the promoted value and convert back, so incrementing
+            // always gives 1 and decrementing flips the value.
+            let promoted = typ.promoted();
+            let src = self.emit_cast(src, typ, &promoted, instructions);
+            let stepped = Value::Var(self.make_temp(&promoted));
+            instructions.push(Instruction::Binary {
+                op: self.convert_inc_dec_op(operator),
+                src1: src,
+                src2: Value::Constant(Constant::of_type(&promoted, 1)),
+                dst: stepped.clone(),
+            });
+            let converted = self.emit_cast(stepped, &promoted, typ, instructions);
+            instructions.push(Instruction::Copy {
+                src: converted,
+                dst,
+            });
+        } else {
+            instructions.push(Instruction::Binary {
+                op: self.convert_inc_dec_op(operator),
+                src1: src,
+                src2: Value::Constant(Constant::of_type(typ, 1)),
+                dst,
+            });
+        }
+    }
+
+    /// `dst = ptr + index` or `dst = ptr - index`, counting in elements of the
+    /// type `ptr` points to. `index` is always a long.
+    fn emit_add_ptr(
+        &mut self,
+        ptr: Value,
+        index: Value,
+        subtract: bool,
+        ptr_type: &types::T,
+        dst: Value,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        let types::T::Pointer(referenced) = ptr_type else {
+            panic!("Pointer arithmetic on {:?}", ptr_type);
+        };
+
+        let index = if subtract {
+            let negated = Value::Var(self.make_temp(&types::T::Long));
+            instructions.push(Instruction::Unary {
+                op: UnaryOperator::Negate,
+                src: index,
+                dst: negated.clone(),
+            });
+            negated
+        } else {
+            index
+        };
+
+        if !referenced.has_variable_size() {
+            instructions.push(Instruction::AddPtr {
+                ptr,
+                index,
+                scale: self.symbols.size_of(referenced),
+                dst,
+            });
+            return;
+        }
+
+        // Stepping over variable length arrays takes their size in bytes,
+        // which is only known at run time.
+        let size = self.emit_size(referenced, instructions);
+        let offset = Value::Var(self.make_temp(&types::T::Long));
+        instructions.push(Instruction::Binary {
+            op: BinaryOperator::Multiply,
+            src1: index,
+            src2: size,
+            dst: offset.clone(),
+        });
+        instructions.push(Instruction::AddPtr {
+            ptr,
+            index: offset,
+            scale: 1,
+            dst,
+        });
+    }
+
+    /// The size of `typ` in bytes as a long, computed from the lengths of any
+    /// variable length arrays it is made of.
+    fn emit_size(&mut self, typ: &types::T, instructions: &mut Vec<Instruction>) -> Value {
+        let (element, length) = match typ.unqualified() {
+            types::T::VariableArray { typ, count } => (typ, Value::Var(count.clone())),
+            types::T::Array { typ, size } if typ.has_variable_size() => {
+                (typ, Value::Constant(Constant::Long(*size)))
+            }
+            typ => return Value::Constant(Constant::Long(self.symbols.size_of(typ))),
+        };
+
+        let element_size = self.emit_size(element, instructions);
+        let dst = Value::Var(self.make_temp(&types::T::Long));
+        instructions.push(Instruction::Binary {
+            op: BinaryOperator::Multiply,
+            src1: length,
+            src2: element_size,
+            dst: dst.clone(),
+        });
+        dst
+    }
+
+    /// Convert `src` from one scalar type to another.
+    fn emit_cast(
+        &mut self,
+        src: Value,
+        from: &types::T,
+        to: &types::T,
+        instructions: &mut Vec<Instruction>,
+    ) -> Value {
+        if from == to {
+            return src;
+        }
+
+        // Converting to `_Bool` asks whether the value is nonzero, so that
+        // e.g. 256 and 0.5 both become 1 instead of being truncated to 0.
+        if *to == types::T::Bool {
+            let zero = match from {
+                types::T::Pointer(_) => Constant::ULong(0),
+                typ => Constant::of_type(typ, 0),
+            };
+            let dst = Value::Var(self.make_temp(to));
+            instructions.push(Instruction::Binary {
+                op: BinaryOperator::NotEqual,
+                src1: src,
+                src2: Value::Constant(zero),
+                dst: dst.clone(),
+            });
+            return dst;
+        }
+
+        // The conversions to floating point need at least a longword.
+        if *from == types::T::Bool && to.is_floating() {
+            let widened = self.emit_cast(src, from, &types::T::Int, instructions);
+            return self.emit_cast(widened, &types::T::Int, to, instructions);
+        }
+
+        let dst = Value::Var(self.make_temp(to));
+        let dst_copy = dst.clone();
+
+        instructions.push(if from.is_floating() && to.is_floating() {
+            Instruction::FloatingConvert { src, dst }
+        } else if to.is_floating() && from.is_signed() {
+            Instruction::IntToFloating { src, dst }
+        } else if to.is_floating() {
+            Instruction::UIntToFloating { src, dst }
+        } else if from.is_floating() && to.is_signed() {
+            Instruction::FloatingToInt { src, dst }
+        } else if from.is_floating() {
+            Instruction::FloatingToUInt { src, dst }
+        } else if from.size() == to.size() {
+            // Only the interpretation of the bits changes, e.g. int to unsigned int.
+            Instruction::Copy { src, dst }
+        } else if to.size() < from.size() {
+            Instruction::Truncate { src, dst }
+        } else if from.is_signed() {
+            Instruction::SignExtend { src, dst }
+        } else {
+            Instruction::ZeroExtend { src, dst }
+        });
+
+        dst_copy
+    }
+
+    fn make_label(&mut self, prefix: &str) -> String {
+        let label = format!("{prefix}.{}", self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// A new variable for an intermediate result. Every temporary is written
+    /// before it is read, and its address is never taken.
+    fn make_temp(&mut self, typ: &types::T) -> String {
         let tmp = format!("tmp.{}", self.next_temp);
         self.next_temp += 1;
+        self.symbols.insert(tmp.clone(), typ.clone());
         tmp
     }
 
@@ -140,6 +1893,39 @@ impl TackyGenerator {
         match op {
             ast::UnaryOperator::Negate => UnaryOperator::Negate,
             ast::UnaryOperator::Complement => UnaryOperator::Complement,
+            ast::UnaryOperator::Not => UnaryOperator::Not,
+            ast::UnaryOperator::Plus => unreachable!("unary plus does not generate code"),
+        }
+    }
+
+    fn convert_binary_op(&self, op: ast::BinaryOperator) -> BinaryOperator {
+        match op {
+            ast::BinaryOperator::Add => BinaryOperator::Add,
+            ast::BinaryOperator::Subtract => BinaryOperator::Subtract,
+            ast::BinaryOperator::Multiply => BinaryOperator::Multiply,
+            ast::BinaryOperator::Divide => BinaryOperator::Divide,
+            ast::BinaryOperator::Remainder => BinaryOperator::Remainder,
+            ast::BinaryOperator::BitwiseAnd => BinaryOperator::BitwiseAnd,
+            ast::BinaryOperator::BitwiseOr => BinaryOperator::BitwiseOr,
+            ast::BinaryOperator::BitwiseXor => BinaryOperator::BitwiseXor,
+            ast::BinaryOperator::ShiftLeft => BinaryOperator::ShiftLeft,
+            ast::BinaryOperator::ShiftRight => BinaryOperator::ShiftRight,
+            ast::BinaryOperator::Equal => BinaryOperator::Equal,
+            ast::BinaryOperator::NotEqual => BinaryOperator::NotEqual,
+            ast::BinaryOperator::LessThan => BinaryOperator::LessThan,
+            ast::BinaryOperator::LessOrEqual => BinaryOperator::LessOrEqual,
+            ast::BinaryOperator::GreaterThan => BinaryOperator::GreaterThan,
+            ast::BinaryOperator::GreaterOrEqual => BinaryOperator::GreaterOrEqual,
+            ast::BinaryOperator::And | ast::BinaryOperator::Or => {
+                unreachable!("logical operators are lowered to jumps")
+            }
+        }
+    }
+
+    fn convert_inc_dec_op(&self, op: ast::IncDecOperator) -> BinaryOperator {
+        match op {
+            ast::IncDecOperator::Increment => BinaryOperator::Add,
+            ast::IncDecOperator::Decrement => BinaryOperator::Subtract,
         }
     }
 }
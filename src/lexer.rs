@@ -25,7 +25,7 @@ impl<'src> Lexer<'src> {
     }
 
     pub fn errors(&self) -> Vec<CompilerError> {
-        self.errors.iter().cloned().collect()
+        self.errors.to_vec()
     }
 
     pub fn tokenize(&mut self) -> Vec<Token> {
@@ -61,7 +61,7 @@ impl<'src> Lexer<'src> {
             return Ok(self.handle_ident(start));
         }
 
-        if is_digit(ch) {
+        if is_digit(ch) || (ch == '.' && is_digit(self.peek_next())) {
             return self.handle_number(start);
         }
 
@@ -70,16 +70,86 @@ impl<'src> Lexer<'src> {
             ')' => self.make_token(TokenKind::RightParen, start, true),
             '{' => self.make_token(TokenKind::LeftBrace, start, true),
             '}' => self.make_token(TokenKind::RightBrace, start, true),
+            '[' => self.make_token(TokenKind::LeftBracket, start, true),
+            ']' => self.make_token(TokenKind::RightBracket, start, true),
             ';' => self.make_token(TokenKind::Semicolon, start, true),
+            ',' => self.make_token(TokenKind::Comma, start, true),
+            '.' if self.peek_next() == '.' && self.peek_at(2) == '.' => {
+                self.advance();
+                self.make_double_token(TokenKind::Ellipsis, start)
+            }
+            '.' => self.make_token(TokenKind::Dot, start, true),
+            '?' => self.make_token(TokenKind::Question, start, true),
+            ':' => self.make_token(TokenKind::Colon, start, true),
+            '"' => self.handle_string(start),
             '~' => self.make_token(TokenKind::Tilde, start, true),
-            '-' => {
-                if self.peek_next() == '-' {
+            '+' => match self.peek_next() {
+                '+' => self.make_double_token(TokenKind::PlusPlus, start),
+                '=' => self.make_double_token(TokenKind::PlusEqual, start),
+                _ => self.make_token(TokenKind::Plus, start, true),
+            },
+            '-' => match self.peek_next() {
+                '-' => self.make_double_token(TokenKind::MinusMinus, start),
+                '=' => self.make_double_token(TokenKind::MinusEqual, start),
+                '>' => self.make_double_token(TokenKind::Arrow, start),
+                _ => self.make_token(TokenKind::Minus, start, true),
+            },
+            '*' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::StarEqual, start),
+                _ => self.make_token(TokenKind::Star, start, true),
+            },
+            '/' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::SlashEqual, start),
+                _ => self.make_token(TokenKind::Slash, start, true),
+            },
+            '%' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::PercentEqual, start),
+                _ => self.make_token(TokenKind::Percent, start, true),
+            },
+            '=' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::EqualEqual, start),
+                _ => self.make_token(TokenKind::Equal, start, true),
+            },
+            '!' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::BangEqual, start),
+                _ => self.make_token(TokenKind::Bang, start, true),
+            },
+            '&' => match self.peek_next() {
+                '&' => self.make_double_token(TokenKind::AmpersandAmpersand, start),
+                '=' => self.make_double_token(TokenKind::AmpersandEqual, start),
+                _ => self.make_token(TokenKind::Ampersand, start, true),
+            },
+            '|' => match self.peek_next() {
+                '|' => self.make_double_token(TokenKind::PipePipe, start),
+                '=' => self.make_double_token(TokenKind::PipeEqual, start),
+                _ => self.make_token(TokenKind::Pipe, start, true),
+            },
+            '^' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::CaretEqual, start),
+                _ => self.make_token(TokenKind::Caret, start, true),
+            },
+            '<' if self.peek_next() == '<' => {
+                if self.peek_at(2) == '=' {
+                    self.advance();
+                    return self.make_double_token(TokenKind::LessLessEqual, start);
+                }
+                self.make_double_token(TokenKind::LessLess, start)
+            }
+            '<' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::LessEqual, start),
+                _ => self.make_token(TokenKind::Less, start, true),
+            },
+            '>' if self.peek_next() == '>' => {
+                if self.peek_at(2) == '=' {
                     self.advance();
-                    return self.make_token(TokenKind::MinusMinus, start, true);
-                } else {
-                    return self.make_token(TokenKind::Minus, start, true);
+                    return self.make_double_token(TokenKind::GreaterGreaterEqual, start);
                 }
+                self.make_double_token(TokenKind::GreaterGreater, start)
             }
+            '>' => match self.peek_next() {
+                '=' => self.make_double_token(TokenKind::GreaterEqual, start),
+                _ => self.make_token(TokenKind::Greater, start, true),
+            },
             _ => self.make_error(format!("unexpected character '{ch}'"), true),
         }
     }
@@ -109,9 +179,15 @@ impl<'src> Lexer<'src> {
         Ok(Token::new(kind, start, self.offset))
     }
 
+    /// Make a token that spans the current character and the one after it.
+    fn make_double_token(&mut self, kind: TokenKind, start: usize) -> Result<Token, CompilerError> {
+        self.advance();
+        self.make_token(kind, start, true)
+    }
+
     fn make_error(&mut self, message: String, advance: bool) -> Result<Token, CompilerError> {
         let err = CompilerError::LexError {
-            message: message,
+            message,
             line: self.line,
             column: self.column,
         };
@@ -152,6 +228,14 @@ impl<'src> Lexer<'src> {
         self.source[self.offset + 1]
     }
 
+    fn peek_at(&mut self, distance: usize) -> char {
+        if self.offset + distance >= self.source.len() {
+            return '\0';
+        }
+
+        self.source[self.offset + distance]
+    }
+
     fn handle_ident(&mut self, start: usize) -> Token {
         while !self.is_at_end() && valid_ident_char(self.peek()) {
             self.advance();
@@ -160,8 +244,37 @@ impl<'src> Lexer<'src> {
         let name = self.source[start..self.offset].iter().collect::<String>();
         match name.as_str() {
             "int" => Token::new(TokenKind::Int, start, self.offset),
+            "long" => Token::new(TokenKind::Long, start, self.offset),
+            "signed" => Token::new(TokenKind::Signed, start, self.offset),
+            "unsigned" => Token::new(TokenKind::Unsigned, start, self.offset),
             "void" => Token::new(TokenKind::Void, start, self.offset),
+            "float" => Token::new(TokenKind::Float, start, self.offset),
+            "double" => Token::new(TokenKind::Double, start, self.offset),
+            "_Bool" => Token::new(TokenKind::Bool, start, self.offset),
+            "const" | "__const" => Token::new(TokenKind::Const, start, self.offset),
+            "volatile" | "__volatile" | "__volatile__" => {
+                Token::new(TokenKind::Volatile, start, self.offset)
+            }
+            "asm" | "__asm" | "__asm__" => Token::new(TokenKind::Asm, start, self.offset),
             "return" => Token::new(TokenKind::Return, start, self.offset),
+            "struct" => Token::new(TokenKind::Struct, start, self.offset),
+            "sizeof" => Token::new(TokenKind::Sizeof, start, self.offset),
+            "_Static_assert" => Token::new(TokenKind::StaticAssert, start, self.offset),
+            "_Alignof" => Token::new(TokenKind::Alignof, start, self.offset),
+            "_Alignas" => Token::new(TokenKind::Alignas, start, self.offset),
+            "__attribute__" | "__attribute" => Token::new(TokenKind::Attribute, start, self.offset),
+            "__extension__" => Token::new(TokenKind::Extension, start, self.offset),
+            "restrict" | "__restrict" | "__restrict__" => {
+                Token::new(TokenKind::Restrict, start, self.offset)
+            }
+            "inline" | "__inline" | "__inline__" => {
+                Token::new(TokenKind::Inline, start, self.offset)
+            }
+            "_Noreturn" => Token::new(TokenKind::Noreturn, start, self.offset),
+            "__builtin_va_list" => Token::new(TokenKind::VaList, start, self.offset),
+            "__builtin_va_start" => Token::new(TokenKind::VaStart, start, self.offset),
+            "__builtin_va_arg" => Token::new(TokenKind::VaArg, start, self.offset),
+            "__builtin_va_end" => Token::new(TokenKind::VaEnd, start, self.offset),
             _ => Token::new(TokenKind::Identifier(name), start, self.offset),
         }
     }
@@ -171,21 +284,39 @@ impl<'src> Lexer<'src> {
             self.advance();
         }
 
-        if is_letter_or_underscore(self.peek()) {
-            return self.make_error("invalid identifier".into(), false);
+        let mut is_floating = false;
+
+        if self.peek() == '.' {
+            is_floating = true;
+            self.advance(); // skip '.'
+
+            while !self.is_at_end() && is_digit(self.peek()) {
+                self.advance();
+            }
         }
 
-        if self.peek() != '.' && !is_digit(self.peek_next()) {
-            let literal: String = self.source[start..self.offset].iter().collect();
-            return match literal.parse() {
-                Ok(val) => self.make_token(TokenKind::ConstInt(val), start, false),
-                Err(e) => self.make_error(format!("Error parsing integer literal: {e}"), false),
-            };
+        let has_exponent = matches!(self.peek(), 'e' | 'E')
+            && (is_digit(self.peek_next())
+                || (matches!(self.peek_next(), '+' | '-') && is_digit(self.peek_at(2))));
+        if has_exponent {
+            is_floating = true;
+            self.advance(); // skip 'e'
+            if matches!(self.peek(), '+' | '-') {
+                self.advance();
+            }
+
+            while !self.is_at_end() && is_digit(self.peek()) {
+                self.advance();
+            }
         }
 
-        self.advance(); // skip '.'
+        if !is_floating {
+            return self.handle_integer(start);
+        }
 
-        while !self.is_at_end() && is_digit(self.peek_next()) {
+        let digits_end = self.offset;
+        let is_float = matches!(self.peek(), 'f' | 'F');
+        if is_float {
             self.advance();
         }
 
@@ -193,11 +324,128 @@ impl<'src> Lexer<'src> {
             return self.make_error("invalid identifier".into(), false);
         }
 
-        let literal: String = self.source[start..self.offset].iter().collect();
-        match literal.parse() {
-            Ok(val) => self.make_token(TokenKind::ConstDouble(val), start, false),
-            Err(e) => self.make_error(format!("Error parsing double literal: {e}"), false),
+        // Parse straight to the literal's own type, so a float literal is rounded
+        // only once.
+        let literal: String = self.source[start..digits_end].iter().collect();
+        if is_float {
+            match literal.parse() {
+                Ok(val) => self.make_token(TokenKind::ConstFloat(val), start, false),
+                Err(e) => self.make_error(format!("Error parsing float literal: {e}"), false),
+            }
+        } else {
+            match literal.parse() {
+                Ok(val) => self.make_token(TokenKind::ConstDouble(val), start, false),
+                Err(e) => self.make_error(format!("Error parsing double literal: {e}"), false),
+            }
+        }
+    }
+
+    /// A string literal, with its escape sequences replaced by the characters
+    /// they stand for.
+    fn handle_string(&mut self, start: usize) -> Result<Token, CompilerError> {
+        self.advance(); // skip the opening '"'
+
+        let mut value = String::new();
+        while self.peek() != '"' {
+            if self.is_at_end() || self.peek() == '\n' {
+                return self.make_error("unterminated string literal".into(), false);
+            }
+
+            if self.peek() == '\\' {
+                self.advance();
+                let ch = self.peek();
+                match self.handle_escape() {
+                    Some(escaped) => value.push(escaped),
+                    None => {
+                        return self.make_error(format!("unknown escape sequence '\\{ch}'"), true);
+                    }
+                }
+            } else {
+                value.push(self.peek());
+                self.advance();
+            }
+        }
+
+        self.make_token(TokenKind::StringLiteral(value), start, true)
+    }
+
+    /// The character an escape sequence stands for, once its backslash has
+    /// been consumed: a simple escape like `\n` or up to three octal digits.
+    fn handle_escape(&mut self) -> Option<char> {
+        if self.peek().is_digit(8) {
+            let mut code = 0;
+            for _ in 0..3 {
+                let Some(digit) = self.peek().to_digit(8) else {
+                    break;
+                };
+                code = code * 8 + digit;
+                self.advance();
+            }
+            return char::from_u32(code);
         }
+
+        let escaped = match self.peek() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'a' => '\x07',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            ch @ ('\\' | '\'' | '"' | '?') => ch,
+            _ => return None,
+        };
+        self.advance();
+
+        Some(escaped)
+    }
+
+    /// Finish an integer literal whose digits have already been consumed. The
+    /// suffix and the magnitude of the value decide its type, as in C.
+    fn handle_integer(&mut self, start: usize) -> Result<Token, CompilerError> {
+        let digits_end = self.offset;
+
+        while !self.is_at_end() && matches!(self.peek(), 'u' | 'U' | 'l' | 'L') {
+            self.advance();
+        }
+
+        if valid_ident_char(self.peek()) {
+            return self.make_error("invalid identifier".into(), false);
+        }
+
+        let suffix = self.source[digits_end..self.offset]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+
+        let (is_unsigned, is_long) = match suffix.as_str() {
+            "" => (false, false),
+            "u" => (true, false),
+            "l" => (false, true),
+            "ul" | "lu" => (true, true),
+            _ => return self.make_error(format!("invalid integer suffix '{suffix}'"), false),
+        };
+
+        let literal: String = self.source[start..digits_end].iter().collect();
+        let val: u64 = match literal.parse() {
+            Ok(val) => val,
+            Err(e) => return self.make_error(format!("Error parsing integer literal: {e}"), false),
+        };
+
+        let kind = match (is_unsigned, is_long) {
+            (false, false) if val <= i32::MAX as u64 => TokenKind::ConstInt(val as i32),
+            (false, _) if val <= i64::MAX as u64 => TokenKind::ConstLong(val as i64),
+            (true, false) if val <= u32::MAX as u64 => TokenKind::ConstUInt(val as u32),
+            (true, _) => TokenKind::ConstULong(val),
+            (false, _) => {
+                return self.make_error(
+                    format!("integer literal {literal} is too large for any signed type"),
+                    false,
+                );
+            }
+        };
+
+        self.make_token(kind, start, false)
     }
 }
 
@@ -206,23 +454,13 @@ fn valid_ident_char(ch: char) -> bool {
 }
 
 fn is_letter_or_underscore(ch: char) -> bool {
-    match ch {
-        '_' => true,
-        _ => is_letter(ch),
-    }
+    ch == '_' || is_letter(ch)
 }
 
 fn is_letter(ch: char) -> bool {
-    match ch {
-        'a'..'z' => true,
-        'A'..'Z' => true,
-        _ => false,
-    }
+    ch.is_ascii_alphabetic()
 }
 
 fn is_digit(ch: char) -> bool {
-    match ch {
-        '0'..'9' => true,
-        _ => false,
-    }
+    ch.is_ascii_digit()
 }
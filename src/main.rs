@@ -2,19 +2,38 @@ use std::path::PathBuf;
 
 use clap::{ArgAction, Parser};
 
+use crate::optimizer::Pass;
+use crate::regalloc::Strategy;
+
 pub mod asm;
 pub mod ast;
+pub mod cfg;
 pub mod code_emission;
 pub mod codegen;
+pub mod const_eval;
+pub mod constant_folding;
+pub mod copy_propagation;
+pub mod dead_stores;
+pub mod dominators;
 pub mod driver;
 pub mod errors;
+pub mod gvn;
+pub mod inline_asm;
 pub mod ir;
 pub mod lexer;
+pub mod optimizer;
 pub mod parser;
+pub mod regalloc;
+pub mod resolver;
+pub mod sccp;
+pub mod ssa;
+pub mod symbols;
 pub mod tokens;
+pub mod typechecker;
 pub mod types;
+pub mod unreachable_code;
 
-const LONG_ABOUT: &'static str = r#"nqcc - The Not Quite C compiler written in Rust"#;
+const LONG_ABOUT: &str = r#"nqcc - The Not Quite C compiler written in Rust"#;
 
 /// nqcc - The Not Quite C compiler written in Rust
 #[derive(Parser, Debug, Clone)]
@@ -31,6 +50,10 @@ pub struct Cli {
     #[arg(long, action = ArgAction::SetTrue)]
     parse: bool,
 
+    /// Only lex, parse, and run semantic analysis (variable resolution and type checking).
+    #[arg(long, action = ArgAction::SetTrue)]
+    validate: bool,
+
     /// Only lex, parse, and run assembly generation but stop before
     /// code emission.
     #[arg(long, action = ArgAction::SetTrue)]
@@ -40,6 +63,63 @@ pub struct Cli {
     #[arg(long, action = ArgAction::SetTrue)]
     tacky: bool,
 
+    /// Optimization level: 0 runs no optimizations, 1 the ones that simplify
+    /// the code without moving values around, and 2 all of them.
+    #[arg(
+        short = 'O',
+        value_name = "LEVEL",
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=2)
+    )]
+    optimization_level: u8,
+
+    /// Evaluate operations on constants at compile time.
+    #[arg(long, action = ArgAction::SetTrue)]
+    fold_constants: bool,
+
+    /// Remove code that can never run, and jumps to the next instruction.
+    #[arg(long, action = ArgAction::SetTrue)]
+    eliminate_unreachable_code: bool,
+
+    /// Replace variables with the values last copied into them.
+    #[arg(long, action = ArgAction::SetTrue)]
+    propagate_copies: bool,
+
+    /// Remove writes to variables that are never read afterwards.
+    #[arg(long, action = ArgAction::SetTrue)]
+    eliminate_dead_stores: bool,
+
+    /// Find the variables that only ever hold one constant, even through
+    /// branches, and the branches that always go the same way.
+    #[arg(long, action = ArgAction::SetTrue)]
+    propagate_constants: bool,
+
+    /// Work out each value once, reusing it wherever the same operation on
+    /// the same operands, or a load through the same pointer with nothing
+    /// written in between, comes again.
+    #[arg(long, action = ArgAction::SetTrue)]
+    eliminate_common_subexpressions: bool,
+
+    /// Convert each function to SSA form and back after the other passes,
+    /// to check the conversion.
+    #[arg(long, action = ArgAction::SetTrue)]
+    convert_to_ssa: bool,
+
+    /// Print the TACKY of each function every time the given pass has run.
+    #[arg(long, value_enum, value_name = "PASS")]
+    print_after: Option<Pass>,
+
+    /// How to allocate registers: by graph coloring, or by the faster linear
+    /// scan.
+    #[arg(long, value_enum, value_name = "STRATEGY", default_value_t = Strategy::Graph)]
+    regalloc: Strategy,
+
+    /// Print how long register allocation took for each function, how many
+    /// of its pseudos were left in stack slots, and how many instructions it
+    /// ends up with.
+    #[arg(long, action = ArgAction::SetTrue)]
+    print_regalloc_stats: bool,
+
     /// Emit assembly but do not link (-S)
     #[arg(short = 'S', action = ArgAction::SetTrue)]
     emit_asm: bool,
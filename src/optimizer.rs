@@ -0,0 +1,176 @@
+//! The optimizations run over the TACKY of each function between TACKY
+//! generation and code generation. Each one can be enabled on its own from
+//! the command line, or along with others by an optimization level.
+
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::cfg::Cfg;
+use crate::constant_folding::fold_constants;
+use crate::copy_propagation::propagate_copies;
+use crate::dead_stores::eliminate_dead_stores;
+use crate::gvn::eliminate_common_subexpressions;
+use crate::ir::{Function, Program, TopLevel};
+use crate::sccp::propagate_constants;
+use crate::ssa::Ssa;
+use crate::symbols::SymbolTable;
+use crate::unreachable_code::eliminate_unreachable_code;
+
+/// An optimization pass, named on the command line as its flag is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Pass {
+    FoldConstants,
+    EliminateUnreachableCode,
+    PropagateCopies,
+    EliminateDeadStores,
+    PropagateConstants,
+    EliminateCommonSubexpressions,
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no pass is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+type CfgPass = fn(Cfg, &SymbolTable) -> Cfg;
+
+/// The passes that work on the control flow graph, in the order they run.
+const CFG_PASSES: [(Pass, CfgPass); 3] = [
+    (Pass::EliminateUnreachableCode, eliminate_unreachable_code),
+    (Pass::PropagateCopies, propagate_copies),
+    (Pass::EliminateDeadStores, eliminate_dead_stores),
+];
+
+type SsaPass = fn(&mut Ssa, &SymbolTable);
+
+/// The passes that work on SSA form, in the order they run after the others.
+const SSA_PASSES: [(Pass, SsaPass); 2] = [
+    (Pass::PropagateConstants, propagate_constants),
+    (
+        Pass::EliminateCommonSubexpressions,
+        eliminate_common_subexpressions,
+    ),
+];
+
+/// Which optimizations to run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Optimizations {
+    pub fold_constants: bool,
+    pub eliminate_unreachable_code: bool,
+    pub propagate_copies: bool,
+    pub eliminate_dead_stores: bool,
+    pub propagate_constants: bool,
+    pub eliminate_common_subexpressions: bool,
+    /// Put each function in SSA form and take it back out again after the
+    /// other passes, which on its own only renames variables.
+    pub convert_to_ssa: bool,
+    /// The pass to print each function's TACKY after, every time it runs.
+    pub print_after: Option<Pass>,
+}
+
+impl Optimizations {
+    /// The optimizations of an optimization level. Level 0 runs none, level
+    /// 1 the ones that simplify the code without moving values around, and
+    /// level 2 all of them.
+    pub fn level(level: u8) -> Optimizations {
+        Optimizations {
+            fold_constants: level >= 1,
+            eliminate_unreachable_code: level >= 1,
+            propagate_copies: level >= 2,
+            eliminate_dead_stores: level >= 2,
+            propagate_constants: level >= 2,
+            eliminate_common_subexpressions: level >= 2,
+            convert_to_ssa: false,
+            print_after: None,
+        }
+    }
+
+    fn enabled(&self, pass: Pass) -> bool {
+        match pass {
+            Pass::FoldConstants => self.fold_constants,
+            Pass::EliminateUnreachableCode => self.eliminate_unreachable_code,
+            Pass::PropagateCopies => self.propagate_copies,
+            Pass::EliminateDeadStores => self.eliminate_dead_stores,
+            Pass::PropagateConstants => self.propagate_constants,
+            Pass::EliminateCommonSubexpressions => self.eliminate_common_subexpressions,
+        }
+    }
+
+    /// Whether any of the passes that work on the control flow graph is on,
+    /// or SSA form, which is built from it.
+    fn uses_cfg(&self) -> bool {
+        CFG_PASSES.iter().any(|(pass, _)| self.enabled(*pass)) || self.uses_ssa()
+    }
+
+    /// Whether any of the passes that work on SSA form is on.
+    fn uses_ssa(&self) -> bool {
+        SSA_PASSES.iter().any(|(pass, _)| self.enabled(*pass)) || self.convert_to_ssa
+    }
+
+    /// Prints the function body if it is what `pass` left, as asked for.
+    fn print_after(&self, pass: Pass, function: &str, round: usize, body: &dyn fmt::Debug) {
+        if self.print_after == Some(pass) {
+            println!("TACKY after {pass} (round {round}) in {function}: {body:#?}");
+        }
+    }
+}
+
+/// Optimizes each function, adding any variables the passes create to
+/// `symbols`.
+pub fn optimize(program: &mut Program, optimizations: Optimizations, symbols: &mut SymbolTable) {
+    for top_level in &mut program.0 {
+        if let TopLevel::Function(func) = top_level {
+            optimize_function(func, optimizations, symbols);
+        }
+    }
+}
+
+/// Runs the passes over a function body until none of them changes it any
+/// more, since each one can open up opportunities for the others.
+fn optimize_function(func: &mut Function, optimizations: Optimizations, symbols: &mut SymbolTable) {
+    let Some(body) = &mut func.body else {
+        return;
+    };
+
+    for round in 1.. {
+        let previous = body.clone();
+
+        if optimizations.fold_constants {
+            *body = fold_constants(body, symbols);
+            optimizations.print_after(Pass::FoldConstants, &func.identifier, round, &body);
+        }
+
+        if optimizations.uses_cfg() {
+            let mut cfg = Cfg::new(std::mem::take(body));
+            for (pass, run) in CFG_PASSES {
+                if !optimizations.enabled(pass) {
+                    continue;
+                }
+                cfg = run(cfg, symbols);
+                if optimizations.print_after == Some(pass) {
+                    let instructions = cfg.clone().into_instructions();
+                    optimizations.print_after(pass, &func.identifier, round, &instructions);
+                }
+            }
+            if optimizations.uses_ssa() {
+                let mut ssa = Ssa::new(cfg, symbols);
+                for (pass, run) in SSA_PASSES {
+                    if !optimizations.enabled(pass) {
+                        continue;
+                    }
+                    run(&mut ssa, symbols);
+                    optimizations.print_after(pass, &func.identifier, round, &ssa.listing());
+                }
+                cfg = ssa.into_cfg(&func.identifier, symbols);
+            }
+            *body = cfg.into_instructions();
+        }
+
+        if *body == previous {
+            return;
+        }
+    }
+}
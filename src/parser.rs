@@ -1,22 +1,79 @@
 use crate::{
     ast::{
-        Block, BlockItem, Declaration, Expression, FunctionDeclaration, FunctionParam, Program,
-        Statement,
+        AsmOperand, AsmStatement, Attributes, BinaryOperator, Block, BlockItem, Declaration,
+        Designator, Expression, FunctionDeclaration, FunctionParam, Identifier, IncDecOperator,
+        Initializer, MemberDeclaration, Program, Statement, StaticAssertion, StructDeclaration,
+        UnaryOperator, VariableDeclaration,
     },
+    const_eval,
     errors::CompilerError,
     tokens::{Token, TokenKind},
     types,
 };
 
+/// The part of a declaration after the type specifiers, which names what is
+/// declared and derives its type from the specifiers. It reads inside out:
+/// in `int *(*fp)(int)`, `fp` is a pointer to a function taking an int and
+/// returning a pointer to int.
+#[derive(Debug, Clone)]
+enum Declarator {
+    Ident(String),
+    /// The missing name of a type name or an unnamed parameter.
+    Abstract,
+    Pointer(Box<Declarator>, types::Qualifiers),
+    /// `inner[size]`, or `inner[]` with the length left out.
+    Array {
+        inner: Box<Declarator>,
+        size: Option<Expression>,
+    },
+    Function {
+        params: Vec<(types::T, Declarator)>,
+        variadic: bool,
+        inner: Box<Declarator>,
+    },
+}
+
+/// What a declarator declares.
+#[derive(Debug, Clone)]
+struct Declared {
+    name: Option<String>,
+    typ: types::T,
+    /// The named parameters, when the declarator declares a function.
+    params: Vec<FunctionParam>,
+    /// The length of each variable length array in `typ`, with the variable
+    /// it is to be stored in.
+    lengths: Vec<(String, Expression)>,
+    /// Whether the length of the array declared was left out, in which case
+    /// `typ` is its element type.
+    length_omitted: bool,
+}
+
+/// What the specifiers at the start of a declaration say.
+#[derive(Debug, Clone)]
+struct Specifiers {
+    typ: types::T,
+    /// The strictest alignment any `_Alignas` among them asks for.
+    alignment: Option<i64>,
+    attributes: Attributes,
+    /// The `struct tag` among them, with its members if it defines them.
+    structure: Option<StructDeclaration>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Parser {
     pub current: usize,
     pub tokens: Vec<Token>,
+    /// Numbers the variables that hold the lengths of variable length arrays.
+    next_length: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { current: 0, tokens }
+        Self {
+            current: 0,
+            tokens,
+            next_length: 0,
+        }
     }
 
     pub fn parse_program(&mut self) -> Result<Program, CompilerError> {
@@ -31,121 +88,699 @@ impl Parser {
     }
 
     fn parse_declaration(&mut self) -> Result<Declaration, CompilerError> {
+        while self.peek().kind == TokenKind::Extension {
+            self.take()?;
+        }
         let tok = self.peek();
 
         match tok.kind {
-            TokenKind::Int | TokenKind::Void => {
-                // Skip looking at the next token because it's probably an ident, but it doesn't
-                // matter at the moment.
-                // If the token after that is a (, then it's a function, if not, it must be some kind of var declaration
-                let next = self.peek_at(self.current + 2)?;
+            TokenKind::StaticAssert => self.parse_static_assert(),
+            ref kind if is_declaration_specifier(kind) => {
+                let Specifiers {
+                    typ: base,
+                    alignment,
+                    mut attributes,
+                    structure,
+                } = self.parse_declaration_specifiers()?;
 
-                if matches!(next.kind, TokenKind::LeftParen) {
-                    // It's definitely (probably) a function (we hope)
-                    return self.parse_func_decl();
+                // `struct tag;` or `struct tag { ... };` declares the structure
+                // itself.
+                if let Some(structure) = structure {
+                    if self.peek().kind == TokenKind::Semicolon {
+                        self.take()?;
+                        return Ok(Declaration::StructDecl(structure));
+                    }
+                    if structure.members.is_some() {
+                        return Err(CompilerError::ParseError {
+                            message: format!(
+                                "structure '{}' has to be defined in a declaration of its own",
+                                structure.tag
+                            ),
+                        });
+                    }
                 }
 
-                // it must be a var declaration
-                self.parse_var_decl()
+                let declarator = self.parse_declarator()?;
+                let declared = self.process_declarator(declarator, base)?;
+                self.parse_trailing_attributes(&mut attributes)?;
+
+                let Some(name) = declared.name else {
+                    return Err(CompilerError::ParseError {
+                        message: "declaration does not declare anything".into(),
+                    });
+                };
+
+                // A declarator that ends up naming a function declares one.
+                match declared.typ {
+                    types::T::FunType { .. } if alignment.is_some() => {
+                        Err(CompilerError::ParseError {
+                            message: format!("_Alignas cannot be applied to function '{name}'"),
+                        })
+                    }
+                    types::T::FunType { .. } if !declared.lengths.is_empty() => {
+                        Err(CompilerError::ParseError {
+                            message: format!("function '{name}' has a variably modified type"),
+                        })
+                    }
+                    types::T::FunType { ret, variadic, .. } => {
+                        self.parse_func_decl(name, *ret, declared.params, variadic, attributes)
+                    }
+                    _ if attributes.noreturn => Err(CompilerError::ParseError {
+                        message: format!("variable '{name}' cannot be declared noreturn"),
+                    }),
+                    typ => self.parse_var_decl(
+                        name,
+                        typ,
+                        alignment,
+                        attributes,
+                        declared.lengths,
+                        declared.length_omitted,
+                    ),
+                }
             }
-            TokenKind::Struct => self.parse_struct_decl(),
-            _ => todo!("handle parsing var declarations probably"),
+            // Storage classes such as `typedef` and `extern` are not
+            // keywords yet, so they arrive here as identifiers.
+            ref kind => Err(CompilerError::ParseError {
+                message: format!("Expected: declaration; Got: {:#?}", kind),
+            }),
         }
     }
 
-    fn parse_struct_decl(&mut self) -> Result<Declaration, CompilerError> {
-        todo!()
+    /// `_Static_assert(condition, "message");`, where the message may be
+    /// left out.
+    fn parse_static_assert(&mut self) -> Result<Declaration, CompilerError> {
+        self.expect(TokenKind::StaticAssert)?;
+        self.expect(TokenKind::LeftParen)?;
+        let condition = self.parse_binary_expression(Precedence::Conditional)?;
+        let message = if self.peek().kind == TokenKind::Comma {
+            self.take()?;
+            Some(self.parse_string_literal()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::RightParen)?;
+        self.expect(TokenKind::Semicolon)?;
+
+        Ok(Declaration::StaticAssert(StaticAssertion {
+            condition,
+            message,
+        }))
     }
 
-    fn parse_func_decl(&mut self) -> Result<Declaration, CompilerError> {
-        // first get the type
-        let type_token = self.take()?;
+    /// The rest of a function declaration once its declarator has been parsed:
+    /// either a body or a `;`.
+    fn parse_func_decl(
+        &mut self,
+        name: String,
+        return_type: types::T,
+        params: Vec<FunctionParam>,
+        variadic: bool,
+        attributes: Attributes,
+    ) -> Result<Declaration, CompilerError> {
+        // A declaration without a body, such as a prototype for a library function.
+        let body = if self.peek().kind == TokenKind::Semicolon {
+            self.take()?;
+            None
+        } else {
+            self.parse_block()?
+        };
 
-        let typ = match type_token.kind {
-            TokenKind::Int => types::T::Int,
-            _ => {
+        let func = FunctionDeclaration {
+            return_type,
+            name,
+            params,
+            variadic,
+            body,
+            storage_class: None,
+            attributes,
+        };
+
+        Ok(Declaration::FunDecl(func))
+    }
+
+    /// The rest of a variable declaration once its declarator has been parsed:
+    /// an optional initializer and the closing `;`.
+    fn parse_var_decl(
+        &mut self,
+        name: String,
+        typ: types::T,
+        alignment: Option<i64>,
+        attributes: Attributes,
+        lengths: Vec<(String, Expression)>,
+        length_omitted: bool,
+    ) -> Result<Declaration, CompilerError> {
+        let init = if matches!(self.peek().kind, TokenKind::Equal) {
+            self.take()?;
+            Some(self.parse_initializer()?)
+        } else {
+            None
+        };
+
+        self.expect(TokenKind::Semicolon)?;
+
+        let decl = VariableDeclaration {
+            name,
+            typ,
+            init,
+            storage_class: None,
+            alignment,
+            attributes,
+            lengths,
+            length_omitted,
+        };
+
+        Ok(Declaration::VarDecl(decl))
+    }
+
+    /// An expression, or a brace enclosed list of initializers for the
+    /// elements of an aggregate. Each item may be designated by `[index] =`
+    /// or `.member =`, and the list may end in a comma.
+    fn parse_initializer(&mut self) -> Result<Initializer, CompilerError> {
+        if self.peek().kind != TokenKind::LeftBrace {
+            return Ok(Initializer::Single(self.parse_assignment_expression()?));
+        }
+
+        self.take()?;
+        let mut items = Vec::new();
+
+        while !self.is_at_end() && self.peek().kind != TokenKind::RightBrace {
+            let mut designators = Vec::new();
+            loop {
+                match self.peek().kind {
+                    TokenKind::LeftBracket => {
+                        self.take()?;
+                        let index = self.parse_binary_expression(Precedence::Conditional)?;
+                        self.expect(TokenKind::RightBracket)?;
+                        designators.push(Designator::Index(index));
+                    }
+                    TokenKind::Dot => {
+                        self.take()?;
+                        designators.push(Designator::Member(self.parse_member_name()?));
+                    }
+                    _ => break,
+                }
+            }
+
+            if !designators.is_empty() {
+                self.expect(TokenKind::Equal)?;
+            }
+
+            items.push((designators, self.parse_initializer()?));
+
+            if self.peek().kind != TokenKind::Comma {
+                break;
+            }
+            self.take()?;
+        }
+
+        self.expect(TokenKind::RightBrace)?;
+
+        Ok(Initializer::Compound(items))
+    }
+
+    /// Consume a run of type specifiers and qualifiers such as `const unsigned
+    /// long int` and work out the type they name. The order does not matter.
+    /// Attributes among them are ignored.
+    fn parse_type_specifiers(&mut self) -> Result<types::T, CompilerError> {
+        let specifiers = self.parse_declaration_specifiers()?;
+        if specifiers.alignment.is_some() {
+            return Err(CompilerError::ParseError {
+                message: "_Alignas may only be used in the declaration of a variable or member"
+                    .into(),
+            });
+        }
+        reject_struct_definition(&specifiers)?;
+
+        Ok(specifiers.typ)
+    }
+
+    /// The type specifiers and qualifiers that start a declaration, along
+    /// with the strictest alignment any `_Alignas` among them asks for, the
+    /// attributes mixed in with them and the structure they name, if any.
+    fn parse_declaration_specifiers(&mut self) -> Result<Specifiers, CompilerError> {
+        let mut specifiers = Vec::new();
+        let mut qualifiers = types::Qualifiers::default();
+        let mut alignment: Option<i64> = None;
+        let mut attributes = Attributes::default();
+        let mut structure = None;
+        while is_declaration_specifier(&self.peek().kind) {
+            if self.peek().kind == TokenKind::Attribute {
+                self.parse_attributes(&mut attributes)?;
+                continue;
+            }
+
+            match self.take()?.kind {
+                TokenKind::Const => qualifiers.constant = true,
+                TokenKind::Volatile => qualifiers.volatile = true,
+                // Only a hint for optimizations, which are all safe without it.
+                TokenKind::Restrict | TokenKind::Inline => {}
+                TokenKind::Noreturn => attributes.noreturn = true,
+                TokenKind::Alignas => {
+                    // `_Alignas(0)` has no effect.
+                    let requested = self.parse_alignment_specifier()?;
+                    if requested != 0 {
+                        alignment = Some(alignment.unwrap_or(0).max(requested));
+                    }
+                }
+                TokenKind::Struct if structure.is_none() => {
+                    structure = Some(self.parse_struct_specifier()?);
+                }
+                kind => specifiers.push(kind),
+            }
+        }
+
+        let typ = match &structure {
+            Some(structure) if specifiers.is_empty() => types::T::Structure(structure.tag.clone()),
+            Some(_) => {
                 return Err(CompilerError::ParseError {
-                    message: "type not found where expected".into(),
+                    message: format!("invalid type specifier: struct with {:?}", specifiers),
                 });
             }
+            None => specifier_type(&specifiers)?,
         };
 
-        let ident_token = self.take()?;
-        let ident = match ident_token.kind {
-            TokenKind::Identifier(name) => name,
-            _ => {
+        Ok(Specifiers {
+            typ: typ.qualified(qualifiers),
+            alignment,
+            attributes,
+            structure,
+        })
+    }
+
+    /// The rest of a `struct` type specifier: its tag, followed by the
+    /// declarations of its members if it defines the structure. Every
+    /// structure needs a tag. Attributes of the structure may come before
+    /// the tag or after the closing brace.
+    fn parse_struct_specifier(&mut self) -> Result<StructDeclaration, CompilerError> {
+        let mut attributes = Attributes::default();
+        while self.peek().kind == TokenKind::Attribute {
+            self.parse_attributes(&mut attributes)?;
+        }
+
+        let tag = match self.take()?.kind {
+            TokenKind::Identifier(tag) => tag,
+            kind => {
                 return Err(CompilerError::ParseError {
-                    message: "function name not found where expected".into(),
+                    message: format!("Expected: structure tag; Got: {:#?}", kind),
                 });
             }
         };
 
-        self.expect(TokenKind::LeftParen)?;
+        if self.peek().kind != TokenKind::LeftBrace {
+            return Ok(StructDeclaration {
+                tag,
+                members: None,
+                attributes,
+            });
+        }
 
-        let mut params: Vec<FunctionParam> = Vec::new();
+        self.take()?;
+        let mut members = Vec::new();
+        while !self.is_at_end() && self.peek().kind != TokenKind::RightBrace {
+            members.extend(self.parse_member_declaration()?);
+        }
+        self.expect(TokenKind::RightBrace)?;
+        while self.peek().kind == TokenKind::Attribute {
+            self.parse_attributes(&mut attributes)?;
+        }
 
-        while !self.is_at_end() && self.peek().kind != TokenKind::RightParen {
-            let type_token = self.take()?;
+        if members.is_empty() {
+            return Err(CompilerError::ParseError {
+                message: format!("structure '{tag}' has no members"),
+            });
+        }
 
-            let typ = match type_token.kind {
-                TokenKind::Int => types::T::Int,
-                TokenKind::Void => types::T::Void,
-                _ => {
+        Ok(StructDeclaration {
+            tag,
+            members: Some(members),
+            attributes,
+        })
+    }
+
+    /// A declaration in the body of a structure, which declares one or more
+    /// members with the same type specifiers, as in `int x, *next;`, or
+    /// bit-fields, as in `unsigned low : 4, high : 4;`. Each member may be
+    /// followed by attributes of its own.
+    fn parse_member_declaration(&mut self) -> Result<Vec<MemberDeclaration>, CompilerError> {
+        let specifiers = self.parse_declaration_specifiers()?;
+        reject_struct_definition(&specifiers)?;
+        let mut members = Vec::new();
+
+        loop {
+            let declarator = self.parse_declarator()?;
+            let declared = self.process_declarator(declarator, specifiers.typ.clone())?;
+            let mut attributes = specifiers.attributes.clone();
+            while self.peek().kind == TokenKind::Attribute {
+                self.parse_attributes(&mut attributes)?;
+            }
+            let width = if self.peek().kind == TokenKind::Colon {
+                self.take()?;
+                Some(self.parse_bit_field_width()?)
+            } else {
+                None
+            };
+            while self.peek().kind == TokenKind::Attribute {
+                self.parse_attributes(&mut attributes)?;
+            }
+            let name = match declared.name {
+                Some(name) => name,
+                None if width.is_some() => String::new(),
+                None => {
                     return Err(CompilerError::ParseError {
-                        message: "type not found where expected".into(),
+                        message: "structure member has no name".into(),
                     });
                 }
             };
 
-            // special case for void like this int main(void) { return 2; } // void is the only type that has no name.
-            if typ == types::T::Void {
-                let param = FunctionParam {
-                    typ,
-                    name: "".into(),
-                };
-                params.push(param);
+            if matches!(declared.typ, types::T::FunType { .. }) {
+                return Err(CompilerError::ParseError {
+                    message: format!("structure member '{name}' declared as a function"),
+                });
+            }
+            if !declared.lengths.is_empty() {
+                return Err(CompilerError::ParseError {
+                    message: format!("structure member '{name}' has a variably modified type"),
+                });
+            }
+            if declared.length_omitted {
+                return Err(CompilerError::ParseError {
+                    message: format!("array size missing in structure member '{name}'"),
+                });
+            }
+            if width.is_some() && specifiers.alignment.is_some() {
+                return Err(CompilerError::ParseError {
+                    message: format!("_Alignas cannot be applied to bit-field '{name}'"),
+                });
+            }
+            members.push(MemberDeclaration {
+                name,
+                typ: declared.typ,
+                width,
+                alignment: specifiers.alignment,
+                attributes,
+            });
 
+            if self.peek().kind != TokenKind::Comma {
                 break;
             }
+            self.take()?;
+        }
 
-            // Wasn't a single void param, so we must need to parse the ident still.
-            let next = self.take()?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(members)
+    }
 
-            if let TokenKind::Identifier(name) = next.kind {
-                let param = FunctionParam {
-                    typ,
-                    name: name.into(),
-                };
+    /// The width after the `:` of a bit-field, which has to be a
+    /// non-negative integer constant.
+    fn parse_bit_field_width(&mut self) -> Result<i64, CompilerError> {
+        let expr = self.parse_binary_expression(Precedence::Conditional)?;
+        match const_eval::evaluate_integer(&expr)? {
+            Some(width) if width >= 0 => Ok(width),
+            Some(width) => Err(CompilerError::ParseError {
+                message: format!("negative width {width} in bit-field"),
+            }),
+            None => Err(CompilerError::ParseError {
+                message: "bit-field width must be an integer constant".into(),
+            }),
+        }
+    }
 
-                params.push(param);
-            } else {
+    /// `__attribute__((...))`, a comma separated list of attributes that may
+    /// have parenthesized arguments, as in `__attribute__((aligned(8),
+    /// unused))`. Each name may also be spelled with surrounding `__`. The
+    /// ones that matter here are recorded in `attributes` and the rest are
+    /// skipped.
+    fn parse_attributes(&mut self, attributes: &mut Attributes) -> Result<(), CompilerError> {
+        self.expect(TokenKind::Attribute)?;
+        self.expect(TokenKind::LeftParen)?;
+        self.expect(TokenKind::LeftParen)?;
+
+        while !self.is_at_end() && self.peek().kind != TokenKind::RightParen {
+            // Some attributes, such as `const`, are spelled like keywords.
+            let name = match self.take()?.kind {
+                TokenKind::Identifier(name) => name,
+                _ => String::new(),
+            };
+            let name = name.trim_start_matches("__").trim_end_matches("__");
+
+            match name {
+                "noreturn" => attributes.noreturn = true,
+                "aligned" => {
+                    // Without an argument, the strictest alignment any type
+                    // needs.
+                    let requested = if self.peek().kind == TokenKind::LeftParen {
+                        self.parse_alignment_specifier()?
+                    } else {
+                        16
+                    };
+                    attributes.aligned = attributes.aligned.max(Some(requested));
+                }
+                "section" => {
+                    self.expect(TokenKind::LeftParen)?;
+                    attributes.section = Some(self.parse_string_literal()?);
+                    self.expect(TokenKind::RightParen)?;
+                }
+                "packed" => attributes.packed = true,
+                _ if self.peek().kind == TokenKind::LeftParen => self.skip_parenthesized()?,
+                _ => {}
+            }
+
+            if self.peek().kind != TokenKind::Comma {
+                break;
+            }
+            self.take()?;
+        }
+
+        self.expect(TokenKind::RightParen)?;
+        self.expect(TokenKind::RightParen)?;
+
+        Ok(())
+    }
+
+    /// What may follow a declarator: attributes, and an `asm("name")` label
+    /// giving the assembler name of the symbol. Labels are accepted for the
+    /// sake of system headers, but the symbol keeps its C name.
+    fn parse_trailing_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+    ) -> Result<(), CompilerError> {
+        loop {
+            match self.peek().kind {
+                TokenKind::Attribute => self.parse_attributes(attributes)?,
+                TokenKind::Asm => {
+                    self.take()?;
+                    self.expect(TokenKind::LeftParen)?;
+                    self.parse_string_literal()?;
+                    self.expect(TokenKind::RightParen)?;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips a parenthesized run of tokens, along with any parentheses nested
+    /// in it.
+    fn skip_parenthesized(&mut self) -> Result<(), CompilerError> {
+        self.expect(TokenKind::LeftParen)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.take()?.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The parenthesized operand of `_Alignas`: a type name, which asks for
+    /// the alignment of that type, or an integer constant expression, which
+    /// has to be zero or a power of two.
+    fn parse_alignment_specifier(&mut self) -> Result<i64, CompilerError> {
+        let expr = if self.starts_type_name(self.current) {
+            self.expect(TokenKind::LeftParen)?;
+            let typ = self.parse_type_name()?;
+            self.expect(TokenKind::RightParen)?;
+            Expression::AlignOfType(typ)
+        } else {
+            self.expect(TokenKind::LeftParen)?;
+            let expr = self.parse_binary_expression(Precedence::Conditional)?;
+            self.expect(TokenKind::RightParen)?;
+            expr
+        };
+
+        match const_eval::evaluate_integer(&expr)? {
+            Some(alignment)
+                if alignment == 0 || (alignment > 0 && alignment & (alignment - 1) == 0) =>
+            {
+                Ok(alignment)
+            }
+            Some(alignment) => Err(CompilerError::ParseError {
+                message: format!("requested alignment {alignment} is not a power of two"),
+            }),
+            None => Err(CompilerError::ParseError {
+                message: "requested alignment must be an integer constant".into(),
+            }),
+        }
+    }
+
+    /// One or more adjacent string literals, which are joined into one.
+    fn parse_string_literal(&mut self) -> Result<String, CompilerError> {
+        let mut value = match self.take()?.kind {
+            TokenKind::StringLiteral(value) => value,
+            kind => {
                 return Err(CompilerError::ParseError {
-                    message: "invalid parameter declaration".into(),
+                    message: format!("Expected: string literal; Got: {:#?}", kind),
                 });
             }
+        };
+        while let TokenKind::StringLiteral(more) = self.peek().kind {
+            self.take()?;
+            value.push_str(&more);
+        }
+
+        Ok(value)
+    }
 
-            if matches!(self.peek().kind, TokenKind::Comma) {
+    /// A declarator: `*` with any qualifiers for each level of pointer, around
+    /// a name or a parenthesized declarator, followed by any array sizes and
+    /// parameter lists.
+    /// The name is left out in type names and unnamed parameters.
+    fn parse_declarator(&mut self) -> Result<Declarator, CompilerError> {
+        if self.peek().kind == TokenKind::Star {
+            self.take()?;
+            let mut qualifiers = types::Qualifiers::default();
+            while is_type_qualifier(&self.peek().kind) || self.peek().kind == TokenKind::Attribute {
+                match self.peek().kind {
+                    TokenKind::Const => qualifiers.constant = true,
+                    TokenKind::Volatile => qualifiers.volatile = true,
+                    // Attributes of the pointer itself, which have no effect.
+                    TokenKind::Attribute => {
+                        self.parse_attributes(&mut Attributes::default())?;
+                        continue;
+                    }
+                    _ => {}
+                }
                 self.take()?;
             }
+
+            let inner = self.parse_declarator()?;
+            return Ok(Declarator::Pointer(Box::new(inner), qualifiers));
         }
 
-        self.expect(TokenKind::RightParen)?;
+        let mut declarator = match self.peek().kind {
+            TokenKind::Identifier(name) => {
+                self.take()?;
+                Declarator::Ident(name)
+            }
+            // Parentheses around a declarator, rather than a parameter list,
+            // as in `int (*fp)(int)` or the type name `int (*)(int)`.
+            TokenKind::LeftParen
+                if matches!(
+                    self.peek_at(self.current + 1)?.kind,
+                    TokenKind::Star | TokenKind::LeftParen | TokenKind::Identifier(_)
+                ) =>
+            {
+                self.take()?;
+                let inner = self.parse_declarator()?;
+                self.expect(TokenKind::RightParen)?;
+                inner
+            }
+            _ => Declarator::Abstract,
+        };
 
-        let body = self.parse_block()?;
+        loop {
+            declarator = match self.peek().kind {
+                TokenKind::LeftParen => {
+                    let (params, variadic) = self.parse_param_list()?;
+                    Declarator::Function {
+                        params,
+                        variadic,
+                        inner: Box::new(declarator),
+                    }
+                }
+                TokenKind::LeftBracket => {
+                    self.take()?;
+                    let size = if self.peek().kind == TokenKind::RightBracket {
+                        None
+                    } else {
+                        Some(self.parse_binary_expression(Precedence::Conditional)?)
+                    };
+                    self.expect(TokenKind::RightBracket)?;
+                    Declarator::Array {
+                        inner: Box::new(declarator),
+                        size,
+                    }
+                }
+                _ => break,
+            };
+        }
 
-        let func = FunctionDeclaration {
-            return_type: typ,
-            name: ident,
-            params,
-            body,
-            storage_class: None,
-        };
+        Ok(declarator)
+    }
 
-        Ok(Declaration::FunDecl(func))
+    /// A parenthesized parameter list. `(void)` declares no parameters.
+    fn parse_param_list(&mut self) -> Result<(Vec<(types::T, Declarator)>, bool), CompilerError> {
+        self.expect(TokenKind::LeftParen)?;
+
+        let mut params = Vec::new();
+        let mut variadic = false;
+
+        if self.peek().kind == TokenKind::Void
+            && self.peek_at(self.current + 1)?.kind == TokenKind::RightParen
+        {
+            self.take()?;
+        }
+
+        while !self.is_at_end() && self.peek().kind != TokenKind::RightParen {
+            // `...` may only follow at least one named parameter, and must come last.
+            if self.peek().kind == TokenKind::Ellipsis && !params.is_empty() {
+                self.take()?;
+                variadic = true;
+                break;
+            }
+
+            let base = self.parse_type_specifiers()?;
+            let declarator = self.parse_declarator()?;
+            params.push((base, declarator));
+
+            if self.peek().kind != TokenKind::Comma {
+                break;
+            }
+            self.take()?;
+        }
+
+        self.expect(TokenKind::RightParen)?;
+
+        Ok((params, variadic))
     }
 
-    fn parse_var_decl(&mut self) -> Result<Declaration, CompilerError> {
-        todo!()
+    /// A type with no declared name, as used by casts and `sizeof`, e.g.
+    /// `unsigned long *` or `int (*)(int)`.
+    fn parse_type_name(&mut self) -> Result<types::T, CompilerError> {
+        let base = self.parse_type_specifiers()?;
+        let declarator = self.parse_declarator()?;
+        let declared = self.process_declarator(declarator, base)?;
+
+        if let Some(name) = declared.name {
+            return Err(CompilerError::ParseError {
+                message: format!("unexpected name '{name}' in type name"),
+            });
+        }
+        if !declared.lengths.is_empty() {
+            return Err(CompilerError::ParseError {
+                message: "variable length arrays are only supported in variable declarations"
+                    .into(),
+            });
+        }
+        if declared.length_omitted {
+            return Err(CompilerError::ParseError {
+                message: "array size missing in type name".into(),
+            });
+        }
+
+        Ok(declared.typ)
     }
 
     fn parse_block(&mut self) -> Result<Option<Block>, CompilerError> {
@@ -164,38 +799,428 @@ impl Parser {
     }
 
     fn parse_block_item(&mut self) -> Result<BlockItem, CompilerError> {
+        while self.peek().kind == TokenKind::Extension {
+            self.take()?;
+        }
+
+        let kind = self.peek().kind;
+        if is_declaration_specifier(&kind) || kind == TokenKind::StaticAssert {
+            let decl = self.parse_declaration()?;
+            return Ok(BlockItem::Decl(decl));
+        }
+
         let stmt = self.parse_statement()?;
         Ok(BlockItem::Stmt(stmt))
     }
 
     fn parse_statement(&mut self) -> Result<Statement, CompilerError> {
-        self.expect(TokenKind::Return)?;
+        match self.peek().kind {
+            TokenKind::Return => {
+                self.take()?;
+                if self.peek().kind == TokenKind::Semicolon {
+                    self.take()?;
+                    return Ok(Statement::ReturnStatement(None));
+                }
+
+                let expr = self.parse_expression()?;
+                self.expect(TokenKind::Semicolon)?;
+
+                Ok(Statement::ReturnStatement(Some(expr)))
+            }
+            TokenKind::Semicolon => {
+                self.take()?;
+                Ok(Statement::NullStatement)
+            }
+            TokenKind::Asm => {
+                let asm = self.parse_asm_statement()?;
+                self.expect(TokenKind::Semicolon)?;
+
+                Ok(Statement::Asm(asm))
+            }
+            _ => {
+                let expr = self.parse_expression()?;
+                self.expect(TokenKind::Semicolon)?;
+
+                Ok(Statement::ExpressionStatement(expr))
+            }
+        }
+    }
+
+    /// `asm volatile ("template" : outputs : inputs : clobbers)`, where each
+    /// section after the template may be left out along with the ones after
+    /// it.
+    fn parse_asm_statement(&mut self) -> Result<AsmStatement, CompilerError> {
+        self.expect(TokenKind::Asm)?;
+        while self.peek().kind == TokenKind::Volatile {
+            self.take()?;
+        }
+        self.expect(TokenKind::LeftParen)?;
+        let template = self.parse_string_literal()?;
+
+        if self.peek().kind == TokenKind::RightParen {
+            self.take()?;
+            // Basic asm is copied out as written.
+            return Ok(AsmStatement {
+                template: template.replace('%', "%%"),
+                outputs: Vec::new(),
+                inputs: Vec::new(),
+                clobbers: vec!["memory".into()],
+            });
+        }
+
+        let outputs = self.parse_asm_section(Self::parse_asm_operand)?;
+        let inputs = self.parse_asm_section(Self::parse_asm_operand)?;
+        let clobbers = self.parse_asm_section(Self::parse_string_literal)?;
+        self.expect(TokenKind::RightParen)?;
+
+        Ok(AsmStatement {
+            template,
+            outputs,
+            inputs,
+            clobbers,
+        })
+    }
+
+    /// A section of an `asm` statement: a colon followed by a comma separated
+    /// list, which may be empty. Nothing at all if there is no colon.
+    fn parse_asm_section<T>(
+        &mut self,
+        parse_item: fn(&mut Self) -> Result<T, CompilerError>,
+    ) -> Result<Vec<T>, CompilerError> {
+        let mut items = Vec::new();
+        if self.peek().kind != TokenKind::Colon {
+            return Ok(items);
+        }
+        self.take()?;
+
+        while !matches!(self.peek().kind, TokenKind::Colon | TokenKind::RightParen) {
+            if !items.is_empty() {
+                self.expect(TokenKind::Comma)?;
+            }
+            items.push(parse_item(self)?);
+        }
+
+        Ok(items)
+    }
+
+    fn parse_asm_operand(&mut self) -> Result<AsmOperand, CompilerError> {
+        let name = if self.peek().kind == TokenKind::LeftBracket {
+            self.take()?;
+            let name = match self.take()?.kind {
+                TokenKind::Identifier(name) => name,
+                other => {
+                    return Err(CompilerError::ParseError {
+                        message: format!("Expected: asm operand name; Got: {:#?}", other),
+                    });
+                }
+            };
+            self.expect(TokenKind::RightBracket)?;
+            Some(name)
+        } else {
+            None
+        };
+
+        let constraint = self.parse_string_literal()?;
+        self.expect(TokenKind::LeftParen)?;
         let expr = self.parse_expression()?;
-        self.expect(TokenKind::Semicolon)?;
+        self.expect(TokenKind::RightParen)?;
 
-        Ok(Statement::ReturnStatement(Some(expr)))
+        Ok(AsmOperand {
+            name,
+            constraint,
+            expr,
+        })
     }
 
+    /// A full expression, including the comma operator.
     fn parse_expression(&mut self) -> Result<Expression, CompilerError> {
+        self.parse_binary_expression(Precedence::Comma)
+    }
+
+    /// An expression that stops at a top level comma, as used for initializers
+    /// and function arguments where the comma separates items.
+    fn parse_assignment_expression(&mut self) -> Result<Expression, CompilerError> {
+        self.parse_binary_expression(Precedence::Assignment)
+    }
+
+    /// Precedence climbing over every binary operator in the precedence table.
+    /// Only operators that bind at least as tightly as `min_precedence` are
+    /// consumed here.
+    fn parse_binary_expression(
+        &mut self,
+        min_precedence: Precedence,
+    ) -> Result<Expression, CompilerError> {
+        let mut left = self.parse_unary_expression()?;
+
+        loop {
+            let kind = self.peek().kind;
+            let Some(precedence) = binary_precedence(&kind) else {
+                break;
+            };
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            self.take()?;
+
+            // A right associative operator lets its right operand contain further
+            // operators at the same level, so `a = b = c` is `a = (b = c)`.
+            let right_min = if precedence.is_right_associative() {
+                precedence
+            } else {
+                precedence.next()
+            };
+
+            left = if kind == TokenKind::Equal {
+                let right = self.parse_binary_expression(right_min)?;
+                Expression::Assignment {
+                    lvalue: Box::new(left),
+                    value: Box::new(right),
+                }
+            } else if let Some(operator) = compound_assignment_operator(&kind) {
+                let right = self.parse_binary_expression(right_min)?;
+                Expression::CompoundAssignment {
+                    operator,
+                    lvalue: Box::new(left),
+                    value: Box::new(right),
+                }
+            } else if kind == TokenKind::Question {
+                // The middle operand behaves as if it were parenthesized.
+                let then = self.parse_expression()?;
+                self.expect(TokenKind::Colon)?;
+                let otherwise = self.parse_binary_expression(right_min)?;
+                Expression::Conditional {
+                    condition: Box::new(left),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                }
+            } else if kind == TokenKind::Comma {
+                let right = self.parse_binary_expression(right_min)?;
+                Expression::Comma {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            } else {
+                let right = self.parse_binary_expression(right_min)?;
+                Expression::Binary {
+                    operator: binary_operator(&kind),
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary_expression(&mut self) -> Result<Expression, CompilerError> {
+        let operator = match self.peek().kind {
+            TokenKind::Minus => UnaryOperator::Negate,
+            TokenKind::Tilde => UnaryOperator::Complement,
+            TokenKind::Bang => UnaryOperator::Not,
+            TokenKind::Plus => UnaryOperator::Plus,
+            TokenKind::PlusPlus | TokenKind::MinusMinus => {
+                let tok = self.take()?;
+                return Ok(Expression::PrefixIncDec {
+                    operator: inc_dec_operator(&tok.kind),
+                    expr: Box::new(self.parse_unary_expression()?),
+                });
+            }
+            TokenKind::Star => {
+                self.take()?;
+                let expr = self.parse_unary_expression()?;
+                return Ok(Expression::Dereference(Box::new(expr)));
+            }
+            TokenKind::Ampersand => {
+                self.take()?;
+                let expr = self.parse_unary_expression()?;
+                return Ok(Expression::AddressOf(Box::new(expr)));
+            }
+            TokenKind::Sizeof => {
+                self.take()?;
+                if self.starts_type_name(self.current) {
+                    self.expect(TokenKind::LeftParen)?;
+                    let typ = self.parse_type_name()?;
+                    self.expect(TokenKind::RightParen)?;
+                    return Ok(Expression::SizeOfType(typ));
+                }
+
+                let expr = self.parse_unary_expression()?;
+                return Ok(Expression::SizeOfExpr(Box::new(expr)));
+            }
+            // Only marks an extension so that compilers can keep quiet about it.
+            TokenKind::Extension => {
+                self.take()?;
+                return self.parse_unary_expression();
+            }
+            TokenKind::Alignof => {
+                self.take()?;
+                self.expect(TokenKind::LeftParen)?;
+                let typ = self.parse_type_name()?;
+                self.expect(TokenKind::RightParen)?;
+                return Ok(Expression::AlignOfType(typ));
+            }
+            // A parenthesized type name is a cast; any other parenthesis starts a
+            // grouping, which is handled as a primary expression.
+            TokenKind::LeftParen if self.starts_type_name(self.current) => {
+                self.take()?;
+                let typ = self.parse_type_name()?;
+                self.expect(TokenKind::RightParen)?;
+                let expr = self.parse_unary_expression()?;
+                return Ok(Expression::Cast {
+                    typ,
+                    expr: Box::new(expr),
+                });
+            }
+            _ => return self.parse_postfix_expression(),
+        };
+
+        self.take()?;
+
+        Ok(Expression::Unary {
+            operator,
+            expr: Box::new(self.parse_unary_expression()?),
+        })
+    }
+
+    /// Whether the tokens at `location` are `(` followed by a type name.
+    fn starts_type_name(&mut self, location: usize) -> bool {
+        matches!(
+            self.peek_at(location),
+            Ok(Token {
+                kind: TokenKind::LeftParen,
+                ..
+            })
+        ) && matches!(self.peek_at(location + 1), Ok(tok) if is_declaration_specifier(&tok.kind))
+    }
+
+    /// A primary expression followed by any chain of postfix operators, such
+    /// as `f(x)[i].m->n++`.
+    fn parse_postfix_expression(&mut self) -> Result<Expression, CompilerError> {
+        let mut expr = self.parse_primary_expression()?;
+
+        loop {
+            expr = match self.peek().kind {
+                TokenKind::PlusPlus | TokenKind::MinusMinus => {
+                    let tok = self.take()?;
+                    Expression::PostfixIncDec {
+                        operator: inc_dec_operator(&tok.kind),
+                        expr: Box::new(expr),
+                    }
+                }
+                TokenKind::LeftBracket => {
+                    self.take()?;
+                    let index = self.parse_expression()?;
+                    self.expect(TokenKind::RightBracket)?;
+                    Expression::Subscript {
+                        expr: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                TokenKind::LeftParen => {
+                    self.take()?;
+                    let args = self.parse_arguments()?;
+                    Expression::FunctionCall {
+                        callee: Box::new(expr),
+                        args,
+                    }
+                }
+                TokenKind::Dot => {
+                    self.take()?;
+                    Expression::Dot {
+                        expr: Box::new(expr),
+                        member: self.parse_member_name()?,
+                    }
+                }
+                TokenKind::Arrow => {
+                    self.take()?;
+                    Expression::Arrow {
+                        expr: Box::new(expr),
+                        member: self.parse_member_name()?,
+                    }
+                }
+                _ => break,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// The comma separated arguments of a call, up to and including the closing `)`.
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>, CompilerError> {
+        let mut args = Vec::new();
+
+        if self.peek().kind != TokenKind::RightParen {
+            args.push(self.parse_assignment_expression()?);
+            while self.peek().kind == TokenKind::Comma {
+                self.take()?;
+                args.push(self.parse_assignment_expression()?);
+            }
+        }
+
+        self.expect(TokenKind::RightParen)?;
+
+        Ok(args)
+    }
+
+    fn parse_member_name(&mut self) -> Result<String, CompilerError> {
+        match self.take()?.kind {
+            TokenKind::Identifier(name) => Ok(name),
+            other => Err(CompilerError::ParseError {
+                message: format!("Expected: member name; Got: {:#?}", other),
+            }),
+        }
+    }
+
+    fn parse_primary_expression(&mut self) -> Result<Expression, CompilerError> {
         let tok = self.take()?;
         match tok.kind {
             TokenKind::ConstInt(val) => Ok(Expression::ConstInt(val)),
-            TokenKind::Minus => Ok(Expression::Unary {
-                operator: crate::ast::UnaryOperator::Negate,
-                expr: Box::new(self.parse_expression()?),
-            }),
-            TokenKind::Tilde => Ok(Expression::Unary {
-                operator: crate::ast::UnaryOperator::Complement,
-                expr: Box::new(self.parse_expression()?),
-            }),
+            TokenKind::ConstLong(val) => Ok(Expression::ConstLong(val)),
+            TokenKind::ConstUInt(val) => Ok(Expression::ConstUInt(val)),
+            TokenKind::ConstULong(val) => Ok(Expression::ConstULong(val)),
+            TokenKind::ConstFloat(val) => Ok(Expression::ConstFloat(val)),
+            TokenKind::ConstDouble(val) => Ok(Expression::ConstDouble(val)),
+            TokenKind::Identifier(name) => Ok(Expression::Var(Identifier(name))),
+            TokenKind::LeftParen if self.peek().kind == TokenKind::LeftBrace => {
+                let block = self.parse_block()?.unwrap_or(Block(Vec::new()));
+                self.expect(TokenKind::RightParen)?;
+                Ok(Expression::StatementExpression(block))
+            }
             TokenKind::LeftParen => {
                 let exp = self.parse_expression()?;
                 self.expect(TokenKind::RightParen)?;
                 Ok(Expression::Grouping(Box::new(exp)))
             }
+            TokenKind::VaStart => {
+                self.expect(TokenKind::LeftParen)?;
+                let ap = self.parse_assignment_expression()?;
+                self.expect(TokenKind::Comma)?;
+                self.parse_assignment_expression()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(Expression::VaStart(Box::new(ap)))
+            }
+            TokenKind::VaArg => {
+                self.expect(TokenKind::LeftParen)?;
+                let ap = self.parse_assignment_expression()?;
+                self.expect(TokenKind::Comma)?;
+                let typ = self.parse_type_name()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(Expression::VaArg {
+                    ap: Box::new(ap),
+                    typ,
+                })
+            }
+            TokenKind::VaEnd => {
+                self.expect(TokenKind::LeftParen)?;
+                let ap = self.parse_assignment_expression()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(Expression::VaEnd(Box::new(ap)))
+            }
             _ => {
-                let err = format!("Expected: Integer; Got: {:#?}", tok.kind);
-                return Err(CompilerError::ParseError { message: err });
+                let err = format!("Expected: expression; Got: {:#?}", tok.kind);
+                Err(CompilerError::ParseError { message: err })
             }
         }
     }
@@ -237,4 +1262,415 @@ impl Parser {
     fn is_at_end(&mut self) -> bool {
         self.current >= self.tokens.len() || self.peek().kind == TokenKind::EOF
     }
+
+    /// Applies `declarator` to the type `base` named by the specifiers.
+    fn process_declarator(
+        &mut self,
+        declarator: Declarator,
+        base: types::T,
+    ) -> Result<Declared, CompilerError> {
+        match declarator {
+            Declarator::Ident(name) => Ok(Declared {
+                name: Some(name),
+                typ: base,
+                params: Vec::new(),
+                lengths: Vec::new(),
+                length_omitted: false,
+            }),
+            Declarator::Abstract => Ok(Declared {
+                name: None,
+                typ: base,
+                params: Vec::new(),
+                lengths: Vec::new(),
+                length_omitted: false,
+            }),
+            Declarator::Pointer(inner, qualifiers) => self.process_declarator(
+                *inner,
+                types::T::Pointer(Box::new(base)).qualified(qualifiers),
+            ),
+            Declarator::Array { inner, size } => {
+                if matches!(
+                    base.unqualified(),
+                    types::T::Void | types::T::FunType { .. }
+                ) {
+                    return Err(CompilerError::ParseError {
+                        message: format!("array has incomplete element type {:?}", base),
+                    });
+                }
+
+                // Only the declarator's own length may be left out, as in
+                // `int a[] = {1, 2}`, and not that of an array it points to
+                // or is an element of.
+                let Some(size) = size else {
+                    if !matches!(*inner, Declarator::Ident(_) | Declarator::Abstract) {
+                        return Err(CompilerError::ParseError {
+                            message: "array size missing".into(),
+                        });
+                    }
+                    let mut declared = self.process_declarator(*inner, base)?;
+                    declared.length_omitted = true;
+                    return Ok(declared);
+                };
+
+                let constant = const_eval::evaluate(&size)?;
+                if let Some(constant) = &constant
+                    && constant.integer().is_none()
+                {
+                    return Err(CompilerError::ParseError {
+                        message: format!("size of array has non-integer type {:?}", constant.typ),
+                    });
+                }
+
+                // A length that is not constant makes a variable length array.
+                let Some(size) = constant.and_then(|constant| constant.integer()) else {
+                    let count = format!("vla.{}", self.next_length);
+                    self.next_length += 1;
+
+                    let typ = types::T::VariableArray {
+                        typ: Box::new(base),
+                        count: count.clone(),
+                    };
+                    let mut declared = self.process_declarator(*inner, typ)?;
+                    declared.lengths.insert(0, (count, size));
+                    return Ok(declared);
+                };
+                if size <= 0 {
+                    return Err(CompilerError::ParseError {
+                        message: format!("array size must be positive, not {size}"),
+                    });
+                }
+
+                let typ = types::T::Array {
+                    typ: Box::new(base),
+                    size,
+                };
+                self.process_declarator(*inner, typ)
+            }
+            Declarator::Function {
+                params,
+                variadic,
+                inner,
+            } => {
+                if matches!(base, types::T::FunType { .. }) {
+                    return Err(CompilerError::ParseError {
+                        message: "a function cannot return a function".into(),
+                    });
+                }
+                if matches!(
+                    base,
+                    types::T::Array { .. } | types::T::VariableArray { .. }
+                ) {
+                    return Err(CompilerError::ParseError {
+                        message: "a function cannot return an array".into(),
+                    });
+                }
+
+                let mut named_params = Vec::new();
+                for (param_base, param_declarator) in params {
+                    let param = self.process_declarator(param_declarator, param_base)?;
+                    // A parameter declared as a function is a pointer to one, and
+                    // one declared as an array a pointer to its first element,
+                    // so the length of the array itself, if given, does not matter.
+                    let typ = match param.typ {
+                        typ if param.length_omitted => types::T::Pointer(Box::new(typ)),
+                        typ @ (types::T::FunType { .. } | types::T::VaList) => {
+                            types::T::Pointer(Box::new(typ))
+                        }
+                        types::T::Array { typ, .. } | types::T::VariableArray { typ, .. } => {
+                            types::T::Pointer(typ)
+                        }
+                        typ => typ,
+                    };
+                    if typ.is_variably_modified() {
+                        return Err(CompilerError::ParseError {
+                            message: "variably modified parameter types are not supported".into(),
+                        });
+                    }
+                    named_params.push(FunctionParam {
+                        typ,
+                        name: param.name.unwrap_or_default(),
+                    });
+                }
+
+                // Qualifiers on a parameter or the return value do not affect the
+                // function's type.
+                let typ = types::T::FunType {
+                    params: named_params
+                        .iter()
+                        .map(|p| p.typ.unqualified().clone())
+                        .collect(),
+                    ret: Box::new(base.unqualified().clone()),
+                    variadic,
+                };
+
+                match *inner {
+                    Declarator::Ident(name) => Ok(Declared {
+                        name: Some(name),
+                        typ,
+                        params: named_params,
+                        lengths: Vec::new(),
+                        length_omitted: false,
+                    }),
+                    inner => self.process_declarator(inner, typ),
+                }
+            }
+        }
+    }
+}
+
+/// Binding strength of the binary, conditional and assignment operators,
+/// from loosest to tightest. This is the precedence table from the C standard:
+///
+/// | Level            | Operators                                     | Associativity |
+/// |------------------|-----------------------------------------------|---------------|
+/// | `Comma`          | `,`                                           | left          |
+/// | `Assignment`     | `=` `+=` `-=` `*=` `/=` `%=` `&=` `\|=` `^=` `<<=` `>>=` | right |
+/// | `Conditional`    | `?:`                                          | right         |
+/// | `LogicalOr`      | `\|\|`                                        | left          |
+/// | `LogicalAnd`     | `&&`                                          | left          |
+/// | `BitwiseOr`      | `\|`                                          | left          |
+/// | `BitwiseXor`     | `^`                                           | left          |
+/// | `BitwiseAnd`     | `&`                                           | left          |
+/// | `Equality`       | `==` `!=`                                     | left          |
+/// | `Relational`     | `<` `<=` `>` `>=`                             | left          |
+/// | `Shift`          | `<<` `>>`                                     | left          |
+/// | `Additive`       | `+` `-`                                       | left          |
+/// | `Multiplicative` | `*` `/` `%`                                   | left          |
+///
+/// `Prefix` covers casts and the prefix operators (`+ - ! ~ * & ++ -- sizeof`),
+/// which bind tighter than any binary operator and are right associative.
+/// They are parsed by `parse_unary_expression` rather than by precedence
+/// climbing. The postfix operators (`() [] . -> ++ --`) bind tightest of all
+/// and are left associative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Comma,
+    Assignment,
+    Conditional,
+    LogicalOr,
+    LogicalAnd,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equality,
+    Relational,
+    Shift,
+    Additive,
+    Multiplicative,
+    Prefix,
+}
+
+impl Precedence {
+    pub fn is_right_associative(self) -> bool {
+        matches!(self, Precedence::Assignment | Precedence::Conditional)
+    }
+
+    /// The level that binds one step tighter than this one.
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::LogicalOr,
+            Precedence::LogicalOr => Precedence::LogicalAnd,
+            Precedence::LogicalAnd => Precedence::BitwiseOr,
+            Precedence::BitwiseOr => Precedence::BitwiseXor,
+            Precedence::BitwiseXor => Precedence::BitwiseAnd,
+            Precedence::BitwiseAnd => Precedence::Equality,
+            Precedence::Equality => Precedence::Relational,
+            Precedence::Relational => Precedence::Shift,
+            Precedence::Shift => Precedence::Additive,
+            Precedence::Additive => Precedence::Multiplicative,
+            Precedence::Multiplicative | Precedence::Prefix => Precedence::Prefix,
+        }
+    }
+}
+
+/// The precedence level of a token that can follow an operand, or `None` if
+/// the token does not continue an expression.
+pub fn binary_precedence(kind: &TokenKind) -> Option<Precedence> {
+    match kind {
+        TokenKind::Star | TokenKind::Slash | TokenKind::Percent => Some(Precedence::Multiplicative),
+        TokenKind::Plus | TokenKind::Minus => Some(Precedence::Additive),
+        TokenKind::LessLess | TokenKind::GreaterGreater => Some(Precedence::Shift),
+        TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+            Some(Precedence::Relational)
+        }
+        TokenKind::EqualEqual | TokenKind::BangEqual => Some(Precedence::Equality),
+        TokenKind::Ampersand => Some(Precedence::BitwiseAnd),
+        TokenKind::Caret => Some(Precedence::BitwiseXor),
+        TokenKind::Pipe => Some(Precedence::BitwiseOr),
+        TokenKind::AmpersandAmpersand => Some(Precedence::LogicalAnd),
+        TokenKind::PipePipe => Some(Precedence::LogicalOr),
+        TokenKind::Question => Some(Precedence::Conditional),
+        TokenKind::Equal
+        | TokenKind::PlusEqual
+        | TokenKind::MinusEqual
+        | TokenKind::StarEqual
+        | TokenKind::SlashEqual
+        | TokenKind::PercentEqual
+        | TokenKind::AmpersandEqual
+        | TokenKind::PipeEqual
+        | TokenKind::CaretEqual
+        | TokenKind::LessLessEqual
+        | TokenKind::GreaterGreaterEqual => Some(Precedence::Assignment),
+        TokenKind::Comma => Some(Precedence::Comma),
+        _ => None,
+    }
+}
+
+fn binary_operator(kind: &TokenKind) -> BinaryOperator {
+    match kind {
+        TokenKind::Plus => BinaryOperator::Add,
+        TokenKind::Minus => BinaryOperator::Subtract,
+        TokenKind::Star => BinaryOperator::Multiply,
+        TokenKind::Slash => BinaryOperator::Divide,
+        TokenKind::Percent => BinaryOperator::Remainder,
+        TokenKind::Ampersand => BinaryOperator::BitwiseAnd,
+        TokenKind::Pipe => BinaryOperator::BitwiseOr,
+        TokenKind::Caret => BinaryOperator::BitwiseXor,
+        TokenKind::LessLess => BinaryOperator::ShiftLeft,
+        TokenKind::GreaterGreater => BinaryOperator::ShiftRight,
+        TokenKind::AmpersandAmpersand => BinaryOperator::And,
+        TokenKind::PipePipe => BinaryOperator::Or,
+        TokenKind::EqualEqual => BinaryOperator::Equal,
+        TokenKind::BangEqual => BinaryOperator::NotEqual,
+        TokenKind::Less => BinaryOperator::LessThan,
+        TokenKind::LessEqual => BinaryOperator::LessOrEqual,
+        TokenKind::Greater => BinaryOperator::GreaterThan,
+        TokenKind::GreaterEqual => BinaryOperator::GreaterOrEqual,
+        _ => unreachable!("not a binary operator: {:?}", kind),
+    }
+}
+
+fn compound_assignment_operator(kind: &TokenKind) -> Option<BinaryOperator> {
+    match kind {
+        TokenKind::PlusEqual => Some(BinaryOperator::Add),
+        TokenKind::MinusEqual => Some(BinaryOperator::Subtract),
+        TokenKind::StarEqual => Some(BinaryOperator::Multiply),
+        TokenKind::SlashEqual => Some(BinaryOperator::Divide),
+        TokenKind::PercentEqual => Some(BinaryOperator::Remainder),
+        TokenKind::AmpersandEqual => Some(BinaryOperator::BitwiseAnd),
+        TokenKind::PipeEqual => Some(BinaryOperator::BitwiseOr),
+        TokenKind::CaretEqual => Some(BinaryOperator::BitwiseXor),
+        TokenKind::LessLessEqual => Some(BinaryOperator::ShiftLeft),
+        TokenKind::GreaterGreaterEqual => Some(BinaryOperator::ShiftRight),
+        _ => None,
+    }
+}
+
+fn inc_dec_operator(kind: &TokenKind) -> IncDecOperator {
+    match kind {
+        TokenKind::PlusPlus => IncDecOperator::Increment,
+        TokenKind::MinusMinus => IncDecOperator::Decrement,
+        _ => unreachable!("not an increment or decrement operator: {:?}", kind),
+    }
+}
+
+/// The type named by a list of type specifiers.
+fn specifier_type(specifiers: &[TokenKind]) -> Result<types::T, CompilerError> {
+    let count = |kind: TokenKind| specifiers.iter().filter(|s| **s == kind).count();
+    let invalid = || {
+        Err(CompilerError::ParseError {
+            message: format!("invalid type specifier: {:?}", specifiers),
+        })
+    };
+
+    if specifiers.is_empty() {
+        return Err(CompilerError::ParseError {
+            message: "type not found where expected".into(),
+        });
+    }
+
+    if count(TokenKind::VaList) > 0 {
+        return if specifiers.len() == 1 {
+            Ok(types::T::VaList)
+        } else {
+            invalid()
+        };
+    }
+
+    if count(TokenKind::Float) > 0 || count(TokenKind::Double) > 0 {
+        return match specifiers {
+            [TokenKind::Float] => Ok(types::T::Float),
+            [TokenKind::Double] => Ok(types::T::Double),
+            _ => invalid(),
+        };
+    }
+
+    if count(TokenKind::Void) > 0 {
+        return if specifiers.len() == 1 {
+            Ok(types::T::Void)
+        } else {
+            invalid()
+        };
+    }
+
+    if count(TokenKind::Bool) > 0 {
+        return if specifiers.len() == 1 {
+            Ok(types::T::Bool)
+        } else {
+            invalid()
+        };
+    }
+
+    let (ints, longs) = (count(TokenKind::Int), count(TokenKind::Long));
+    let (signed, unsigned) = (count(TokenKind::Signed), count(TokenKind::Unsigned));
+    if ints > 1 || longs > 1 || signed + unsigned > 1 {
+        return invalid();
+    }
+
+    Ok(match (unsigned == 1, longs == 1) {
+        (false, false) => types::T::Int,
+        (false, true) => types::T::Long,
+        (true, false) => types::T::UInt,
+        (true, true) => types::T::ULong,
+    })
+}
+
+/// Structures may only be defined in a declaration of their own, never in
+/// the type of something else.
+fn reject_struct_definition(specifiers: &Specifiers) -> Result<(), CompilerError> {
+    match &specifiers.structure {
+        Some(StructDeclaration {
+            tag,
+            members: Some(_),
+            ..
+        }) => Err(CompilerError::ParseError {
+            message: format!("structure '{tag}' has to be defined in a declaration of its own"),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn is_type_specifier(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Int
+            | TokenKind::Long
+            | TokenKind::Signed
+            | TokenKind::Unsigned
+            | TokenKind::Float
+            | TokenKind::Double
+            | TokenKind::Bool
+            | TokenKind::Void
+            | TokenKind::VaList
+            | TokenKind::Struct
+    )
+}
+
+fn is_type_qualifier(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Const | TokenKind::Volatile | TokenKind::Restrict
+    )
+}
+
+/// Tokens that can start a declaration or a type name.
+fn is_declaration_specifier(kind: &TokenKind) -> bool {
+    is_type_specifier(kind)
+        || is_type_qualifier(kind)
+        || matches!(
+            kind,
+            TokenKind::Alignas | TokenKind::Attribute | TokenKind::Inline | TokenKind::Noreturn
+        )
 }
@@ -0,0 +1,753 @@
+//! Register allocation by graph coloring, after Chaitin and Briggs.
+//!
+//! Two pseudos interfere when one is written while the other is live, so
+//! they cannot share a register. The interference graph, which also has a
+//! node for each hard register, is colored with the registers available;
+//! pseudos that get no color are left for stack slots.
+//!
+//! Before coloring, the two sides of a `Mov` are merged into one node when
+//! they do not interfere and the tests of Briggs and George show this cannot
+//! make the graph harder to color. The move then copies a register to itself
+//! and is removed.
+//!
+//! For quick builds there is also linear scan, after Poletto and Sarkar,
+//! which skips the graph and coalescing. Each pseudo gets the range of
+//! instructions from where it is first written or live to where it is last
+//! live, and the ranges are handed registers in order of their start. A
+//! range is one interval that is never split, so a pseudo live across a jump
+//! back keeps its register, or its stack slot, for everything in between.
+//! Both share the liveness analysis, and leave what they spill for stack
+//! slots in the same way.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, VecDeque};
+
+use clap::ValueEnum;
+
+use crate::asm::{AsmType, Instruction, Operand, Register};
+use crate::cfg::{Cfg, NodeId};
+
+/// The registers integers and pointers are kept in, in order of preference.
+/// A function has to preserve the last five for its caller, which costs a
+/// save and a restore. %r10 and %r11 are left as scratch registers for
+/// fixing up instructions, as are %xmm14 and %xmm15.
+const GP_REGISTERS: [Register; 12] = [
+    Register::RAX,
+    Register::RCX,
+    Register::RDX,
+    Register::RSI,
+    Register::RDI,
+    Register::R8,
+    Register::R9,
+    Register::RBX,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+/// The registers floating point values are kept in, in order of preference.
+const XMM_REGISTERS: [Register; 14] = [
+    Register::XMM0,
+    Register::XMM1,
+    Register::XMM2,
+    Register::XMM3,
+    Register::XMM4,
+    Register::XMM5,
+    Register::XMM6,
+    Register::XMM7,
+    Register::XMM8,
+    Register::XMM9,
+    Register::XMM10,
+    Register::XMM11,
+    Register::XMM12,
+    Register::XMM13,
+];
+
+/// A node of the interference graph. Hard registers are always their
+/// 64-bit selves, or a vector register.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Node {
+    Reg(Register),
+    Pseudo(String),
+}
+
+#[derive(Debug, Default)]
+struct Graph {
+    neighbors: BTreeMap<Node, BTreeSet<Node>>,
+    /// How often each pseudo is read or written, which is roughly what
+    /// keeping it on the stack would cost.
+    spill_costs: BTreeMap<Node, usize>,
+}
+
+impl Graph {
+    fn add_node(&mut self, node: Node) {
+        self.neighbors.entry(node).or_default();
+    }
+
+    fn add_edge(&mut self, a: &Node, b: &Node) {
+        self.neighbors.get_mut(a).unwrap().insert(b.clone());
+        self.neighbors.get_mut(b).unwrap().insert(a.clone());
+    }
+
+    fn interfere(&self, a: &Node, b: &Node) -> bool {
+        self.neighbors[a].contains(b)
+    }
+
+    fn degree(&self, node: &Node) -> usize {
+        self.neighbors[node].len()
+    }
+
+    /// Merges `removed` into `kept`, which takes over its edges.
+    fn merge(&mut self, kept: &Node, removed: &Node) {
+        let neighbors = self.neighbors.remove(removed).unwrap();
+        for neighbor in &neighbors {
+            self.neighbors.get_mut(neighbor).unwrap().remove(removed);
+            self.add_edge(kept, neighbor);
+        }
+        let cost = self.spill_costs.remove(removed).unwrap_or(0);
+        *self.spill_costs.entry(kept.clone()).or_default() += cost;
+    }
+}
+
+/// How registers are allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Strategy {
+    /// Coloring the interference graph, which gives the better code.
+    #[default]
+    Graph,
+    /// Linear scan over live ranges, which is faster.
+    Linear,
+}
+
+/// Keeps as many as possible of `pseudos`, the pseudos that can live in a
+/// register, in one, and removes the moves that coalescing leaves pointless.
+/// `pseudos` gives each one's type. Pseudos that get no register keep their
+/// name, or that of the pseudo they were merged with, for a stack slot.
+/// `returns` are the registers the function returns its value in.
+///
+/// Returns the registers the function now uses that it has to preserve.
+pub fn allocate_registers(
+    instructions: &mut Vec<Instruction>,
+    pseudos: &HashMap<String, AsmType>,
+    returns: &[Register],
+    strategy: Strategy,
+) -> Vec<Register> {
+    let mut allocator = Allocator {
+        pseudos,
+        returns,
+        aliases: HashMap::new(),
+    };
+
+    if strategy == Strategy::Linear {
+        let colors = allocator.linear_scan(instructions);
+        return allocator.rewrite(instructions, &colors);
+    }
+
+    // Every merge changes which moves can be coalesced safely, and the graph
+    // is only an approximation of that after a few, so it is rebuilt until
+    // nothing more can be merged.
+    let graph = loop {
+        let mut graph = allocator.build_graph(instructions);
+        if !allocator.coalesce(&mut graph, instructions) {
+            break graph;
+        }
+    };
+
+    let colors = allocator.color(&graph);
+    allocator.rewrite(instructions, &colors)
+}
+
+struct Allocator<'a> {
+    pseudos: &'a HashMap<String, AsmType>,
+    returns: &'a [Register],
+    /// The node each coalesced pseudo was merged into.
+    aliases: HashMap<String, Node>,
+}
+
+impl Allocator<'_> {
+    /// The node a pseudo stands for now, after coalescing.
+    fn resolve(&self, name: &str) -> Node {
+        match self.aliases.get(name) {
+            Some(Node::Pseudo(other)) => self.resolve(other),
+            Some(node) => node.clone(),
+            None => Node::Pseudo(name.to_string()),
+        }
+    }
+
+    /// The node for an operand that is a pseudo or a register the allocator
+    /// assigns, if it is one.
+    fn node(&self, operand: &Operand) -> Option<Node> {
+        match operand {
+            Operand::Pseudo(name) if self.pseudos.contains_key(name) => Some(self.resolve(name)),
+            Operand::Reg(reg) => allocatable(*reg).map(Node::Reg),
+            _ => None,
+        }
+    }
+
+    fn is_xmm(&self, node: &Node) -> bool {
+        match node {
+            Node::Reg(reg) => reg.is_xmm(),
+            Node::Pseudo(name) => self.pseudos[name].is_floating(),
+        }
+    }
+
+    /// The number of registers there are for the node's values.
+    fn colors(&self, node: &Node) -> usize {
+        if self.is_xmm(node) {
+            XMM_REGISTERS.len()
+        } else {
+            GP_REGISTERS.len()
+        }
+    }
+
+    /// The nodes the instruction reads, followed by those it writes.
+    fn uses_and_defs(&self, instr: &Instruction) -> (Vec<Node>, Vec<Node>) {
+        let mut reads: Vec<&Operand> = Vec::new();
+        let mut writes: Vec<&Operand> = Vec::new();
+        let mut implicit_reads: Vec<Register> = Vec::new();
+        let mut implicit_writes: Vec<Register> = Vec::new();
+
+        match instr {
+            Instruction::Mov { src, dst, .. }
+            | Instruction::Movsx { src, dst }
+            | Instruction::MovZeroExtend { src, dst, .. }
+            | Instruction::Cvtsi2 { src, dst, .. }
+            | Instruction::Cvtt2si { src, dst, .. }
+            | Instruction::Cvtss2sd { src, dst }
+            | Instruction::Cvtsd2ss { src, dst }
+            | Instruction::Lea { src, dst } => {
+                reads.push(src);
+                writes.push(dst);
+            }
+            // Xoring a register with itself zeroes it, whatever it held.
+            Instruction::Xor { src, dst, .. } if src == dst => writes.push(dst),
+            Instruction::Add { src, dst, .. }
+            | Instruction::Sub { src, dst, .. }
+            | Instruction::Imul { src, dst, .. }
+            | Instruction::And { src, dst, .. }
+            | Instruction::Or { src, dst, .. }
+            | Instruction::Xor { src, dst, .. }
+            | Instruction::MulFloat { src, dst, .. }
+            | Instruction::DivFloat { src, dst, .. } => {
+                reads.extend([src, dst]);
+                writes.push(dst);
+            }
+            // A count that is not an immediate is moved to %cl when the
+            // instruction is fixed up.
+            Instruction::Sal { count, dst, .. }
+            | Instruction::Sar { count, dst, .. }
+            | Instruction::Shr { count, dst, .. } => {
+                reads.extend([count, dst]);
+                writes.push(dst);
+                if !matches!(count, Operand::Imm(_)) {
+                    implicit_writes.push(Register::RCX);
+                }
+            }
+            // setcc only writes the low byte of its destination.
+            Instruction::Neg { dst, .. }
+            | Instruction::Not { dst, .. }
+            | Instruction::SetCC { dst, .. } => {
+                reads.push(dst);
+                writes.push(dst);
+            }
+            Instruction::Cmp { src, dst, .. } => reads.extend([src, dst]),
+            Instruction::Idiv { src, .. } | Instruction::Div { src, .. } => {
+                reads.push(src);
+                implicit_reads.extend([Register::RAX, Register::RDX]);
+                implicit_writes.extend([Register::RAX, Register::RDX]);
+            }
+            Instruction::Cdq { .. } => {
+                implicit_reads.push(Register::RAX);
+                implicit_writes.push(Register::RDX);
+            }
+            Instruction::Push(src) => reads.push(src),
+            Instruction::Call { uses, .. } => {
+                implicit_reads.extend(uses);
+                implicit_writes.extend(caller_saved());
+            }
+            Instruction::CallIndirect { target, uses } => {
+                reads.push(target);
+                implicit_reads.extend(uses);
+                implicit_writes.extend(caller_saved());
+            }
+            Instruction::InlineAsm { uses, clobbers, .. } => {
+                implicit_reads.extend(uses);
+                implicit_writes.extend(clobbers);
+            }
+            Instruction::Ret => implicit_reads.extend(self.returns),
+            Instruction::Jmp(_)
+            | Instruction::JmpCC { .. }
+            | Instruction::Label(_)
+            | Instruction::AllocateStack(_)
+            | Instruction::DeallocateStack(_) => {}
+        }
+
+        // Memory operands read the registers that make up their address,
+        // even when they are written.
+        let address_registers = reads
+            .iter()
+            .chain(&writes)
+            .flat_map(|operand| match operand {
+                Operand::Memory(base, _) => vec![*base],
+                Operand::Indexed { base, index, .. } => vec![*base, *index],
+                _ => Vec::new(),
+            });
+        let uses = reads
+            .iter()
+            .filter_map(|operand| self.node(operand))
+            .chain(
+                address_registers
+                    .chain(implicit_reads)
+                    .filter_map(allocatable)
+                    .map(Node::Reg),
+            )
+            .collect();
+        let defs = writes
+            .iter()
+            .filter_map(|operand| self.node(operand))
+            .chain(
+                implicit_writes
+                    .into_iter()
+                    .filter_map(allocatable)
+                    .map(Node::Reg),
+            )
+            .collect();
+
+        (uses, defs)
+    }
+
+    fn build_graph(&self, instructions: &[Instruction]) -> Graph {
+        let mut graph = Graph::default();
+        for registers in [&GP_REGISTERS[..], &XMM_REGISTERS[..]] {
+            for reg in registers {
+                graph.add_node(Node::Reg(*reg));
+            }
+            for (i, a) in registers.iter().enumerate() {
+                for b in &registers[i + 1..] {
+                    graph.add_edge(&Node::Reg(*a), &Node::Reg(*b));
+                }
+            }
+        }
+        for instr in instructions {
+            for (operand, _) in instr.operands() {
+                if let Some(node @ Node::Pseudo(_)) = self.node(operand) {
+                    *graph.spill_costs.entry(node.clone()).or_default() += 1;
+                    graph.add_node(node);
+                }
+            }
+        }
+
+        let live_after = self.live_after(instructions);
+        for (instr, live) in instructions.iter().zip(&live_after) {
+            // A move does not make its destination interfere with its
+            // source, since they hold the same value.
+            let moved = match instr {
+                Instruction::Mov { src, .. } => self.node(src),
+                _ => None,
+            };
+
+            for def in self.uses_and_defs(instr).1 {
+                for other in live {
+                    if *other != def
+                        && Some(other) != moved.as_ref()
+                        && self.is_xmm(other) == self.is_xmm(&def)
+                    {
+                        graph.add_edge(&def, other);
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// The nodes that are live right after each instruction.
+    fn live_after(&self, instructions: &[Instruction]) -> Vec<BTreeSet<Node>> {
+        let cfg = Cfg::new(instructions.to_vec());
+        let live_out = self.live_registers(&cfg);
+
+        // The blocks hold the instructions in their order.
+        let mut live_after = Vec::with_capacity(instructions.len());
+        for (id, block) in &cfg.blocks {
+            let mut live = live_out[id].clone();
+            let mut block_live = Vec::with_capacity(block.instructions.len());
+            for instr in block.instructions.iter().rev() {
+                block_live.push(live.clone());
+                let (uses, defs) = self.uses_and_defs(instr);
+                for def in &defs {
+                    live.remove(def);
+                }
+                live.extend(uses);
+            }
+            live_after.extend(block_live.into_iter().rev());
+        }
+        live_after
+    }
+
+    /// The nodes that are live at the end of each block, found by a backward
+    /// dataflow analysis like the one that finds dead stores.
+    fn live_registers(&self, cfg: &Cfg<Instruction>) -> BTreeMap<usize, BTreeSet<Node>> {
+        let mut ins: BTreeMap<usize, BTreeSet<Node>> =
+            cfg.blocks.keys().map(|id| (*id, BTreeSet::new())).collect();
+        let meet = |id: usize, ins: &BTreeMap<usize, BTreeSet<Node>>| {
+            let mut live = BTreeSet::new();
+            for successor in cfg.successors(NodeId::Block(id)) {
+                if let NodeId::Block(next) = successor {
+                    live.extend(ins[next].iter().cloned());
+                }
+            }
+            live
+        };
+
+        let mut pending: VecDeque<usize> = cfg.blocks.keys().rev().copied().collect();
+        while let Some(id) = pending.pop_front() {
+            let mut live = meet(id, &ins);
+            for instr in cfg.blocks[&id].instructions.iter().rev() {
+                let (uses, defs) = self.uses_and_defs(instr);
+                for def in &defs {
+                    live.remove(def);
+                }
+                live.extend(uses);
+            }
+
+            if live != ins[&id] {
+                ins.insert(id, live);
+                for predecessor in cfg.predecessors(NodeId::Block(id)) {
+                    if let NodeId::Block(previous) = predecessor
+                        && !pending.contains(previous)
+                    {
+                        pending.push_back(*previous);
+                    }
+                }
+            }
+        }
+
+        cfg.blocks.keys().map(|id| (*id, meet(*id, &ins))).collect()
+    }
+
+    /// Merges the two sides of each move where that is safe. Returns whether
+    /// anything was merged.
+    fn coalesce(&mut self, graph: &mut Graph, instructions: &[Instruction]) -> bool {
+        let mut merged = false;
+        for instr in instructions {
+            let Instruction::Mov { src, dst, .. } = instr else {
+                continue;
+            };
+            let (Some(src), Some(dst)) = (self.node(src), self.node(dst)) else {
+                continue;
+            };
+            if src == dst || self.is_xmm(&src) != self.is_xmm(&dst) || graph.interfere(&src, &dst) {
+                continue;
+            }
+
+            let (kept, removed) = match (&src, &dst) {
+                (Node::Reg(_), Node::Reg(_)) => continue,
+                (Node::Reg(_), Node::Pseudo(_)) => (src, dst),
+                (Node::Pseudo(_), Node::Reg(_)) => (dst, src),
+                // Pseudos that end up on the stack share the slot of the one
+                // they were merged into, so it has to be the same size.
+                (Node::Pseudo(a), Node::Pseudo(b)) if self.pseudos[a] == self.pseudos[b] => {
+                    (src, dst)
+                }
+                (Node::Pseudo(_), Node::Pseudo(_)) => continue,
+            };
+
+            let safe = match kept {
+                Node::Reg(_) => self.george(graph, &kept, &removed),
+                Node::Pseudo(_) => self.briggs(graph, &kept, &removed),
+            };
+            if safe {
+                graph.merge(&kept, &removed);
+                let Node::Pseudo(name) = removed else {
+                    unreachable!()
+                };
+                self.aliases.insert(name, kept);
+                merged = true;
+            }
+        }
+        merged
+    }
+
+    /// Briggs's test: the merged node has fewer neighbors of significant
+    /// degree, with as many neighbors as there are colors or more, than there
+    /// are colors. It can then always be colored once the others are.
+    fn briggs(&self, graph: &Graph, a: &Node, b: &Node) -> bool {
+        let colors = self.colors(a);
+        let neighbors: BTreeSet<&Node> = graph.neighbors[a]
+            .iter()
+            .chain(&graph.neighbors[b])
+            .collect();
+        let significant = neighbors
+            .into_iter()
+            .filter(|neighbor| graph.degree(neighbor) >= colors)
+            .count();
+        significant < colors
+    }
+
+    /// George's test: every neighbor of the pseudo either already interferes
+    /// with the register or has insignificant degree.
+    fn george(&self, graph: &Graph, reg: &Node, pseudo: &Node) -> bool {
+        let colors = self.colors(pseudo);
+        graph.neighbors[pseudo]
+            .iter()
+            .all(|neighbor| graph.interfere(neighbor, reg) || graph.degree(neighbor) < colors)
+    }
+
+    /// Picks a register for each pseudo node that can have one.
+    fn color(&self, graph: &Graph) -> BTreeMap<String, Register> {
+        // The number of neighbors each pseudo not taken out yet has left: the
+        // registers and the pseudos still in.
+        let mut degrees: HashMap<&Node, usize> = graph
+            .neighbors
+            .iter()
+            .filter(|(node, _)| matches!(node, Node::Pseudo(_)))
+            .map(|(node, neighbors)| (node, neighbors.len()))
+            .collect();
+        let mut low: BTreeSet<&Node> = degrees
+            .iter()
+            .filter(|(node, degree)| **degree < self.colors(node))
+            .map(|(node, _)| *node)
+            .collect();
+        let mut spills: BinaryHeap<SpillCandidate> = degrees
+            .iter()
+            .map(|(node, degree)| SpillCandidate::new(graph, node, *degree))
+            .collect();
+
+        // Take the nodes out one at a time, each one with fewer remaining
+        // neighbors than there are colors if there is such a node. Otherwise
+        // the node that is cheapest to spill per neighbor goes next, in the
+        // hope that its neighbors still leave it a color.
+        let mut stack = Vec::new();
+        while !degrees.is_empty() {
+            let next = match low.pop_first() {
+                Some(node) => node,
+                // A candidate's degree only goes down as its neighbors are
+                // taken out, so its cost only goes up. One whose degree has
+                // changed since is put back with its cost now, and the first
+                // that is up to date is the cheapest.
+                None => loop {
+                    let candidate = spills.pop().unwrap();
+                    match degrees.get(candidate.node) {
+                        Some(degree) if *degree == candidate.degree => break candidate.node,
+                        Some(degree) => {
+                            spills.push(SpillCandidate::new(graph, candidate.node, *degree))
+                        }
+                        None => {}
+                    }
+                },
+            };
+            degrees.remove(next);
+
+            for neighbor in &graph.neighbors[next] {
+                let Some(degree) = degrees.get_mut(neighbor) else {
+                    continue;
+                };
+                *degree -= 1;
+                if *degree + 1 == self.colors(neighbor) {
+                    low.insert(neighbor);
+                }
+            }
+            stack.push(next);
+        }
+
+        let mut colors: BTreeMap<String, Register> = BTreeMap::new();
+        while let Some(node) = stack.pop() {
+            let Node::Pseudo(name) = node else {
+                unreachable!()
+            };
+            let taken: Vec<Register> = graph.neighbors[node]
+                .iter()
+                .filter_map(|neighbor| match neighbor {
+                    Node::Reg(reg) => Some(*reg),
+                    Node::Pseudo(other) => colors.get(other).copied(),
+                })
+                .collect();
+            let registers = if self.is_xmm(node) {
+                &XMM_REGISTERS[..]
+            } else {
+                &GP_REGISTERS[..]
+            };
+            if let Some(reg) = registers.iter().find(|reg| !taken.contains(reg)) {
+                colors.insert(name.clone(), *reg);
+            }
+        }
+
+        colors
+    }
+
+    /// Picks a register for as many pseudos as it can in one pass over their
+    /// live ranges. A range runs from the first instruction that writes the
+    /// pseudo or after which it is live to the last one after which it is
+    /// live, so two pseudos whose ranges do not overlap can share a register.
+    /// Hard registers are only taken at the instructions where they are, so
+    /// a pseudo gets a register that is free over its whole range.
+    fn linear_scan(&self, instructions: &[Instruction]) -> BTreeMap<String, Register> {
+        let live_after = self.live_after(instructions);
+        let mut ranges: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        let mut taken: HashMap<Register, BTreeSet<usize>> = HashMap::new();
+        for (i, (instr, live)) in instructions.iter().zip(&live_after).enumerate() {
+            let (_, defs) = self.uses_and_defs(instr);
+            for node in defs.iter().chain(live) {
+                match node {
+                    Node::Reg(reg) => {
+                        taken.entry(*reg).or_default().insert(i);
+                    }
+                    Node::Pseudo(name) => ranges.entry(name.clone()).or_insert((i, i)).1 = i,
+                }
+            }
+        }
+        let free_over = |reg: &Register, start: usize, end: usize| {
+            taken
+                .get(reg)
+                .is_none_or(|points| points.range(start..=end).next().is_none())
+        };
+
+        let mut order: Vec<(String, (usize, usize))> = ranges.into_iter().collect();
+        order.sort_by_key(|(_, (start, _))| *start);
+
+        // The ranges that hold a register, with where they end.
+        let mut active: Vec<(usize, &String, Register)> = Vec::new();
+        let mut colors: BTreeMap<String, Register> = BTreeMap::new();
+        for (name, (start, end)) in &order {
+            let (start, end) = (*start, *end);
+            active.retain(|(active_end, ..)| *active_end >= start);
+
+            let xmm = self.pseudos[name].is_floating();
+            let registers = if xmm {
+                &XMM_REGISTERS[..]
+            } else {
+                &GP_REGISTERS[..]
+            };
+            let free = registers.iter().find(|reg| {
+                free_over(reg, start, end) && !active.iter().any(|(_, _, other)| other == *reg)
+            });
+            if let Some(reg) = free {
+                active.push((end, name, *reg));
+                colors.insert(name.clone(), *reg);
+                continue;
+            }
+
+            // With no register free, the range that goes on the longest is
+            // spilled, which frees a register for the longest.
+            let longest = active
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, _, reg))| reg.is_xmm() == xmm && free_over(reg, start, end))
+                .max_by_key(|(_, (active_end, ..))| *active_end);
+            if let Some((index, (active_end, _, _))) = longest
+                && *active_end > end
+            {
+                let (_, spilled, reg) = active.remove(index);
+                colors.remove(spilled);
+                active.push((end, name, reg));
+                colors.insert(name.clone(), reg);
+            }
+        }
+
+        colors
+    }
+
+    /// Puts each pseudo's register in its place and removes the moves that
+    /// now copy something to itself.
+    fn rewrite(
+        &self,
+        instructions: &mut Vec<Instruction>,
+        colors: &BTreeMap<String, Register>,
+    ) -> Vec<Register> {
+        let mut used: BTreeSet<Register> = BTreeSet::new();
+        for instr in instructions.iter_mut() {
+            for (operand, ty) in instr.operands_mut() {
+                let Operand::Pseudo(name) = operand else {
+                    continue;
+                };
+                if !self.pseudos.contains_key(name) {
+                    continue;
+                }
+
+                let reg = match self.resolve(name) {
+                    Node::Reg(reg) => Some(reg),
+                    Node::Pseudo(other) => {
+                        let reg = colors.get(&other).copied();
+                        *name = other;
+                        reg
+                    }
+                };
+                if let Some(reg) = reg {
+                    used.insert(reg);
+                    // A vector register has one name, whatever part of it is
+                    // read, as when a double is pushed as a quadword.
+                    *operand = Operand::Reg(if reg.is_xmm() { reg } else { reg.sized(ty) });
+                }
+            }
+        }
+
+        instructions
+            .retain(|instr| !matches!(instr, Instruction::Mov { src, dst, .. } if src == dst));
+
+        used.into_iter()
+            .filter(|reg| reg.is_callee_saved())
+            .collect()
+    }
+}
+
+/// A node that may be spilled when none has few enough neighbors left to be
+/// sure of a color, with its degree at the time. Out of a `BinaryHeap` comes
+/// the one that costs the least per neighbor, and of those the least node.
+struct SpillCandidate<'a> {
+    cost: f64,
+    degree: usize,
+    node: &'a Node,
+}
+
+impl<'a> SpillCandidate<'a> {
+    fn new(graph: &Graph, node: &'a Node, degree: usize) -> Self {
+        SpillCandidate {
+            cost: graph.spill_costs[node] as f64 / degree as f64,
+            degree,
+            node,
+        }
+    }
+}
+
+impl Ord for SpillCandidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.node.cmp(self.node))
+    }
+}
+
+impl PartialOrd for SpillCandidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SpillCandidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SpillCandidate<'_> {}
+
+/// The 64-bit register, or vector register, that `reg` is part of, if it is
+/// one the allocator assigns.
+fn allocatable(reg: Register) -> Option<Register> {
+    let reg = match reg {
+        Register::RSP | Register::RBP => return None,
+        reg if reg.is_xmm() => reg,
+        reg => reg.sized(AsmType::Quadword),
+    };
+    (GP_REGISTERS.contains(&reg) || XMM_REGISTERS.contains(&reg)).then_some(reg)
+}
+
+/// The registers a call may overwrite.
+fn caller_saved() -> impl Iterator<Item = Register> {
+    GP_REGISTERS
+        .into_iter()
+        .filter(|reg| !reg.is_callee_saved())
+        .chain(XMM_REGISTERS)
+}
@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        Block, BlockItem, Declaration, Designator, Expression, FunctionDeclaration, FunctionParam,
+        Identifier, Initializer, MemberDeclaration, Program, Statement, StaticAssertion,
+        StructDeclaration, VariableDeclaration,
+    },
+    errors::CompilerError,
+    types,
+};
+
+/// Gives every local variable and structure tag a unique name and rejects
+/// programs that use undeclared variables, declare a variable twice in the
+/// same scope, or assign to something that is not an lvalue.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    next_id: usize,
+    scopes: Vec<Scope>,
+}
+
+/// What is declared in one scope. Structure tags have a namespace of their
+/// own, apart from variables and functions.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    identifiers: HashMap<String, ScopeEntry>,
+    /// The unique name of the structure each tag declared here refers to.
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct ScopeEntry {
+    unique_name: String,
+    /// Functions and global variables have linkage, so every declaration of
+    /// one refers to the same entity and keeps its original name.
+    has_linkage: bool,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn resolve_program(&mut self, program: &Program) -> Result<Program, CompilerError> {
+        let mut decls = Vec::new();
+
+        // File scope, which holds the function names and global variables.
+        self.scopes.push(Scope::default());
+
+        for decl in &program.0 {
+            let resolved = match decl {
+                Declaration::FunDecl(func) => Declaration::FunDecl(self.resolve_function(func)?),
+                Declaration::VarDecl(var) => Declaration::VarDecl(self.resolve_global(var)?),
+                Declaration::StructDecl(structure) => {
+                    Declaration::StructDecl(self.resolve_struct_decl(structure))
+                }
+                Declaration::StaticAssert(assertion) => {
+                    Declaration::StaticAssert(self.resolve_static_assert(assertion)?)
+                }
+            };
+            decls.push(resolved);
+        }
+
+        self.scopes.pop();
+
+        Ok(Program(decls))
+    }
+
+    /// A variable declared at file scope keeps its name, which is how other
+    /// files refer to it.
+    fn resolve_global(
+        &mut self,
+        decl: &VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompilerError> {
+        let typ = self.resolve_type(&decl.typ);
+        let lengths = self.resolve_lengths(&decl.lengths)?;
+        self.declare_global(&decl.name)?;
+
+        let init = match &decl.init {
+            Some(init) => Some(self.resolve_initializer(init)?),
+            None => None,
+        };
+
+        Ok(VariableDeclaration {
+            typ,
+            init,
+            lengths,
+            ..decl.clone()
+        })
+    }
+
+    fn resolve_function(
+        &mut self,
+        func: &FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompilerError> {
+        let return_type = self.resolve_type(&func.return_type);
+        self.declare_global(&func.name)?;
+
+        self.scopes.push(Scope::default());
+
+        let mut params = Vec::new();
+        for param in &func.params {
+            let typ = self.resolve_type(&param.typ);
+            let name = if param.name.is_empty() {
+                param.name.clone()
+            } else {
+                self.declare(&param.name)?
+            };
+
+            params.push(FunctionParam { typ, name });
+        }
+
+        let body = match &func.body {
+            Some(block) => Some(self.resolve_block(block)?),
+            None => None,
+        };
+
+        self.scopes.pop();
+
+        Ok(FunctionDeclaration {
+            return_type,
+            params,
+            body,
+            ..func.clone()
+        })
+    }
+
+    fn resolve_block(&mut self, block: &Block) -> Result<Block, CompilerError> {
+        let mut items = Vec::new();
+
+        for item in &block.0 {
+            let resolved = match item {
+                BlockItem::Stmt(stmt) => BlockItem::Stmt(self.resolve_statement(stmt)?),
+                BlockItem::Decl(Declaration::VarDecl(decl)) => {
+                    BlockItem::Decl(Declaration::VarDecl(self.resolve_var_decl(decl)?))
+                }
+                BlockItem::Decl(Declaration::StaticAssert(assertion)) => BlockItem::Decl(
+                    Declaration::StaticAssert(self.resolve_static_assert(assertion)?),
+                ),
+                BlockItem::Decl(Declaration::FunDecl(func)) => {
+                    if func.body.is_some() {
+                        return Err(CompilerError::SemanticError {
+                            message: format!(
+                                "function '{}' cannot be defined at block scope",
+                                func.name
+                            ),
+                        });
+                    }
+                    BlockItem::Decl(Declaration::FunDecl(self.resolve_function(func)?))
+                }
+                BlockItem::Decl(Declaration::StructDecl(structure)) => {
+                    BlockItem::Decl(Declaration::StructDecl(self.resolve_struct_decl(structure)))
+                }
+            };
+            items.push(resolved);
+        }
+
+        Ok(Block(items))
+    }
+
+    fn resolve_var_decl(
+        &mut self,
+        decl: &VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompilerError> {
+        // The type and array lengths come before the new name is in scope.
+        let typ = self.resolve_type(&decl.typ);
+        let lengths = self.resolve_lengths(&decl.lengths)?;
+        let name = self.declare(&decl.name)?;
+
+        // The new name is in scope for its own initializer, as in `int a = a = 1;`.
+        let init = match &decl.init {
+            Some(init) => Some(self.resolve_initializer(init)?),
+            None => None,
+        };
+
+        Ok(VariableDeclaration {
+            name,
+            typ,
+            init,
+            lengths,
+            ..decl.clone()
+        })
+    }
+
+    /// Every declaration of a tag in the same scope refers to the same
+    /// structure, while declaring it in an inner scope introduces a new one
+    /// that hides the outer. The tag is in scope for the structure's own
+    /// members, so they can point to it.
+    fn resolve_struct_decl(&mut self, structure: &StructDeclaration) -> StructDeclaration {
+        let tag = self.declare_tag(&structure.tag);
+        let members = structure.members.as_ref().map(|members| {
+            members
+                .iter()
+                .map(|member| MemberDeclaration {
+                    typ: self.resolve_type(&member.typ),
+                    ..member.clone()
+                })
+                .collect()
+        });
+
+        StructDeclaration {
+            tag,
+            members,
+            attributes: structure.attributes.clone(),
+        }
+    }
+
+    fn resolve_lengths(
+        &mut self,
+        lengths: &[(String, Expression)],
+    ) -> Result<Vec<(String, Expression)>, CompilerError> {
+        lengths
+            .iter()
+            .map(|(count, length)| Ok((count.clone(), self.resolve_expression(length)?)))
+            .collect()
+    }
+
+    fn resolve_static_assert(
+        &mut self,
+        assertion: &StaticAssertion,
+    ) -> Result<StaticAssertion, CompilerError> {
+        Ok(StaticAssertion {
+            condition: self.resolve_expression(&assertion.condition)?,
+            ..assertion.clone()
+        })
+    }
+
+    fn resolve_initializer(&mut self, init: &Initializer) -> Result<Initializer, CompilerError> {
+        match init {
+            Initializer::Single(expr) => Ok(Initializer::Single(self.resolve_expression(expr)?)),
+            Initializer::Compound(items) => {
+                let mut resolved = Vec::new();
+                for (designators, item) in items {
+                    let designators = designators
+                        .iter()
+                        .map(|designator| match designator {
+                            Designator::Index(index) => {
+                                Ok(Designator::Index(self.resolve_expression(index)?))
+                            }
+                            Designator::Member(_) => Ok(designator.clone()),
+                        })
+                        .collect::<Result<_, CompilerError>>()?;
+                    resolved.push((designators, self.resolve_initializer(item)?));
+                }
+                Ok(Initializer::Compound(resolved))
+            }
+            Initializer::Zero(_) => Ok(init.clone()),
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &Statement) -> Result<Statement, CompilerError> {
+        match stmt {
+            Statement::ReturnStatement(expr) => {
+                let expr = match expr {
+                    Some(e) => Some(self.resolve_expression(e)?),
+                    None => None,
+                };
+                Ok(Statement::ReturnStatement(expr))
+            }
+            Statement::ExpressionStatement(expr) => Ok(Statement::ExpressionStatement(
+                self.resolve_expression(expr)?,
+            )),
+            Statement::NullStatement => Ok(Statement::NullStatement),
+            Statement::Asm(asm) => {
+                let mut asm = asm.clone();
+                for output in &mut asm.outputs {
+                    output.expr = self.resolve_lvalue(&output.expr, "asm output")?;
+                }
+                for input in &mut asm.inputs {
+                    input.expr = self.resolve_expression(&input.expr)?;
+                }
+                Ok(Statement::Asm(asm))
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &Expression) -> Result<Expression, CompilerError> {
+        let resolved = match expr {
+            Expression::ConstInt(_)
+            | Expression::ConstLong(_)
+            | Expression::ConstUInt(_)
+            | Expression::ConstULong(_)
+            | Expression::ConstFloat(_)
+            | Expression::ConstDouble(_)
+            | Expression::StringConstant(_) => expr.clone(),
+            Expression::Var(ident) => Expression::Var(Identifier(self.lookup(&ident.name())?)),
+            Expression::Grouping(inner) => {
+                Expression::Grouping(Box::new(self.resolve_expression(inner)?))
+            }
+            Expression::Cast { typ, expr } => Expression::Cast {
+                typ: self.resolve_type(typ),
+                expr: Box::new(self.resolve_expression(expr)?),
+            },
+            Expression::Unary { operator, expr } => Expression::Unary {
+                operator: *operator,
+                expr: Box::new(self.resolve_expression(expr)?),
+            },
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => Expression::Binary {
+                operator: *operator,
+                left: Box::new(self.resolve_expression(left)?),
+                right: Box::new(self.resolve_expression(right)?),
+            },
+            Expression::Assignment { lvalue, value } => Expression::Assignment {
+                lvalue: Box::new(self.resolve_lvalue(lvalue, "=")?),
+                value: Box::new(self.resolve_expression(value)?),
+            },
+            Expression::CompoundAssignment {
+                operator,
+                lvalue,
+                value,
+            } => Expression::CompoundAssignment {
+                operator: *operator,
+                lvalue: Box::new(self.resolve_lvalue(lvalue, "compound assignment")?),
+                value: Box::new(self.resolve_expression(value)?),
+            },
+            Expression::PrefixIncDec { operator, expr } => Expression::PrefixIncDec {
+                operator: *operator,
+                expr: Box::new(self.resolve_lvalue(expr, "prefix increment/decrement")?),
+            },
+            Expression::PostfixIncDec { operator, expr } => Expression::PostfixIncDec {
+                operator: *operator,
+                expr: Box::new(self.resolve_lvalue(expr, "postfix increment/decrement")?),
+            },
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => Expression::Conditional {
+                condition: Box::new(self.resolve_expression(condition)?),
+                then: Box::new(self.resolve_expression(then)?),
+                otherwise: Box::new(self.resolve_expression(otherwise)?),
+            },
+            Expression::Comma { left, right } => Expression::Comma {
+                left: Box::new(self.resolve_expression(left)?),
+                right: Box::new(self.resolve_expression(right)?),
+            },
+            Expression::FunctionCall { callee, args } => Expression::FunctionCall {
+                callee: Box::new(self.resolve_expression(callee)?),
+                args: args
+                    .iter()
+                    .map(|arg| self.resolve_expression(arg))
+                    .collect::<Result<_, _>>()?,
+            },
+            Expression::AddressOf(expr) => {
+                Expression::AddressOf(Box::new(self.resolve_lvalue(expr, "&")?))
+            }
+            Expression::Dereference(expr) => {
+                Expression::Dereference(Box::new(self.resolve_expression(expr)?))
+            }
+            Expression::Subscript { expr, index } => Expression::Subscript {
+                expr: Box::new(self.resolve_expression(expr)?),
+                index: Box::new(self.resolve_expression(index)?),
+            },
+            Expression::Dot { expr, member } => Expression::Dot {
+                expr: Box::new(self.resolve_expression(expr)?),
+                member: member.clone(),
+            },
+            Expression::Arrow { expr, member } => Expression::Arrow {
+                expr: Box::new(self.resolve_expression(expr)?),
+                member: member.clone(),
+            },
+            Expression::SizeOfExpr(expr) => {
+                Expression::SizeOfExpr(Box::new(self.resolve_expression(expr)?))
+            }
+            Expression::SizeOfType(typ) => Expression::SizeOfType(self.resolve_type(typ)),
+            Expression::AlignOfType(typ) => Expression::AlignOfType(self.resolve_type(typ)),
+            Expression::VaStart(ap) => Expression::VaStart(Box::new(self.resolve_expression(ap)?)),
+            Expression::VaArg { ap, typ } => Expression::VaArg {
+                ap: Box::new(self.resolve_expression(ap)?),
+                typ: self.resolve_type(typ),
+            },
+            Expression::VaEnd(ap) => Expression::VaEnd(Box::new(self.resolve_expression(ap)?)),
+            // The block has its own scope, like any compound statement.
+            Expression::StatementExpression(block) => {
+                self.scopes.push(Scope::default());
+                let block = self.resolve_block(block);
+                self.scopes.pop();
+                Expression::StatementExpression(block?)
+            }
+            Expression::If { .. } => {
+                return Err(CompilerError::SemanticError {
+                    message: "conditional expressions are not supported yet".into(),
+                });
+            }
+        };
+
+        Ok(resolved)
+    }
+
+    /// Resolves the operand of an operator that writes to it, which must be an lvalue.
+    fn resolve_lvalue(
+        &mut self,
+        expr: &Expression,
+        context: &str,
+    ) -> Result<Expression, CompilerError> {
+        match expr.ungrouped() {
+            Expression::Var(_)
+            | Expression::Dereference(_)
+            | Expression::Subscript { .. }
+            | Expression::Dot { .. }
+            | Expression::Arrow { .. } => self.resolve_expression(expr),
+            other => Err(CompilerError::SemanticError {
+                message: format!("invalid lvalue in {context}: {other:?}"),
+            }),
+        }
+    }
+
+    /// `typ` with each structure tag in it replaced by the unique name of
+    /// the structure it refers to here. A tag that is not declared yet
+    /// declares a structure in the current scope, to be defined later.
+    fn resolve_type(&mut self, typ: &types::T) -> types::T {
+        match typ {
+            types::T::Structure(tag) => {
+                let visible = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.tags.get(tag).cloned());
+                types::T::Structure(visible.unwrap_or_else(|| self.declare_tag(tag)))
+            }
+            types::T::Pointer(referenced) => {
+                types::T::Pointer(Box::new(self.resolve_type(referenced)))
+            }
+            types::T::Array { typ, size } => types::T::Array {
+                typ: Box::new(self.resolve_type(typ)),
+                size: *size,
+            },
+            types::T::VariableArray { typ, count } => types::T::VariableArray {
+                typ: Box::new(self.resolve_type(typ)),
+                count: count.clone(),
+            },
+            types::T::FunType {
+                params,
+                ret,
+                variadic,
+            } => types::T::FunType {
+                params: params
+                    .iter()
+                    .map(|param| self.resolve_type(param))
+                    .collect(),
+                ret: Box::new(self.resolve_type(ret)),
+                variadic: *variadic,
+            },
+            types::T::Qualified { typ, qualifiers } => types::T::Qualified {
+                typ: Box::new(self.resolve_type(typ)),
+                qualifiers: *qualifiers,
+            },
+            _ => typ.clone(),
+        }
+    }
+
+    /// Declares the structure `tag` in the current scope, unless it already
+    /// is, and returns its unique name. Tags at file scope keep their name.
+    fn declare_tag(&mut self, tag: &str) -> String {
+        let file_scope = self.scopes.len() == 1;
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("declarations are always resolved inside a scope");
+
+        if let Some(unique) = scope.tags.get(tag) {
+            return unique.clone();
+        }
+
+        let unique = if file_scope {
+            tag.to_owned()
+        } else {
+            let unique = format!("{tag}.{}", self.next_id);
+            self.next_id += 1;
+            unique
+        };
+        scope.tags.insert(tag.to_owned(), unique.clone());
+        unique
+    }
+
+    fn declare(&mut self, name: &str) -> Result<String, CompilerError> {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("declarations are always resolved inside a scope");
+
+        if scope.identifiers.contains_key(name) {
+            return Err(CompilerError::SemanticError {
+                message: format!("duplicate declaration of variable '{name}'"),
+            });
+        }
+
+        let unique = format!("var.{name}.{}", self.next_id);
+        self.next_id += 1;
+        scope.identifiers.insert(
+            name.to_owned(),
+            ScopeEntry {
+                unique_name: unique.clone(),
+                has_linkage: false,
+            },
+        );
+
+        Ok(unique)
+    }
+
+    /// Declares a function or variable with linkage: any at file scope, or a
+    /// function declared in a block. Either may be declared any number of
+    /// times; the type checker makes sure the declarations agree.
+    fn declare_global(&mut self, name: &str) -> Result<(), CompilerError> {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("declarations are always resolved inside a scope");
+
+        if let Some(entry) = scope.identifiers.get(name)
+            && !entry.has_linkage
+        {
+            return Err(CompilerError::SemanticError {
+                message: format!("'{name}' redeclared as a different kind of symbol"),
+            });
+        }
+
+        scope.identifiers.insert(
+            name.to_owned(),
+            ScopeEntry {
+                unique_name: name.to_owned(),
+                has_linkage: true,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<String, CompilerError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| {
+                scope
+                    .identifiers
+                    .get(name)
+                    .map(|entry| entry.unique_name.clone())
+            })
+            .ok_or_else(|| CompilerError::SemanticError {
+                message: format!("use of undeclared variable '{name}'"),
+            })
+    }
+}
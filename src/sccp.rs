@@ -0,0 +1,400 @@
+//! Sparse conditional constant propagation, after Wegman and Zadeck: works
+//! out which versions of variables in SSA form only ever hold one constant,
+//! together with which edges of the graph control can ever take. A constant
+//! then decides a branch even when it only gets there through phis, and a
+//! value that only reaches a phi along an edge that is never taken does not
+//! stop the phi being constant.
+//!
+//! Every version starts out with no known value. A block is only looked at
+//! once an edge into it is found to be taken, and again whenever a value it
+//! reads changes, until nothing changes any more. Each read of a version
+//! with a constant value then reads the constant instead, which leaves what
+//! wrote the version nothing to do. Conditional jumps that only ever go one
+//! way become unconditional or go, and blocks that are never reached are
+//! removed.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::cfg::{Flow, FlowInstruction, NodeId};
+use crate::constant_folding::{Folded, fold_instruction};
+use crate::ir::{Constant, Instruction, Value};
+use crate::ssa::Ssa;
+use crate::symbols::SymbolTable;
+
+/// What is known of the value of a version.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lattice {
+    /// Nothing yet: nothing found to run so far writes it.
+    Unknown,
+    Constant(Constant),
+    /// More than one value, or one that cannot be known at compile time.
+    Varying,
+}
+
+impl Lattice {
+    /// What is known of a value that may be either.
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Unknown, other) | (other, Lattice::Unknown) => other,
+            (Lattice::Constant(a), Lattice::Constant(b)) if a == b => self,
+            _ => Lattice::Varying,
+        }
+    }
+}
+
+pub fn propagate_constants(ssa: &mut Ssa, symbols: &SymbolTable) {
+    let mut propagation = Propagation::new(ssa, symbols);
+    propagation.run();
+    let Propagation {
+        values,
+        executable,
+        reached,
+        labels,
+        ..
+    } = propagation;
+    let labels: HashMap<String, usize> = labels
+        .into_iter()
+        .map(|(label, id)| (label.to_string(), id))
+        .collect();
+
+    let constant = |value: &Value| match value {
+        Value::Var(name) => match values.get(name) {
+            Some(Lattice::Constant(constant)) => Some(Value::Constant(*constant)),
+            _ => None,
+        },
+        Value::Constant(_) => None,
+    };
+
+    let ids: Vec<usize> = ssa.cfg.blocks.keys().copied().collect();
+    for id in ids.iter().copied().filter(|id| reached.contains(id)) {
+        let node = NodeId::Block(id);
+        let block = ssa.cfg.blocks.get_mut(&id).unwrap();
+
+        for instr in &mut block.instructions {
+            instr.replace_sources(constant);
+        }
+
+        let Some(Flow::ConditionalJump(target)) = block.instructions.last().map(|i| i.flow())
+        else {
+            continue;
+        };
+        let taken = NodeId::Block(labels[target]);
+        let Some(fall_through) = block.successors.iter().copied().find(|s| *s != taken) else {
+            continue;
+        };
+        let target = target.to_string();
+        let dropped = match (
+            executable.contains(&(node, taken)),
+            executable.contains(&(node, fall_through)),
+        ) {
+            (true, false) => {
+                *block.instructions.last_mut().unwrap() = Instruction::Jump(target);
+                fall_through
+            }
+            (false, true) => {
+                block.instructions.pop();
+                taken
+            }
+            _ => continue,
+        };
+        ssa.cfg.remove_edge(node, dropped);
+    }
+
+    for id in ids.into_iter().filter(|id| !reached.contains(id)) {
+        ssa.cfg.remove_block(id);
+        ssa.phis.remove(&id);
+    }
+
+    // A phi only picks between the edges that are left.
+    for (id, phis) in &mut ssa.phis {
+        let predecessors = ssa.cfg.predecessors(NodeId::Block(*id));
+        for phi in phis {
+            phi.sources.retain(|(from, _)| predecessors.contains(from));
+            for (_, src) in &mut phi.sources {
+                if let Some(new) = constant(src) {
+                    *src = new;
+                }
+            }
+        }
+    }
+
+    // What writes a constant version is left with nothing to do, unless
+    // the version is still read where a constant cannot go. An operation
+    // there writes the constant instead.
+    let mut read: HashSet<String> = HashSet::new();
+    for block in ssa.cfg.blocks.values() {
+        for instr in &block.instructions {
+            read.extend(instr.sources().into_iter().filter_map(|value| match value {
+                Value::Var(name) => Some(name.clone()),
+                Value::Constant(_) => None,
+            }));
+        }
+    }
+    for phi in ssa.phis.values().flatten() {
+        read.extend(phi.sources.iter().filter_map(|(_, src)| match src {
+            Value::Var(name) => Some(name.clone()),
+            Value::Constant(_) => None,
+        }));
+    }
+
+    for phis in ssa.phis.values_mut() {
+        phis.retain(|phi| {
+            constant(&Value::Var(phi.dst.clone())).is_none() || read.contains(&phi.dst)
+        });
+    }
+    for block in ssa.cfg.blocks.values_mut() {
+        block.instructions.retain_mut(|instr| {
+            if !is_foldable(instr) {
+                return true;
+            }
+            let [dst] = instr.destinations()[..] else {
+                return true;
+            };
+            let dst = Value::Var(dst.to_string());
+            let Some(src) = constant(&dst) else {
+                return true;
+            };
+            if !read.contains(&dst.name()) {
+                return false;
+            }
+            *instr = Instruction::Copy { src, dst };
+            true
+        });
+    }
+    ssa.phis.retain(|_, phis| !phis.is_empty());
+}
+
+/// Whether the instruction only works out its result from its operands, so
+/// that constant operands make it constant.
+fn is_foldable(instr: &Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::Unary { .. }
+            | Instruction::Binary { .. }
+            | Instruction::Copy { .. }
+            | Instruction::SignExtend { .. }
+            | Instruction::ZeroExtend { .. }
+            | Instruction::Truncate { .. }
+            | Instruction::IntToFloating { .. }
+            | Instruction::FloatingToInt { .. }
+            | Instruction::UIntToFloating { .. }
+            | Instruction::FloatingToUInt { .. }
+            | Instruction::FloatingConvert { .. }
+    )
+}
+
+struct Propagation<'a> {
+    ssa: &'a Ssa,
+    symbols: &'a SymbolTable,
+    /// What is known so far of each version written in the function. The
+    /// others hold whatever they held on entry.
+    values: HashMap<String, Lattice>,
+    /// The edges found so far to be taken.
+    executable: HashSet<(NodeId, NodeId)>,
+    /// The blocks an edge found to be taken leads to.
+    reached: HashSet<usize>,
+    /// The blocks to look at again.
+    pending: VecDeque<usize>,
+    /// The blocks that read each version, in a phi or an instruction.
+    readers: HashMap<&'a str, Vec<usize>>,
+    /// The block each label starts.
+    labels: HashMap<&'a str, usize>,
+}
+
+impl<'a> Propagation<'a> {
+    fn new(ssa: &'a Ssa, symbols: &'a SymbolTable) -> Propagation<'a> {
+        let mut propagation = Propagation {
+            ssa,
+            symbols,
+            values: HashMap::new(),
+            executable: HashSet::new(),
+            reached: HashSet::new(),
+            pending: VecDeque::new(),
+            readers: HashMap::new(),
+            labels: HashMap::new(),
+        };
+
+        for (id, phis) in &ssa.phis {
+            for phi in phis {
+                propagation.values.insert(phi.dst.clone(), Lattice::Unknown);
+                for (_, src) in &phi.sources {
+                    propagation.add_reader(src, *id);
+                }
+            }
+        }
+        for (id, block) in &ssa.cfg.blocks {
+            for instr in &block.instructions {
+                if let Instruction::Label(label) = instr {
+                    propagation.labels.insert(label, *id);
+                }
+                for value in instr.sources() {
+                    propagation.add_reader(value, *id);
+                }
+                for name in instr.destinations() {
+                    if ssa.is_version(&Value::Var(name.to_string())) {
+                        propagation
+                            .values
+                            .insert(name.to_string(), Lattice::Unknown);
+                    }
+                }
+            }
+        }
+
+        propagation
+    }
+
+    fn add_reader(&mut self, value: &'a Value, id: usize) {
+        if let Value::Var(name) = value
+            && self.ssa.is_version(value)
+        {
+            self.readers.entry(name).or_default().push(id);
+        }
+    }
+
+    fn run(&mut self) {
+        for successor in self.ssa.cfg.successors(NodeId::Entry) {
+            self.take_edge(NodeId::Entry, *successor);
+        }
+        while let Some(id) = self.pending.pop_front() {
+            self.visit(id);
+        }
+    }
+
+    /// Works out again what the block writes and where it can go next.
+    fn visit(&mut self, id: usize) {
+        let ssa = self.ssa;
+        let node = NodeId::Block(id);
+
+        for phi in ssa.phis.get(&id).into_iter().flatten() {
+            let value = phi
+                .sources
+                .iter()
+                .filter(|(from, _)| self.executable.contains(&(*from, node)))
+                .fold(Lattice::Unknown, |value, (_, src)| {
+                    value.meet(self.value(src))
+                });
+            self.update(&phi.dst, value);
+        }
+
+        let block = &ssa.cfg.blocks[&id];
+        for instr in &block.instructions {
+            let destinations = instr.destinations();
+            if destinations
+                .iter()
+                .any(|name| self.values.contains_key(*name))
+            {
+                let value = self.evaluate(instr);
+                for name in destinations {
+                    if self.values.contains_key(name) {
+                        self.update(name, value);
+                    }
+                }
+            }
+        }
+
+        let successors = &block.successors;
+        match block.instructions.last() {
+            Some(
+                instr @ (Instruction::JumpIfZero { condition, target }
+                | Instruction::JumpIfNotZero { condition, target }),
+            ) => {
+                let taken = NodeId::Block(self.labels[target.as_str()]);
+                let fall_through = successors.iter().copied().find(|s| *s != taken);
+                match self.value(condition) {
+                    Lattice::Unknown => {}
+                    Lattice::Varying => {
+                        for successor in successors {
+                            self.take_edge(node, *successor);
+                        }
+                    }
+                    Lattice::Constant(constant) => {
+                        let mut decided = instr.clone();
+                        decided.replace_sources(|_| Some(Value::Constant(constant)));
+                        match fold_instruction(&decided, self.symbols) {
+                            Some(Folded::Jump(_)) => self.take_edge(node, taken),
+                            _ => self.take_edge(node, fall_through.unwrap_or(taken)),
+                        }
+                    }
+                }
+            }
+            _ => {
+                for successor in successors {
+                    self.take_edge(node, *successor);
+                }
+            }
+        }
+    }
+
+    /// What is known of the result of the instruction.
+    fn evaluate(&self, instr: &Instruction) -> Lattice {
+        if !is_foldable(instr) {
+            return Lattice::Varying;
+        }
+
+        let (mut unknown, mut varying) = (false, false);
+        let mut operands = instr.clone();
+        operands.replace_sources(|value| match self.value(value) {
+            Lattice::Constant(constant) => Some(Value::Constant(constant)),
+            Lattice::Unknown => {
+                unknown = true;
+                None
+            }
+            Lattice::Varying => {
+                varying = true;
+                None
+            }
+        });
+        if varying {
+            return Lattice::Varying;
+        }
+        if unknown {
+            return Lattice::Unknown;
+        }
+
+        match fold_instruction(&operands, self.symbols) {
+            Some(Folded::Constant(constant, _)) => Lattice::Constant(constant),
+            _ => Lattice::Varying,
+        }
+    }
+
+    /// What is known of the value.
+    fn value(&self, value: &Value) -> Lattice {
+        match value {
+            Value::Constant(constant) => Lattice::Constant(*constant),
+            Value::Var(name) => self.values.get(name).copied().unwrap_or(Lattice::Varying),
+        }
+    }
+
+    /// Lowers what is known of a version to take in `value` as well, and
+    /// looks again at the blocks that read it if that changes anything.
+    fn update(&mut self, name: &str, value: Lattice) {
+        let old = self.values[name];
+        let new = old.meet(value);
+        if new == old {
+            return;
+        }
+        self.values.insert(name.to_string(), new);
+        let readers = self.readers.get(name).cloned().unwrap_or_default();
+        for id in readers {
+            if self.reached.contains(&id) {
+                self.look_at(id);
+            }
+        }
+    }
+
+    fn take_edge(&mut self, from: NodeId, to: NodeId) {
+        if !self.executable.insert((from, to)) {
+            return;
+        }
+        if let NodeId::Block(id) = to {
+            self.reached.insert(id);
+            self.look_at(id);
+        }
+    }
+
+    fn look_at(&mut self, id: usize) {
+        if !self.pending.contains(&id) {
+            self.pending.push_back(id);
+        }
+    }
+}
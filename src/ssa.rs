@@ -0,0 +1,657 @@
+//! Static single assignment form: every variable is written by exactly one
+//! instruction, so each read names the one write it sees. Where different
+//! writes of a variable meet at the top of a block, a phi picks the one for
+//! the edge control came in on.
+//!
+//! Only the variables that are only ever read and written by name are
+//! renamed: scalars that are neither static nor volatile and whose address
+//! is never taken. The value such a variable has on entry to the function,
+//! as a parameter or uninitialized, keeps the variable's own name.
+//!
+//! Construction follows Cytron et al.: a phi goes on the dominance frontier
+//! of each block that writes a variable, wherever the variable is live, and
+//! the reads and writes are then renamed walking down the dominator tree.
+//!
+//! Leaving SSA form, the versions of a variable and the values each phi joins
+//! get one name back when none of them is live where another is written,
+//! which leaves the phis nothing to do. Otherwise, once an optimization has
+//! moved reads past writes, the versions of each variable get its name back
+//! where they still can, the rest keep their own, and each phi becomes
+//! copies on the edges into its block. The copies on an edge are meant to
+//! happen all at once, so they are put in an order where none overwrites a
+//! value another still has to read.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::cfg::{Cfg, NodeId};
+use crate::dominators::Dominators;
+use crate::ir::{Instruction, Value};
+use crate::symbols::SymbolTable;
+use crate::unreachable_code::remove_unreachable_blocks;
+
+/// `dst = phi(sources)` at the top of a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi {
+    pub dst: String,
+    /// The value for each edge into the block, by the node it comes from.
+    pub sources: Vec<(NodeId, Value)>,
+}
+
+/// An entry of the listing of a function in SSA form.
+pub enum Listed<'a> {
+    Phi(&'a Phi),
+    Instruction(&'a Instruction),
+}
+
+impl fmt::Debug for Listed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listed::Phi(phi) => phi.fmt(f),
+            Listed::Instruction(instr) => instr.fmt(f),
+        }
+    }
+}
+
+/// A function in SSA form. Blocks that cannot be reached are left out.
+#[derive(Debug, Clone)]
+pub struct Ssa {
+    pub cfg: Cfg,
+    /// The phis at the top of each block that has any.
+    pub phis: BTreeMap<usize, Vec<Phi>>,
+    /// The variable each version is of, for every renamed variable and its
+    /// versions.
+    variables: HashMap<String, String>,
+}
+
+impl Ssa {
+    /// Puts a function in SSA form, adding each new version of a variable to
+    /// the symbol table with the variable's type.
+    pub fn new(mut cfg: Cfg, symbols: &mut SymbolTable) -> Ssa {
+        remove_unreachable_blocks(&mut cfg);
+        let variables = renamable(&cfg, symbols)
+            .into_iter()
+            .map(|name| (name.clone(), name))
+            .collect();
+
+        let mut ssa = Ssa {
+            cfg,
+            phis: BTreeMap::new(),
+            variables,
+        };
+        let dominators = Dominators::new(&ssa.cfg);
+        ssa.insert_phis(&dominators);
+        ssa.rename(&dominators, symbols);
+        ssa
+    }
+
+    /// Whether the value is a version of a renamed variable, so is written
+    /// by one instruction or phi, or on entry to the function.
+    pub fn is_version(&self, value: &Value) -> bool {
+        matches!(value, Value::Var(name) if self.variables.contains_key(name))
+    }
+
+    /// The instructions of the blocks in order, each block's phis right
+    /// after its label, for printing.
+    pub fn listing(&self) -> Vec<Listed<'_>> {
+        let mut listing = Vec::new();
+        for (id, block) in &self.cfg.blocks {
+            let mut instructions = block.instructions.iter().peekable();
+            if let Some(label @ Instruction::Label(_)) = instructions.peek() {
+                listing.push(Listed::Instruction(label));
+                instructions.next();
+            }
+            listing.extend(self.phis.get(id).into_iter().flatten().map(Listed::Phi));
+            listing.extend(instructions.map(Listed::Instruction));
+        }
+        listing
+    }
+
+    /// Takes the function back out of SSA form. `function` names the blocks
+    /// added to hold the copies of an edge that cannot go anywhere else.
+    pub fn into_cfg(self, function: &str, symbols: &mut SymbolTable) -> Cfg {
+        let names = self.names(symbols);
+        let rename = |name: &mut String| {
+            if let Some(new) = names.get(name.as_str()) {
+                *name = new.clone();
+            }
+        };
+        let renamed = |value: &Value| match value {
+            Value::Var(name) => names.get(name).map(|new| Value::Var(new.clone())),
+            Value::Constant(_) => None,
+        };
+
+        // The copies the phis at the top of `to` make along the edge from
+        // `from`, minus those that copy a variable to itself.
+        let edge_copies = |from: NodeId, to: NodeId| -> Vec<(String, Value)> {
+            let NodeId::Block(to) = to else {
+                return Vec::new();
+            };
+            self.phis
+                .get(&to)
+                .into_iter()
+                .flatten()
+                .filter_map(|phi| {
+                    let (_, src) = phi.sources.iter().find(|(node, _)| *node == from)?;
+                    let mut dst = phi.dst.clone();
+                    rename(&mut dst);
+                    let src = renamed(src).unwrap_or_else(|| src.clone());
+                    (src != Value::Var(dst.clone())).then_some((dst, src))
+                })
+                .collect()
+        };
+
+        let mut labels: HashSet<String> = self
+            .cfg
+            .blocks
+            .values()
+            .filter_map(|block| match block.instructions.first() {
+                Some(Instruction::Label(label)) => Some(label.clone()),
+                _ => None,
+            })
+            .collect();
+        let labels_by_block: HashMap<String, NodeId> = self
+            .cfg
+            .blocks
+            .iter()
+            .filter_map(|(id, block)| match block.instructions.first() {
+                Some(Instruction::Label(label)) => Some((label.clone(), NodeId::Block(*id))),
+                _ => None,
+            })
+            .collect();
+
+        let mut instructions = Vec::new();
+        // Blocks that only make the copies of the edge a conditional jump
+        // takes, then jump on to where it was going. They go at the end of
+        // the function, where nothing runs into them.
+        let mut edge_blocks = Vec::new();
+        if let Some(first) = self.cfg.entry_successors.first() {
+            let copies = edge_copies(NodeId::Entry, *first);
+            instructions.extend(sequentialize(copies, symbols));
+        }
+
+        let ids: Vec<usize> = self.cfg.blocks.keys().copied().collect();
+        for (i, id) in ids.iter().enumerate() {
+            let node = NodeId::Block(*id);
+            let next = ids
+                .get(i + 1)
+                .map_or(NodeId::Exit, |next| NodeId::Block(*next));
+
+            let mut block = self.cfg.blocks[id].instructions.clone();
+            for instr in &mut block {
+                instr.replace_sources(renamed);
+                for dst in instr.destinations_mut() {
+                    rename(dst);
+                }
+            }
+
+            match block.last_mut() {
+                Some(Instruction::Return(_)) => instructions.extend(block),
+                Some(Instruction::Jump(target)) => {
+                    let copies = edge_copies(node, labels_by_block[target.as_str()]);
+                    let jump = block.pop();
+                    instructions.extend(block);
+                    instructions.extend(sequentialize(copies, symbols));
+                    instructions.extend(jump);
+                }
+                Some(
+                    Instruction::JumpIfZero { target, .. }
+                    | Instruction::JumpIfNotZero { target, .. },
+                ) => {
+                    let copies = edge_copies(node, labels_by_block[target.as_str()]);
+                    if !copies.is_empty() {
+                        let label = (1..)
+                            .map(|n| format!("{function}.edge.{n}"))
+                            .find(|label| !labels.contains(label))
+                            .unwrap();
+                        labels.insert(label.clone());
+                        edge_blocks.push(Instruction::Label(label.clone()));
+                        edge_blocks.extend(sequentialize(copies, symbols));
+                        edge_blocks.push(Instruction::Jump(std::mem::replace(target, label)));
+                    }
+                    instructions.extend(block);
+                    // Only the edge that falls through runs what comes
+                    // right after the jump.
+                    instructions.extend(sequentialize(edge_copies(node, next), symbols));
+                }
+                _ => {
+                    instructions.extend(block);
+                    instructions.extend(sequentialize(edge_copies(node, next), symbols));
+                }
+            }
+        }
+        instructions.extend(edge_blocks);
+
+        Cfg::new(instructions)
+    }
+
+    /// Puts a phi for a variable at the top of each block on the dominance
+    /// frontier of a block that writes it, or of a block that already has a
+    /// phi for it, where the variable is live.
+    fn insert_phis(&mut self, dominators: &Dominators) {
+        let frontiers = dominators.frontiers(&self.cfg);
+        let live_in = self.live_in();
+
+        let mut written: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (id, block) in &self.cfg.blocks {
+            for instr in &block.instructions {
+                for name in instr.destinations() {
+                    if self.variables.contains_key(name) {
+                        written.entry(name).or_default().push(*id);
+                    }
+                }
+            }
+        }
+
+        for (variable, mut pending) in written {
+            let mut placed = HashSet::new();
+            while let Some(id) = pending.pop() {
+                let Some(frontier) = frontiers.get(&NodeId::Block(id)) else {
+                    continue;
+                };
+                for node in frontier {
+                    let NodeId::Block(next) = node else {
+                        continue;
+                    };
+                    if live_in[next].contains(variable) && placed.insert(*next) {
+                        self.phis.entry(*next).or_default().push(Phi {
+                            dst: variable.to_string(),
+                            sources: Vec::new(),
+                        });
+                        pending.push(*next);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gives every write of a renamed variable a new version, and every read
+    /// the version that reaches it. A block starts out with the versions at
+    /// the end of its immediate dominator, which is renamed before it.
+    fn rename(&mut self, dominators: &Dominators, symbols: &mut SymbolTable) {
+        let Ssa {
+            cfg,
+            phis,
+            variables,
+        } = self;
+
+        let mut versions_at_end: HashMap<NodeId, HashMap<String, String>> = HashMap::new();
+        for node in dominators.preorder() {
+            let mut current = match dominators.immediate_dominator(node) {
+                Some(idom) => versions_at_end[&idom].clone(),
+                None => HashMap::new(),
+            };
+
+            if let NodeId::Block(id) = node {
+                for phi in phis.get_mut(&id).into_iter().flatten() {
+                    let version = new_version(&phi.dst, variables, symbols);
+                    current.insert(phi.dst.clone(), version.clone());
+                    phi.dst = version;
+                }
+
+                for instr in &mut cfg.blocks.get_mut(&id).unwrap().instructions {
+                    instr.replace_sources(|value| match value {
+                        Value::Var(name) => current.get(name).cloned().map(Value::Var),
+                        Value::Constant(_) => None,
+                    });
+                    for dst in instr.destinations_mut() {
+                        if variables.contains_key(dst.as_str()) {
+                            let version = new_version(dst, variables, symbols);
+                            current.insert(dst.clone(), version.clone());
+                            *dst = version;
+                        }
+                    }
+                }
+            }
+
+            for successor in cfg.successors(node) {
+                let NodeId::Block(next) = successor else {
+                    continue;
+                };
+                for phi in phis.get_mut(next).into_iter().flatten() {
+                    let variable = &variables[&phi.dst];
+                    let version = current.get(variable).unwrap_or(variable);
+                    phi.sources.push((node, Value::Var(version.clone())));
+                }
+            }
+
+            versions_at_end.insert(node, current);
+        }
+    }
+
+    /// The name each version gets back when leaving SSA form, for those whose
+    /// name changes.
+    fn names(&self, symbols: &SymbolTable) -> HashMap<String, String> {
+        let live_in = self.live_in();
+
+        // Each version goes in a group with its variable, and with the
+        // values of any phi it is or is a source of.
+        let mut groups = Groups::default();
+        for (version, variable) in &self.variables {
+            groups.join(version, variable);
+        }
+        for phi in self.phis.values().flatten() {
+            for (_, src) in &phi.sources {
+                if let Value::Var(name) = src
+                    && self.variables.contains_key(name)
+                {
+                    groups.join(&phi.dst, name);
+                }
+            }
+        }
+        let interfering = self.interfering(&mut groups, &live_in, symbols);
+
+        // Where that fails, the versions of each variable can still share
+        // its name on their own, leaving the phis between them copies. Names
+        // that only change with that stay the same from one pass through SSA
+        // form to the next.
+        let mut variable_groups = Groups::default();
+        for (version, variable) in &self.variables {
+            variable_groups.join(version, variable);
+        }
+        let variables_interfering = self.interfering(&mut variable_groups, &live_in, symbols);
+
+        // A group that shares a name takes that of the variable whose value
+        // on entry it reads, such as a parameter, since nothing copies that
+        // value anywhere else. There is at most one, as two would interfere.
+        // Any other group takes the first of its variables'.
+        let live_on_entry = self.live_on_entry(&live_in);
+        let mut shared: HashMap<String, String> = HashMap::new();
+        for variable in self.variables.values() {
+            let group = groups.find(variable);
+            if interfering.contains(&group) {
+                continue;
+            }
+            let name = shared.entry(group).or_insert_with(|| variable.clone());
+            if live_on_entry.contains(variable)
+                || (!live_on_entry.contains(name) && variable < name)
+            {
+                *name = variable.clone();
+            }
+        }
+
+        self.variables
+            .iter()
+            .filter_map(|(version, variable)| {
+                let name = match shared.get(&groups.find(version)) {
+                    Some(name) => name,
+                    None if !variables_interfering.contains(&variable_groups.find(version)) => {
+                        variable
+                    }
+                    None => return None,
+                };
+                (name != version).then(|| (version.clone(), name.clone()))
+            })
+            .collect()
+    }
+
+    /// The groups that cannot share a name: those where a member is live
+    /// where another is written, or whose members differ in type.
+    fn interfering(
+        &self,
+        groups: &mut Groups,
+        live_in: &BTreeMap<usize, HashSet<String>>,
+        symbols: &SymbolTable,
+    ) -> HashSet<String> {
+        let mut interfering = HashSet::new();
+        let mut check = |groups: &mut Groups, written: &str, live: &HashSet<String>| {
+            for other in live {
+                if other != written && groups.find(other) == groups.find(written) {
+                    interfering.insert(groups.find(written));
+                }
+            }
+        };
+
+        for (id, block) in &self.cfg.blocks {
+            let mut live = self.live_out(*id, live_in);
+            for instr in block.instructions.iter().rev() {
+                for name in instr.destinations() {
+                    if self.variables.contains_key(name) {
+                        check(groups, name, &live);
+                    }
+                }
+                self.transfer(&mut live, instr);
+            }
+            for phi in self.phis.get(id).into_iter().flatten() {
+                check(groups, &phi.dst, &live_in[id]);
+            }
+        }
+
+        // Every variable is written on entry to the function.
+        let live = self.live_on_entry(live_in);
+        for name in &live {
+            check(groups, name, &live);
+        }
+
+        for (version, variable) in &self.variables {
+            if symbols.type_of(version) != symbols.type_of(variable) {
+                interfering.insert(groups.find(version));
+            }
+        }
+        interfering
+    }
+
+    /// The renamed variables whose value on entry to the function is read,
+    /// including by the phis of the first block.
+    fn live_on_entry(&self, live_in: &BTreeMap<usize, HashSet<String>>) -> HashSet<String> {
+        let Some(NodeId::Block(first)) = self.cfg.entry_successors.first() else {
+            return HashSet::new();
+        };
+        let mut live = live_in[first].clone();
+        for phi in self.phis.get(first).into_iter().flatten() {
+            live.remove(&phi.dst);
+        }
+        live.extend(self.phi_reads(NodeId::Entry, *first));
+        live
+    }
+
+    /// The renamed variables that are live at the top of each block, right
+    /// after its phis, found by a backward dataflow analysis.
+    fn live_in(&self) -> BTreeMap<usize, HashSet<String>> {
+        let mut ins: BTreeMap<usize, HashSet<String>> = self
+            .cfg
+            .blocks
+            .keys()
+            .map(|id| (*id, HashSet::new()))
+            .collect();
+
+        let mut pending: VecDeque<usize> = self.cfg.blocks.keys().rev().copied().collect();
+        while let Some(id) = pending.pop_front() {
+            let mut live = self.live_out(id, &ins);
+            for instr in self.cfg.blocks[&id].instructions.iter().rev() {
+                self.transfer(&mut live, instr);
+            }
+
+            if live != ins[&id] {
+                ins.insert(id, live);
+                for predecessor in self.cfg.predecessors(NodeId::Block(id)) {
+                    if let NodeId::Block(previous) = predecessor
+                        && !pending.contains(previous)
+                    {
+                        pending.push_back(*previous);
+                    }
+                }
+            }
+        }
+
+        ins
+    }
+
+    /// The renamed variables live at the end of a block: those live at the
+    /// top of its successors that their phis do not write, and those the
+    /// phis read along the edges from it.
+    fn live_out(&self, id: usize, ins: &BTreeMap<usize, HashSet<String>>) -> HashSet<String> {
+        let node = NodeId::Block(id);
+        let mut live = HashSet::new();
+        for successor in self.cfg.successors(node) {
+            let NodeId::Block(next) = successor else {
+                continue;
+            };
+            let written: HashSet<&String> = self
+                .phis
+                .get(next)
+                .into_iter()
+                .flatten()
+                .map(|phi| &phi.dst)
+                .collect();
+            live.extend(
+                ins[next]
+                    .iter()
+                    .filter(|name| !written.contains(name))
+                    .cloned(),
+            );
+            live.extend(self.phi_reads(node, *next));
+        }
+        live
+    }
+
+    /// The renamed variables the phis at the top of `to` read along the edge
+    /// from `from`.
+    fn phi_reads(&self, from: NodeId, to: usize) -> impl Iterator<Item = String> + '_ {
+        self.phis
+            .get(&to)
+            .into_iter()
+            .flatten()
+            .filter_map(move |phi| {
+                phi.sources
+                    .iter()
+                    .find(|(node, _)| *node == from)
+                    .and_then(|(_, src)| match src {
+                        Value::Var(name) if self.variables.contains_key(name) => Some(name.clone()),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Updates the renamed variables live before `instr`, given those live
+    /// after it.
+    fn transfer(&self, live: &mut HashSet<String>, instr: &Instruction) {
+        for name in instr.destinations() {
+            live.remove(name);
+        }
+        for value in instr.sources() {
+            if let Value::Var(name) = value
+                && self.variables.contains_key(name)
+            {
+                live.insert(name.clone());
+            }
+        }
+    }
+}
+
+/// The variables of the function that are only ever read and written by
+/// name, as the instructions that use them say, so can be renamed.
+fn renamable(cfg: &Cfg, symbols: &SymbolTable) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut address_taken = HashSet::new();
+    for instr in cfg.blocks.values().flat_map(|block| &block.instructions) {
+        if let Instruction::GetAddress {
+            src: Value::Var(name),
+            ..
+        } = instr
+        {
+            address_taken.insert(name.as_str());
+        }
+        for value in instr.sources() {
+            if let Value::Var(name) = value {
+                names.insert(name.as_str());
+            }
+        }
+        names.extend(instr.destinations());
+    }
+
+    names
+        .into_iter()
+        .filter(|name| {
+            let typ = symbols.type_of(name);
+            typ.is_scalar()
+                && !typ.qualifiers().volatile
+                && !symbols.is_static(name)
+                && !address_taken.contains(name)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// A new version of `variable`, named after it.
+fn new_version(
+    variable: &str,
+    variables: &mut HashMap<String, String>,
+    symbols: &mut SymbolTable,
+) -> String {
+    let version = fresh_name(variable, symbols);
+    variables.insert(version.clone(), variable.to_string());
+    version
+}
+
+/// A name for a new variable of the same type as `variable` that is not in
+/// the symbol table yet, which it is then added to.
+fn fresh_name(variable: &str, symbols: &mut SymbolTable) -> String {
+    let name = (1..)
+        .map(|n| format!("{variable}.ssa.{n}"))
+        .find(|name| symbols.get(name).is_none())
+        .unwrap();
+    symbols.insert(name.clone(), symbols.type_of(variable).clone());
+    name
+}
+
+/// Orders copies that are meant to happen all at once so that none of them
+/// overwrites a variable another one still has to read. Where they form a
+/// cycle, one destination is saved in a new variable first.
+fn sequentialize(mut copies: Vec<(String, Value)>, symbols: &mut SymbolTable) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    while !copies.is_empty() {
+        let read = |copies: &[(String, Value)], name: &str| {
+            copies
+                .iter()
+                .any(|(_, src)| matches!(src, Value::Var(other) if other == name))
+        };
+        match copies.iter().position(|(dst, _)| !read(&copies, dst)) {
+            Some(i) => {
+                let (dst, src) = copies.remove(i);
+                instructions.push(Instruction::Copy {
+                    src,
+                    dst: Value::Var(dst),
+                });
+            }
+            None => {
+                let saved = copies[0].0.clone();
+                let temporary = fresh_name(&saved, symbols);
+                instructions.push(Instruction::Copy {
+                    src: Value::Var(saved.clone()),
+                    dst: Value::Var(temporary.clone()),
+                });
+                for (_, src) in &mut copies {
+                    if *src == Value::Var(saved.clone()) {
+                        *src = Value::Var(temporary.clone());
+                    }
+                }
+            }
+        }
+    }
+    instructions
+}
+
+/// Disjoint sets of names, each one identified by one of its members.
+#[derive(Debug, Default)]
+struct Groups {
+    parents: HashMap<String, String>,
+}
+
+impl Groups {
+    fn find(&mut self, name: &str) -> String {
+        let Some(parent) = self.parents.get(name).cloned() else {
+            return name.to_string();
+        };
+        let root = self.find(&parent);
+        self.parents.insert(name.to_string(), root.clone());
+        root
+    }
+
+    fn join(&mut self, a: &str, b: &str) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parents.insert(a, b);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::types;
+
+/// One piece of the initial contents of an object with static storage
+/// duration, laid out in memory one after the other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaticInit {
+    /// A byte, which is how a `_Bool` is stored.
+    UChar(u8),
+    Int(i32),
+    Long(i64),
+    UInt(u32),
+    ULong(u64),
+    Float(f32),
+    Double(f64),
+    /// `bytes` zero bytes.
+    Zero(i64),
+    /// The address of the static object or function `name` plus `offset`
+    /// bytes, which the linker fills in.
+    Address {
+        name: String,
+        offset: i64,
+    },
+}
+
+impl StaticInit {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            StaticInit::UChar(v) => *v == 0,
+            StaticInit::Int(v) => *v == 0,
+            StaticInit::Long(v) => *v == 0,
+            StaticInit::UInt(v) => *v == 0,
+            StaticInit::ULong(v) => *v == 0,
+            // Negative zero has its sign bit set.
+            StaticInit::Float(v) => v.to_bits() == 0,
+            StaticInit::Double(v) => v.to_bits() == 0,
+            StaticInit::Zero(_) => true,
+            StaticInit::Address { .. } => false,
+        }
+    }
+}
+
+/// Where the members of a structure type are, and how big it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLayout {
+    /// The members in declaration order. Unnamed bit-fields only take up
+    /// space, so are left out.
+    pub members: Vec<Member>,
+    pub size: i64,
+    pub alignment: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub typ: types::T,
+    /// Bytes from the start of the structure. For a bit-field, the start of
+    /// the unit that holds it.
+    pub offset: i64,
+    pub bit_field: Option<BitField>,
+}
+
+/// Where the bits of a bit-field are in the unit that holds it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitField {
+    /// Bits from the least significant bit of the unit.
+    pub shift: i64,
+    pub width: i64,
+    /// Bytes in the unit: as many as in the declared type, or in a packed
+    /// structure just the bytes the bit-field covers.
+    pub size: i64,
+}
+
+impl StructLayout {
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+impl Member {
+    /// The type of the member's value. A bit-field narrower than an int
+    /// reads as an int, as does one that is exactly as wide as an int if it
+    /// is signed, since every value it holds fits.
+    pub fn value_type(&self) -> types::T {
+        let Some(field) = self.bit_field else {
+            return self.typ.clone();
+        };
+        let typ = match self.typ.unqualified() {
+            types::T::Bool => types::T::Bool,
+            _ if field.width < 32 => types::T::Int,
+            typ if field.width == 32 && typ.is_signed() => types::T::Int,
+            _ if field.width == 32 => types::T::UInt,
+            typ => typ.clone(),
+        };
+        typ.qualified(self.typ.qualifiers())
+    }
+}
+
+/// The type of every function, variable and TACKY temporary in the program,
+/// keyed by its unique name. Built by the type checker and extended during
+/// TACKY generation.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, types::T>,
+    /// The objects with static storage duration, with their initial contents
+    /// once a declaration has given them any.
+    statics: HashMap<String, Option<Vec<StaticInit>>>,
+    /// The alignment requested for variables declared with `_Alignas`, and
+    /// for variables and functions declared with the `aligned` attribute.
+    alignments: HashMap<String, i64>,
+    /// The section named by the `section` attribute of a variable or
+    /// function, for those that should not go in the usual one.
+    sections: HashMap<String, String>,
+    /// The functions declared `noreturn`.
+    noreturn: HashSet<String>,
+    /// The layout of every structure type that has been defined, keyed by
+    /// its tag as the resolver made it unique.
+    structures: HashMap<String, StructLayout>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: String, typ: types::T) {
+        self.symbols.insert(name, typ);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&types::T> {
+        self.symbols.get(name)
+    }
+
+    /// Look up a symbol that earlier passes guarantee to exist.
+    pub fn type_of(&self, name: &str) -> &types::T {
+        self.symbols
+            .get(name)
+            .unwrap_or_else(|| panic!("Symbol {name} is missing from the symbol table"))
+    }
+
+    /// Records that `name` has static storage duration. A declaration without
+    /// an initializer leaves any earlier initial contents in place.
+    pub fn declare_static(&mut self, name: String, init: Option<Vec<StaticInit>>) {
+        let entry = self.statics.entry(name).or_default();
+        if init.is_some() {
+            *entry = init;
+        }
+    }
+
+    pub fn is_static(&self, name: &str) -> bool {
+        self.statics.contains_key(name)
+    }
+
+    /// Requires the variable or function `name` to be aligned to at least
+    /// `alignment` bytes. The strictest requirement of all its declarations wins.
+    pub fn require_alignment(&mut self, name: String, alignment: i64) {
+        let entry = self.alignments.entry(name).or_insert(alignment);
+        *entry = (*entry).max(alignment);
+    }
+
+    /// The alignment requested for the variable or function `name`, beyond what its type
+    /// needs, if any.
+    pub fn requested_alignment(&self, name: &str) -> Option<i64> {
+        self.alignments.get(name).copied()
+    }
+
+    /// Places the variable or function `name` in the section `section`. A
+    /// symbol can only be in one section, so this fails if an earlier
+    /// declaration named another one.
+    pub fn place_in_section(&mut self, name: String, section: String) -> Result<(), String> {
+        match self.sections.get(&name) {
+            Some(previous) if *previous != section => Err(previous.clone()),
+            _ => {
+                self.sections.insert(name, section);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn section(&self, name: &str) -> Option<&str> {
+        self.sections.get(name).map(String::as_str)
+    }
+
+    pub fn declare_noreturn(&mut self, name: String) {
+        self.noreturn.insert(name);
+    }
+
+    /// Whether any declaration of the function `name` says it never returns.
+    pub fn is_noreturn(&self, name: &str) -> bool {
+        self.noreturn.contains(name)
+    }
+
+    pub fn define_structure(&mut self, tag: String, layout: StructLayout) {
+        self.structures.insert(tag, layout);
+    }
+
+    /// The layout of the structure `tag`, or `None` while it is incomplete.
+    pub fn structure(&self, tag: &str) -> Option<&StructLayout> {
+        self.structures.get(tag)
+    }
+
+    /// The layout of a structure type that earlier passes guarantee to be
+    /// complete.
+    pub fn layout_of(&self, typ: &types::T) -> &StructLayout {
+        match typ.unqualified() {
+            types::T::Structure(tag) => self
+                .structure(tag)
+                .unwrap_or_else(|| panic!("Structure {tag} has no layout")),
+            other => panic!("{:?} is not a structure type", other),
+        }
+    }
+
+    /// The member `name` of a structure type, which the type checker has
+    /// made sure exists.
+    pub fn member(&self, structure: &types::T, name: &str) -> &Member {
+        self.layout_of(structure)
+            .member(name)
+            .unwrap_or_else(|| panic!("{:?} has no member {name}", structure))
+    }
+
+    /// Whether objects of type `typ` have a known size.
+    pub fn is_complete(&self, typ: &types::T) -> bool {
+        match typ.unqualified() {
+            types::T::Void | types::T::FunType { .. } => false,
+            types::T::Structure(tag) => self.structures.contains_key(tag),
+            types::T::Array { typ, .. } | types::T::VariableArray { typ, .. } => {
+                self.is_complete(typ)
+            }
+            _ => true,
+        }
+    }
+
+    /// The size of `typ` in bytes, like `T::size` but also for structures
+    /// and arrays of them.
+    pub fn size_of(&self, typ: &types::T) -> i64 {
+        match typ.unqualified() {
+            types::T::Structure(_) => self.layout_of(typ).size,
+            types::T::Array { typ, size } => self.size_of(typ) * size,
+            typ => typ.size(),
+        }
+    }
+
+    /// The alignment of `typ` in bytes, like `T::alignment` but also for
+    /// structures and arrays of them.
+    pub fn alignment_of(&self, typ: &types::T) -> i64 {
+        match typ.unqualified() {
+            types::T::Structure(_) => self.layout_of(typ).alignment,
+            types::T::Array { typ, .. } | types::T::VariableArray { typ, .. } => {
+                self.alignment_of(typ)
+            }
+            typ => typ.alignment(),
+        }
+    }
+
+    /// The initial contents of a static object, or `None` if no declaration
+    /// has initialized it, in which case it starts out zeroed.
+    pub fn static_init(&self, name: &str) -> Option<&[StaticInit]> {
+        self.statics.get(name).and_then(|init| init.as_deref())
+    }
+}
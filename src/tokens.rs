@@ -21,39 +21,88 @@ pub enum TokenKind {
     ConstLong(i64),
     ConstUInt(u32),
     ConstULong(u64),
+    ConstFloat(f32),
     ConstDouble(f64),
-    // StringLiteral(String),
+    StringLiteral(String),
 
     // Keywords
     Int,
+    Long,
+    Signed,
+    Unsigned,
+    Float,
+    Double,
+    Bool,
     Void,
+    Const,
+    Volatile,
     Return,
+    Asm,
     Struct,
+    Sizeof,
+    StaticAssert,
+    Alignof,
+    Alignas,
+    // GNU extensions that system headers rely on.
+    Attribute,
+    Extension,
+    Restrict,
+    Inline,
+    Noreturn,
+    // The builtins behind <stdarg.h>.
+    VaList,
+    VaStart,
+    VaArg,
+    VaEnd,
 
     // Delimiters
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
-    // LeftBracket,
-    // RightBracket,
+    LeftBracket,
+    RightBracket,
     Semicolon,
     Comma,
+    Dot,
+    Ellipsis,
+    Arrow,
+    Question,
+    Colon,
     // Operators
-    // Plus,
+    Plus,
+    PlusPlus,
     Minus,
     MinusMinus,
     Tilde,
-    // Star,
-    // Slash,
-    // Equal,
-    // EqualEqual,
-    // BangEqual,
-    // Bang,
-    // Greater,
-    // Less,
-    // GreaterEqual,
-    // LessEqual,
+    Star,
+    Slash,
+    Percent,
+    Ampersand,
+    AmpersandAmpersand,
+    Pipe,
+    PipePipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+    Equal,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpersandEqual,
+    PipeEqual,
+    CaretEqual,
+    LessLessEqual,
+    GreaterGreaterEqual,
+    EqualEqual,
+    BangEqual,
+    Bang,
+    Greater,
+    Less,
+    GreaterEqual,
+    LessEqual,
 
     // Special
     EOF,
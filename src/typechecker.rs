@@ -0,0 +1,2250 @@
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        AsmStatement, Attributes, BinaryOperator, Block, BlockItem, Declaration, Designator,
+        Expression, FunctionDeclaration, Identifier, Initializer, MemberDeclaration, Program,
+        Statement, StaticAssertion, StructDeclaration, UnaryOperator, VariableDeclaration,
+    },
+    const_eval,
+    errors::CompilerError,
+    inline_asm::{self, Clobber, Constraint, Placement},
+    symbols::{BitField, Member, StaticInit, StructLayout, SymbolTable},
+    types,
+};
+
+/// Records the type of every declaration in the symbol table and makes every
+/// implicit conversion in the program explicit by wrapping it in a
+/// `Expression::Cast`, so later passes can read types straight off the tree.
+#[derive(Debug, Clone, Default)]
+pub struct TypeChecker {
+    symbols: SymbolTable,
+    return_type: Option<types::T>,
+    /// Whether the function being checked takes `...`, so may use `va_start`.
+    variadic: bool,
+    /// Whether the function being checked is declared `noreturn`, so may not
+    /// contain a `return` statement.
+    noreturn: bool,
+    defined_functions: HashSet<String>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn check_program(&mut self, program: &Program) -> Result<Program, CompilerError> {
+        let mut decls = Vec::new();
+
+        for decl in &program.0 {
+            let checked = match decl {
+                Declaration::FunDecl(func) => Declaration::FunDecl(self.check_function(func)?),
+                Declaration::VarDecl(var) => Declaration::VarDecl(self.check_global_var(var)?),
+                Declaration::StructDecl(structure) => {
+                    self.check_struct_decl(structure)?;
+                    decl.clone()
+                }
+                Declaration::StaticAssert(assertion) => {
+                    Declaration::StaticAssert(self.check_static_assert(assertion)?)
+                }
+            };
+            decls.push(checked);
+        }
+
+        Ok(Program(decls))
+    }
+
+    /// A variable at file scope has static storage duration, so its initial
+    /// value is worked out here and stored in the data section. It may be
+    /// declared more than once, but only initialized once.
+    fn check_global_var(
+        &mut self,
+        decl: &VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompilerError> {
+        let decl = &self.complete_length(decl)?;
+        check_object_type(&decl.name, &decl.typ, &self.symbols)?;
+        if decl.typ.is_variably_modified() {
+            return Err(CompilerError::SemanticError {
+                message: format!("'{}' has a variably modified type at file scope", decl.name),
+            });
+        }
+
+        if let Some(previous) = self.symbols.get(&decl.name)
+            && (*previous != decl.typ || !self.symbols.is_static(&decl.name))
+        {
+            return Err(CompilerError::SemanticError {
+                message: format!("conflicting declarations of '{}'", decl.name),
+            });
+        }
+
+        if decl.init.is_some() && self.symbols.static_init(&decl.name).is_some() {
+            return Err(CompilerError::SemanticError {
+                message: format!("redefinition of '{}'", decl.name),
+            });
+        }
+
+        self.symbols.insert(decl.name.clone(), decl.typ.clone());
+        self.check_alignment(decl)?;
+        self.check_attributes(&decl.name, &decl.attributes, false)?;
+
+        let init = match &decl.init {
+            Some(init) => Some(self.check_initializer(init, &decl.typ)?),
+            None => None,
+        };
+        let static_init = match &init {
+            Some(init) => Some(self.static_initializer(init, &decl.typ)?),
+            None => None,
+        };
+        self.symbols.declare_static(decl.name.clone(), static_init);
+
+        Ok(VariableDeclaration {
+            init,
+            ..decl.clone()
+        })
+    }
+
+    fn check_function(
+        &mut self,
+        func: &FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompilerError> {
+        // `(void)` is recorded as a single unnamed void parameter.
+        let params = func
+            .params
+            .iter()
+            .filter(|p| p.typ != types::T::Void)
+            .collect::<Vec<_>>();
+
+        // Qualifiers on a parameter only matter inside the function, and
+        // those on the return value not at all.
+        let return_type = func.return_type.unqualified().clone();
+        let fun_type = types::T::FunType {
+            params: params.iter().map(|p| p.typ.unqualified().clone()).collect(),
+            ret: Box::new(return_type.clone()),
+            variadic: func.variadic,
+        };
+
+        if passes_structure(&fun_type) {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "function '{}' passes a structure by value, which is not supported",
+                    func.name
+                ),
+            });
+        }
+
+        if let Some(previous) = self.symbols.get(&func.name)
+            && *previous != fun_type
+        {
+            return Err(CompilerError::SemanticError {
+                message: format!("conflicting declarations of function '{}'", func.name),
+            });
+        }
+
+        if func.body.is_some() && !self.defined_functions.insert(func.name.clone()) {
+            return Err(CompilerError::SemanticError {
+                message: format!("function '{}' is defined more than once", func.name),
+            });
+        }
+
+        self.symbols.insert(func.name.clone(), fun_type);
+        self.check_attributes(&func.name, &func.attributes, false)?;
+        if func.attributes.noreturn {
+            self.symbols.declare_noreturn(func.name.clone());
+        }
+
+        for param in params.iter().filter(|p| !p.name.is_empty()) {
+            self.symbols.insert(param.name.clone(), param.typ.clone());
+        }
+
+        // A declaration without a body may be inside another function, whose
+        // body is still being checked.
+        let body = match &func.body {
+            Some(block) => {
+                self.return_type = Some(return_type.clone());
+                self.variadic = func.variadic;
+                self.noreturn = self.symbols.is_noreturn(&func.name);
+                let body = self.check_block(block)?;
+                self.return_type = None;
+                Some(body)
+            }
+            None => None,
+        };
+
+        Ok(FunctionDeclaration {
+            return_type,
+            body,
+            ..func.clone()
+        })
+    }
+
+    fn check_block(&mut self, block: &Block) -> Result<Block, CompilerError> {
+        let mut items = Vec::new();
+
+        for item in &block.0 {
+            let checked = match item {
+                BlockItem::Stmt(stmt) => BlockItem::Stmt(self.check_statement(stmt)?),
+                BlockItem::Decl(Declaration::VarDecl(decl)) => {
+                    BlockItem::Decl(Declaration::VarDecl(self.check_var_decl(decl)?))
+                }
+                BlockItem::Decl(Declaration::StaticAssert(assertion)) => BlockItem::Decl(
+                    Declaration::StaticAssert(self.check_static_assert(assertion)?),
+                ),
+                BlockItem::Decl(Declaration::FunDecl(func)) => {
+                    BlockItem::Decl(Declaration::FunDecl(self.check_function(func)?))
+                }
+                BlockItem::Decl(Declaration::StructDecl(structure)) => {
+                    self.check_struct_decl(structure)?;
+                    item.clone()
+                }
+            };
+            items.push(checked);
+        }
+
+        Ok(Block(items))
+    }
+
+    fn check_var_decl(
+        &mut self,
+        decl: &VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompilerError> {
+        let decl = &self.complete_length(decl)?;
+        check_object_type(&decl.name, &decl.typ, &self.symbols)?;
+
+        // Each length becomes a long, held in its own variable.
+        let mut lengths = Vec::new();
+        for (count, length) in &decl.lengths {
+            let (length, typ) = self.check_expression(length)?;
+            if !typ.is_integer() {
+                return Err(CompilerError::SemanticError {
+                    message: format!("size of variable length array has type {:?}", typ),
+                });
+            }
+            self.symbols.insert(count.clone(), types::T::Long);
+            lengths.push((count.clone(), convert_to(length, &typ, &types::T::Long)));
+        }
+
+        self.symbols.insert(decl.name.clone(), decl.typ.clone());
+        self.check_alignment(decl)?;
+        self.check_attributes(&decl.name, &decl.attributes, true)?;
+
+        if decl.init.is_some() && decl.typ.has_variable_size() {
+            return Err(CompilerError::SemanticError {
+                message: "a variable length array cannot be initialized".into(),
+            });
+        }
+        let init = match &decl.init {
+            Some(init) => Some(self.check_initializer(init, &decl.typ)?),
+            None => None,
+        };
+
+        Ok(VariableDeclaration {
+            init,
+            lengths,
+            ..decl.clone()
+        })
+    }
+
+    /// An alignment requested with `_Alignas` may only make a variable more
+    /// strictly aligned than its type.
+    fn check_alignment(&mut self, decl: &VariableDeclaration) -> Result<(), CompilerError> {
+        let Some(alignment) = decl.alignment else {
+            return Ok(());
+        };
+
+        let minimum = self.symbols.alignment_of(&decl.typ);
+        if alignment < minimum {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "requested alignment {alignment} of '{}' is less than the {minimum} its type requires",
+                    source_name(&decl.name)
+                ),
+            });
+        }
+
+        self.symbols.require_alignment(decl.name.clone(), alignment);
+        Ok(())
+    }
+
+    /// Lays out the members of a structure definition in order, each at the
+    /// next offset its alignment allows. As the System V ABI has it, a
+    /// bit-field goes in the bits that follow unless that would take it
+    /// across a boundary of its declared type's alignment, and one of zero
+    /// width skips to the next such boundary. A member may ask for a
+    /// stricter alignment than its type's with `_Alignas` or the `aligned`
+    /// attribute, while a `packed` one needs none, and a packed bit-field
+    /// goes in the very next bit. The structure is as strictly aligned as its
+    /// strictest named member, or as its own `aligned` attribute asks, and
+    /// padded to a multiple of that.
+    fn check_struct_decl(&mut self, structure: &StructDeclaration) -> Result<(), CompilerError> {
+        let Some(members) = &structure.members else {
+            return Ok(());
+        };
+        let tag = source_tag(&structure.tag);
+        if self.symbols.structure(&structure.tag).is_some() {
+            return Err(CompilerError::SemanticError {
+                message: format!("redefinition of structure '{tag}'"),
+            });
+        }
+
+        let mut layout = StructLayout {
+            members: Vec::new(),
+            size: 0,
+            alignment: 1,
+        };
+        // Counted in bits, since a bit-field may end part way into a byte.
+        let mut end = 0;
+        for member in members {
+            if !member.name.is_empty() && layout.member(&member.name).is_some() {
+                return Err(CompilerError::SemanticError {
+                    message: format!("duplicate member '{}' in structure '{tag}'", member.name),
+                });
+            }
+            if !self.symbols.is_complete(&member.typ) {
+                return Err(CompilerError::SemanticError {
+                    message: format!(
+                        "structure member '{}' has incomplete type {:?}",
+                        member.name, member.typ
+                    ),
+                });
+            }
+
+            let natural = self.symbols.alignment_of(&member.typ);
+            let packed = structure.attributes.packed || member.attributes.packed;
+            let mut alignment = if packed { 1 } else { natural };
+            if let Some(requested) = member.alignment {
+                if requested < natural {
+                    return Err(CompilerError::SemanticError {
+                        message: format!(
+                            "requested alignment {requested} of member '{}' is less than the {natural} its type requires",
+                            member.name
+                        ),
+                    });
+                }
+                alignment = alignment.max(requested);
+            }
+            if let Some(aligned) = member.attributes.aligned {
+                alignment = alignment.max(aligned);
+            }
+            let Some(width) = member.width else {
+                let offset = round_up((end + 7) / 8, alignment);
+                end = (offset + self.symbols.size_of(&member.typ)) * 8;
+                layout.alignment = layout.alignment.max(alignment);
+                layout.members.push(Member {
+                    name: member.name.clone(),
+                    typ: member.typ.clone(),
+                    offset,
+                    bit_field: None,
+                });
+                continue;
+            };
+
+            check_bit_field(member, width)?;
+            let unit = natural * 8;
+            if width == 0 || (!packed && end / unit != (end + width - 1) / unit) {
+                end = round_up(end, unit);
+            }
+            if let Some(aligned) = member.attributes.aligned {
+                end = round_up(end, aligned * 8);
+            }
+            if !member.name.is_empty() {
+                // A packed bit-field is read and written by way of just the
+                // bytes it covers, as it may start part way into any of them.
+                let (offset, size) = if packed {
+                    (end / 8, (end % 8 + width + 7) / 8)
+                } else {
+                    (end / unit * natural, self.symbols.size_of(&member.typ))
+                };
+                if size > 8 {
+                    return Err(CompilerError::SemanticError {
+                        message: format!(
+                            "bit-field '{}' of packed structure '{tag}' spans {size} bytes, which is not supported",
+                            member.name
+                        ),
+                    });
+                }
+                layout.alignment = layout.alignment.max(alignment);
+                layout.members.push(Member {
+                    name: member.name.clone(),
+                    typ: member.typ.clone(),
+                    offset,
+                    bit_field: Some(BitField {
+                        shift: end - offset * 8,
+                        width,
+                        size,
+                    }),
+                });
+            }
+            end += width;
+        }
+        if let Some(aligned) = structure.attributes.aligned {
+            layout.alignment = layout.alignment.max(aligned);
+        }
+        layout.size = round_up((end + 7) / 8, layout.alignment);
+
+        self.symbols.define_structure(structure.tag.clone(), layout);
+        Ok(())
+    }
+
+    /// Records the alignment and section that the attributes of a variable
+    /// or function ask for. Automatic variables live in the frame, which is
+    /// not in any section.
+    fn check_attributes(
+        &mut self,
+        name: &str,
+        attributes: &Attributes,
+        automatic: bool,
+    ) -> Result<(), CompilerError> {
+        if let Some(alignment) = attributes.aligned {
+            self.symbols.require_alignment(name.to_string(), alignment);
+        }
+
+        if let Some(section) = &attributes.section {
+            if automatic {
+                return Err(CompilerError::SemanticError {
+                    message: "a local variable cannot be placed in a section".into(),
+                });
+            }
+            if let Err(previous) = self
+                .symbols
+                .place_in_section(name.to_string(), section.clone())
+            {
+                return Err(CompilerError::SemanticError {
+                    message: format!(
+                        "'{name}' is placed in section \"{section}\", but was declared in \"{previous}\""
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The condition has to be an integer constant expression. A nonzero
+    /// value passes, and zero stops compilation with the message.
+    fn check_static_assert(
+        &mut self,
+        assertion: &StaticAssertion,
+    ) -> Result<StaticAssertion, CompilerError> {
+        let (condition, _) = self.check_expression(&assertion.condition)?;
+        match const_eval::evaluate_integer(&condition)? {
+            Some(0) => Err(CompilerError::SemanticError {
+                message: match &assertion.message {
+                    Some(message) => format!("static assertion failed: \"{message}\""),
+                    None => "static assertion failed".into(),
+                },
+            }),
+            Some(_) => Ok(StaticAssertion {
+                condition,
+                ..assertion.clone()
+            }),
+            None => Err(CompilerError::SemanticError {
+                message: "static assertion expression is not an integer constant expression".into(),
+            }),
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) -> Result<Statement, CompilerError> {
+        let return_type = self
+            .return_type
+            .clone()
+            .expect("statements only appear inside functions");
+
+        match stmt {
+            Statement::ReturnStatement(_) if self.noreturn => Err(CompilerError::SemanticError {
+                message: "function declared noreturn has a return statement".into(),
+            }),
+            Statement::ReturnStatement(Some(_)) if return_type == types::T::Void => {
+                Err(CompilerError::SemanticError {
+                    message: "void function returns a value".into(),
+                })
+            }
+            Statement::ReturnStatement(Some(expr)) => Ok(Statement::ReturnStatement(Some(
+                self.check_and_convert(expr, &return_type)?,
+            ))),
+            Statement::ReturnStatement(None) if return_type != types::T::Void => {
+                Err(CompilerError::SemanticError {
+                    message: "non-void function returns without a value".into(),
+                })
+            }
+            Statement::ReturnStatement(None) => Ok(stmt.clone()),
+            Statement::ExpressionStatement(expr) => {
+                let (expr, _) = self.check_expression(expr)?;
+                Ok(Statement::ExpressionStatement(expr))
+            }
+            Statement::NullStatement => Ok(Statement::NullStatement),
+            Statement::Asm(asm) => Ok(Statement::Asm(self.check_asm(asm)?)),
+        }
+    }
+
+    /// Checks the operands of an `asm` statement against their constraints
+    /// and makes sure registers can be found for all of them. Constant
+    /// inputs are folded, so they reach code generation as constants.
+    fn check_asm(&mut self, asm: &AsmStatement) -> Result<AsmStatement, CompilerError> {
+        let mut checked = asm.clone();
+        let mut output_constraints = Vec::new();
+        for (i, output) in checked.outputs.iter_mut().enumerate() {
+            let constraint = Constraint::parse(&output.constraint, true)?;
+            let (expr, typ) = if constraint.placement == Placement::Memory {
+                self.check_designator(&output.expr)?
+            } else {
+                self.check_expression(&output.expr)?
+            };
+            self.check_modifiable(&expr)?;
+            if constraint.placement == Placement::Memory && is_bit_field(&expr, &self.symbols) {
+                return Err(CompilerError::SemanticError {
+                    message: format!("memory asm operand {i} is a bit-field"),
+                });
+            }
+            check_asm_operand(i, &constraint, &typ)?;
+
+            output.expr = expr;
+            output_constraints.push(constraint);
+        }
+
+        let mut input_constraints = Vec::new();
+        for (i, input) in checked.inputs.iter_mut().enumerate() {
+            let i = asm.outputs.len() + i;
+            let constraint = Constraint::parse(&input.constraint, false)?;
+            let (expr, typ) = match constraint.placement {
+                Placement::Memory => {
+                    let (expr, typ) = self.check_designator(&input.expr)?;
+                    if !is_lvalue(&expr) {
+                        return Err(CompilerError::SemanticError {
+                            message: format!("memory asm operand {i} is not an lvalue"),
+                        });
+                    }
+                    if is_bit_field(&expr, &self.symbols) {
+                        return Err(CompilerError::SemanticError {
+                            message: format!("memory asm operand {i} is a bit-field"),
+                        });
+                    }
+                    (expr, typ)
+                }
+                Placement::Immediate => {
+                    let (expr, typ) = self.check_expression(&input.expr)?;
+                    match const_eval::evaluate_integer(&expr)? {
+                        Some(value) if typ.is_integer() => {
+                            (Expression::ConstLong(value), types::T::Long)
+                        }
+                        _ => {
+                            return Err(CompilerError::SemanticError {
+                                message: format!(
+                                    "asm operand {i} has to be an integer constant expression"
+                                ),
+                            });
+                        }
+                    }
+                }
+                _ => self.check_expression(&input.expr)?,
+            };
+            check_asm_operand(i, &constraint, &typ)?;
+
+            input.expr = expr;
+            input_constraints.push(constraint);
+        }
+
+        let mut clobbered = Vec::new();
+        for clobber in &asm.clobbers {
+            if let Clobber::Register(reg) = Clobber::parse(clobber)? {
+                clobbered.push(reg);
+            }
+        }
+        inline_asm::assign_locations(&output_constraints, &input_constraints, &clobbered)?;
+
+        let names: Vec<_> = asm
+            .outputs
+            .iter()
+            .chain(&asm.inputs)
+            .map(|o| o.name.clone())
+            .collect();
+        inline_asm::substitute(&asm.template, &names, |_, _| String::new())?;
+
+        Ok(checked)
+    }
+
+    /// Type check `expr` and convert it to `target` as if by assignment.
+    fn check_and_convert(
+        &mut self,
+        expr: &Expression,
+        target: &types::T,
+    ) -> Result<Expression, CompilerError> {
+        let (expr, typ) = self.check_expression(expr)?;
+        convert_by_assignment(expr, &typ, target)
+    }
+
+    /// Type check the initializer of an object of type `typ`. Designators and
+    /// elided braces are resolved, so in the result every element of every
+    /// array and every member of every structure has exactly one item, in
+    /// order, and the ones the initializer leaves out are zeroed.
+    fn check_initializer(
+        &mut self,
+        init: &Initializer,
+        typ: &types::T,
+    ) -> Result<Initializer, CompilerError> {
+        match (init, typ.unqualified()) {
+            (Initializer::Single(_), types::T::Array { .. }) => Err(CompilerError::SemanticError {
+                message: "an array must be initialized with a brace enclosed list".into(),
+            }),
+            (Initializer::Single(expr), typ) => {
+                Ok(Initializer::Single(self.check_and_convert(expr, typ)?))
+            }
+            (
+                Initializer::Compound(items),
+                aggregate @ (types::T::Array { .. } | types::T::Structure(_)),
+            ) => {
+                let mut checked = Initializer::Zero(typ.clone());
+                let paths = self.initializer_paths(items, typ)?;
+
+                for ((_, item), path) in items.iter().zip(paths) {
+                    if path[0] >= subobject_count(aggregate, &self.symbols) {
+                        let kind = match aggregate {
+                            types::T::Array { .. } => "array",
+                            _ => "structure",
+                        };
+                        return Err(CompilerError::SemanticError {
+                            message: format!("excess elements in {kind} initializer"),
+                        });
+                    }
+
+                    let subobject = subobject_type(typ, &path, &self.symbols).clone();
+                    let item = self.check_initializer(item, &subobject)?;
+                    *subobject_mut(&mut checked, &path, &self.symbols) = item;
+                }
+
+                Ok(checked)
+            }
+            (Initializer::Compound(items), typ) => match items.as_slice() {
+                [(designators, item)] if designators.is_empty() => {
+                    self.check_initializer(item, typ)
+                }
+                [] => Err(CompilerError::SemanticError {
+                    message: "empty scalar initializer".into(),
+                }),
+                [(designators, _)] if !designators.is_empty() => {
+                    Err(CompilerError::SemanticError {
+                        message: format!("designator in initializer for scalar type {:?}", typ),
+                    })
+                }
+                _ => Err(CompilerError::SemanticError {
+                    message: "excess elements in scalar initializer".into(),
+                }),
+            },
+            (Initializer::Zero(_), _) => Ok(init.clone()),
+        }
+    }
+
+    /// The subobject of an aggregate of type `typ` that each item of a brace
+    /// enclosed list initializes. The bounds of the outermost aggregate are
+    /// left to the caller to check.
+    fn initializer_paths(
+        &mut self,
+        items: &[(Vec<Designator>, Initializer)],
+        typ: &types::T,
+    ) -> Result<Vec<Vec<i64>>, CompilerError> {
+        let mut paths = Vec::new();
+        let mut next = vec![0];
+
+        for (designators, item) in items {
+            let mut path = if designators.is_empty() {
+                next
+            } else {
+                self.designated_path(designators, typ)?
+            };
+
+            // Without braces of its own, an item initializes the first
+            // scalar of the aggregate it lands on, and the ones after it
+            // take the following scalars. A structure can also be
+            // initialized as a whole from a value of its type.
+            if let Initializer::Single(expr) = item
+                && path[0] < subobject_count(typ, &self.symbols)
+            {
+                let mut expr_type = None;
+                loop {
+                    match subobject_type(typ, &path, &self.symbols)
+                        .unqualified()
+                        .clone()
+                    {
+                        types::T::Array { .. } => {}
+                        structure @ types::T::Structure(_) => {
+                            let expr_type = match &expr_type {
+                                Some(expr_type) => expr_type,
+                                None => expr_type.insert(self.check_expression(expr)?.1),
+                            };
+                            if *expr_type == structure {
+                                break;
+                            }
+                        }
+                        _ => break,
+                    }
+                    path.push(0);
+                }
+            }
+
+            next = next_subobject(typ, path.clone(), &self.symbols);
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+
+    /// The declaration with the length of its array filled in if it was
+    /// left out: one more than the highest element its initializer sets.
+    fn complete_length(
+        &mut self,
+        decl: &VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompilerError> {
+        if !decl.length_omitted {
+            return Ok(decl.clone());
+        }
+
+        let items = match &decl.init {
+            Some(Initializer::Compound(items)) => items,
+            Some(_) => {
+                return Err(CompilerError::SemanticError {
+                    message: "an array must be initialized with a brace enclosed list".into(),
+                });
+            }
+            None => {
+                return Err(CompilerError::SemanticError {
+                    message: format!("array size missing in '{}'", decl.name),
+                });
+            }
+        };
+        // As long as any index could be, so that only the element type
+        // limits where the items go.
+        let unbounded = types::T::Array {
+            typ: Box::new(decl.typ.clone()),
+            size: i64::MAX,
+        };
+        let size = self
+            .initializer_paths(items, &unbounded)?
+            .iter()
+            .map(|path| path[0] + 1)
+            .max()
+            .unwrap_or(0);
+        if size == 0 {
+            return Err(CompilerError::SemanticError {
+                message: format!("array '{}' has no elements", decl.name),
+            });
+        }
+
+        Ok(VariableDeclaration {
+            typ: types::T::Array {
+                typ: Box::new(decl.typ.clone()),
+                size,
+            },
+            length_omitted: false,
+            ..decl.clone()
+        })
+    }
+
+    /// The position within an object of type `typ` that a run of designators
+    /// picks out, as the index into each level of nested array or the
+    /// position of the member in each level of nested structure.
+    fn designated_path(
+        &mut self,
+        designators: &[Designator],
+        typ: &types::T,
+    ) -> Result<Vec<i64>, CompilerError> {
+        let mut path = Vec::new();
+        let mut current = typ.unqualified().clone();
+
+        for designator in designators {
+            match (designator, &current) {
+                (Designator::Index(index), types::T::Array { typ, size }) => {
+                    let (index, _) = self.check_expression(index)?;
+                    let value = match const_eval::evaluate_integer(&index)? {
+                        Some(value) => value,
+                        None => {
+                            return Err(CompilerError::SemanticError {
+                                message: "array index in initializer is not an integer constant"
+                                    .into(),
+                            });
+                        }
+                    };
+                    if value < 0 || value >= *size {
+                        return Err(CompilerError::SemanticError {
+                            message: format!(
+                                "array index {value} in initializer exceeds array bounds"
+                            ),
+                        });
+                    }
+
+                    path.push(value);
+                    current = typ.unqualified().clone();
+                }
+                (Designator::Index(_), _) => {
+                    return Err(CompilerError::SemanticError {
+                        message: format!("array index in initializer for type {:?}", current),
+                    });
+                }
+                (Designator::Member(member), types::T::Structure(tag)) => {
+                    let layout = self.symbols.layout_of(&current);
+                    let Some(index) = layout.members.iter().position(|m| m.name == *member) else {
+                        return Err(CompilerError::SemanticError {
+                            message: format!(
+                                "structure '{}' has no member named '{member}'",
+                                source_tag(tag)
+                            ),
+                        });
+                    };
+
+                    path.push(index as i64);
+                    current = layout.members[index].typ.unqualified().clone();
+                }
+                (Designator::Member(member), _) => {
+                    return Err(CompilerError::SemanticError {
+                        message: format!(
+                            "member designator '.{member}' in initializer for non-structure type {:?}",
+                            current
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// The initial contents of a static object of type `typ` with the checked
+    /// initializer `init`. Every scalar in it has to be a constant, or an
+    /// address the linker can work out.
+    fn static_initializer(
+        &self,
+        init: &Initializer,
+        typ: &types::T,
+    ) -> Result<Vec<StaticInit>, CompilerError> {
+        let mut data = Vec::new();
+        self.collect_static_init(init, typ, &mut data)?;
+
+        // Merge runs of zeroes, so a mostly empty array takes a single `.zero`.
+        let mut merged: Vec<StaticInit> = Vec::new();
+        for item in data {
+            match (merged.last_mut(), item) {
+                (Some(StaticInit::Zero(bytes)), StaticInit::Zero(more)) => *bytes += more,
+                (_, item) => merged.push(item),
+            }
+        }
+
+        Ok(merged)
+    }
+
+    fn collect_static_init(
+        &self,
+        init: &Initializer,
+        typ: &types::T,
+        data: &mut Vec<StaticInit>,
+    ) -> Result<(), CompilerError> {
+        let not_constant = || {
+            Err(CompilerError::SemanticError {
+                message: "initializer element is not constant".into(),
+            })
+        };
+
+        match init {
+            Initializer::Zero(typ) => data.push(StaticInit::Zero(self.symbols.size_of(typ))),
+            Initializer::Compound(items) => match typ.unqualified() {
+                types::T::Array { typ: element, .. } => {
+                    for (_, item) in items {
+                        self.collect_static_init(item, element, data)?;
+                    }
+                }
+                // The padding between members and after the last is zeroed.
+                // Bit-fields may share bytes, so their bits are gathered
+                // into the bytes that follow `end` until the next member.
+                types::T::Structure(_) => {
+                    let layout = self.symbols.layout_of(typ);
+                    let mut end = 0;
+                    let mut bytes: Vec<u8> = Vec::new();
+                    for ((_, item), member) in items.iter().zip(&layout.members) {
+                        if let Some(field) = member.bit_field {
+                            let value = match item {
+                                Initializer::Single(expr) => {
+                                    match const_eval::evaluate_integer(expr)? {
+                                        Some(value) => value,
+                                        None => return not_constant(),
+                                    }
+                                }
+                                _ => 0,
+                            };
+                            let start = (member.offset - end) * 8 + field.shift;
+                            let len = ((start + field.width + 7) / 8) as usize;
+                            bytes.resize(bytes.len().max(len), 0);
+                            for bit in (0..field.width).filter(|bit| value >> bit & 1 == 1) {
+                                bytes[((start + bit) / 8) as usize] |= 1 << ((start + bit) % 8);
+                            }
+                            continue;
+                        }
+
+                        end += bytes.len() as i64;
+                        data.extend(bytes.drain(..).map(StaticInit::UChar));
+                        if member.offset > end {
+                            data.push(StaticInit::Zero(member.offset - end));
+                        }
+                        self.collect_static_init(item, &member.typ, data)?;
+                        end = member.offset + self.symbols.size_of(&member.typ);
+                    }
+                    end += bytes.len() as i64;
+                    data.extend(bytes.drain(..).map(StaticInit::UChar));
+                    if layout.size > end {
+                        data.push(StaticInit::Zero(layout.size - end));
+                    }
+                }
+                _ => panic!("Compound initializer for {:?} was not checked", typ),
+            },
+            Initializer::Single(expr) => match typ.unqualified() {
+                types::T::Pointer(_) if is_null_pointer_constant(without_pointer_casts(expr)) => {
+                    data.push(StaticInit::ULong(0));
+                }
+                types::T::Pointer(_) => match self.address_constant(expr)? {
+                    Some((name, offset)) => data.push(StaticInit::Address { name, offset }),
+                    None => return not_constant(),
+                },
+                // A pointer converted to `_Bool` is only false when it is null,
+                // and the address of an object never is.
+                types::T::Bool if self.converted_pointer(expr).is_some() => {
+                    let pointer = self.converted_pointer(expr).unwrap();
+                    if is_null_pointer_constant(without_pointer_casts(pointer)) {
+                        data.push(StaticInit::UChar(0));
+                    } else if self.address_constant(pointer)?.is_some() {
+                        data.push(StaticInit::UChar(1));
+                    } else {
+                        return not_constant();
+                    }
+                }
+                typ => match const_eval::evaluate(expr)? {
+                    Some(value) => data.push(value.static_init(typ)),
+                    None => return not_constant(),
+                },
+            },
+        }
+
+        Ok(())
+    }
+
+    /// The pointer that `expr` converts to another type, if it is such a
+    /// conversion.
+    fn converted_pointer<'a>(&self, expr: &'a Expression) -> Option<&'a Expression> {
+        match expr.ungrouped() {
+            Expression::Cast { expr: pointer, .. }
+                if matches!(
+                    expression_type(pointer, &self.symbols),
+                    types::T::Pointer(_)
+                ) =>
+            {
+                Some(pointer)
+            }
+            _ => None,
+        }
+    }
+
+    /// The object or function a pointer valued constant expression points
+    /// into, and the offset in bytes from its start, such as `arr` plus 8 for
+    /// `&arr[2]`. `None` if the expression is not an address constant.
+    fn address_constant(&self, expr: &Expression) -> Result<Option<(String, i64)>, CompilerError> {
+        match expr.ungrouped() {
+            Expression::Cast {
+                typ: types::T::Pointer(_),
+                expr,
+            } => self.address_constant(expr),
+            Expression::AddressOf(inner) => self.lvalue_address(inner),
+            Expression::Binary {
+                operator: operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                left,
+                right,
+            } => {
+                let types::T::Pointer(referenced) = expression_type(left, &self.symbols) else {
+                    return Ok(None);
+                };
+                let (Some((name, offset)), Some(index)) = (
+                    self.address_constant(left)?,
+                    const_eval::evaluate_integer(right)?,
+                ) else {
+                    return Ok(None);
+                };
+                let index = if *operator == BinaryOperator::Subtract {
+                    -index
+                } else {
+                    index
+                };
+                Ok(Some((
+                    name,
+                    offset + index * self.symbols.size_of(&referenced),
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The address of an lvalue designating (part of) a static object or a
+    /// function, as for `address_constant`.
+    fn lvalue_address(&self, expr: &Expression) -> Result<Option<(String, i64)>, CompilerError> {
+        match expr.ungrouped() {
+            Expression::Var(ident)
+                if self.symbols.is_static(&ident.name()) || self.function_name(expr).is_some() =>
+            {
+                Ok(Some((ident.name(), 0)))
+            }
+            Expression::Dereference(pointer) => self.address_constant(pointer),
+            Expression::Subscript {
+                expr: pointer,
+                index,
+            } => {
+                let (Some((name, offset)), Some(index)) = (
+                    self.address_constant(pointer)?,
+                    const_eval::evaluate_integer(index)?,
+                ) else {
+                    return Ok(None);
+                };
+                let element = lvalue_type(expr, &self.symbols);
+                Ok(Some((
+                    name,
+                    offset + index * self.symbols.size_of(&element),
+                )))
+            }
+            Expression::Dot {
+                expr: structure,
+                member,
+            } => {
+                let Some((name, offset)) = self.lvalue_address(structure)? else {
+                    return Ok(None);
+                };
+                let structure = lvalue_type(structure, &self.symbols);
+                Ok(Some((
+                    name,
+                    offset + self.symbols.member(&structure, member).offset,
+                )))
+            }
+            Expression::Arrow {
+                expr: pointer,
+                member,
+            } => {
+                let Some((name, offset)) = self.address_constant(pointer)? else {
+                    return Ok(None);
+                };
+                let types::T::Pointer(structure) = expression_type(pointer, &self.symbols) else {
+                    panic!("'->' applied to a non-pointer");
+                };
+                Ok(Some((
+                    name,
+                    offset + self.symbols.member(&structure, member).offset,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Type check an expression, returning the rewritten expression and its type.
+    /// A function used as a value decays to a pointer to it, and an array to
+    /// a pointer to its first element, which for a `va_list` is its record.
+    fn check_expression(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        match self.check_designator(expr)? {
+            (expr, typ @ types::T::FunType { .. }) => Ok((
+                Expression::AddressOf(Box::new(expr)),
+                types::T::Pointer(Box::new(typ)),
+            )),
+            (
+                expr,
+                typ @ (types::T::Array { .. } | types::T::VariableArray { .. } | types::T::VaList),
+            ) => {
+                let pointer = match typ {
+                    types::T::Array { typ, .. } | types::T::VariableArray { typ, .. } => {
+                        types::T::Pointer(typ)
+                    }
+                    typ => types::T::Pointer(Box::new(typ)),
+                };
+                let decayed = Expression::Cast {
+                    typ: pointer.clone(),
+                    expr: Box::new(Expression::AddressOf(Box::new(expr))),
+                };
+                Ok((decayed, pointer))
+            }
+            checked => Ok(checked),
+        }
+    }
+
+    /// Type check an expression without decaying a function or an array to a
+    /// pointer, as the operands of `&` and `sizeof` are.
+    fn check_designator(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        match expr {
+            Expression::ConstInt(_) => Ok((expr.clone(), types::T::Int)),
+            Expression::ConstLong(_) => Ok((expr.clone(), types::T::Long)),
+            Expression::ConstUInt(_) => Ok((expr.clone(), types::T::UInt)),
+            Expression::ConstULong(_) => Ok((expr.clone(), types::T::ULong)),
+            Expression::ConstFloat(_) => Ok((expr.clone(), types::T::Float)),
+            Expression::ConstDouble(_) => Ok((expr.clone(), types::T::Double)),
+            Expression::StringConstant(_) | Expression::If { .. } => {
+                Err(CompilerError::SemanticError {
+                    message: format!("unsupported expression: {:?}", expr),
+                })
+            }
+            Expression::Var(ident) => match self.symbols.get(&ident.name()) {
+                Some(typ) => Ok((expr.clone(), typ.unqualified().clone())),
+                None => panic!("Variable {} was not resolved", ident.name()),
+            },
+            Expression::Grouping(inner) => {
+                let (inner, typ) = self.check_designator(inner)?;
+                Ok((Expression::Grouping(Box::new(inner)), typ))
+            }
+            Expression::Cast { typ, expr } => {
+                // A cast produces a value, which has no qualifiers.
+                let typ = typ.unqualified();
+                let (inner, inner_type) = self.check_expression(expr)?;
+                // Pointers only convert to and from integers.
+                let pointer_and_floating = (matches!(typ, types::T::Pointer(_))
+                    && inner_type.is_floating())
+                    || (typ.is_floating() && matches!(inner_type, types::T::Pointer(_)));
+                if *typ != types::T::Void
+                    && (!(typ.is_scalar() && inner_type.is_scalar()) || pointer_and_floating)
+                {
+                    return Err(CompilerError::SemanticError {
+                        message: format!("cannot cast {:?} to {:?}", inner_type, typ),
+                    });
+                }
+
+                let cast = Expression::Cast {
+                    typ: typ.clone(),
+                    expr: Box::new(inner),
+                };
+                Ok((cast, typ.clone()))
+            }
+            Expression::Unary { operator, expr } => {
+                let (inner, typ) = match operator {
+                    UnaryOperator::Not => {
+                        let (inner, _) = self.check_scalar_operand(expr)?;
+                        (inner, types::T::Int)
+                    }
+                    UnaryOperator::Complement => promote(self.check_integer_operand(expr)?),
+                    UnaryOperator::Negate | UnaryOperator::Plus => {
+                        promote(self.check_arithmetic_operand(expr)?)
+                    }
+                };
+
+                let unary = Expression::Unary {
+                    operator: *operator,
+                    expr: Box::new(inner),
+                };
+                Ok((unary, typ))
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => self.check_binary(*operator, left, right),
+            Expression::Assignment { lvalue, value } => {
+                let (lvalue, lvalue_type) = self.check_expression(lvalue)?;
+                self.check_modifiable(&lvalue)?;
+                let value = self.check_and_convert(value, &lvalue_type)?;
+                let assignment = Expression::Assignment {
+                    lvalue: Box::new(lvalue),
+                    value: Box::new(value),
+                };
+                Ok((assignment, lvalue_type))
+            }
+            Expression::CompoundAssignment {
+                operator,
+                lvalue,
+                value,
+            } => {
+                let (lvalue, lvalue_type) = self.check_scalar_operand(lvalue)?;
+                self.check_modifiable(&lvalue)?;
+                let (value, value_type) = if operator.requires_integers()
+                    || matches!(lvalue_type, types::T::Pointer(_))
+                {
+                    self.check_integer_operand(value)?
+                } else {
+                    self.check_arithmetic_operand(value)?
+                };
+
+                if operator.requires_integers() && !lvalue_type.is_integer() {
+                    return Err(CompilerError::SemanticError {
+                        message: format!("invalid operands to {:?}: {:?}", operator, lvalue_type),
+                    });
+                }
+
+                // The right hand side is converted to the type the operation is carried
+                // out in, which TACKY generation then reads back off the cast. Pointers
+                // can only be stepped by an integer, which is widened to a long.
+                let computation_type = match lvalue_type {
+                    types::T::Pointer(_)
+                        if matches!(operator, BinaryOperator::Add | BinaryOperator::Subtract) =>
+                    {
+                        check_pointer_arithmetic(&lvalue_type, &self.symbols)?;
+                        types::T::Long
+                    }
+                    types::T::Pointer(_) => {
+                        return Err(CompilerError::SemanticError {
+                            message: format!("invalid operands to {:?} on a pointer", operator),
+                        });
+                    }
+                    _ => operation_type(*operator, &lvalue_type, &value_type),
+                };
+
+                let compound = Expression::CompoundAssignment {
+                    operator: *operator,
+                    lvalue: Box::new(lvalue),
+                    value: Box::new(convert_to(value, &value_type, &computation_type)),
+                };
+                Ok((compound, lvalue_type))
+            }
+            Expression::PrefixIncDec { operator, expr } => {
+                let (inner, typ) = self.check_scalar_operand(expr)?;
+                self.check_modifiable(&inner)?;
+                if matches!(typ, types::T::Pointer(_)) {
+                    check_pointer_arithmetic(&typ, &self.symbols)?;
+                }
+                let inc_dec = Expression::PrefixIncDec {
+                    operator: *operator,
+                    expr: Box::new(inner),
+                };
+                Ok((inc_dec, typ))
+            }
+            Expression::PostfixIncDec { operator, expr } => {
+                let (inner, typ) = self.check_scalar_operand(expr)?;
+                self.check_modifiable(&inner)?;
+                if matches!(typ, types::T::Pointer(_)) {
+                    check_pointer_arithmetic(&typ, &self.symbols)?;
+                }
+                let inc_dec = Expression::PostfixIncDec {
+                    operator: *operator,
+                    expr: Box::new(inner),
+                };
+                Ok((inc_dec, typ))
+            }
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let (condition, _) = self.check_scalar_operand(condition)?;
+                let (then, then_type) = self.check_expression(then)?;
+                let (otherwise, otherwise_type) = self.check_expression(otherwise)?;
+
+                let result_type = if then_type == types::T::Void && otherwise_type == types::T::Void
+                {
+                    types::T::Void
+                } else if then_type.is_arithmetic() && otherwise_type.is_arithmetic() {
+                    common_type(&then_type, &otherwise_type)
+                } else {
+                    common_pointer_type(&then, &then_type, &otherwise, &otherwise_type)?
+                };
+
+                let conditional = Expression::Conditional {
+                    condition: Box::new(condition),
+                    then: Box::new(convert_to(then, &then_type, &result_type)),
+                    otherwise: Box::new(convert_to(otherwise, &otherwise_type, &result_type)),
+                };
+                Ok((conditional, result_type))
+            }
+            Expression::Comma { left, right } => {
+                let (left, _) = self.check_expression(left)?;
+                let (right, typ) = self.check_expression(right)?;
+                let comma = Expression::Comma {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+                Ok((comma, typ))
+            }
+            Expression::FunctionCall { callee, args } => self.check_call(callee, args),
+            Expression::AddressOf(inner) => {
+                let (inner, _) = self.check_designator(inner)?;
+                if let Expression::Dot { .. } = inner.ungrouped()
+                    && !is_lvalue(&inner)
+                {
+                    return Err(CompilerError::SemanticError {
+                        message: "cannot take the address of a member of a structure value".into(),
+                    });
+                }
+                if is_bit_field(&inner, &self.symbols) {
+                    return Err(CompilerError::SemanticError {
+                        message: "cannot take the address of a bit-field".into(),
+                    });
+                }
+                let typ = lvalue_type(&inner, &self.symbols);
+                Ok((
+                    Expression::AddressOf(Box::new(inner)),
+                    types::T::Pointer(Box::new(typ)),
+                ))
+            }
+            Expression::Dereference(inner) => {
+                let (inner, typ) = self.check_expression(inner)?;
+                let referenced = dereferenced_type(&typ)?;
+                Ok((Expression::Dereference(Box::new(inner)), referenced))
+            }
+            Expression::Subscript { expr, index } => {
+                let (expr, expr_type) = self.check_expression(expr)?;
+                let (index, index_type) = self.check_expression(index)?;
+
+                // `i[p]` means the same as `p[i]`, so put the pointer first.
+                let (pointer, pointer_type, index, index_type) =
+                    if matches!(expr_type, types::T::Pointer(_)) {
+                        (expr, expr_type, index, index_type)
+                    } else {
+                        (index, index_type, expr, expr_type)
+                    };
+
+                if !index_type.is_integer() {
+                    return Err(CompilerError::SemanticError {
+                        message: "subscript requires a pointer and an integer".into(),
+                    });
+                }
+
+                let referenced = dereferenced_type(&pointer_type)?;
+                let subscript = Expression::Subscript {
+                    expr: Box::new(pointer),
+                    index: Box::new(convert_to(index, &index_type, &types::T::Long)),
+                };
+                Ok((subscript, referenced))
+            }
+            // A member has the qualifiers of the structure it is in, which
+            // only matter once it decays, or has its address taken.
+            Expression::Dot { expr, member } => {
+                let (inner, _) = self.check_expression(expr)?;
+                let structure = lvalue_type(&inner, &self.symbols);
+                self.check_member(&structure, member)?;
+                let typ = member_type(&structure, member, &self.symbols);
+                let dot = Expression::Dot {
+                    expr: Box::new(inner),
+                    member: member.clone(),
+                };
+                Ok((dot, typ.unqualified().clone()))
+            }
+            Expression::Arrow { expr, member } => {
+                let (inner, typ) = self.check_expression(expr)?;
+                let types::T::Pointer(structure) = &typ else {
+                    return Err(CompilerError::SemanticError {
+                        message: format!("'->' requires a pointer to a structure, not {:?}", typ),
+                    });
+                };
+                self.check_member(structure, member)?;
+                let typ = member_type(structure, member, &self.symbols);
+                let arrow = Expression::Arrow {
+                    expr: Box::new(inner),
+                    member: member.clone(),
+                };
+                Ok((arrow, typ.unqualified().clone()))
+            }
+            Expression::SizeOfExpr(inner) => {
+                let (inner, typ) = self.check_designator(inner)?;
+                check_complete(&typ, &self.symbols)?;
+                if is_bit_field(&inner, &self.symbols) {
+                    return Err(CompilerError::SemanticError {
+                        message: "sizeof applied to a bit-field".into(),
+                    });
+                }
+                // Unless the operand is a variable length array, it is never
+                // evaluated and only its type matters, so this is the constant
+                // `sizeof (typ)`.
+                if typ.has_variable_size() {
+                    Ok((Expression::SizeOfExpr(Box::new(inner)), types::T::ULong))
+                } else {
+                    let size = self.symbols.size_of(&typ) as u64;
+                    Ok((Expression::ConstULong(size), types::T::ULong))
+                }
+            }
+            // Only the type checker knows the layout of a structure, so the
+            // size of any type is worked out here. A type name is never a
+            // variable length array.
+            Expression::SizeOfType(typ) => {
+                check_complete(typ, &self.symbols)?;
+                let size = self.symbols.size_of(typ) as u64;
+                Ok((Expression::ConstULong(size), types::T::ULong))
+            }
+            Expression::AlignOfType(typ) => {
+                check_complete(typ, &self.symbols)?;
+                let alignment = self.symbols.alignment_of(typ) as u64;
+                Ok((Expression::ConstULong(alignment), types::T::ULong))
+            }
+            Expression::VaStart(ap) => {
+                if !self.variadic {
+                    return Err(CompilerError::SemanticError {
+                        message: "va_start used in a function with fixed arguments".into(),
+                    });
+                }
+
+                let ap = self.check_va_list(ap)?;
+                Ok((Expression::VaStart(Box::new(ap)), types::T::Void))
+            }
+            Expression::VaArg { ap, typ } => {
+                let typ = typ.unqualified();
+                // A float argument is always promoted to double on its way in.
+                if !typ.is_scalar() || *typ == types::T::Float {
+                    return Err(CompilerError::SemanticError {
+                        message: format!("va_arg cannot read a value of type {:?}", typ),
+                    });
+                }
+
+                let ap = self.check_va_list(ap)?;
+                let va_arg = Expression::VaArg {
+                    ap: Box::new(ap),
+                    typ: typ.clone(),
+                };
+                Ok((va_arg, typ.clone()))
+            }
+            Expression::VaEnd(ap) => {
+                let ap = self.check_va_list(ap)?;
+                Ok((Expression::VaEnd(Box::new(ap)), types::T::Void))
+            }
+            Expression::StatementExpression(block) => {
+                if self.return_type.is_none() {
+                    return Err(CompilerError::SemanticError {
+                        message: "statement expressions are only allowed inside functions".into(),
+                    });
+                }
+                let block = self.check_block(block)?;
+                let typ = match block.result() {
+                    Some(result) => expression_type(result, &self.symbols),
+                    None => types::T::Void,
+                };
+                Ok((Expression::StatementExpression(block), typ))
+            }
+        }
+    }
+
+    fn check_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        let binary = |operator, left, right| Expression::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+
+        if operator.is_logical() {
+            let (left, _) = self.check_scalar_operand(left)?;
+            let (right, _) = self.check_scalar_operand(right)?;
+            return Ok((binary(operator, left, right), types::T::Int));
+        }
+
+        let (left, left_type) = self.check_scalar_operand(left)?;
+        let (right, right_type) = self.check_scalar_operand(right)?;
+        let left_pointer = matches!(left_type, types::T::Pointer(_));
+        let right_pointer = matches!(right_type, types::T::Pointer(_));
+
+        if !left_pointer && !right_pointer {
+            if operator.requires_integers() && !(left_type.is_integer() && right_type.is_integer())
+            {
+                return Err(CompilerError::SemanticError {
+                    message: format!(
+                        "invalid operands to {:?}: {:?} and {:?}",
+                        operator, left_type, right_type
+                    ),
+                });
+            }
+
+            let common = operation_type(operator, &left_type, &right_type);
+            let result_type = if operator.is_comparison() {
+                types::T::Int
+            } else {
+                common.clone()
+            };
+
+            let left = convert_to(left, &left_type, &common);
+            let right = convert_to(right, &right_type, &common);
+            return Ok((binary(operator, left, right), result_type));
+        }
+
+        let invalid = || {
+            Err(CompilerError::SemanticError {
+                message: format!(
+                    "invalid operands to {:?}: {:?} and {:?}",
+                    operator, left_type, right_type
+                ),
+            })
+        };
+
+        match operator {
+            BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                let common = common_pointer_type(&left, &left_type, &right, &right_type)?;
+                let left = convert_to(left, &left_type, &common);
+                let right = convert_to(right, &right_type, &common);
+                Ok((binary(operator, left, right), types::T::Int))
+            }
+            BinaryOperator::LessThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterOrEqual
+                if compatible_pointers(&left_type, &right_type) =>
+            {
+                Ok((binary(operator, left, right), types::T::Int))
+            }
+            // Pointer arithmetic always has the pointer on the left, with the
+            // integer widened to a long.
+            BinaryOperator::Add | BinaryOperator::Subtract
+                if left_pointer && right_type.is_integer() =>
+            {
+                check_pointer_arithmetic(&left_type, &self.symbols)?;
+                let right = convert_to(right, &right_type, &types::T::Long);
+                Ok((binary(operator, left, right), left_type))
+            }
+            BinaryOperator::Add if right_pointer && left_type.is_integer() => {
+                check_pointer_arithmetic(&right_type, &self.symbols)?;
+                let left = convert_to(left, &left_type, &types::T::Long);
+                Ok((binary(operator, right, left), right_type))
+            }
+            BinaryOperator::Subtract if compatible_pointers(&left_type, &right_type) => {
+                check_pointer_arithmetic(&left_type, &self.symbols)?;
+                Ok((binary(operator, left, right), types::T::Long))
+            }
+            _ => invalid(),
+        }
+    }
+
+    fn check_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+    ) -> Result<(Expression, types::T), CompilerError> {
+        let (callee, callee_type) = self.check_expression(callee)?;
+        let types::T::Pointer(referenced) = &callee_type else {
+            return Err(CompilerError::SemanticError {
+                message: format!("called object of type {:?} is not a function", callee_type),
+            });
+        };
+        let types::T::FunType {
+            params,
+            ret,
+            variadic,
+        } = referenced.unqualified().clone()
+        else {
+            return Err(CompilerError::SemanticError {
+                message: format!("called object of type {:?} is not a function", callee_type),
+            });
+        };
+
+        // A function called by name is called directly, anything else through
+        // the pointer the callee evaluates to.
+        let direct = match &callee {
+            Expression::AddressOf(inner) => self.function_name(inner),
+            _ => None,
+        };
+        let callee_name = match &direct {
+            Some(name) => format!("function '{name}'"),
+            None => "function".into(),
+        };
+        let callee = match direct {
+            Some(name) => Expression::Var(Identifier(name)),
+            None => callee,
+        };
+
+        if passes_structure(referenced.unqualified()) {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "{callee_name} passes a structure by value, which is not supported"
+                ),
+            });
+        }
+
+        if args.len() < params.len() || (!variadic && args.len() > params.len()) {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "{} expects {}{} arguments but was called with {}",
+                    callee_name,
+                    if variadic { "at least " } else { "" },
+                    params.len(),
+                    args.len()
+                ),
+            });
+        }
+
+        let mut checked_args = args
+            .iter()
+            .zip(params.iter())
+            .map(|(arg, param)| self.check_and_convert(arg, param))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Arguments matching the `...` only undergo the default argument
+        // promotions, which turn float into double and narrow integers into
+        // int.
+        for arg in &args[params.len()..] {
+            let (arg, typ) = self.check_scalar_operand(arg)?;
+            checked_args.push(if typ == types::T::Float {
+                convert_to(arg, &typ, &types::T::Double)
+            } else {
+                promote((arg, typ)).0
+            });
+        }
+        let args = checked_args;
+
+        let call = Expression::FunctionCall {
+            callee: Box::new(callee),
+            args,
+        };
+        Ok((call, *ret))
+    }
+
+    /// The name of the function `expr` designates, if it is a function's name.
+    fn function_name(&self, expr: &Expression) -> Option<String> {
+        match expr.ungrouped() {
+            Expression::Var(ident)
+                if matches!(
+                    self.symbols.get(&ident.name()),
+                    Some(types::T::FunType { .. })
+                ) =>
+            {
+                Some(ident.name())
+            }
+            _ => None,
+        }
+    }
+
+    /// Rejects modifying a function, an array, a member of a structure value,
+    /// or an lvalue whose type is `const`, including an object reached
+    /// through a pointer to const and a structure with a const member.
+    fn check_modifiable(&self, lvalue: &Expression) -> Result<(), CompilerError> {
+        // Functions and arrays have decayed to pointers by now, which are not
+        // lvalues.
+        match lvalue.ungrouped() {
+            Expression::Dot { .. } if !is_lvalue(lvalue) => {
+                return Err(CompilerError::SemanticError {
+                    message: "cannot assign to a member of a structure value".into(),
+                });
+            }
+            Expression::Var(_)
+            | Expression::Dereference(_)
+            | Expression::Subscript { .. }
+            | Expression::Dot { .. }
+            | Expression::Arrow { .. } => {}
+            Expression::Cast { .. } => {
+                return Err(CompilerError::SemanticError {
+                    message: "cannot assign to an array".into(),
+                });
+            }
+            _ => {
+                return Err(CompilerError::SemanticError {
+                    message: "cannot assign to a function".into(),
+                });
+            }
+        }
+
+        let typ = lvalue_type(lvalue, &self.symbols);
+        if typ.qualifiers().constant {
+            return Err(CompilerError::SemanticError {
+                message: "cannot modify a const-qualified object".into(),
+            });
+        }
+        if has_const_member(&typ, &self.symbols) {
+            return Err(CompilerError::SemanticError {
+                message: format!("cannot assign to {:?}, which has a const member", typ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Rejects accessing `member` in an object of type `structure` unless it
+    /// is a complete structure with such a member.
+    fn check_member(&self, structure: &types::T, member: &str) -> Result<(), CompilerError> {
+        let types::T::Structure(tag) = structure.unqualified() else {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "member access requires a structure type, not {:?}",
+                    structure
+                ),
+            });
+        };
+        let Some(layout) = self.symbols.structure(tag) else {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "member access into incomplete structure '{}'",
+                    source_tag(tag)
+                ),
+            });
+        };
+        if layout.member(member).is_none() {
+            return Err(CompilerError::SemanticError {
+                message: format!(
+                    "structure '{}' has no member named '{member}'",
+                    source_tag(tag)
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The `ap` operand of the `va_*` builtins, which must be a `va_list`
+    /// object or a parameter declared as one, so either way a pointer to its
+    /// record once it has decayed.
+    fn check_va_list(&mut self, ap: &Expression) -> Result<Expression, CompilerError> {
+        let (ap, typ) = self.check_expression(ap)?;
+        if typ != types::T::Pointer(Box::new(types::T::VaList)) {
+            return Err(CompilerError::SemanticError {
+                message: format!("expected a va_list object, found {:?}", typ),
+            });
+        }
+
+        Ok(ap)
+    }
+
+    fn check_integer_operand(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        let (expr, typ) = self.check_expression(expr)?;
+        if !typ.is_integer() {
+            return Err(CompilerError::SemanticError {
+                message: format!("operand of type {:?} is not an integer", typ),
+            });
+        }
+
+        Ok((expr, typ))
+    }
+
+    fn check_arithmetic_operand(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        let (expr, typ) = self.check_expression(expr)?;
+        if !typ.is_arithmetic() {
+            return Err(CompilerError::SemanticError {
+                message: format!("operand of type {:?} is not arithmetic", typ),
+            });
+        }
+
+        Ok((expr, typ))
+    }
+
+    fn check_scalar_operand(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<(Expression, types::T), CompilerError> {
+        let (expr, typ) = self.check_expression(expr)?;
+        if !typ.is_scalar() {
+            return Err(CompilerError::SemanticError {
+                message: format!("operand of type {:?} is not a scalar", typ),
+            });
+        }
+
+        Ok((expr, typ))
+    }
+}
+
+/// The type of an expression that has already been through the type checker.
+/// Every implicit conversion is explicit by then, so this only has to look at
+/// the outermost node and, for a few operators, the type of their operands.
+pub fn expression_type(expr: &Expression, symbols: &SymbolTable) -> types::T {
+    match expr {
+        Expression::ConstInt(_) => types::T::Int,
+        Expression::ConstLong(_) => types::T::Long,
+        Expression::ConstUInt(_) => types::T::UInt,
+        Expression::ConstULong(_) => types::T::ULong,
+        Expression::ConstFloat(_) => types::T::Float,
+        Expression::ConstDouble(_) => types::T::Double,
+        Expression::Var(ident) => symbols.type_of(&ident.name()).unqualified().clone(),
+        Expression::Cast { typ, .. } => typ.clone(),
+        Expression::Unary {
+            operator: UnaryOperator::Not,
+            ..
+        } => types::T::Int,
+        Expression::Grouping(expr)
+        | Expression::Unary { expr, .. }
+        | Expression::PrefixIncDec { expr, .. }
+        | Expression::PostfixIncDec { expr, .. } => expression_type(expr, symbols),
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            let left_type = expression_type(left, symbols);
+            if operator.is_comparison() || operator.is_logical() {
+                types::T::Int
+            } else if *operator == BinaryOperator::Subtract
+                && matches!(left_type, types::T::Pointer(_))
+                && matches!(expression_type(right, symbols), types::T::Pointer(_))
+            {
+                types::T::Long
+            } else {
+                left_type
+            }
+        }
+        Expression::Assignment { lvalue, .. } | Expression::CompoundAssignment { lvalue, .. } => {
+            expression_type(lvalue, symbols)
+        }
+        Expression::Conditional { then, .. } => expression_type(then, symbols),
+        Expression::Comma { right, .. } => expression_type(right, symbols),
+        // Direct calls name the function, indirect ones evaluate a pointer to it.
+        Expression::FunctionCall { callee, .. } => match expression_type(callee, symbols) {
+            types::T::FunType { ret, .. } => *ret,
+            types::T::Pointer(referenced) => match *referenced {
+                types::T::FunType { ret, .. } => *ret,
+                other => panic!("Called a pointer to non-function {:?}", other),
+            },
+            other => panic!("Called a non-function of type {:?}", other),
+        },
+        Expression::AddressOf(inner) => types::T::Pointer(Box::new(lvalue_type(inner, symbols))),
+        Expression::Dereference(_)
+        | Expression::Subscript { .. }
+        | Expression::Dot { .. }
+        | Expression::Arrow { .. } => lvalue_type(expr, symbols).unqualified().clone(),
+        Expression::SizeOfExpr(_) | Expression::SizeOfType(_) | Expression::AlignOfType(_) => {
+            types::T::ULong
+        }
+        Expression::VaStart(_) | Expression::VaEnd(_) => types::T::Void,
+        Expression::StatementExpression(block) => match block.result() {
+            Some(result) => expression_type(result, symbols),
+            None => types::T::Void,
+        },
+        Expression::VaArg { typ, .. } => typ.clone(),
+        Expression::StringConstant(_) | Expression::If { .. } => {
+            panic!("Expression was not type checked: {:?}", expr)
+        }
+    }
+}
+
+/// The type of the object an lvalue designates, including the qualifiers that
+/// `expression_type` drops from its value.
+pub fn lvalue_type(expr: &Expression, symbols: &SymbolTable) -> types::T {
+    match expr.ungrouped() {
+        Expression::Var(ident) => symbols.type_of(&ident.name()).clone(),
+        Expression::Dereference(inner) | Expression::Subscript { expr: inner, .. } => {
+            match expression_type(inner, symbols) {
+                types::T::Pointer(referenced) => *referenced,
+                other => panic!("Dereferenced a non-pointer of type {:?}", other),
+            }
+        }
+        Expression::Dot { expr, member } => {
+            member_type(&lvalue_type(expr, symbols), member, symbols)
+        }
+        Expression::Arrow { expr, member } => match expression_type(expr, symbols) {
+            types::T::Pointer(structure) => member_type(&structure, member, symbols),
+            other => panic!("'->' applied to a non-pointer of type {:?}", other),
+        },
+        other => expression_type(other, symbols),
+    }
+}
+
+/// The type of `member` in an object of the structure type `structure`, with
+/// the qualifiers of the structure added to its own.
+fn member_type(structure: &types::T, member: &str, symbols: &SymbolTable) -> types::T {
+    let typ = symbols.member(structure, member).value_type();
+    typ.qualified(structure.qualifiers())
+}
+
+/// Whether `expr` designates a bit-field, which has no address of its own.
+fn is_bit_field(expr: &Expression, symbols: &SymbolTable) -> bool {
+    let (structure, member) = match expr.ungrouped() {
+        Expression::Dot { expr, member } => (lvalue_type(expr, symbols), member),
+        Expression::Arrow { expr, member } => match expression_type(expr, symbols) {
+            types::T::Pointer(structure) => (*structure, member),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    symbols.member(&structure, member).bit_field.is_some()
+}
+
+/// Whether `expr` designates an object. Every expression the type checker
+/// accepts as an operand of `&` does, except a member of a structure value
+/// such as `f().x`.
+pub fn is_lvalue(expr: &Expression) -> bool {
+    match expr.ungrouped() {
+        Expression::Var(_)
+        | Expression::Dereference(_)
+        | Expression::Subscript { .. }
+        | Expression::Arrow { .. } => true,
+        Expression::Dot { expr, .. } => is_lvalue(expr),
+        _ => false,
+    }
+}
+
+/// Whether an object of type `typ` has a const member, possibly nested in
+/// another structure or an array, so cannot be assigned to as a whole.
+fn has_const_member(typ: &types::T, symbols: &SymbolTable) -> bool {
+    match typ.unqualified() {
+        types::T::Structure(_) => symbols.layout_of(typ).members.iter().any(|member| {
+            member.typ.qualifiers().constant || has_const_member(&member.typ, symbols)
+        }),
+        types::T::Array { typ, .. } => typ.qualifiers().constant || has_const_member(typ, symbols),
+        _ => false,
+    }
+}
+
+/// Whether a function of type `typ` takes or returns a structure, which
+/// calls cannot pass by value yet.
+fn passes_structure(typ: &types::T) -> bool {
+    let types::T::FunType { params, ret, .. } = typ else {
+        return false;
+    };
+    params
+        .iter()
+        .chain([&**ret])
+        .any(|typ| matches!(typ.unqualified(), types::T::Structure(_)))
+}
+
+/// The name a variable was declared with, before the resolver made the name
+/// of a local one unique as `var.<name>.<n>`.
+fn source_name(name: &str) -> &str {
+    name.strip_prefix("var.")
+        .and_then(|unique| unique.rsplit_once('.'))
+        .map_or(name, |(name, _)| name)
+}
+
+/// The tag a structure was declared with, before the resolver made the tag
+/// of one declared in a block unique as `<tag>.<n>`.
+fn source_tag(tag: &str) -> &str {
+    tag.split_once('.').map_or(tag, |(tag, _)| tag)
+}
+
+/// Rejects declaring an object of a type that has no values, or whose size
+/// is not known.
+fn check_object_type(
+    name: &str,
+    typ: &types::T,
+    symbols: &SymbolTable,
+) -> Result<(), CompilerError> {
+    if *typ.unqualified() == types::T::Void {
+        return Err(CompilerError::SemanticError {
+            message: format!("variable '{}' declared void", name),
+        });
+    }
+    if !symbols.is_complete(typ) {
+        return Err(CompilerError::SemanticError {
+            message: format!(
+                "variable '{}' has incomplete type {:?}",
+                source_name(name),
+                typ
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// A bit-field has to be an integer no wider than its type, and only one
+/// without a name may be empty.
+fn check_bit_field(member: &MemberDeclaration, width: i64) -> Result<(), CompilerError> {
+    let field = if member.name.is_empty() {
+        "unnamed bit-field".to_string()
+    } else {
+        format!("bit-field '{}'", member.name)
+    };
+
+    let typ = member.typ.unqualified();
+    let bits = match typ {
+        types::T::Bool => 1,
+        types::T::Int | types::T::UInt | types::T::Long | types::T::ULong => typ.size() * 8,
+        _ => {
+            return Err(CompilerError::SemanticError {
+                message: format!("{field} has invalid type {:?}", member.typ),
+            });
+        }
+    };
+    if width > bits {
+        return Err(CompilerError::SemanticError {
+            message: format!("width of {field} exceeds its type"),
+        });
+    }
+    if width == 0 && !member.name.is_empty() {
+        return Err(CompilerError::SemanticError {
+            message: format!("zero width for {field}"),
+        });
+    }
+
+    Ok(())
+}
+
+/// `value` rounded up to the next multiple of `alignment`.
+fn round_up(value: i64, alignment: i64) -> i64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+/// The number of subobjects directly within an aggregate of type `typ`: the
+/// elements of an array, or the members of a structure.
+fn subobject_count(typ: &types::T, symbols: &SymbolTable) -> i64 {
+    match typ.unqualified() {
+        types::T::Array { size, .. } => *size,
+        types::T::Structure(_) => symbols.layout_of(typ).members.len() as i64,
+        other => panic!("No subobject of scalar type {:?}", other),
+    }
+}
+
+/// The type of the subobject at `path` within an object of type `typ`, where
+/// each step is an index into an array or the position of a member.
+fn subobject_type<'a>(typ: &'a types::T, path: &[i64], symbols: &'a SymbolTable) -> &'a types::T {
+    path.iter().fold(typ, |typ, index| match typ.unqualified() {
+        types::T::Array { typ, .. } => typ,
+        types::T::Structure(_) => &symbols.layout_of(typ).members[*index as usize].typ,
+        other => panic!("No subobject of scalar type {:?}", other),
+    })
+}
+
+/// The item of a checked initializer for the subobject at `path`, after
+/// spelling out any zeroed aggregate on the way as one zeroed item per
+/// subobject.
+fn subobject_mut<'a>(
+    init: &'a mut Initializer,
+    path: &[i64],
+    symbols: &SymbolTable,
+) -> &'a mut Initializer {
+    let Some((index, rest)) = path.split_first() else {
+        return init;
+    };
+
+    if let Initializer::Zero(typ) = init {
+        let zeroes = (0..subobject_count(typ, symbols))
+            .map(|i| {
+                let subobject = subobject_type(typ, &[i], symbols).clone();
+                (Vec::new(), Initializer::Zero(subobject))
+            })
+            .collect();
+        *init = Initializer::Compound(zeroes);
+    }
+
+    match init {
+        Initializer::Compound(items) => subobject_mut(&mut items[*index as usize].1, rest, symbols),
+        _ => panic!("No subobject in initializer {:?}", init),
+    }
+}
+
+/// The subobject an initializer without designators goes to after the one at
+/// `path`: the next subobject of the innermost aggregate that has one left.
+/// Past the end of the whole object, the index into the outermost aggregate
+/// is out of bounds.
+fn next_subobject(typ: &types::T, mut path: Vec<i64>, symbols: &SymbolTable) -> Vec<i64> {
+    loop {
+        let index = path.pop().expect("an initializer path is never empty") + 1;
+        if path.is_empty() {
+            return vec![index];
+        }
+
+        if index < subobject_count(subobject_type(typ, &path, symbols), symbols) {
+            path.push(index);
+            return path;
+        }
+    }
+}
+
+/// The type a binary operation is carried out in. Shifts take the type of
+/// their left operand, and converting the count to it as well is harmless
+/// because any count that does not fit is undefined anyway. Everything else
+/// uses the usual arithmetic conversions.
+pub fn operation_type(operator: BinaryOperator, left: &types::T, right: &types::T) -> types::T {
+    match operator {
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => left.promoted(),
+        _ => common_type(left, right),
+    }
+}
+
+/// The type both operands of an arithmetic operation are converted to: both
+/// are promoted, then the narrower or signed one is converted to the other.
+pub fn common_type(left: &types::T, right: &types::T) -> types::T {
+    let (left, right) = (&left.promoted(), &right.promoted());
+    if left == right {
+        left.clone()
+    } else if *left == types::T::Double || *right == types::T::Double {
+        types::T::Double
+    } else if *left == types::T::Float || *right == types::T::Float {
+        types::T::Float
+    } else if left.size() == right.size() {
+        if left.is_signed() {
+            right.clone()
+        } else {
+            left.clone()
+        }
+    } else if left.size() > right.size() {
+        left.clone()
+    } else {
+        right.clone()
+    }
+}
+
+/// Only integers and pointers fit in a general purpose register, while any
+/// object can be in memory.
+fn check_asm_operand(
+    index: usize,
+    constraint: &Constraint,
+    typ: &types::T,
+) -> Result<(), CompilerError> {
+    let fits = match constraint.placement {
+        Placement::Memory => !matches!(typ, types::T::FunType { .. } | types::T::Void),
+        _ => typ.is_integer() || matches!(typ, types::T::Pointer(_)),
+    };
+    if fits {
+        Ok(())
+    } else {
+        Err(CompilerError::SemanticError {
+            message: format!(
+                "asm operand {index} of type {:?} does not fit its constraint",
+                typ
+            ),
+        })
+    }
+}
+
+/// An operand of an arithmetic operator after the integer promotions.
+fn promote((expr, typ): (Expression, types::T)) -> (Expression, types::T) {
+    let promoted = typ.promoted();
+    (convert_to(expr, &typ, &promoted), promoted)
+}
+
+/// The type two operands are converted to when at least one is a pointer:
+/// both must point to the same type, or one must be a null pointer constant.
+/// The result points to a type with the qualifiers of both.
+fn common_pointer_type(
+    left: &Expression,
+    left_type: &types::T,
+    right: &Expression,
+    right_type: &types::T,
+) -> Result<types::T, CompilerError> {
+    if left_type == right_type {
+        Ok(left_type.clone())
+    } else if let (types::T::Pointer(left_referenced), types::T::Pointer(right_referenced)) =
+        (left_type, right_type)
+        && compatible_pointers(left_type, right_type)
+    {
+        let qualifiers = left_referenced
+            .qualifiers()
+            .union(right_referenced.qualifiers());
+        let referenced = left_referenced.unqualified().clone().qualified(qualifiers);
+        Ok(types::T::Pointer(Box::new(referenced)))
+    } else if is_null_pointer_constant(left) && matches!(right_type, types::T::Pointer(_)) {
+        Ok(right_type.clone())
+    } else if is_null_pointer_constant(right) && matches!(left_type, types::T::Pointer(_)) {
+        Ok(left_type.clone())
+    } else {
+        Err(CompilerError::SemanticError {
+            message: format!("incompatible types {:?} and {:?}", left_type, right_type),
+        })
+    }
+}
+
+/// Pointers to the same type, ignoring the qualifiers on that type.
+fn compatible_pointers(left: &types::T, right: &types::T) -> bool {
+    match (left, right) {
+        (types::T::Pointer(left), types::T::Pointer(right)) => {
+            left.unqualified().is_compatible(right.unqualified())
+        }
+        _ => false,
+    }
+}
+
+/// `expr` with any conversions to pointer types stripped, as inserted around
+/// a null pointer constant.
+fn without_pointer_casts(expr: &Expression) -> &Expression {
+    match expr.ungrouped() {
+        Expression::Cast {
+            typ: types::T::Pointer(_),
+            expr,
+        } => without_pointer_casts(expr),
+        expr => expr,
+    }
+}
+
+/// An integer constant expression with the value 0.
+fn is_null_pointer_constant(expr: &Expression) -> bool {
+    matches!(const_eval::evaluate_integer(expr), Ok(Some(0)))
+}
+
+/// The type of the value read through a pointer of type `typ`.
+fn dereferenced_type(typ: &types::T) -> Result<types::T, CompilerError> {
+    match typ {
+        types::T::Pointer(referenced) if *referenced.unqualified() == types::T::Void => {
+            Err(CompilerError::SemanticError {
+                message: "cannot dereference a void pointer".into(),
+            })
+        }
+        types::T::Pointer(referenced) => Ok(referenced.unqualified().clone()),
+        _ => Err(CompilerError::SemanticError {
+            message: format!("cannot dereference a non-pointer of type {:?}", typ),
+        }),
+    }
+}
+
+/// Pointer arithmetic is scaled by the size of the referenced type, so that
+/// type has to be complete.
+fn check_pointer_arithmetic(
+    pointer: &types::T,
+    symbols: &SymbolTable,
+) -> Result<(), CompilerError> {
+    match pointer {
+        types::T::Pointer(referenced) => check_complete(referenced, symbols),
+        _ => Ok(()),
+    }
+}
+
+fn check_complete(typ: &types::T, symbols: &SymbolTable) -> Result<(), CompilerError> {
+    if symbols.is_complete(typ) {
+        Ok(())
+    } else {
+        Err(CompilerError::SemanticError {
+            message: format!("cannot take the size of incomplete type {:?}", typ),
+        })
+    }
+}
+
+/// Convert `expr` to `to` as an assignment would: between arithmetic types,
+/// from a pointer to `_Bool`, and to a pointer only from a pointer to the same
+/// type or a null pointer constant. The pointer may gain qualifiers on the
+/// way but not lose them. A structure is only assigned one of its own type.
+fn convert_by_assignment(
+    expr: Expression,
+    from: &types::T,
+    to: &types::T,
+) -> Result<Expression, CompilerError> {
+    if let types::T::Structure(_) = to {
+        return if from == to {
+            Ok(expr)
+        } else {
+            Err(CompilerError::SemanticError {
+                message: format!("cannot convert {:?} to {:?}", from, to),
+            })
+        };
+    }
+
+    if !to.is_scalar() {
+        return Err(CompilerError::SemanticError {
+            message: format!("cannot assign a value of type {:?}", to),
+        });
+    }
+
+    if let (types::T::Pointer(from_referenced), types::T::Pointer(to_referenced)) = (from, to)
+        && compatible_pointers(from, to)
+    {
+        if !to_referenced
+            .qualifiers()
+            .contains(from_referenced.qualifiers())
+        {
+            return Err(CompilerError::SemanticError {
+                message: format!("conversion from {:?} to {:?} discards qualifiers", from, to),
+            });
+        }
+
+        return Ok(convert_to(expr, from, to));
+    }
+
+    let null_pointer = matches!(to, types::T::Pointer(_)) && is_null_pointer_constant(&expr);
+    let pointer_to_bool = matches!(from, types::T::Pointer(_)) && *to == types::T::Bool;
+    if from == to || (from.is_arithmetic() && to.is_arithmetic()) || null_pointer || pointer_to_bool
+    {
+        Ok(convert_to(expr, from, to))
+    } else {
+        Err(CompilerError::SemanticError {
+            message: format!("cannot convert {:?} to {:?}", from, to),
+        })
+    }
+}
+
+fn convert_to(expr: Expression, from: &types::T, to: &types::T) -> Expression {
+    if from == to {
+        return expr;
+    }
+
+    Expression::Cast {
+        typ: to.clone(),
+        expr: Box::new(expr),
+    }
+}
@@ -1,3 +1,31 @@
+/// The `const` and `volatile` qualifiers on a type.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Qualifiers {
+    /// The object cannot be modified after it is initialized.
+    pub constant: bool,
+    /// The object may be read or written outside the program's control, so
+    /// every access has to happen exactly as written.
+    pub volatile: bool,
+}
+
+impl Qualifiers {
+    pub fn is_empty(&self) -> bool {
+        !self.constant && !self.volatile
+    }
+
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            constant: self.constant || other.constant,
+            volatile: self.volatile || other.volatile,
+        }
+    }
+
+    /// Whether every qualifier in `other` is also in `self`.
+    pub fn contains(self, other: Qualifiers) -> bool {
+        self.union(other) == self
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum T {
     Char,
@@ -7,10 +35,201 @@ pub enum T {
     Long,
     UInt,
     ULong,
+    /// `_Bool`, a byte that only ever holds 0 or 1.
+    Bool,
+    Float,
     Double,
     Pointer(Box<T>),
     Void,
-    Array { typ: Box<T>, size: i64 },
-    FunType {},
+    Array {
+        typ: Box<T>,
+        size: i64,
+    },
+    /// An array whose length is only known at run time. The declaration that
+    /// introduces it stores the length in the variable named `count` before
+    /// anything can refer to the array.
+    VariableArray {
+        typ: Box<T>,
+        count: String,
+    },
+    FunType {
+        params: Vec<T>,
+        ret: Box<T>,
+        /// Whether the parameter list ends in `...`.
+        variadic: bool,
+    },
     Structure(String),
+    /// `__builtin_va_list`, the System V `va_list` record: the offsets of the
+    /// next general purpose and vector register in the register save area,
+    /// followed by pointers to the next stack argument and to the save area.
+    /// `va_list` is an array of one of these, so like an array it decays to a
+    /// pointer to the record, and a parameter declared as one is that pointer.
+    VaList,
+    /// A type with `const` or `volatile` qualifiers. Only the types of
+    /// declared objects and of what pointers point to carry them; the type
+    /// checker strips them from the value of an expression. Never nested, and
+    /// `qualifiers` is never empty.
+    Qualified {
+        typ: Box<T>,
+        qualifiers: Qualifiers,
+    },
+}
+
+impl T {
+    /// This type with `qualifiers` added to any it already has. Qualifying
+    /// an array qualifies its elements, as for a member of a const structure.
+    pub fn qualified(self, qualifiers: Qualifiers) -> T {
+        if qualifiers.is_empty() {
+            return self;
+        }
+
+        match self {
+            T::Array { typ, size } => T::Array {
+                typ: Box::new(typ.qualified(qualifiers)),
+                size,
+            },
+            T::Qualified {
+                typ,
+                qualifiers: existing,
+            } => T::Qualified {
+                typ,
+                qualifiers: existing.union(qualifiers),
+            },
+            typ => T::Qualified {
+                typ: Box::new(typ),
+                qualifiers,
+            },
+        }
+    }
+
+    /// This type without its top-level qualifiers, e.g. `int *` for
+    /// `int *const`. Qualifiers on a pointer's referenced type are kept.
+    pub fn unqualified(&self) -> &T {
+        match self {
+            T::Qualified { typ, .. } => typ,
+            typ => typ,
+        }
+    }
+
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            T::Qualified { qualifiers, .. } => *qualifiers,
+            _ => Qualifiers::default(),
+        }
+    }
+
+    /// Size of a value of this type in bytes. A structure's depends on its
+    /// definition, which only `SymbolTable::size_of` knows.
+    pub fn size(&self) -> i64 {
+        match self {
+            T::Qualified { typ, .. } => typ.size(),
+            T::Char | T::SChar | T::UChar | T::Bool => 1,
+            T::Int | T::UInt | T::Float => 4,
+            T::Long | T::ULong | T::Double | T::Pointer(_) => 8,
+            T::VaList => 24,
+            T::Array { typ, size } => typ.size() * size,
+            T::Structure(_) => panic!("size of {:?} depends on its definition", self),
+            T::Void | T::FunType { .. } | T::VariableArray { .. } => {
+                panic!("size of {:?} is not known at compile time", self)
+            }
+        }
+    }
+
+    /// Alignment of an object of this type in bytes. An array is aligned like
+    /// its elements.
+    pub fn alignment(&self) -> i64 {
+        match self {
+            T::Qualified { typ, .. } | T::Array { typ, .. } | T::VariableArray { typ, .. } => {
+                typ.alignment()
+            }
+            T::VaList => 8,
+            _ => self.size(),
+        }
+    }
+
+    /// Whether the size of this type, or of a type it is derived from, is
+    /// only known at run time, as for `int (*)[n]`.
+    pub fn is_variably_modified(&self) -> bool {
+        match self {
+            T::VariableArray { .. } => true,
+            T::Qualified { typ, .. } | T::Array { typ, .. } | T::Pointer(typ) => {
+                typ.is_variably_modified()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the size of this type is only known at run time: a variable
+    /// length array, or an array of them.
+    pub fn has_variable_size(&self) -> bool {
+        match self.unqualified() {
+            T::VariableArray { .. } => true,
+            T::Array { typ, .. } => typ.has_variable_size(),
+            _ => false,
+        }
+    }
+
+    /// Whether the two types are the same, except that a variable length
+    /// array matches any array with a matching element type: their lengths
+    /// can only be compared at run time.
+    pub fn is_compatible(&self, other: &T) -> bool {
+        match (self, other) {
+            (
+                T::VariableArray { typ, .. },
+                T::VariableArray { typ: other, .. } | T::Array { typ: other, .. },
+            )
+            | (T::Array { typ, .. }, T::VariableArray { typ: other, .. }) => {
+                typ.is_compatible(other)
+            }
+            (
+                T::Array { typ, size },
+                T::Array {
+                    typ: other,
+                    size: other_size,
+                },
+            ) => size == other_size && typ.is_compatible(other),
+            (T::Pointer(typ), T::Pointer(other)) => typ.is_compatible(other),
+            (
+                T::Qualified { typ, qualifiers },
+                T::Qualified {
+                    typ: other,
+                    qualifiers: other_qualifiers,
+                },
+            ) => qualifiers == other_qualifiers && typ.is_compatible(other),
+            _ => self == other,
+        }
+    }
+
+    /// Types that can be used as a truth value: arithmetic types and pointers.
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || matches!(self.unqualified(), T::Pointer(_))
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self.unqualified(), T::Float | T::Double)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self.unqualified(), T::Char | T::SChar | T::Int | T::Long)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self.unqualified(),
+            T::Char | T::SChar | T::UChar | T::Bool | T::Int | T::UInt | T::Long | T::ULong
+        )
+    }
+
+    /// The type this one is converted to by the integer promotions: types
+    /// narrower than `int` become `int`.
+    pub fn promoted(&self) -> T {
+        match self.unqualified() {
+            T::Char | T::SChar | T::UChar | T::Bool => T::Int,
+            typ => typ.clone(),
+        }
+    }
 }
@@ -0,0 +1,87 @@
+//! Unreachable code elimination: removes the blocks that control can never
+//! reach, then the jumps and labels that only led from one block to the one
+//! right after it.
+
+use std::collections::HashSet;
+
+use crate::cfg::{Cfg, NodeId};
+use crate::ir::Instruction;
+use crate::symbols::SymbolTable;
+
+pub fn eliminate_unreachable_code(mut cfg: Cfg, symbols: &SymbolTable) -> Cfg {
+    remove_unreachable_blocks(&mut cfg);
+    remove_redundant_jumps(&mut cfg, symbols);
+    remove_unused_labels(&mut cfg);
+    cfg
+}
+
+/// Removes the blocks that cannot be reached from the entry.
+pub fn remove_unreachable_blocks(cfg: &mut Cfg) {
+    let mut reached = HashSet::new();
+    let mut pending = vec![NodeId::Entry];
+    while let Some(node) = pending.pop() {
+        if reached.insert(node) {
+            pending.extend_from_slice(cfg.successors(node));
+        }
+    }
+
+    let unreachable: Vec<usize> = cfg
+        .blocks
+        .keys()
+        .copied()
+        .filter(|id| !reached.contains(&NodeId::Block(*id)))
+        .collect();
+    for id in unreachable {
+        cfg.remove_block(id);
+    }
+}
+
+/// Removes a jump at the end of a block when the block continues with the
+/// next one either way. A conditional jump is kept if it reads a volatile
+/// variable, since that read has to happen.
+fn remove_redundant_jumps(cfg: &mut Cfg, symbols: &SymbolTable) {
+    let ids: Vec<usize> = cfg.blocks.keys().copied().collect();
+    for pair in ids.windows(2) {
+        let (id, next) = (pair[0], pair[1]);
+        let block = cfg.blocks.get_mut(&id).unwrap();
+        if block.successors != [NodeId::Block(next)] {
+            continue;
+        }
+
+        let redundant = match block.instructions.last() {
+            Some(Instruction::Jump(_)) => true,
+            Some(
+                Instruction::JumpIfZero { condition, .. }
+                | Instruction::JumpIfNotZero { condition, .. },
+            ) => !condition.is_volatile(symbols),
+            _ => false,
+        };
+        if redundant {
+            block.instructions.pop();
+        }
+    }
+}
+
+/// Removes the labels that no jump is left to.
+fn remove_unused_labels(cfg: &mut Cfg) {
+    let targets: HashSet<String> = cfg
+        .blocks
+        .values()
+        .filter_map(|block| match block.instructions.last() {
+            Some(
+                Instruction::Jump(target)
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. },
+            ) => Some(target.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for block in cfg.blocks.values_mut() {
+        if let Some(Instruction::Label(label)) = block.instructions.first()
+            && !targets.contains(label)
+        {
+            block.instructions.remove(0);
+        }
+    }
+}